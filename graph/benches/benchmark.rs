@@ -0,0 +1,103 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use graph::{
+    algorithm::{
+        breadth_first_search, breadth_first_traverse, connected_components, depth_first_search,
+        page_rank, parallel_breadth_first, parallel_connected_components, PageRankOptions,
+    },
+    BinaryGrid, Csr,
+};
+use std::hint::black_box;
+
+const GRID_SIZE: usize = 1_000;
+const LARGE_NODES: usize = 1_000_000;
+const LARGE_DEGREE: usize = 8;
+
+/// an open grid with every 4th column walled off except for a gap at alternating ends
+fn maze() -> BinaryGrid {
+    let grid = (0..GRID_SIZE)
+        .map(|r| {
+            (0..GRID_SIZE)
+                .map(|c| match c % 4 {
+                    3 => r == if (c / 4) % 2 == 0 { GRID_SIZE - 1 } else { 0 },
+                    _ => true,
+                })
+                .collect()
+        })
+        .collect();
+
+    BinaryGrid::new_hv(grid)
+}
+
+/// `LARGE_NODES` nodes with `LARGE_DEGREE` random out edges each, from a fixed xorshift sequence
+fn large_graph() -> Csr {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut random = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state as usize % LARGE_NODES
+    };
+
+    let edges = (0..LARGE_NODES).flat_map(|v| (0..LARGE_DEGREE).map(move |_| v));
+    let edges: Vec<(usize, usize)> = edges.map(|v| (v, random())).collect();
+    Csr::from_edges(LARGE_NODES, edges)
+}
+
+fn traversal_benchmark(c: &mut Criterion) {
+    let grid = maze();
+    let start = (0, 0);
+    // the last column is a wall, the bottom right open cell is next to it
+    let goal = (GRID_SIZE - 1, GRID_SIZE - 2);
+    assert!(breadth_first_search(&grid, &start, |rc| rc == &goal).is_some());
+
+    c.bench_function("bfs traverse", |b| {
+        b.iter(|| black_box(breadth_first_traverse(&grid, &start).count()));
+    });
+
+    c.bench_function("bfs search near", |b| {
+        b.iter(|| black_box(breadth_first_search(&grid, &start, |rc| rc == &(10, 2))));
+    });
+
+    c.bench_function("bfs search far", |b| {
+        b.iter(|| black_box(breadth_first_search(&grid, &start, |rc| rc == &goal)));
+    });
+
+    c.bench_function("dfs search far", |b| {
+        b.iter(|| black_box(depth_first_search(&grid, &start, |rc| rc == &goal)));
+    });
+
+    c.bench_function("astar far", |b| {
+        b.iter(|| black_box(grid.shortest_path(&start, &goal)));
+    });
+}
+
+fn large_graph_benchmark(c: &mut Criterion) {
+    let graph = large_graph();
+    let mut group = c.benchmark_group("large graph");
+    group.sample_size(10);
+
+    group.bench_function("bfs traverse sequential", |b| {
+        b.iter(|| black_box(breadth_first_traverse(&graph, &0).count()));
+    });
+
+    group.bench_function("bfs traverse parallel", |b| {
+        b.iter(|| black_box(parallel_breadth_first(&graph, 0).reached()));
+    });
+
+    group.bench_function("connected components sequential", |b| {
+        b.iter(|| black_box(connected_components(&graph).len()));
+    });
+
+    group.bench_function("connected components parallel", |b| {
+        b.iter(|| black_box(parallel_connected_components(&graph)));
+    });
+
+    group.bench_function("page rank parallel", |b| {
+        b.iter(|| black_box(page_rank(&graph, PageRankOptions::default())));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, traversal_benchmark, large_graph_benchmark);
+criterion_main!(benches);
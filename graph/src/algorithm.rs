@@ -1,113 +1,369 @@
+mod flow;
+mod parallel;
+mod shortest_path;
+mod spanning_tree;
+mod structure;
+
+pub use flow::*;
+pub use parallel::*;
+pub use shortest_path::*;
+pub use spanning_tree::*;
+pub use structure::*;
+
 use crate::graph::Graph;
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     hash::Hash,
 };
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// A node and the path that led to it, the start node has no parent.
+///
+/// Searches can return paths hundreds of thousands of nodes long, so cloning,
+/// comparing, converting and dropping walk the chain in a loop instead of
+/// recursing once per node.
+#[derive(Debug)]
 pub struct Path<Node> {
     pub node: Node,
     pub parent: Option<Box<Path<Node>>>,
 }
 
-impl<Node> From<Path<Node>> for Vec<Node> {
-    fn from(path: Path<Node>) -> Self {
-        let mut result = vec![];
+impl<Node> Path<Node> {
+    /// The nodes from the start to `node`, leaving the path as it is
+    pub fn nodes(&self) -> Vec<Node>
+    where
+        Node: Clone,
+    {
+        let mut nodes: Vec<Node> = self.links().map(|path| path.node.clone()).collect();
+        nodes.reverse();
+        nodes
+    }
 
-        if let Some(parent) = path.parent {
-            result.append(&mut (*parent).into())
-        }
+    /// This path, then its parent and so on back to the start
+    fn links(&self) -> impl Iterator<Item = &Path<Node>> {
+        std::iter::successors(Some(self), |path| path.parent.as_deref())
+    }
+}
 
-        result.push(path.node);
+impl<Node: Clone> Clone for Path<Node> {
+    fn clone(&self) -> Self {
+        path_from(self.nodes())
+    }
+}
 
-        result
+impl<Node: PartialEq> PartialEq for Path<Node> {
+    fn eq(&self, other: &Self) -> bool {
+        self.links()
+            .map(|path| &path.node)
+            .eq(other.links().map(|path| &path.node))
     }
 }
 
-pub fn breadth_first_traverse<Node: Clone + Eq + Hash>(
-    graph: &impl Graph<V = Node>,
-    start: &Node,
-) -> Vec<Path<Node>> {
-    let mut frontier: VecDeque<Path<Node>> = VecDeque::new();
-    frontier.push_back(Path {
-        node: start.clone(),
-        parent: None,
-    });
-
-    let mut seen: HashSet<Node> = HashSet::new();
-    let mut paths: Vec<Path<Node>> = vec![];
-
-    while let Some(path) = frontier.pop_front() {
-        if seen.contains(&path.node) {
-            continue;
+impl<Node: Eq> Eq for Path<Node> {}
+
+impl<Node> Drop for Path<Node> {
+    fn drop(&mut self) {
+        let mut parent = self.parent.take();
+        while let Some(mut path) = parent {
+            parent = path.parent.take();
         }
-        seen.insert(path.node.clone());
+    }
+}
 
-        paths.push(path.clone());
+impl<Node: Clone> From<Path<Node>> for Vec<Node> {
+    fn from(path: Path<Node>) -> Self {
+        path.nodes()
+    }
+}
 
-        for edge in graph.out_edges(&path.node) {
-            let (_, next_node) = graph.vertices(&edge);
+/// Nodes in breadth first order from a start node, discovered lazily as the iterator advances.
+///
+/// Each node remembers the node it was discovered from, so the path to anything
+/// visited so far can be rebuilt with [`BreadthFirst::path`] without every
+/// path keeping its own copy of the chain.
+pub struct BreadthFirst<'graph, G: Graph + ?Sized> {
+    graph: &'graph G,
+    start: G::V,
+    frontier: VecDeque<G::V>,
+    parents: HashMap<G::V, G::V>,
+}
 
-            frontier.push_back(Path {
-                node: next_node,
-                parent: Some(Box::new(path.clone())),
-            })
-        }
+/// Nodes in depth first order from a start node, see [`BreadthFirst`]
+pub struct DepthFirst<'graph, G: Graph + ?Sized> {
+    graph: &'graph G,
+    /// (node, the node it was reached from), a node can be on here more than once
+    frontier: Vec<(G::V, Option<G::V>)>,
+    parents: HashMap<G::V, G::V>,
+    visited: HashSet<G::V>,
+}
+
+impl<G: Graph + ?Sized> BreadthFirst<'_, G>
+where
+    G::V: Clone + Eq + Hash,
+{
+    /// the node `node` was discovered from, None for the start and undiscovered nodes
+    pub fn parent(&self, node: &G::V) -> Option<&G::V> {
+        self.parents.get(node)
     }
 
-    paths
+    /// path from the start to an already discovered node
+    pub fn path(&self, node: &G::V) -> Option<Path<G::V>> {
+        self.seen(node)
+            .then(|| path_to(&self.parents, node.clone()))
+    }
+
+    fn seen(&self, node: &G::V) -> bool {
+        node == &self.start || self.parents.contains_key(node)
+    }
 }
 
-pub fn depth_first_traverse<Node: Clone + Eq + Hash>(
-    graph: &impl Graph<V = Node>,
-    start: &Node,
-) -> Vec<Path<Node>> {
-    let mut frontier: Vec<Path<Node>> = Vec::new();
-    frontier.push(Path {
-        node: start.clone(),
-        parent: None,
-    });
-
-    let mut seen: HashSet<Node> = HashSet::new();
-    let mut paths: Vec<Path<Node>> = vec![];
-
-    while let Some(path) = frontier.pop() {
-        if seen.contains(&path.node) {
-            continue;
+impl<G: Graph + ?Sized> Iterator for BreadthFirst<'_, G>
+where
+    G::V: Clone + Eq + Hash,
+{
+    type Item = G::V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.frontier.pop_front()?;
+
+        for edge in self.graph.out_edges(&node) {
+            let (_, next_node) = self.graph.vertices(&edge);
+
+            // marking nodes as they're queued keeps each one on the frontier at most once
+            if !self.seen(&next_node) {
+                self.parents.insert(next_node.clone(), node.clone());
+                self.frontier.push_back(next_node);
+            }
         }
-        seen.insert(path.node.clone());
 
-        paths.push(path.clone());
+        Some(node)
+    }
+}
 
-        for edge in graph.out_edges(&path.node) {
-            let (_, next_node) = graph.vertices(&edge);
+impl<G: Graph + ?Sized> DepthFirst<'_, G>
+where
+    G::V: Clone + Eq + Hash,
+{
+    /// the node `node` was visited from, None for the start and unvisited nodes
+    pub fn parent(&self, node: &G::V) -> Option<&G::V> {
+        self.parents.get(node)
+    }
 
-            frontier.push(Path {
-                node: next_node,
-                parent: Some(Box::new(path.clone())),
-            })
+    /// path from the start to an already visited node
+    pub fn path(&self, node: &G::V) -> Option<Path<G::V>> {
+        self.visited
+            .contains(node)
+            .then(|| path_to(&self.parents, node.clone()))
+    }
+}
+
+impl<G: Graph + ?Sized> Iterator for DepthFirst<'_, G>
+where
+    G::V: Clone + Eq + Hash,
+{
+    type Item = G::V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // a node can only be marked when it's popped, the last route to it wins
+        let (node, parent) = loop {
+            let (node, parent) = self.frontier.pop()?;
+            if !self.visited.contains(&node) {
+                break (node, parent);
+            }
+        };
+
+        self.visited.insert(node.clone());
+        if let Some(parent) = parent {
+            self.parents.insert(node.clone(), parent);
+        }
+
+        for edge in self.graph.out_edges(&node) {
+            let (_, next_node) = self.graph.vertices(&edge);
+
+            if !self.visited.contains(&next_node) {
+                self.frontier.push((next_node, Some(node.clone())));
+            }
         }
+
+        Some(node)
     }
+}
 
-    paths
+pub fn breadth_first_traverse<'graph, G: Graph + ?Sized>(
+    graph: &'graph G,
+    start: &G::V,
+) -> BreadthFirst<'graph, G>
+where
+    G::V: Clone + Eq + Hash,
+{
+    BreadthFirst {
+        graph,
+        start: start.clone(),
+        frontier: VecDeque::from([start.clone()]),
+        parents: HashMap::new(),
+    }
 }
 
+pub fn depth_first_traverse<'graph, G: Graph + ?Sized>(
+    graph: &'graph G,
+    start: &G::V,
+) -> DepthFirst<'graph, G>
+where
+    G::V: Clone + Eq + Hash,
+{
+    DepthFirst {
+        graph,
+        frontier: vec![(start.clone(), None)],
+        parents: HashMap::new(),
+        visited: HashSet::new(),
+    }
+}
+
+/// Stops at the first node matching `is_target`, the rest of the graph isn't explored
 pub fn breadth_first_search<Node: Clone + Eq + Hash>(
-    graph: &impl Graph<V = Node>,
+    graph: &(impl Graph<V = Node> + ?Sized),
     start: &Node,
     is_target: impl Fn(&Node) -> bool,
 ) -> Option<Path<Node>> {
-    breadth_first_traverse(graph, start)
-        .into_iter()
-        .find(|path| is_target(&path.node))
+    let mut nodes = breadth_first_traverse(graph, start);
+    let target = nodes.find(|node| is_target(node))?;
+    nodes.path(&target)
 }
 
+/// Stops at the first node matching `is_target`, the rest of the graph isn't explored
 pub fn depth_first_search<Node: Clone + Eq + Hash>(
-    graph: &impl Graph<V = Node>,
+    graph: &(impl Graph<V = Node> + ?Sized),
     start: &Node,
     is_target: impl Fn(&Node) -> bool,
 ) -> Option<Path<Node>> {
-    depth_first_traverse(graph, start)
+    let mut nodes = depth_first_traverse(graph, start);
+    let target = nodes.find(|node| is_target(node))?;
+    nodes.path(&target)
+}
+
+/// Rebuilds the path to `target` by following `parents` back to a node without one
+pub(crate) fn path_to<Node: Clone + Eq + Hash>(
+    parents: &HashMap<Node, Node>,
+    target: Node,
+) -> Path<Node> {
+    let mut nodes = vec![target];
+    while let Some(parent) = parents.get(nodes.last().unwrap()) {
+        nodes.push(parent.clone());
+    }
+    nodes.reverse();
+
+    path_from(nodes)
+}
+
+/// Panics if `nodes` is empty
+pub(crate) fn path_from<Node>(nodes: Vec<Node>) -> Path<Node> {
+    nodes
         .into_iter()
-        .find(|path| is_target(&path.node))
+        .fold(None, |parent, node| {
+            Some(Path {
+                node,
+                parent: parent.map(Box::new),
+            })
+        })
+        .expect("a path has at least one node")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// n -> n + 1 and n -> 2n, without end
+    struct Doubling;
+
+    impl Graph for Doubling {
+        type V = u64;
+        type E = (u64, u64);
+
+        fn out_vertices(&self, v: &Self::V) -> Vec<Self::V> {
+            vec![v + 1, v * 2]
+        }
+
+        fn in_vertices(&self, v: &Self::V) -> Vec<Self::V> {
+            let mut vertices = vec![v - 1];
+            if v % 2 == 0 {
+                vertices.push(v / 2);
+            }
+            vertices
+        }
+
+        fn out_edges(&self, v: &Self::V) -> Vec<Self::E> {
+            self.out_vertices(v).into_iter().map(|w| (*v, w)).collect()
+        }
+
+        fn in_edges(&self, v: &Self::V) -> Vec<Self::E> {
+            self.in_vertices(v).into_iter().map(|w| (w, *v)).collect()
+        }
+
+        fn edges(&self, from: &Self::V, to: &Self::V) -> Vec<Self::E> {
+            self.out_edges(from)
+                .into_iter()
+                .filter(|(_, w)| w == to)
+                .collect()
+        }
+
+        fn vertices(&self, e: &Self::E) -> (Self::V, Self::V) {
+            *e
+        }
+
+        // there's no listing an infinite graph, and the searches only ever
+        // ask for neighbours, so these are left empty
+        fn all_vertices(&self) -> Vec<Self::V> {
+            vec![]
+        }
+
+        fn all_edges(&self) -> Vec<Self::E> {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn test_breadth_first() {
+        let mut nodes = breadth_first_traverse(&Doubling, &1);
+        assert_eq!(
+            nodes.by_ref().take(8).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 6, 5, 8, 7]
+        );
+        assert_eq!(nodes.parent(&8), Some(&4));
+        assert_eq!(nodes.parent(&1), None);
+        assert_eq!(Vec::from(nodes.path(&6).unwrap()), vec![1, 2, 3, 6]);
+        assert!(nodes.path(&1000).is_none());
+
+        // fewest steps from 1 to 100 is 1 2 3 6 12 24 25 50 100
+        let path = breadth_first_search(&Doubling, &1, |n| *n == 100).unwrap();
+        assert_eq!(Vec::from(path).len(), 9);
+    }
+
+    #[test]
+    fn test_depth_first() {
+        let mut nodes = depth_first_traverse(&Doubling, &1);
+        assert_eq!(
+            nodes.by_ref().take(5).collect::<Vec<_>>(),
+            vec![1, 2, 4, 8, 16]
+        );
+        assert_eq!(Vec::from(nodes.path(&16).unwrap()), vec![1, 2, 4, 8, 16]);
+        assert!(nodes.path(&3).is_none());
+
+        let path = depth_first_search(&Doubling, &1, |n| *n == 64).unwrap();
+        assert_eq!(Vec::from(path), vec![1, 2, 4, 8, 16, 32, 64]);
+    }
+
+    #[test]
+    fn test_long_path() {
+        // 0 <- 1 <- ... <- 199_999, far deeper than the stack would allow recursing into
+        let parents: HashMap<u32, u32> = (1..200_000).map(|n| (n, n - 1)).collect();
+        let path = path_to(&parents, 199_999);
+
+        let copy = path.clone();
+        assert_eq!(copy, path);
+        assert_ne!(copy, path_to(&parents, 199_998));
+
+        let nodes = Vec::from(path);
+        assert_eq!(nodes.len(), 200_000);
+        assert_eq!(nodes.first(), Some(&0));
+        assert_eq!(copy.nodes(), nodes);
+    }
 }
@@ -0,0 +1,400 @@
+use crate::graph::{Graph, Weighted};
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+};
+
+/// Residual capacity below this counts as saturated, so rounding errors don't
+/// turn into endless tiny augmenting paths
+const EPSILON: f64 = 1e-9;
+
+/// The result of a maximum flow computation
+#[derive(Debug, Clone)]
+pub struct MaxFlow<Node, E> {
+    /// total flow from the source to the sink
+    pub value: f64,
+    /// every edge carrying flow, with how much
+    pub flows: Vec<(E, f64)>,
+    /// edges from the source side to the sink side of a minimum cut,
+    /// all saturated, their capacities add up to `value`
+    pub min_cut: Vec<E>,
+    /// nodes on the source side of the minimum cut
+    pub source_side: Vec<Node>,
+}
+
+/// Maximum flow from `source` to `sink` with edge weights as capacities,
+/// augmenting along shortest paths (Edmonds-Karp) in O(VE²)
+///
+/// Panics if `source` and `sink` are the same node
+pub fn edmonds_karp<Node, G>(
+    graph: &G,
+    source: &Node,
+    sink: &Node,
+) -> MaxFlow<Node, <G as Graph>::E>
+where
+    Node: Clone + Eq + Hash,
+    G: Graph<V = Node> + Weighted<E = <G as Graph>::E>,
+    <G as Graph>::E: Clone,
+{
+    let mut network = Residual::new(graph, source, sink);
+    let (s, t) = (network.source, network.sink);
+
+    loop {
+        // the arc used to reach every node, found breadth first
+        let mut via: Vec<Option<usize>> = vec![None; network.arcs_out.len()];
+        let mut frontier = VecDeque::from([s]);
+
+        while let Some(v) = frontier.pop_front() {
+            for arc in &network.arcs_out[v] {
+                let w = network.heads[*arc];
+                if w != s && via[w].is_none() && network.residual[*arc] > EPSILON {
+                    via[w] = Some(*arc);
+                    frontier.push_back(w);
+                }
+            }
+        }
+
+        if via[t].is_none() {
+            break;
+        }
+
+        let mut path = vec![];
+        let mut v = t;
+        while let Some(arc) = via[v] {
+            path.push(arc);
+            v = network.heads[arc ^ 1];
+        }
+        network.augment(&path);
+    }
+
+    network.into_max_flow()
+}
+
+/// Maximum flow like [`edmonds_karp`], pushing blocking flows through a level
+/// graph (Dinic) in O(V²E), which is usually a lot faster
+///
+/// Panics if `source` and `sink` are the same node
+pub fn dinic<Node, G>(graph: &G, source: &Node, sink: &Node) -> MaxFlow<Node, <G as Graph>::E>
+where
+    Node: Clone + Eq + Hash,
+    G: Graph<V = Node> + Weighted<E = <G as Graph>::E>,
+    <G as Graph>::E: Clone,
+{
+    let mut network = Residual::new(graph, source, sink);
+    let (s, t) = (network.source, network.sink);
+    let n = network.arcs_out.len();
+
+    loop {
+        // distance from the source in the residual graph
+        let mut level: Vec<Option<usize>> = vec![None; n];
+        level[s] = Some(0);
+        let mut frontier = VecDeque::from([s]);
+
+        while let Some(v) = frontier.pop_front() {
+            for arc in &network.arcs_out[v] {
+                let w = network.heads[*arc];
+                if level[w].is_none() && network.residual[*arc] > EPSILON {
+                    level[w] = level[v].map(|l| l + 1);
+                    frontier.push_back(w);
+                }
+            }
+        }
+
+        if level[t].is_none() {
+            break;
+        }
+
+        // walk forward along arcs that go one level deeper, backing off from
+        // dead ends, until no path to the sink is left at these levels
+        let mut next_arc = vec![0; n];
+        let mut path: Vec<usize> = vec![];
+        let mut v = s;
+
+        loop {
+            if v == t {
+                network.augment(&path);
+                path.clear();
+                v = s;
+                continue;
+            }
+
+            let admissible = network.arcs_out[v][next_arc[v]..].iter().position(|arc| {
+                let w = network.heads[*arc];
+                network.residual[*arc] > EPSILON && level[w] == level[v].map(|l| l + 1)
+            });
+
+            match admissible {
+                Some(skip) => {
+                    next_arc[v] += skip;
+                    let arc = network.arcs_out[v][next_arc[v]];
+                    path.push(arc);
+                    v = network.heads[arc];
+                }
+                None => {
+                    next_arc[v] = network.arcs_out[v].len();
+                    match path.pop() {
+                        Some(arc) => {
+                            v = network.heads[arc ^ 1];
+                            next_arc[v] += 1;
+                        }
+                        None => break,
+                    }
+                }
+            }
+        }
+    }
+
+    network.into_max_flow()
+}
+
+/// Every edge of the graph as a pair of arcs, `2i` along edge `i` and `2i + 1`
+/// back against it, so `arc ^ 1` is always the opposite arc
+struct Residual<Node, E> {
+    nodes: Vec<Node>,
+    edges: Vec<E>,
+    capacity: Vec<f64>,
+    /// the node each arc points to
+    heads: Vec<usize>,
+    residual: Vec<f64>,
+    arcs_out: Vec<Vec<usize>>,
+    source: usize,
+    sink: usize,
+}
+
+impl<Node: Clone + Eq + Hash, E: Clone> Residual<Node, E> {
+    fn new<G>(graph: &G, source: &Node, sink: &Node) -> Self
+    where
+        G: Graph<V = Node, E = E> + Weighted<E = E>,
+    {
+        assert!(source != sink, "the source is also the sink");
+
+        let mut nodes = graph.all_vertices();
+        let mut index: HashMap<Node, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.clone(), i))
+            .collect();
+        let mut arcs_out = vec![vec![]; nodes.len()];
+
+        let mut index_of = |node: Node, arcs_out: &mut Vec<Vec<usize>>| {
+            *index.entry(node.clone()).or_insert_with(|| {
+                nodes.push(node);
+                arcs_out.push(vec![]);
+                nodes.len() - 1
+            })
+        };
+
+        let source = index_of(source.clone(), &mut arcs_out);
+        let sink = index_of(sink.clone(), &mut arcs_out);
+
+        let edges = graph.all_edges();
+        let mut capacity = Vec::with_capacity(edges.len());
+        let mut heads = Vec::with_capacity(edges.len() * 2);
+        let mut residual = Vec::with_capacity(edges.len() * 2);
+
+        for edge in &edges {
+            let (from, to) = graph.vertices(edge);
+            let from = index_of(from, &mut arcs_out);
+            let to = index_of(to, &mut arcs_out);
+            let weight = graph.weight(edge).max(0.0);
+
+            arcs_out[from].push(heads.len());
+            heads.push(to);
+            residual.push(weight);
+
+            arcs_out[to].push(heads.len());
+            heads.push(from);
+            residual.push(0.0);
+
+            capacity.push(weight);
+        }
+
+        Self {
+            nodes,
+            edges,
+            capacity,
+            heads,
+            residual,
+            arcs_out,
+            source,
+            sink,
+        }
+    }
+
+    /// pushes as much as the path's tightest arc allows
+    fn augment(&mut self, path: &[usize]) {
+        let amount = path
+            .iter()
+            .map(|arc| self.residual[*arc])
+            .fold(f64::INFINITY, f64::min);
+
+        for arc in path {
+            self.residual[*arc] -= amount;
+            self.residual[*arc ^ 1] += amount;
+        }
+    }
+
+    fn into_max_flow(self) -> MaxFlow<Node, E> {
+        // whatever the source can still reach after the last augmenting path
+        let mut reachable = vec![false; self.nodes.len()];
+        reachable[self.source] = true;
+        let mut frontier = VecDeque::from([self.source]);
+        while let Some(v) = frontier.pop_front() {
+            for arc in &self.arcs_out[v] {
+                let w = self.heads[*arc];
+                if !reachable[w] && self.residual[*arc] > EPSILON {
+                    reachable[w] = true;
+                    frontier.push_back(w);
+                }
+            }
+        }
+
+        let mut flows = vec![];
+        let mut min_cut = vec![];
+        let mut value = 0.0;
+
+        for (i, edge) in self.edges.iter().enumerate() {
+            let (from, to) = (self.heads[2 * i + 1], self.heads[2 * i]);
+            // what was pushed along the edge is what could now be pushed back
+            let flow = self.residual[2 * i + 1];
+
+            if flow > EPSILON {
+                flows.push((edge.clone(), flow));
+            }
+            if reachable[from] && !reachable[to] {
+                min_cut.push(edge.clone());
+                value += self.capacity[i];
+            }
+        }
+
+        let source_side = self
+            .nodes
+            .into_iter()
+            .zip(reachable)
+            .filter(|(_, reachable)| *reachable)
+            .map(|(node, _)| node)
+            .collect();
+
+        MaxFlow {
+            value,
+            flows,
+            min_cut,
+            source_side,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DiGraph, EdgeId, NodeId};
+
+    /// the flow network from CLRS, with a maximum flow of 23
+    fn network() -> (DiGraph<&'static str, f64>, HashMap<&'static str, NodeId>) {
+        let mut graph = DiGraph::new();
+        let ids: HashMap<&str, NodeId> = ["s", "v1", "v2", "v3", "v4", "t"]
+            .into_iter()
+            .map(|name| (name, graph.add_node(name)))
+            .collect();
+
+        for (from, to, capacity) in [
+            ("s", "v1", 16.0),
+            ("s", "v2", 13.0),
+            ("v2", "v1", 4.0),
+            ("v1", "v3", 12.0),
+            ("v3", "v2", 9.0),
+            ("v2", "v4", 14.0),
+            ("v4", "v3", 7.0),
+            ("v3", "t", 20.0),
+            ("v4", "t", 4.0),
+        ] {
+            graph.add_edge(ids[from], ids[to], capacity);
+        }
+
+        (graph, ids)
+    }
+
+    fn check(graph: &DiGraph<&str, f64>, flow: &MaxFlow<NodeId, EdgeId>, s: NodeId, t: NodeId) {
+        // capacities hold and whatever goes into a node comes back out
+        let mut balance: HashMap<NodeId, f64> = HashMap::new();
+        for (edge, amount) in &flow.flows {
+            assert!(*amount <= graph.weight(edge) + EPSILON);
+            let (from, to) = graph.vertices(edge);
+            *balance.entry(from).or_default() -= amount;
+            *balance.entry(to).or_default() += amount;
+        }
+        for (node, amount) in balance {
+            match node {
+                _ if node == s => assert_eq!(amount, -flow.value),
+                _ if node == t => assert_eq!(amount, flow.value),
+                _ => assert!(amount.abs() < EPSILON),
+            }
+        }
+
+        let cut: f64 = flow.min_cut.iter().map(|edge| graph.weight(edge)).sum();
+        assert_eq!(cut, flow.value);
+        assert!(flow.source_side.contains(&s));
+        assert!(!flow.source_side.contains(&t));
+    }
+
+    #[test]
+    fn test_max_flow() {
+        let (graph, ids) = network();
+        let (s, t) = (ids["s"], ids["t"]);
+
+        for flow in [edmonds_karp(&graph, &s, &t), dinic(&graph, &s, &t)] {
+            assert_eq!(flow.value, 23.0);
+            check(&graph, &flow, s, t);
+
+            let mut cut: Vec<(&str, &str)> = flow
+                .min_cut
+                .iter()
+                .map(|edge| {
+                    let (from, to) = graph.vertices(edge);
+                    (*graph.node(from).unwrap(), *graph.node(to).unwrap())
+                })
+                .collect();
+            cut.sort();
+            assert_eq!(cut, vec![("v1", "v3"), ("v4", "t"), ("v4", "v3")]);
+        }
+    }
+
+    #[test]
+    fn test_no_path() {
+        let (mut graph, ids) = network();
+        let island = graph.add_node("island");
+
+        for flow in [
+            edmonds_karp(&graph, &ids["s"], &island),
+            dinic(&graph, &ids["s"], &island),
+        ] {
+            assert_eq!(flow.value, 0.0);
+            assert!(flow.flows.is_empty());
+            assert!(flow.min_cut.is_empty());
+            assert_eq!(flow.source_side.len(), 6);
+        }
+    }
+
+    #[test]
+    fn test_undirected_grid() {
+        // a 4x4 grid with every road stored both ways at capacity 1, the corner has two ways out
+        let mut graph = DiGraph::new();
+        let ids: Vec<Vec<NodeId>> = (0..4)
+            .map(|r| (0..4).map(|c| graph.add_node((r, c))).collect())
+            .collect();
+        for r in 0..4 {
+            for c in 0..4 {
+                for (nr, nc) in [(r + 1, c), (r, c + 1)] {
+                    if nr < 4 && nc < 4 {
+                        graph.add_edge(ids[r][c], ids[nr][nc], 1.0);
+                        graph.add_edge(ids[nr][nc], ids[r][c], 1.0);
+                    }
+                }
+            }
+        }
+
+        let (s, t) = (ids[0][0], ids[3][3]);
+        assert_eq!(edmonds_karp(&graph, &s, &t).value, 2.0);
+        assert_eq!(dinic(&graph, &s, &t).value, 2.0);
+    }
+}
@@ -0,0 +1,349 @@
+//! Algorithms for big [`Csr`] graphs that spread the work over every core with rayon
+
+use super::{path_from, Path};
+use crate::Csr;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const UNVISITED: usize = usize::MAX;
+
+/// The tree a breadth first search grows from its start node
+#[derive(Debug, Clone)]
+pub struct BreadthFirstTree {
+    start: usize,
+    parents: Vec<usize>,
+    depths: Vec<usize>,
+    levels: usize,
+}
+
+impl BreadthFirstTree {
+    /// fewest edges from the start to `v`, None if it can't be reached
+    pub fn depth(&self, v: usize) -> Option<usize> {
+        Some(self.depths[v]).filter(|depth| *depth != UNVISITED)
+    }
+
+    /// the node `v` was discovered from, None for the start and unreachable nodes
+    pub fn parent(&self, v: usize) -> Option<usize> {
+        Some(self.parents[v]).filter(|parent| *parent != UNVISITED && v != self.start)
+    }
+
+    pub fn path(&self, v: usize) -> Option<Path<usize>> {
+        self.depth(v)?;
+
+        let mut nodes = vec![v];
+        while let Some(parent) = self.parent(*nodes.last().unwrap()) {
+            nodes.push(parent);
+        }
+        nodes.reverse();
+
+        Some(path_from(nodes))
+    }
+
+    /// number of nodes reachable from the start, the start included
+    pub fn reached(&self) -> usize {
+        self.depths.par_iter().filter(|d| **d != UNVISITED).count()
+    }
+
+    /// number of distinct depths, one more than the deepest node's
+    pub fn levels(&self) -> usize {
+        self.levels
+    }
+}
+
+/// Breadth first search one level at a time, with the nodes of a level expanded in parallel.
+///
+/// Nodes are claimed with an atomic swap of their parent, so when several nodes
+/// of a level lead to the same next node any one of them can end up its parent.
+/// The depths are the same as a sequential search's.
+pub fn parallel_breadth_first(graph: &Csr, start: usize) -> BreadthFirstTree {
+    let n = graph.node_count();
+    let parents: Vec<AtomicUsize> = (0..n).map(|_| AtomicUsize::new(UNVISITED)).collect();
+    let mut depths = vec![UNVISITED; n];
+
+    parents[start].store(start, Ordering::Relaxed);
+    depths[start] = 0;
+    let mut frontier = vec![start];
+    let mut levels = 0;
+
+    while !frontier.is_empty() {
+        levels += 1;
+        for v in &frontier {
+            depths[*v] = levels - 1;
+        }
+
+        frontier = frontier
+            .par_iter()
+            .flat_map_iter(|v| {
+                let parents = &parents;
+                graph.successors(*v).iter().copied().filter(move |next| {
+                    parents[*next]
+                        .compare_exchange(UNVISITED, *v, Ordering::Relaxed, Ordering::Relaxed)
+                        .is_ok()
+                })
+            })
+            .collect();
+    }
+
+    BreadthFirstTree {
+        start,
+        parents: parents.into_iter().map(AtomicUsize::into_inner).collect(),
+        depths,
+        levels,
+    }
+}
+
+/// Settings for [`page_rank`]
+#[derive(Debug, Clone, Copy)]
+pub struct PageRankOptions {
+    /// chance of following an edge rather than jumping to a random node
+    pub damping: f64,
+    /// stop once the ranks move less than this in total over an iteration
+    pub tolerance: f64,
+    pub max_iterations: usize,
+}
+
+impl Default for PageRankOptions {
+    fn default() -> Self {
+        Self {
+            damping: 0.85,
+            tolerance: 1e-6,
+            max_iterations: 100,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PageRank {
+    /// rank of every node, they add up to 1
+    pub ranks: Vec<f64>,
+    pub iterations: usize,
+    /// false if `max_iterations` ran out first
+    pub converged: bool,
+}
+
+/// PageRank by power iteration, every node pulling rank from its in neighbors in parallel.
+///
+/// Nodes without out edges hand their rank out to every node evenly.
+pub fn page_rank(graph: &Csr, options: PageRankOptions) -> PageRank {
+    let n = graph.node_count();
+    if n == 0 {
+        return PageRank {
+            ranks: vec![],
+            iterations: 0,
+            converged: true,
+        };
+    }
+
+    let jump = (1.0 - options.damping) / n as f64;
+    let mut ranks = vec![1.0 / n as f64; n];
+
+    for iteration in 1..=options.max_iterations {
+        // rank every node sends down each of its edges
+        let shares: Vec<f64> = (0..n)
+            .into_par_iter()
+            .map(|v| match graph.out_degree(v) {
+                0 => 0.0,
+                degree => ranks[v] / degree as f64,
+            })
+            .collect();
+        let dangling: f64 = (0..n)
+            .into_par_iter()
+            .filter(|v| graph.out_degree(*v) == 0)
+            .map(|v| ranks[v])
+            .sum();
+        let base = jump + options.damping * dangling / n as f64;
+
+        let next: Vec<f64> = (0..n)
+            .into_par_iter()
+            .map(|v| {
+                let pulled: f64 = graph.predecessors(v).iter().map(|u| shares[*u]).sum();
+                base + options.damping * pulled
+            })
+            .collect();
+
+        let change: f64 = next
+            .par_iter()
+            .zip(&ranks)
+            .map(|(new, old)| (new - old).abs())
+            .sum();
+        ranks = next;
+
+        if change < options.tolerance {
+            return PageRank {
+                ranks,
+                iterations: iteration,
+                converged: true,
+            };
+        }
+    }
+
+    PageRank {
+        ranks,
+        iterations: options.max_iterations,
+        converged: false,
+    }
+}
+
+/// The component of every node, ignoring edge direction, named by its smallest node.
+///
+/// Every edge is unioned into a shared lock free union find in parallel. Roots are
+/// only ever linked under a smaller node, so no two threads can make a cycle.
+pub fn parallel_connected_components(graph: &Csr) -> Vec<usize> {
+    let parents: Vec<AtomicUsize> = (0..graph.node_count()).map(AtomicUsize::new).collect();
+
+    (0..graph.node_count()).into_par_iter().for_each(|v| {
+        for w in graph.successors(v) {
+            union(&parents, v, *w);
+        }
+    });
+
+    (0..graph.node_count())
+        .into_par_iter()
+        .map(|v| find(&parents, v))
+        .collect()
+}
+
+fn find(parents: &[AtomicUsize], mut v: usize) -> usize {
+    loop {
+        let parent = parents[v].load(Ordering::Acquire);
+        if parent == v {
+            return v;
+        }
+
+        // path halving, skipping a parent only ever moves a node closer to its root
+        let grandparent = parents[parent].load(Ordering::Acquire);
+        let _ =
+            parents[v].compare_exchange(parent, grandparent, Ordering::AcqRel, Ordering::Acquire);
+        v = grandparent;
+    }
+}
+
+fn union(parents: &[AtomicUsize], a: usize, b: usize) {
+    loop {
+        let (a, b) = (find(parents, a), find(parents, b));
+        if a == b {
+            return;
+        }
+
+        let (low, high) = (a.min(b), a.max(b));
+        // fails if another thread linked `high` somewhere first, then try again from the new roots
+        if parents[high]
+            .compare_exchange(high, low, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{breadth_first_traverse, connected_components};
+
+    /// `n` nodes with about `n * degree` edges from a fixed xorshift sequence
+    fn random_graph(n: usize, degree: usize) -> Csr {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize % n
+        };
+
+        Csr::from_edges(n, (0..n * degree).map(|_| (random(), random())))
+    }
+
+    #[test]
+    fn test_breadth_first() {
+        let graph = random_graph(2_000, 2);
+        let tree = parallel_breadth_first(&graph, 0);
+
+        let mut sequential = breadth_first_traverse(&graph, &0);
+        let order: Vec<usize> = sequential.by_ref().collect();
+        assert_eq!(tree.reached(), order.len());
+
+        for v in order {
+            let depth = Vec::from(sequential.path(&v).unwrap()).len() - 1;
+            assert_eq!(tree.depth(v), Some(depth));
+
+            let path = Vec::from(tree.path(v).unwrap());
+            assert_eq!(path.len(), depth + 1);
+            assert!(path
+                .windows(2)
+                .all(|pair| graph.successors(pair[0]).contains(&pair[1])));
+        }
+
+        assert_eq!(tree.parent(0), None);
+        assert_eq!(tree.depth(0), Some(0));
+    }
+
+    #[test]
+    fn test_breadth_first_levels() {
+        // 0 -> 1 -> 2 -> 3, and 4 on its own
+        let graph = Csr::from_edges(5, [(0, 1), (1, 2), (2, 3)]);
+        let tree = parallel_breadth_first(&graph, 1);
+
+        assert_eq!(tree.levels(), 3);
+        assert_eq!(tree.reached(), 3);
+        assert_eq!(tree.depth(0), None);
+        assert_eq!(tree.depth(3), Some(2));
+        assert_eq!(tree.parent(3), Some(2));
+        assert!(tree.path(4).is_none());
+    }
+
+    #[test]
+    fn test_page_rank() {
+        // a ring passes rank around evenly
+        let ring = Csr::from_edges(4, [(0, 1), (1, 2), (2, 3), (3, 0)]);
+        let rank = page_rank(&ring, PageRankOptions::default());
+        assert!(rank.converged);
+        assert!(rank.ranks.iter().all(|r| (r - 0.25).abs() < 1e-9));
+
+        // everyone links to 0, which only links back to 1, and nobody links to 2 or 3
+        let star = Csr::from_edges(4, [(1, 0), (2, 0), (3, 0), (0, 1)]);
+        let rank = page_rank(&star, PageRankOptions::default());
+        assert!(rank.converged);
+        assert!((rank.ranks.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(rank.ranks[0] > rank.ranks[1]);
+        assert!(rank.ranks[1] > rank.ranks[2]);
+        assert!((rank.ranks[2] - rank.ranks[3]).abs() < 1e-9);
+
+        let rank = page_rank(
+            &star,
+            PageRankOptions {
+                max_iterations: 2,
+                ..PageRankOptions::default()
+            },
+        );
+        assert!(!rank.converged);
+        assert_eq!(rank.iterations, 2);
+
+        // without damping a node with no way in keeps nothing
+        let rank = page_rank(
+            &star,
+            PageRankOptions {
+                damping: 1.0,
+                ..PageRankOptions::default()
+            },
+        );
+        assert!(rank.ranks[2] < 1e-6);
+    }
+
+    #[test]
+    fn test_connected_components() {
+        let graph = random_graph(5_000, 1);
+        let labels = parallel_connected_components(&graph);
+
+        let components = connected_components(&graph);
+        for component in &components {
+            let smallest = *component.iter().min().unwrap();
+            assert!(component.iter().all(|v| labels[*v] == smallest));
+        }
+
+        let mut distinct = labels.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        assert_eq!(distinct.len(), components.len());
+    }
+}
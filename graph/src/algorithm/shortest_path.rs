@@ -0,0 +1,421 @@
+use super::{path_from, path_to, Path};
+use crate::graph::{Graph, Weighted};
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    hash::Hash,
+};
+
+/// A cycle whose weights add up to less than zero, so the costs of the nodes
+/// on or behind it have no minimum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NegativeCycle<Node> {
+    /// nodes in edge order, the last one has an edge back to the first
+    pub cycle: Vec<Node>,
+}
+
+/// Shortest paths from a single start node to every node reachable from it
+#[derive(Debug, Clone)]
+pub struct ShortestPaths<Node> {
+    costs: HashMap<Node, f64>,
+    parents: HashMap<Node, Node>,
+}
+
+impl<Node: Clone + Eq + Hash> ShortestPaths<Node> {
+    /// total weight of the cheapest path to `node`, None if it can't be reached
+    pub fn cost(&self, node: &Node) -> Option<f64> {
+        self.costs.get(node).copied()
+    }
+
+    pub fn path(&self, node: &Node) -> Option<Path<Node>> {
+        self.costs
+            .contains_key(node)
+            .then(|| path_to(&self.parents, node.clone()))
+    }
+}
+
+/// Shortest paths between every pair of nodes
+#[derive(Debug, Clone)]
+pub struct AllPairs<Node> {
+    nodes: Vec<Node>,
+    index: HashMap<Node, usize>,
+    costs: Vec<Vec<f64>>,
+    next: Vec<Vec<Option<usize>>>,
+}
+
+impl<Node: Clone + Eq + Hash> AllPairs<Node> {
+    /// total weight of the cheapest path, None if `to` can't be reached from `from`
+    pub fn cost(&self, from: &Node, to: &Node) -> Option<f64> {
+        let (i, j) = (*self.index.get(from)?, *self.index.get(to)?);
+        self.next[i][j].map(|_| self.costs[i][j])
+    }
+
+    pub fn path(&self, from: &Node, to: &Node) -> Option<Path<Node>> {
+        let (mut i, j) = (*self.index.get(from)?, *self.index.get(to)?);
+        self.next[i][j]?;
+
+        let mut nodes = vec![self.nodes[i].clone()];
+        while i != j {
+            i = self.next[i][j]?;
+            nodes.push(self.nodes[i].clone());
+        }
+
+        Some(path_from(nodes))
+    }
+}
+
+/// Cheapest path from `start` to the first node matching `is_target`, with its total weight.
+///
+/// Weights must not be negative, see [`bellman_ford`] for graphs where they can be.
+pub fn dijkstra<Node, G>(
+    graph: &G,
+    start: &Node,
+    is_target: impl Fn(&Node) -> bool,
+) -> Option<(Path<Node>, f64)>
+where
+    Node: Clone + Eq + Hash,
+    G: Graph<V = Node> + Weighted<E = <G as Graph>::E>,
+{
+    astar(graph, start, is_target, |_| 0.0)
+}
+
+/// Dijkstra guided by `heuristic`, an estimate of the remaining cost from a node to the target.
+///
+/// The path is only guaranteed to be the cheapest if the heuristic never overestimates.
+/// Weights must not be negative.
+pub fn astar<Node, G>(
+    graph: &G,
+    start: &Node,
+    is_target: impl Fn(&Node) -> bool,
+    heuristic: impl Fn(&Node) -> f64,
+) -> Option<(Path<Node>, f64)>
+where
+    Node: Clone + Eq + Hash,
+    G: Graph<V = Node> + Weighted<E = <G as Graph>::E>,
+{
+    let mut frontier = BinaryHeap::new();
+    frontier.push(Frontier {
+        estimate: heuristic(start),
+        cost: 0.0,
+        node: start.clone(),
+    });
+
+    let mut costs: HashMap<Node, f64> = HashMap::from([(start.clone(), 0.0)]);
+    let mut parents: HashMap<Node, Node> = HashMap::new();
+
+    while let Some(Frontier { cost, node, .. }) = frontier.pop() {
+        // a cheaper way to this node was found after this entry was pushed
+        if cost > costs[&node] {
+            continue;
+        }
+
+        if is_target(&node) {
+            return Some((path_to(&parents, node), cost));
+        }
+
+        for edge in graph.out_edges(&node) {
+            let (_, next_node) = graph.vertices(&edge);
+            let next_cost = cost + graph.weight(&edge);
+
+            if costs.get(&next_node).is_none_or(|c| next_cost < *c) {
+                costs.insert(next_node.clone(), next_cost);
+                parents.insert(next_node.clone(), node.clone());
+                frontier.push(Frontier {
+                    estimate: next_cost + heuristic(&next_node),
+                    cost: next_cost,
+                    node: next_node,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+/// Cheapest paths from `start` to every reachable node. Weights may be negative,
+/// as long as no cycle reachable from `start` adds up to less than zero.
+pub fn bellman_ford<Node, G>(
+    graph: &G,
+    start: &Node,
+) -> Result<ShortestPaths<Node>, NegativeCycle<Node>>
+where
+    Node: Clone + Eq + Hash,
+    G: Graph<V = Node> + Weighted<E = <G as Graph>::E>,
+{
+    let edges: Vec<(Node, Node, f64)> = graph
+        .all_edges()
+        .iter()
+        .map(|edge| {
+            let (from, to) = graph.vertices(edge);
+            (from, to, graph.weight(edge))
+        })
+        .collect();
+    let rounds = graph.all_vertices().len();
+
+    let mut costs: HashMap<Node, f64> = HashMap::from([(start.clone(), 0.0)]);
+    let mut parents: HashMap<Node, Node> = HashMap::new();
+
+    for _ in 1..rounds {
+        let mut changed = false;
+
+        for (from, to, weight) in &edges {
+            if let Some(cost) = costs.get(from) {
+                let next_cost = cost + weight;
+                if costs.get(to).is_none_or(|c| next_cost < *c) {
+                    costs.insert(to.clone(), next_cost);
+                    parents.insert(to.clone(), from.clone());
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    // anything that can still get cheaper is on or behind a negative cycle
+    for (from, to, weight) in &edges {
+        if let Some(cost) = costs.get(from) {
+            if costs.get(to).is_none_or(|c| cost + weight < *c) {
+                parents.insert(to.clone(), from.clone());
+                return Err(NegativeCycle {
+                    cycle: find_cycle(&parents, to.clone(), rounds),
+                });
+            }
+        }
+    }
+
+    Ok(ShortestPaths { costs, parents })
+}
+
+/// Cheapest paths between every pair of nodes in O(V³)
+pub fn floyd_warshall<Node, G>(graph: &G) -> Result<AllPairs<Node>, NegativeCycle<Node>>
+where
+    Node: Clone + Eq + Hash,
+    G: Graph<V = Node> + Weighted<E = <G as Graph>::E>,
+{
+    let nodes = graph.all_vertices();
+    let index: HashMap<Node, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.clone(), i))
+        .collect();
+
+    let n = nodes.len();
+    let mut costs = vec![vec![f64::INFINITY; n]; n];
+    let mut next = vec![vec![None; n]; n];
+
+    for i in 0..n {
+        costs[i][i] = 0.0;
+        next[i][i] = Some(i);
+    }
+
+    for edge in graph.all_edges() {
+        let (from, to) = graph.vertices(&edge);
+        let (i, j) = (index[&from], index[&to]);
+        let weight = graph.weight(&edge);
+
+        // parallel edges, only the cheapest one matters
+        if weight < costs[i][j] {
+            costs[i][j] = weight;
+            next[i][j] = Some(j);
+        }
+    }
+
+    for k in 0..n {
+        for i in 0..n {
+            if next[i][k].is_none() {
+                continue;
+            }
+            for j in 0..n {
+                if next[k][j].is_some() && costs[i][k] + costs[k][j] < costs[i][j] {
+                    costs[i][j] = costs[i][k] + costs[k][j];
+                    next[i][j] = next[i][k];
+                }
+            }
+        }
+    }
+
+    // a node that gets back to itself for less than nothing is on a negative cycle,
+    // which bellman_ford knows how to walk
+    if let Some(i) = (0..n).find(|i| costs[*i][*i] < 0.0) {
+        return match bellman_ford(graph, &nodes[i]) {
+            Err(cycle) => Err(cycle),
+            Ok(_) => unreachable!("floyd_warshall found a negative cycle that bellman_ford didn't"),
+        };
+    }
+
+    Ok(AllPairs {
+        nodes,
+        index,
+        costs,
+        next,
+    })
+}
+
+/// A heap entry, ordered so the one with the lowest estimate is popped first
+pub(crate) struct Frontier<Node> {
+    pub(crate) estimate: f64,
+    pub(crate) cost: f64,
+    pub(crate) node: Node,
+}
+
+impl<Node> PartialEq for Frontier<Node> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<Node> Eq for Frontier<Node> {}
+
+impl<Node> PartialOrd for Frontier<Node> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Node> Ord for Frontier<Node> {
+    /// reversed, so the BinaryHeap pops the lowest estimate first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+/// Walks back `rounds` parents from a node that could still be relaxed, which is
+/// enough to be inside the cycle, then goes around it once
+fn find_cycle<Node: Clone + Eq + Hash>(
+    parents: &HashMap<Node, Node>,
+    mut node: Node,
+    rounds: usize,
+) -> Vec<Node> {
+    for _ in 0..rounds {
+        node = parents[&node].clone();
+    }
+
+    let mut cycle = vec![node.clone()];
+    let mut parent = parents[&node].clone();
+    while parent != node {
+        cycle.push(parent.clone());
+        parent = parents[&parent].clone();
+    }
+    cycle.reverse();
+
+    cycle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::breadth_first_search;
+    use super::*;
+    use crate::{DiGraph, NodeId};
+
+    /// a -1-> b -1-> c -1-> d, plus a shortcut a -5-> d and a dead end e
+    fn chain() -> (DiGraph<char, f64>, Vec<NodeId>) {
+        let mut graph = DiGraph::new();
+        let ids: Vec<NodeId> = "abcde".chars().map(|c| graph.add_node(c)).collect();
+
+        graph.add_edge(ids[0], ids[1], 1.0);
+        graph.add_edge(ids[1], ids[2], 1.0);
+        graph.add_edge(ids[2], ids[3], 1.0);
+        graph.add_edge(ids[0], ids[3], 5.0);
+
+        (graph, ids)
+    }
+
+    fn nodes(path: Option<Path<NodeId>>) -> Vec<NodeId> {
+        path.map(Vec::from).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        let (graph, ids) = chain();
+        let [a, b, c, d, e] = ids[..] else {
+            unreachable!()
+        };
+
+        // fewest edges isn't the cheapest
+        let bfs = breadth_first_search(&graph, &a, |node| node == &d);
+        assert_eq!(nodes(bfs), vec![a, d]);
+
+        let (path, cost) = dijkstra(&graph, &a, |node| node == &d).unwrap();
+        assert_eq!(Vec::from(path), vec![a, b, c, d]);
+        assert_eq!(cost, 3.0);
+
+        assert!(dijkstra(&graph, &a, |node| node == &e).is_none());
+        assert_eq!(dijkstra(&graph, &a, |node| node == &a).unwrap().1, 0.0);
+    }
+
+    #[test]
+    fn test_astar() {
+        let (graph, ids) = chain();
+        let [a, _, _, d, _] = ids[..] else {
+            unreachable!()
+        };
+
+        // remaining hops to d, never more than the real cost
+        let heuristic = |node: &NodeId| (d.index() - node.index()) as f64;
+        let (path, cost) = astar(&graph, &a, |node| node == &d, heuristic).unwrap();
+        assert_eq!(path.node, d);
+        assert_eq!(cost, 3.0);
+    }
+
+    #[test]
+    fn test_bellman_ford() {
+        let (mut graph, ids) = chain();
+        let [a, b, c, d, e] = ids[..] else {
+            unreachable!()
+        };
+        graph.add_edge(a, e, 4.0);
+        graph.add_edge(e, c, -3.0);
+
+        let paths = bellman_ford(&graph, &a).unwrap();
+        assert_eq!(paths.cost(&c), Some(1.0));
+        assert_eq!(paths.cost(&d), Some(2.0));
+        assert_eq!(nodes(paths.path(&d)), vec![a, e, c, d]);
+        assert_eq!(paths.cost(&a), Some(0.0));
+
+        let paths = bellman_ford(&graph, &b).unwrap();
+        assert_eq!(paths.cost(&a), None);
+        assert!(paths.path(&e).is_none());
+
+        graph.add_edge(d, b, -3.0);
+        let NegativeCycle { cycle } = bellman_ford(&graph, &a).unwrap_err();
+        assert_eq!(cycle.len(), 3);
+        for node in [b, c, d] {
+            assert!(cycle.contains(&node));
+        }
+        let weight: f64 = (0..cycle.len())
+            .map(|i| {
+                let edge = graph.find_edge(cycle[i], cycle[(i + 1) % cycle.len()]);
+                graph.weight(&edge.unwrap())
+            })
+            .sum();
+        assert!(weight < 0.0);
+    }
+
+    #[test]
+    fn test_floyd_warshall() {
+        let (mut graph, ids) = chain();
+        let [a, b, c, d, e] = ids[..] else {
+            unreachable!()
+        };
+        graph.add_edge(d, e, 2.0);
+        graph.add_edge(e, a, -1.0);
+        graph.add_edge(a, d, 2.5);
+
+        let all = floyd_warshall(&graph).unwrap();
+        for from in &ids {
+            let paths = bellman_ford(&graph, from).unwrap();
+            for to in &ids {
+                assert_eq!(all.cost(from, to), paths.cost(to));
+            }
+        }
+        assert_eq!(nodes(all.path(&b, &a)), vec![b, c, d, e, a]);
+        assert_eq!(nodes(all.path(&a, &e)), vec![a, d, e]);
+        assert_eq!(nodes(all.path(&c, &c)), vec![c]);
+
+        graph.add_edge(b, b, -0.5);
+        assert_eq!(floyd_warshall(&graph).unwrap_err().cycle, vec![b]);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::graph::{Graph, Weighted};
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    hash::Hash,
+};
+
+/// Minimum spanning forest using Kruskal's algorithm: the cheapest edges that
+/// connect every connected component without forming a cycle, with their total weight.
+///
+/// Edges are undirected, an edge stored in both directions is picked at most once.
+pub fn kruskal<Node, G>(graph: &G) -> (Vec<<G as Graph>::E>, f64)
+where
+    Node: Clone + Eq + Hash,
+    G: Graph<V = Node> + Weighted<E = <G as Graph>::E>,
+{
+    let mut index: HashMap<Node, usize> = HashMap::new();
+    let mut edges: Vec<(f64, usize, usize, <G as Graph>::E)> = graph
+        .all_edges()
+        .into_iter()
+        .map(|edge| {
+            let (from, to) = graph.vertices(&edge);
+            let next = index.len();
+            let from = *index.entry(from).or_insert(next);
+            let next = index.len();
+            let to = *index.entry(to).or_insert(next);
+            (graph.weight(&edge), from, to, edge)
+        })
+        .collect();
+    edges.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut sets = DisjointSets::new(index.len());
+    let mut tree = vec![];
+    let mut total = 0.0;
+
+    for (weight, from, to, edge) in edges {
+        if sets.union(from, to) {
+            tree.push(edge);
+            total += weight;
+        }
+    }
+
+    (tree, total)
+}
+
+/// Minimum spanning forest using Prim's algorithm, see [`kruskal`]
+pub fn prim<Node, G>(graph: &G) -> (Vec<<G as Graph>::E>, f64)
+where
+    Node: Clone + Eq + Hash,
+    G: Graph<V = Node> + Weighted<E = <G as Graph>::E>,
+{
+    let mut nodes: Vec<Node> = graph.all_vertices();
+    let mut index: HashMap<Node, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.clone(), i))
+        .collect();
+
+    // (weight, endpoints) per edge and the edges touching every node, in either direction
+    let mut edges: Vec<Option<<G as Graph>::E>> = vec![];
+    let mut ends: Vec<(f64, usize, usize)> = vec![];
+    let mut touching: Vec<Vec<usize>> = vec![vec![]; nodes.len()];
+
+    for edge in graph.all_edges() {
+        let (from, to) = graph.vertices(&edge);
+        let [from, to] = [from, to].map(|node| {
+            *index.entry(node.clone()).or_insert_with(|| {
+                nodes.push(node);
+                touching.push(vec![]);
+                nodes.len() - 1
+            })
+        });
+
+        touching[from].push(edges.len());
+        touching[to].push(edges.len());
+        ends.push((graph.weight(&edge), from, to));
+        edges.push(Some(edge));
+    }
+
+    let mut in_tree = vec![false; nodes.len()];
+    let mut frontier = BinaryHeap::new();
+    let mut tree = vec![];
+    let mut total = 0.0;
+
+    for root in 0..nodes.len() {
+        if in_tree[root] {
+            continue;
+        }
+
+        in_tree[root] = true;
+        frontier.extend(touching[root].iter().map(|e| Cheapest(ends[*e].0, *e)));
+
+        while let Some(Cheapest(weight, e)) = frontier.pop() {
+            let (_, from, to) = ends[e];
+            let next = match (in_tree[from], in_tree[to]) {
+                (true, false) => to,
+                (false, true) => from,
+                _ => continue,
+            };
+
+            in_tree[next] = true;
+            tree.push(edges[e].take().unwrap());
+            total += weight;
+            frontier.extend(touching[next].iter().map(|e| Cheapest(ends[*e].0, *e)));
+        }
+    }
+
+    (tree, total)
+}
+
+/// Union-find over 0..n with path halving and union by size
+struct DisjointSets {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl DisjointSets {
+    fn new(n: usize) -> Self {
+        Self {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+        }
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    /// false if `a` and `b` were already in the same set
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.size[a] < self.size[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parent[b] = a;
+        self.size[a] += self.size[b];
+        true
+    }
+}
+
+/// (weight, edge) ordered so the BinaryHeap pops the lowest weight first
+struct Cheapest(f64, usize);
+
+impl PartialEq for Cheapest {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Cheapest {}
+
+impl PartialOrd for Cheapest {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Cheapest {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.total_cmp(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DiGraph, EdgeId};
+
+    /// the example from the wikipedia article on Kruskal's algorithm, plus a separate pair
+    fn network() -> DiGraph<char, f64> {
+        let mut graph = DiGraph::new();
+        let ids: HashMap<char, _> = "ABCDEFGXY"
+            .chars()
+            .map(|c| (c, graph.add_node(c)))
+            .collect();
+
+        for (from, to, weight) in [
+            ('A', 'B', 7.0),
+            ('A', 'D', 5.0),
+            ('B', 'C', 8.0),
+            ('B', 'D', 9.0),
+            ('B', 'E', 7.0),
+            ('C', 'E', 5.0),
+            ('D', 'E', 15.0),
+            ('D', 'F', 6.0),
+            ('E', 'F', 8.0),
+            ('E', 'G', 9.0),
+            ('F', 'G', 11.0),
+            ('X', 'Y', 1.0),
+            // the same road stored the other way round as well
+            ('Y', 'X', 1.0),
+        ] {
+            graph.add_edge(ids[&from], ids[&to], weight);
+        }
+
+        graph
+    }
+
+    fn names(graph: &DiGraph<char, f64>, edges: &[EdgeId]) -> Vec<String> {
+        let mut names: Vec<String> = edges
+            .iter()
+            .map(|edge| {
+                let (from, to) = graph.vertices(edge);
+                let mut name = [*graph.node(from).unwrap(), *graph.node(to).unwrap()];
+                name.sort();
+                name.iter().collect()
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_spanning_forest() {
+        let graph = network();
+        let expected = vec!["AB", "AD", "BE", "CE", "DF", "EG", "XY"];
+
+        for (edges, total) in [kruskal(&graph), prim(&graph)] {
+            assert_eq!(total, 40.0);
+            assert_eq!(names(&graph, &edges), expected);
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let graph: DiGraph<(), f64> = DiGraph::new();
+        assert_eq!(kruskal(&graph), (vec![], 0.0));
+        assert_eq!(prim(&graph), (vec![], 0.0));
+    }
+}
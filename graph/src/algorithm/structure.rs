@@ -0,0 +1,563 @@
+use crate::graph::Graph;
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+};
+
+/// A cycle that makes a topological order impossible
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle<Node> {
+    /// nodes in edge order, the last one has an edge back to the first
+    pub cycle: Vec<Node>,
+}
+
+/// Orders the nodes so that every edge goes from an earlier node to a later one,
+/// using Kahn's algorithm of repeatedly taking a node nothing points to anymore
+pub fn topological_sort<Node: Clone + Eq + Hash>(
+    graph: &(impl Graph<V = Node> + ?Sized),
+) -> Result<Vec<Node>, Cycle<Node>> {
+    let indexed = Indexed::directed(graph);
+    let n = indexed.nodes.len();
+
+    let mut in_degree = vec![0; n];
+    for next in indexed.adjacent.iter().flatten() {
+        in_degree[*next] += 1;
+    }
+
+    let mut ready: VecDeque<usize> = (0..n).filter(|v| in_degree[*v] == 0).collect();
+    let mut order = Vec::with_capacity(n);
+
+    while let Some(v) = ready.pop_front() {
+        order.push(v);
+        for next in &indexed.adjacent[v] {
+            in_degree[*next] -= 1;
+            if in_degree[*next] == 0 {
+                ready.push_back(*next);
+            }
+        }
+    }
+
+    if order.len() == n {
+        return Ok(indexed.nodes_at(order));
+    }
+
+    // every node left over still has a predecessor that's left over, so walking
+    // backwards from any of them has to run into a cycle
+    let mut predecessors = vec![vec![]; n];
+    for (v, adjacent) in indexed.adjacent.iter().enumerate() {
+        for next in adjacent {
+            predecessors[*next].push(v);
+        }
+    }
+
+    let mut walked = vec![(0..n).find(|v| in_degree[*v] > 0).unwrap()];
+    let mut position = HashMap::from([(walked[0], 0)]);
+    loop {
+        let v = *walked.last().unwrap();
+        let prev = *predecessors[v]
+            .iter()
+            .find(|prev| in_degree[**prev] > 0)
+            .unwrap();
+
+        if let Some(start) = position.get(&prev) {
+            let mut cycle = walked.split_off(*start);
+            cycle.reverse();
+            return Err(Cycle {
+                cycle: indexed.nodes_at(cycle),
+            });
+        }
+
+        position.insert(prev, walked.len());
+        walked.push(prev);
+    }
+}
+
+/// Same as [`topological_sort`] but orders the nodes by when a depth first search finishes them
+pub fn topological_sort_dfs<Node: Clone + Eq + Hash>(
+    graph: &(impl Graph<V = Node> + ?Sized),
+) -> Result<Vec<Node>, Cycle<Node>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        New,
+        Open,
+        Done,
+    }
+
+    let indexed = Indexed::directed(graph);
+    let n = indexed.nodes.len();
+
+    let mut state = vec![State::New; n];
+    let mut finished = Vec::with_capacity(n);
+
+    for root in 0..n {
+        if state[root] != State::New {
+            continue;
+        }
+
+        state[root] = State::Open;
+        // (node, position of the next edge to follow), the nodes form the current dfs path
+        let mut stack = vec![(root, 0)];
+
+        while let Some((v, i)) = stack.last_mut() {
+            let v = *v;
+
+            match indexed.adjacent[v].get(*i) {
+                Some(next) => {
+                    *i += 1;
+                    match state[*next] {
+                        State::New => {
+                            state[*next] = State::Open;
+                            stack.push((*next, 0));
+                        }
+                        State::Open => {
+                            let start = stack.iter().position(|(w, _)| w == next).unwrap();
+                            let cycle = stack[start..].iter().map(|(w, _)| *w).collect();
+                            return Err(Cycle {
+                                cycle: indexed.nodes_at(cycle),
+                            });
+                        }
+                        State::Done => {}
+                    }
+                }
+                None => {
+                    state[v] = State::Done;
+                    finished.push(v);
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    finished.reverse();
+    Ok(indexed.nodes_at(finished))
+}
+
+/// Groups of nodes that can all reach each other, found with Tarjan's algorithm.
+///
+/// The components come out in reverse topological order: no edge leads from
+/// a component to one listed after it.
+pub fn strongly_connected_components<Node: Clone + Eq + Hash>(
+    graph: &(impl Graph<V = Node> + ?Sized),
+) -> Vec<Vec<Node>> {
+    let indexed = Indexed::directed(graph);
+    let n = indexed.nodes.len();
+
+    let mut discovered: Vec<Option<usize>> = vec![None; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = vec![];
+    let mut components = vec![];
+    let mut counter = 0;
+
+    for root in 0..n {
+        if discovered[root].is_some() {
+            continue;
+        }
+
+        let mut calls = vec![(root, 0)];
+        discovered[root] = Some(counter);
+        low[root] = counter;
+        counter += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some((v, i)) = calls.last_mut() {
+            let v = *v;
+
+            if let Some(next) = indexed.adjacent[v].get(*i).copied() {
+                *i += 1;
+                match discovered[next] {
+                    None => {
+                        discovered[next] = Some(counter);
+                        low[next] = counter;
+                        counter += 1;
+                        stack.push(next);
+                        on_stack[next] = true;
+                        calls.push((next, 0));
+                    }
+                    Some(index) if on_stack[next] => low[v] = low[v].min(index),
+                    Some(_) => {}
+                }
+                continue;
+            }
+
+            calls.pop();
+            if let Some((parent, _)) = calls.last() {
+                low[*parent] = low[*parent].min(low[v]);
+            }
+
+            if Some(low[v]) == discovered[v] {
+                let mut component = vec![];
+                loop {
+                    let w = stack.pop().unwrap();
+                    on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                components.push(indexed.nodes_at(component));
+            }
+        }
+    }
+
+    components
+}
+
+/// Groups of nodes connected to each other, ignoring edge direction
+pub fn connected_components<Node: Clone + Eq + Hash>(
+    graph: &(impl Graph<V = Node> + ?Sized),
+) -> Vec<Vec<Node>> {
+    let indexed = Indexed::undirected(graph);
+    let n = indexed.nodes.len();
+
+    let mut seen = vec![false; n];
+    let mut components = vec![];
+
+    for root in 0..n {
+        if seen[root] {
+            continue;
+        }
+
+        seen[root] = true;
+        let mut component = vec![root];
+        let mut i = 0;
+        while let Some(v) = component.get(i).copied() {
+            i += 1;
+            for next in &indexed.adjacent[v] {
+                if !seen[*next] {
+                    seen[*next] = true;
+                    component.push(*next);
+                }
+            }
+        }
+
+        components.push(indexed.nodes_at(component));
+    }
+
+    components
+}
+
+/// Nodes whose removal would split their connected component, ignoring edge direction
+pub fn articulation_points<Node: Clone + Eq + Hash>(
+    graph: &(impl Graph<V = Node> + ?Sized),
+) -> Vec<Node> {
+    let indexed = Indexed::undirected(graph);
+    let (points, _) = cut_points(&indexed);
+    indexed.nodes_at(points)
+}
+
+/// Edges whose removal would split their connected component, ignoring edge direction.
+///
+/// Edges in both directions between the same two nodes count as one undirected edge.
+pub fn bridges<Node: Clone + Eq + Hash>(
+    graph: &(impl Graph<V = Node> + ?Sized),
+) -> Vec<(Node, Node)> {
+    let indexed = Indexed::undirected(graph);
+    let (_, bridges) = cut_points(&indexed);
+    bridges
+        .into_iter()
+        .map(|(a, b)| (indexed.nodes[a].clone(), indexed.nodes[b].clone()))
+        .collect()
+}
+
+/// Articulation points and bridges of an undirected graph, from the low links of a depth first search
+fn cut_points<Node>(indexed: &Indexed<Node>) -> (Vec<usize>, Vec<(usize, usize)>) {
+    let n = indexed.nodes.len();
+
+    let mut discovered: Vec<Option<usize>> = vec![None; n];
+    let mut low = vec![0; n];
+    let mut is_point = vec![false; n];
+    let mut bridges = vec![];
+    let mut counter = 0;
+
+    for root in 0..n {
+        if discovered[root].is_some() {
+            continue;
+        }
+
+        discovered[root] = Some(counter);
+        low[root] = counter;
+        counter += 1;
+
+        let mut root_children = 0;
+        // (node, its parent in the dfs tree, position of the next neighbor to look at)
+        let mut calls = vec![(root, None, 0)];
+
+        while let Some((v, parent, i)) = calls.last_mut() {
+            let (v, parent) = (*v, *parent);
+
+            if let Some(next) = indexed.adjacent[v].get(*i).copied() {
+                *i += 1;
+                match discovered[next] {
+                    None => {
+                        discovered[next] = Some(counter);
+                        low[next] = counter;
+                        counter += 1;
+                        if v == root {
+                            root_children += 1;
+                        }
+                        calls.push((next, Some(v), 0));
+                    }
+                    // the edge just walked down isn't a way back up
+                    Some(_) if Some(next) == parent => {}
+                    Some(index) => low[v] = low[v].min(index),
+                }
+                continue;
+            }
+
+            calls.pop();
+            if let Some(parent) = parent {
+                low[parent] = low[parent].min(low[v]);
+
+                let parent_discovered = discovered[parent].unwrap();
+                if low[v] > parent_discovered {
+                    bridges.push((parent, v));
+                }
+                if parent != root && low[v] >= parent_discovered {
+                    is_point[parent] = true;
+                }
+            }
+        }
+
+        if root_children > 1 {
+            is_point[root] = true;
+        }
+    }
+
+    let points = (0..n).filter(|v| is_point[*v]).collect();
+    (points, bridges)
+}
+
+/// The graph copied into adjacency lists of indices, so the algorithms above can
+/// keep their bookkeeping in plain vectors
+struct Indexed<Node> {
+    nodes: Vec<Node>,
+    adjacent: Vec<Vec<usize>>,
+}
+
+impl<Node: Clone + Eq + Hash> Indexed<Node> {
+    fn directed(graph: &(impl Graph<V = Node> + ?Sized)) -> Self {
+        Self::new(graph, false)
+    }
+
+    /// every edge in both directions, without duplicates or self loops
+    fn undirected(graph: &(impl Graph<V = Node> + ?Sized)) -> Self {
+        Self::new(graph, true)
+    }
+
+    fn new(graph: &(impl Graph<V = Node> + ?Sized), undirected: bool) -> Self {
+        let mut nodes = graph.all_vertices();
+        let mut index: HashMap<Node, usize> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.clone(), i))
+            .collect();
+        let mut adjacent = vec![vec![]; nodes.len()];
+
+        let mut index_of = |node: Node, adjacent: &mut Vec<Vec<usize>>| {
+            *index.entry(node.clone()).or_insert_with(|| {
+                nodes.push(node);
+                adjacent.push(vec![]);
+                nodes.len() - 1
+            })
+        };
+
+        for edge in graph.all_edges() {
+            let (from, to) = graph.vertices(&edge);
+            let from = index_of(from, &mut adjacent);
+            let to = index_of(to, &mut adjacent);
+
+            adjacent[from].push(to);
+            if undirected {
+                adjacent[to].push(from);
+            }
+        }
+
+        if undirected {
+            for (v, adjacent) in adjacent.iter_mut().enumerate() {
+                adjacent.retain(|next| *next != v);
+                adjacent.sort_unstable();
+                adjacent.dedup();
+            }
+        }
+
+        Self { nodes, adjacent }
+    }
+
+    fn nodes_at(&self, indices: Vec<usize>) -> Vec<Node> {
+        indices.into_iter().map(|i| self.nodes[i].clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AdjacencyList;
+    use std::collections::HashSet;
+
+    fn build(edges: &[(&'static str, &'static str)]) -> AdjacencyList<&'static str> {
+        let mut graph: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (from, to) in edges {
+            graph.entry(from).or_default().push(to);
+        }
+        AdjacencyList::new(graph)
+    }
+
+    /// both directions of every edge
+    fn build_undirected(edges: &[(&'static str, &'static str)]) -> AdjacencyList<&'static str> {
+        let both: Vec<_> = edges
+            .iter()
+            .flat_map(|(a, b)| [(*a, *b), (*b, *a)])
+            .collect();
+        build(&both)
+    }
+
+    fn sets(groups: Vec<Vec<&&'static str>>) -> HashSet<Vec<&'static str>> {
+        groups
+            .into_iter()
+            .map(|group| {
+                let mut group: Vec<&str> = group.into_iter().copied().collect();
+                group.sort();
+                group
+            })
+            .collect()
+    }
+
+    fn assert_topological(order: &[&&str], edges: &[(&str, &str)]) {
+        let position = |node: &str| order.iter().position(|n| **n == node).unwrap();
+        for (from, to) in edges {
+            assert!(position(from) < position(to), "{} before {}", from, to);
+        }
+    }
+
+    fn assert_cycle(cycle: &[&&str], edges: &[(&str, &str)]) {
+        assert!(!cycle.is_empty());
+        for i in 0..cycle.len() {
+            let edge = (*cycle[i], *cycle[(i + 1) % cycle.len()]);
+            assert!(edges.contains(&edge), "{:?} is not an edge", edge);
+        }
+    }
+
+    const BUILD: &[(&str, &str)] = &[
+        ("core", "parser"),
+        ("core", "runtime"),
+        ("parser", "compiler"),
+        ("runtime", "compiler"),
+        ("compiler", "cli"),
+        ("docs", "cli"),
+    ];
+
+    #[test]
+    fn test_topological_sort() {
+        let graph = build(BUILD);
+
+        for order in [
+            topological_sort(&&graph).unwrap(),
+            topological_sort_dfs(&&graph).unwrap(),
+        ] {
+            assert_eq!(order.len(), 6);
+            assert_topological(&order, BUILD);
+        }
+    }
+
+    #[test]
+    fn test_topological_sort_cycle() {
+        let mut edges = BUILD.to_vec();
+        edges.push(("compiler", "core"));
+        edges.push(("cli", "tests"));
+        let graph = build(&edges);
+
+        for cycle in [
+            topological_sort(&&graph).unwrap_err().cycle,
+            topological_sort_dfs(&&graph).unwrap_err().cycle,
+        ] {
+            assert_cycle(&cycle, &edges);
+            assert!(cycle.contains(&&"core") && cycle.contains(&&"compiler"));
+        }
+
+        let graph = build(&[("a", "a")]);
+        assert_eq!(topological_sort(&&graph).unwrap_err().cycle, vec![&"a"]);
+        assert_eq!(topological_sort_dfs(&&graph).unwrap_err().cycle, vec![&"a"]);
+    }
+
+    #[test]
+    fn test_strongly_connected_components() {
+        let edges = [
+            ("a", "b"),
+            ("b", "c"),
+            ("c", "a"),
+            ("c", "d"),
+            ("d", "e"),
+            ("e", "d"),
+            ("e", "f"),
+            ("g", "g"),
+        ];
+        let graph = build(&edges);
+        let components = strongly_connected_components(&&graph);
+
+        assert_eq!(
+            sets(components.clone()),
+            sets(vec![
+                vec![&"a", &"b", &"c"],
+                vec![&"d", &"e"],
+                vec![&"f"],
+                vec![&"g"]
+            ])
+        );
+
+        // reverse topological: whatever a component points to comes before it
+        let position = |node: &str| {
+            components
+                .iter()
+                .position(|component| component.contains(&&node))
+                .unwrap()
+        };
+        assert!(position("f") < position("d"));
+        assert!(position("d") < position("a"));
+    }
+
+    #[test]
+    fn test_connected_components() {
+        // only one direction is stored, the components don't care
+        let graph = build(&[("a", "b"), ("c", "b"), ("d", "e"), ("f", "f")]);
+        assert_eq!(
+            sets(connected_components(&&graph)),
+            sets(vec![vec![&"a", &"b", &"c"], vec![&"d", &"e"], vec![&"f"]])
+        );
+    }
+
+    #[test]
+    fn test_articulation_points_and_bridges() {
+        // two triangles joined by the path c - x - d, with a tail d - y
+        let graph = build_undirected(&[
+            ("a", "b"),
+            ("b", "c"),
+            ("c", "a"),
+            ("c", "x"),
+            ("x", "d"),
+            ("d", "e"),
+            ("e", "f"),
+            ("f", "d"),
+            ("d", "y"),
+        ]);
+
+        let mut points: Vec<&str> = articulation_points(&&graph).into_iter().copied().collect();
+        points.sort();
+        assert_eq!(points, vec!["c", "d", "x"]);
+
+        let mut bridges: Vec<(&str, &str)> = bridges(&&graph)
+            .into_iter()
+            .map(|(a, b)| match a < b {
+                true => (*a, *b),
+                false => (*b, *a),
+            })
+            .collect();
+        bridges.sort();
+        assert_eq!(bridges, vec![("c", "x"), ("d", "x"), ("d", "y")]);
+
+        let cycle = build_undirected(&[("a", "b"), ("b", "c"), ("c", "a")]);
+        assert!(articulation_points(&&cycle).is_empty());
+        assert!(super::bridges(&&cycle).is_empty());
+    }
+}
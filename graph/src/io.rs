@@ -0,0 +1,105 @@
+//! Reading and writing graphs as Graphviz DOT, GraphML and CSV edge lists
+
+mod dot;
+mod edge_list;
+mod graphml;
+
+pub use dot::*;
+pub use edge_list::*;
+
+use crate::{algorithm::Path, graph::Graph};
+use std::{collections::HashMap, hash::Hash};
+
+#[derive(Debug)]
+pub enum ParseError {
+    Syntax {
+        /// 1 based line number
+        line: usize,
+        message: String,
+    },
+    InvalidWeight {
+        line: usize,
+        value: String,
+    },
+}
+
+/// Writes any [`Graph`] in one of the supported formats.
+///
+/// ```
+/// use graph::{io::Export, AdjacencyList};
+/// use std::collections::HashMap;
+///
+/// let graph = AdjacencyList::new(HashMap::from([("a", vec!["b"])]));
+/// let graph = &graph;
+///
+/// let mut csv = Vec::new();
+/// Export::new(&graph, |v| v.to_string()).write_edge_list(&mut csv).unwrap();
+/// assert_eq!(String::from_utf8(csv).unwrap(), "from,to\na,b\n");
+/// ```
+pub struct Export<'graph, G: Graph + ?Sized> {
+    graph: &'graph G,
+    vertex_label: Label<'graph, G::V>,
+    edge_label: Option<Label<'graph, G::E>>,
+    highlighted: Vec<Vec<G::V>>,
+}
+
+type Label<'graph, T> = Box<dyn Fn(&T) -> String + 'graph>;
+
+/// vertices, and edges as (from, to, edge) positions among them
+type Indexed<V, E> = (Vec<V>, Vec<(usize, usize, E)>);
+
+impl<'graph, G: Graph + ?Sized> Export<'graph, G>
+where
+    G::V: Clone + Eq + Hash,
+{
+    /// `vertex_label` names every vertex in the output
+    pub fn new(graph: &'graph G, vertex_label: impl Fn(&G::V) -> String + 'graph) -> Self {
+        Self {
+            graph,
+            vertex_label: Box::new(vertex_label),
+            edge_label: None,
+            highlighted: vec![],
+        }
+    }
+
+    /// Labels the edges too, the label becomes the third column of an edge list
+    pub fn edge_label(mut self, edge_label: impl Fn(&G::E) -> String + 'graph) -> Self {
+        self.edge_label = Some(Box::new(edge_label));
+        self
+    }
+
+    /// Draws the vertices and edges along `path` in a different color, DOT only
+    pub fn highlight(mut self, path: &Path<G::V>) -> Self {
+        self.highlighted.push(path.nodes());
+        self
+    }
+
+    /// Every vertex once, with its position as the id used in the output,
+    /// and every edge as (from, to, edge) positions
+    fn indexed(&self) -> Indexed<G::V, G::E> {
+        let mut vertices = self.graph.all_vertices();
+        let mut index: HashMap<G::V, usize> = vertices
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (v.clone(), i))
+            .collect();
+
+        let edges = self
+            .graph
+            .all_edges()
+            .into_iter()
+            .map(|e| {
+                let (from, to) = self.graph.vertices(&e);
+                let [from, to] = [from, to].map(|v| {
+                    *index.entry(v.clone()).or_insert_with(|| {
+                        vertices.push(v);
+                        vertices.len() - 1
+                    })
+                });
+                (from, to, e)
+            })
+            .collect();
+
+        (vertices, edges)
+    }
+}
@@ -0,0 +1,518 @@
+use super::{Export, ParseError};
+use crate::{graph::Graph, DiGraph, NodeId};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    io::{self, Write},
+};
+
+impl<G: Graph + ?Sized> Export<'_, G>
+where
+    G::V: Clone + Eq + Hash,
+{
+    /// Writes a Graphviz `digraph`, with the highlighted paths drawn in red
+    pub fn write_dot(&self, writer: &mut impl Write) -> io::Result<()> {
+        let (vertices, edges) = self.indexed();
+        let index: HashMap<&G::V, usize> =
+            vertices.iter().enumerate().map(|(i, v)| (v, i)).collect();
+
+        let mut marked_vertices = HashSet::new();
+        let mut marked_edges = HashSet::new();
+        for path in &self.highlighted {
+            let path: Vec<usize> = path.iter().filter_map(|v| index.get(v).copied()).collect();
+            marked_vertices.extend(path.iter().copied());
+            marked_edges.extend(path.windows(2).map(|pair| (pair[0], pair[1])));
+        }
+
+        writeln!(writer, "digraph {{")?;
+
+        for (i, v) in vertices.iter().enumerate() {
+            write!(
+                writer,
+                "  n{} [label={}",
+                i,
+                dot_string(&(self.vertex_label)(v))
+            )?;
+            if marked_vertices.contains(&i) {
+                write!(writer, ", {}", HIGHLIGHT)?;
+            }
+            writeln!(writer, "];")?;
+        }
+
+        for (from, to, e) in &edges {
+            let mut attributes = vec![];
+            if let Some(edge_label) = &self.edge_label {
+                attributes.push(format!("label={}", dot_string(&edge_label(e))));
+            }
+            if marked_edges.contains(&(*from, *to)) {
+                attributes.push(HIGHLIGHT.to_string());
+            }
+
+            write!(writer, "  n{} -> n{}", from, to)?;
+            if !attributes.is_empty() {
+                write!(writer, " [{}]", attributes.join(", "))?;
+            }
+            writeln!(writer, ";")?;
+        }
+
+        writeln!(writer, "}}")
+    }
+}
+
+const HIGHLIGHT: &str = "color=red, penwidth=2";
+
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Reads the common subset of DOT: a `graph` or `digraph` with node and edge
+/// statements, `a -> b -> c` chains and attribute lists.
+///
+/// Vertices are named by their DOT id. An edge's `weight` attribute becomes its
+/// weight, 1 if it has none, and every edge of an undirected `graph` is added in
+/// both directions. `edge [..]` sets defaults for the edges after it. Other
+/// attributes are read and ignored. Subgraphs, ports and
+/// HTML strings aren't supported.
+///
+/// ```
+/// let graph = graph::io::read_dot("digraph { a -> b -> c [weight=2]; d }").unwrap();
+/// assert_eq!(graph.node_count(), 4);
+/// assert_eq!(graph.edge_count(), 2);
+/// ```
+pub fn read_dot(text: &str) -> Result<DiGraph<String, f64>, ParseError> {
+    let tokens = tokenize(text)?;
+    let last_line = text.lines().count().max(1);
+    Parser {
+        tokens,
+        position: 0,
+        last_line,
+        graph: DiGraph::new(),
+        ids: HashMap::new(),
+        edge_defaults: HashMap::new(),
+    }
+    .parse()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Id(String),
+    /// `->`
+    Arrow,
+    /// `--`
+    Line,
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+    Semicolon,
+    Comma,
+    Equals,
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, ParseError> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut chars = text.chars().peekable();
+    let syntax = |line: usize, message: &str| ParseError::Syntax {
+        line,
+        message: message.to_string(),
+    };
+
+    while let Some(c) = chars.next() {
+        let token = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '{' => Token::OpenBrace,
+            '}' => Token::CloseBrace,
+            '[' => Token::OpenBracket,
+            ']' => Token::CloseBracket,
+            ';' => Token::Semicolon,
+            ',' => Token::Comma,
+            '=' => Token::Equals,
+            '#' => {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+                continue;
+            }
+            '/' if chars.next_if_eq(&'/').is_some() => {
+                while chars.next_if(|c| *c != '\n').is_some() {}
+                continue;
+            }
+            '/' if chars.next_if_eq(&'*').is_some() => {
+                loop {
+                    match chars.next() {
+                        Some('*') if chars.next_if_eq(&'/').is_some() => break,
+                        Some('\n') => line += 1,
+                        Some(_) => {}
+                        None => return Err(syntax(line, "unterminated comment")),
+                    }
+                }
+                continue;
+            }
+            '-' if chars.next_if_eq(&'>').is_some() => Token::Arrow,
+            '-' if chars.next_if_eq(&'-').is_some() => Token::Line,
+            '"' => {
+                let start = line;
+                let mut id = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if chars.next_if_eq(&'"').is_some() => id.push('"'),
+                        // a backslash before a newline continues the string on the next line
+                        Some('\\') if chars.next_if_eq(&'\n').is_some() => line += 1,
+                        Some('\n') => {
+                            line += 1;
+                            id.push('\n');
+                        }
+                        Some(c) => id.push(c),
+                        None => return Err(syntax(start, "unterminated string")),
+                    }
+                }
+                Token::Id(id)
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' => {
+                let mut id = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '.')
+                {
+                    id.push(c);
+                }
+                if id == "-" {
+                    return Err(syntax(line, "expected -> or --"));
+                }
+                Token::Id(id)
+            }
+            _ => return Err(syntax(line, &format!("unexpected {:?}", c))),
+        };
+
+        tokens.push((token, line));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    last_line: usize,
+    graph: DiGraph<String, f64>,
+    ids: HashMap<String, NodeId>,
+    /// from `edge [..]` statements so far
+    edge_defaults: HashMap<String, String>,
+}
+
+impl Parser {
+    fn parse(mut self) -> Result<DiGraph<String, f64>, ParseError> {
+        if self.keyword("strict") {
+            self.position += 1;
+        }
+
+        let directed = match self.next() {
+            Some(Token::Id(kind)) if kind.eq_ignore_ascii_case("digraph") => true,
+            Some(Token::Id(kind)) if kind.eq_ignore_ascii_case("graph") => false,
+            _ => return Err(self.error("expected graph or digraph")),
+        };
+
+        if let Some(Token::Id(_)) = self.peek() {
+            self.position += 1;
+        }
+        self.expect(Token::OpenBrace, "expected {")?;
+
+        loop {
+            match self.peek() {
+                Some(Token::CloseBrace) => {
+                    self.position += 1;
+                    break;
+                }
+                Some(Token::Semicolon) => self.position += 1,
+                Some(Token::OpenBrace) => return Err(self.error("subgraphs are not supported")),
+                _ if self.keyword("subgraph") => {
+                    return Err(self.error("subgraphs are not supported"))
+                }
+                // default attributes for the edges after them
+                _ if self.keyword("edge") => {
+                    self.position += 1;
+                    let defaults = self.attributes()?;
+                    self.edge_defaults.extend(defaults);
+                }
+                // the graph's and the nodes' don't change the graph
+                _ if ["graph", "node"].iter().any(|kw| self.keyword(kw)) => {
+                    self.position += 1;
+                    self.attributes()?;
+                }
+                Some(Token::Id(_)) => self.statement(directed)?,
+                _ => return Err(self.error("expected a statement")),
+            }
+        }
+
+        if self.peek().is_some() {
+            return Err(self.error("unexpected text after the graph"));
+        }
+
+        Ok(self.graph)
+    }
+
+    /// `a`, `a [..]`, `a -> b -> c [..]` or `name = value`
+    fn statement(&mut self, directed: bool) -> Result<(), ParseError> {
+        let first = self.id()?;
+
+        if self.peek() == Some(&Token::Equals) {
+            self.position += 1;
+            self.id()?;
+            return Ok(());
+        }
+
+        let mut chain = vec![first];
+        while let Some(op @ (Token::Arrow | Token::Line)) = self.peek() {
+            if (*op == Token::Arrow) != directed {
+                return Err(self.error(match directed {
+                    true => "-- in a digraph",
+                    false => "-> in an undirected graph",
+                }));
+            }
+            self.position += 1;
+            chain.push(self.id()?);
+        }
+
+        let line = self.line();
+        let attributes = self.attributes()?;
+        let weight = match attributes
+            .get("weight")
+            .or_else(|| self.edge_defaults.get("weight"))
+        {
+            Some(value) => value.parse().map_err(|_| ParseError::InvalidWeight {
+                line,
+                value: value.clone(),
+            })?,
+            None => 1.0,
+        };
+
+        let ids: Vec<NodeId> = chain.into_iter().map(|name| self.node(name)).collect();
+        for pair in ids.windows(2) {
+            self.graph.add_edge(pair[0], pair[1], weight);
+            if !directed {
+                self.graph.add_edge(pair[1], pair[0], weight);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// any number of `[name = value, ...]` lists
+    fn attributes(&mut self) -> Result<HashMap<String, String>, ParseError> {
+        let mut attributes = HashMap::new();
+
+        while self.peek() == Some(&Token::OpenBracket) {
+            self.position += 1;
+            loop {
+                match self.peek() {
+                    Some(Token::CloseBracket) => {
+                        self.position += 1;
+                        break;
+                    }
+                    Some(Token::Comma | Token::Semicolon) => self.position += 1,
+                    _ => {
+                        let name = self.id()?;
+                        self.expect(Token::Equals, "expected =")?;
+                        attributes.insert(name, self.id()?);
+                    }
+                }
+            }
+        }
+
+        Ok(attributes)
+    }
+
+    fn node(&mut self, name: String) -> NodeId {
+        let graph = &mut self.graph;
+        *self
+            .ids
+            .entry(name.clone())
+            .or_insert_with(|| graph.add_node(name))
+    }
+
+    fn id(&mut self) -> Result<String, ParseError> {
+        match self.next() {
+            Some(Token::Id(id)) => Ok(id),
+            _ => {
+                self.position -= 1;
+                Err(self.error("expected an id"))
+            }
+        }
+    }
+
+    fn keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Id(id)) if id.eq_ignore_ascii_case(keyword))
+    }
+
+    fn expect(&mut self, token: Token, message: &str) -> Result<(), ParseError> {
+        match self.peek() == Some(&token) {
+            true => {
+                self.position += 1;
+                Ok(())
+            }
+            false => Err(self.error(message)),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    /// always moves forward, even past the end, so callers can step back
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map(|(_, line)| *line)
+            .unwrap_or(self.last_line)
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError::Syntax {
+            line: self.line(),
+            message: message.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{algorithm::dijkstra, AdjacencyList};
+
+    fn names(graph: &DiGraph<String, f64>) -> Vec<(String, String, f64)> {
+        let mut edges: Vec<_> = graph
+            .edge_ids()
+            .map(|e| {
+                let (from, to) = graph.endpoints(e).unwrap();
+                (
+                    graph.node(from).unwrap().clone(),
+                    graph.node(to).unwrap().clone(),
+                    *graph.edge(e).unwrap(),
+                )
+            })
+            .collect();
+        edges.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        edges
+    }
+
+    #[test]
+    fn test_read_dot() {
+        let graph = read_dot(
+            r#"
+            /* flights */
+            strict digraph routes {
+                rankdir = LR;
+                node [shape=box]
+                ATL -> BOS -> "New York" [weight=2.5, color=blue];
+                BOS -> ATL // back
+                # a lone airport
+                MOB [label="Mobile"];
+            }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(
+            names(&graph),
+            vec![
+                ("ATL".to_string(), "BOS".to_string(), 2.5),
+                ("BOS".to_string(), "ATL".to_string(), 1.0),
+                ("BOS".to_string(), "New York".to_string(), 2.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_edge_defaults() {
+        let graph = read_dot(
+            "digraph { a -> b; edge [weight=3]; c -> d; e -> f [weight=1]; edge [color=red] g -> h }",
+        )
+        .unwrap();
+        let weights: Vec<f64> = names(&graph).into_iter().map(|(_, _, w)| w).collect();
+        assert_eq!(weights, vec![1.0, 3.0, 1.0, 3.0]);
+
+        assert!(matches!(
+            read_dot("digraph {\n edge [weight=x]\n a -> b }"),
+            Err(ParseError::InvalidWeight { line: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_read_undirected() {
+        let graph = read_dot("graph { a -- b; b -- c [weight=-1] }").unwrap();
+        assert_eq!(graph.edge_count(), 4);
+        assert_eq!(names(&graph)[0], ("a".to_string(), "b".to_string(), 1.0));
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let line = |text: &str| match read_dot(text) {
+            Err(ParseError::Syntax { line, .. }) => line,
+            Err(ParseError::InvalidWeight { line, .. }) => line,
+            Ok(_) => panic!("{} parsed", text),
+        };
+
+        assert_eq!(line("digraph { a -- b }"), 1);
+        assert_eq!(line("graph {\n a -> b }"), 2);
+        assert_eq!(line("digraph {\n a -> b\n c -> d [weight=x] }"), 3);
+        assert_eq!(line("digraph { subgraph x { a } }"), 1);
+        assert_eq!(line("digraph { a -> }"), 1);
+        assert_eq!(line("digraph {\n a\n"), 2);
+        assert_eq!(line("digraph { \"a }"), 1);
+        assert_eq!(line("tree { }"), 1);
+    }
+
+    #[test]
+    fn test_write_dot() {
+        let graph = AdjacencyList::new(HashMap::from([("a", vec!["b"]), ("b", vec!["c"])]));
+        let graph = &graph;
+
+        let mut dot = Vec::new();
+        Export::new(&graph, |v| format!("say \"{}\"", v))
+            .write_dot(&mut dot)
+            .unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+        assert!(dot.starts_with("digraph {\n"));
+        assert!(dot.contains(r#"[label="say \"a\""];"#));
+        assert_eq!(dot.matches("->").count(), 2);
+        assert!(!dot.contains("color"));
+    }
+
+    #[test]
+    fn test_highlight_round_trip() {
+        let graph = read_dot("digraph { a -> b [weight=1]; b -> c [weight=1]; a -> c [weight=5] }")
+            .unwrap();
+        let id = |name: &str| {
+            graph
+                .node_ids()
+                .find(|v| graph.node(*v).unwrap() == name)
+                .unwrap()
+        };
+        let (path, _) = dijkstra(&graph, &id("a"), |v| *v == id("c")).unwrap();
+
+        let mut dot = Vec::new();
+        Export::new(&graph, |v| graph.node(*v).unwrap().clone())
+            .edge_label(|e| graph.edge(*e).unwrap().to_string())
+            .highlight(&path)
+            .write_dot(&mut dot)
+            .unwrap();
+        let dot = String::from_utf8(dot).unwrap();
+
+        // a, b, c and the two edges between them
+        assert_eq!(dot.matches(HIGHLIGHT).count(), 5);
+        assert!(dot.contains(r#"n0 -> n2 [label="5"];"#));
+
+        let read = read_dot(&dot).unwrap();
+        assert_eq!(read.node_count(), 3);
+        assert_eq!(read.edge_count(), 3);
+    }
+}
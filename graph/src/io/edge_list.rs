@@ -0,0 +1,331 @@
+use super::{Export, ParseError};
+use crate::{graph::Graph, AdjacencyList, DiGraph, NodeId};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    io::{self, Write},
+};
+
+impl<G: Graph + ?Sized> Export<'_, G>
+where
+    G::V: Clone + Eq + Hash,
+{
+    /// Writes a CSV edge list: a `from,to` header, then a line per edge.
+    ///
+    /// Edge labels go in a third column, and vertices without any edges
+    /// get a line of their own so they aren't lost. An empty vertex label is
+    /// an [`io::ErrorKind::InvalidInput`] error, it would read back as a missing vertex.
+    pub fn write_edge_list(&self, writer: &mut impl Write) -> io::Result<()> {
+        let (vertices, edges) = self.indexed();
+        let labels: Vec<String> = vertices.iter().map(|v| (self.vertex_label)(v)).collect();
+        if labels.iter().any(String::is_empty) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "empty vertex label",
+            ));
+        }
+        let labels: Vec<String> = labels.iter().map(|label| csv_field(label)).collect();
+
+        match self.edge_label {
+            Some(_) => writeln!(writer, "from,to,label")?,
+            None => writeln!(writer, "from,to")?,
+        }
+
+        let mut connected = HashSet::new();
+        for (from, to, e) in &edges {
+            connected.extend([*from, *to]);
+            write!(writer, "{},{}", labels[*from], labels[*to])?;
+            if let Some(edge_label) = &self.edge_label {
+                write!(writer, ",{}", csv_field(&edge_label(e)))?;
+            }
+            writeln!(writer)?;
+        }
+
+        for (v, label) in labels.iter().enumerate() {
+            if !connected.contains(&v) {
+                writeln!(writer, "{}", label)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a CSV edge list written by [`Export::write_edge_list`] or by hand.
+///
+/// Every line is `from,to`, or a lone vertex without edges, written `from` or
+/// `from,`. A `from,to` or `source,target` header, blank lines and lines
+/// starting with `#` are skipped, and so are any columns after the second.
+/// Quoted fields can go on over several lines.
+pub fn read_edge_list(text: &str) -> Result<AdjacencyList<String>, ParseError> {
+    let mut graph: HashMap<String, Vec<String>> = HashMap::new();
+
+    for record in records(text) {
+        let (_, mut fields) = record?;
+        let from = fields.remove(0);
+
+        match fields.into_iter().next().filter(|to| !to.is_empty()) {
+            Some(to) => {
+                graph.entry(to.clone()).or_default();
+                graph.entry(from).or_default().push(to);
+            }
+            None => {
+                graph.entry(from).or_default();
+            }
+        }
+    }
+
+    Ok(AdjacencyList::new(graph))
+}
+
+/// Reads an edge list like [`read_edge_list`], with the third column as the
+/// edge weight. Edges without one weigh 1.
+pub fn read_weighted_edge_list(text: &str) -> Result<DiGraph<String, f64>, ParseError> {
+    let mut graph = DiGraph::new();
+    let mut ids: HashMap<String, NodeId> = HashMap::new();
+    let mut id = |graph: &mut DiGraph<String, f64>, name: String| {
+        *ids.entry(name.clone())
+            .or_insert_with(|| graph.add_node(name))
+    };
+
+    for record in records(text) {
+        let (line, fields) = record?;
+        let mut fields = fields.into_iter();
+
+        let from = id(&mut graph, fields.next().unwrap());
+        let Some(to) = fields.next().filter(|to| !to.is_empty()) else {
+            continue;
+        };
+        let to = id(&mut graph, to);
+
+        let weight = match fields.next() {
+            Some(value) => value
+                .parse()
+                .map_err(|_| ParseError::InvalidWeight { line, value })?,
+            None => 1.0,
+        };
+
+        graph.add_edge(from, to, weight);
+    }
+
+    Ok(graph)
+}
+
+/// (first line number, fields) of every record that isn't blank, a comment or
+/// the header. A record runs on over the next lines while a quote is open
+fn records(text: &str) -> impl Iterator<Item = Result<(usize, Vec<String>), ParseError>> + '_ {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+    std::iter::from_fn(move || loop {
+        let (line, first) = lines.next()?;
+        if first.trim().is_empty() || first.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let mut text = first.to_string();
+        let fields = loop {
+            match parse_record(&text) {
+                Err(UNTERMINATED_QUOTE) => match lines.next() {
+                    Some((_, next)) => {
+                        text.push('\n');
+                        text += next;
+                    }
+                    None => break Err(UNTERMINATED_QUOTE),
+                },
+                fields => break fields,
+            }
+        };
+        return Some((line, fields));
+    })
+    .enumerate()
+    .filter_map(|(record, (line, fields))| {
+        let fields = match fields {
+            Ok(fields) => fields,
+            Err(message) => {
+                return Some(Err(ParseError::Syntax {
+                    line,
+                    message: message.to_string(),
+                }))
+            }
+        };
+
+        let is_header = record == 0
+            && fields.len() >= 2
+            && matches!(
+                (
+                    fields[0].to_lowercase().as_str(),
+                    fields[1].to_lowercase().as_str()
+                ),
+                ("from", "to") | ("source", "target")
+            );
+        if is_header {
+            return None;
+        }
+
+        match fields[0].is_empty() {
+            true => Some(Err(ParseError::Syntax {
+                line,
+                message: "missing vertex".to_string(),
+            })),
+            false => Some(Ok((line, fields))),
+        }
+    })
+}
+
+const UNTERMINATED_QUOTE: &str = "unterminated quote";
+
+/// Splits a CSV record, fields can be quoted with `"` and a quote inside one is written `""`
+fn parse_record(line: &str) -> Result<Vec<String>, &'static str> {
+    let mut fields = vec![];
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+
+        let mut field = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            loop {
+                match chars.next() {
+                    Some('"') if chars.next_if_eq(&'"').is_some() => field.push('"'),
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err(UNTERMINATED_QUOTE),
+                }
+            }
+            while chars.next_if(|c| *c == ' ' || *c == '\t').is_some() {}
+            if chars.peek().is_some_and(|c| *c != ',') {
+                return Err("text after a closing quote");
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| *c != ',') {
+                field.push(c);
+            }
+            field.truncate(field.trim_end().len());
+        }
+        fields.push(field);
+
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+/// Quotes a field if it would otherwise be read back differently
+fn csv_field(field: &str) -> String {
+    let plain = !field.is_empty()
+        && field.trim() == field
+        && !field.starts_with('#')
+        && !field.contains([',', '"', '\n', '\r']);
+
+    match plain {
+        true => field.to_string(),
+        false => format!("\"{}\"", field.replace('"', "\"\"")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_record() {
+        assert_eq!(parse_record("a,b").unwrap(), vec!["a", "b"]);
+        assert_eq!(parse_record(" a , b ,").unwrap(), vec!["a", "b", ""]);
+        assert_eq!(
+            parse_record(r#""New York, NY","say ""hi""""#).unwrap(),
+            vec!["New York, NY", r#"say "hi""#]
+        );
+        assert!(parse_record(r#""open"#).is_err());
+        assert!(parse_record(r#""a"b,c"#).is_err());
+    }
+
+    #[test]
+    fn test_read_edge_list() {
+        let graph =
+            read_edge_list("from,to\n# comment\nATL,BOS\nATL,DFW,ignored\n\nMOB\n").unwrap();
+        let graph = &graph;
+
+        let mut vertices: Vec<&str> = graph.all_vertices().iter().map(|v| v.as_str()).collect();
+        vertices.sort();
+        assert_eq!(vertices, vec!["ATL", "BOS", "DFW", "MOB"]);
+        assert_eq!(graph.all_edges().len(), 2);
+        assert_eq!(graph.out_vertices(&&"ATL".to_string()).len(), 2);
+
+        assert!(matches!(
+            read_edge_list("a,b\n,c"),
+            Err(ParseError::Syntax { line: 2, .. })
+        ));
+        assert!(matches!(
+            read_edge_list("a,b\nc,\"open\n\nd,e\n"),
+            Err(ParseError::Syntax { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_read_weighted_edge_list() {
+        let graph = read_weighted_edge_list("a,b,2.5\nb,c\n").unwrap();
+        let weights: Vec<f64> = graph
+            .all_edges()
+            .iter()
+            .map(|e| *graph.edge(*e).unwrap())
+            .collect();
+        assert_eq!(weights, vec![2.5, 1.0]);
+
+        assert!(matches!(
+            read_weighted_edge_list("a,b,1\nb,c,far"),
+            Err(ParseError::InvalidWeight { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut graph: DiGraph<String, f64> = DiGraph::new();
+        let names = ["plain", "with, comma", "\"quoted\"", "alone", "two\nlines"];
+        let ids: Vec<NodeId> = names
+            .iter()
+            .map(|n| graph.add_node(n.to_string()))
+            .collect();
+        graph.add_edge(ids[0], ids[1], 1.5);
+        graph.add_edge(ids[1], ids[2], -2.0);
+        graph.add_edge(ids[4], ids[0], 3.0);
+
+        let mut csv = Vec::new();
+        Export::new(&graph, |v| graph.node(*v).unwrap().clone())
+            .edge_label(|e| graph.edge(*e).unwrap().to_string())
+            .write_edge_list(&mut csv)
+            .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(
+            csv,
+            "from,to,label\nplain,\"with, comma\",1.5\n\"with, comma\",\"\"\"quoted\"\"\",-2\n\"two\nlines\",plain,3\nalone\n"
+        );
+
+        // an empty `to` is a lone vertex, not an edge to a vertex named ""
+        let read = read_weighted_edge_list(&(csv + "no edges,\n")).unwrap();
+        let mut names: Vec<&String> = read.node_ids().map(|v| read.node(v).unwrap()).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "\"quoted\"",
+                "alone",
+                "no edges",
+                "plain",
+                "two\nlines",
+                "with, comma"
+            ]
+        );
+        let weights: Vec<f64> = read.edge_ids().map(|e| *read.edge(e).unwrap()).collect();
+        assert_eq!(weights, vec![1.5, -2.0, 3.0]);
+
+        let read = read_edge_list("a,\n\"b\nc\",a\n").unwrap();
+        let read = &read;
+        assert_eq!(read.all_vertices().len(), 2);
+        assert_eq!(read.all_edges().len(), 1);
+
+        graph.add_node(String::new());
+        let export = Export::new(&graph, |v| graph.node(*v).unwrap().clone());
+        let error = export.write_edge_list(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,100 @@
+use super::Export;
+use crate::graph::Graph;
+use std::{
+    hash::Hash,
+    io::{self, Write},
+};
+
+impl<G: Graph + ?Sized> Export<'_, G>
+where
+    G::V: Clone + Eq + Hash,
+{
+    /// Writes a directed GraphML document with the labels as `label` data
+    pub fn write_graphml(&self, writer: &mut impl Write) -> io::Result<()> {
+        let (vertices, edges) = self.indexed();
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        writeln!(
+            writer,
+            r#"  <key id="v_label" for="node" attr.name="label" attr.type="string"/>"#
+        )?;
+        if self.edge_label.is_some() {
+            writeln!(
+                writer,
+                r#"  <key id="e_label" for="edge" attr.name="label" attr.type="string"/>"#
+            )?;
+        }
+        writeln!(writer, r#"  <graph id="G" edgedefault="directed">"#)?;
+
+        for (i, v) in vertices.iter().enumerate() {
+            writeln!(
+                writer,
+                r#"    <node id="n{}"><data key="v_label">{}</data></node>"#,
+                i,
+                xml_escape(&(self.vertex_label)(v))
+            )?;
+        }
+
+        for (i, (from, to, e)) in edges.iter().enumerate() {
+            write!(
+                writer,
+                r#"    <edge id="e{}" source="n{}" target="n{}""#,
+                i, from, to
+            )?;
+            match &self.edge_label {
+                Some(edge_label) => writeln!(
+                    writer,
+                    r#"><data key="e_label">{}</data></edge>"#,
+                    xml_escape(&edge_label(e))
+                )?,
+                None => writeln!(writer, "/>")?,
+            }
+        }
+
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")
+    }
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AdjacencyList;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_write_graphml() {
+        let graph = AdjacencyList::new(HashMap::from([("R&D", vec!["<ops>"])]));
+        let graph = &graph;
+
+        let mut xml = Vec::new();
+        Export::new(&graph, |v| v.to_string())
+            .edge_label(|_| "reports to".to_string())
+            .write_graphml(&mut xml)
+            .unwrap();
+        let xml = String::from_utf8(xml).unwrap();
+
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains(r#"<data key="v_label">R&amp;D</data>"#));
+        assert!(xml.contains(r#"<data key="v_label">&lt;ops&gt;</data>"#));
+        assert_eq!(xml.matches("<node ").count(), 2);
+        assert_eq!(
+            xml.matches(r#"<data key="e_label">reports to</data></edge>"#)
+                .count(),
+            1
+        );
+        assert!(xml.trim_end().ends_with("</graphml>"));
+    }
+}
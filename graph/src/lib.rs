@@ -1,5 +1,6 @@
 pub mod algorithm;
 mod graph;
+pub mod io;
 mod planning;
 mod query;
 mod types;
@@ -1,5 +1,532 @@
-pub trait Planning {
-    type Solution;
+//! Finding a route from a start state to a goal in any [`Graph`], with the
+//! search strategy picked at runtime
 
-    fn solve(&self) -> Option<Self::Solution>;
+use crate::{
+    algorithm::{path_from, path_to, Frontier, Path},
+    graph::Graph,
+};
+use std::{
+    collections::{BinaryHeap, HashMap, VecDeque},
+    hash::Hash,
+};
+
+/// A graph that knows its own start and goal, like a puzzle whose vertices are its states.
+///
+/// ```
+/// use graph::{Hanoi, Planning, Strategy};
+///
+/// let search = Hanoi::<3>::new().solve(Strategy::AStar);
+/// let moves = Vec::from(search.solution.unwrap().path).len() - 1;
+/// assert_eq!(moves, 7);
+/// ```
+pub trait Planning: Graph {
+    fn problem(&self) -> Problem<'_, Self>;
+
+    fn solve(&self, strategy: Strategy) -> Search<Self::V>
+    where
+        Self::V: Clone + Eq + Hash,
+    {
+        self.problem().solve(strategy)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// fewest steps
+    BreadthFirst,
+    /// fewest steps, keeping only the current path in memory at the price of
+    /// searching the shallow states again for every depth
+    IterativeDeepening,
+    /// cheapest path
+    UniformCost,
+    /// cheapest path, expanding the states the heuristic likes first.
+    /// The heuristic must never overestimate for the path to be the cheapest
+    AStar,
+    /// fewest steps, searching forward from the start and backward from the goal
+    /// until the two meet. Only works for a single goal state, see [`Problem::between`],
+    /// a goal given as a predicate has nothing to search backward from and finds no solution
+    Bidirectional,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// states whose successors were generated
+    pub expanded: usize,
+    /// most states waiting to be expanded at once, for iterative deepening the longest path
+    pub max_frontier: usize,
+}
+
+impl Stats {
+    fn saw_frontier(&mut self, len: usize) {
+        self.max_frontier = self.max_frontier.max(len);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Solution<Node> {
+    pub path: Path<Node>,
+    /// total cost of the moves along the path
+    pub cost: f64,
+}
+
+/// What a search found, the stats are there even when there is no solution
+#[derive(Debug, Clone)]
+pub struct Search<Node> {
+    pub solution: Option<Solution<Node>>,
+    pub stats: Stats,
+}
+
+enum Goal<'graph, Node> {
+    State(Node),
+    Matching(Box<dyn Fn(&Node) -> bool + 'graph>),
+}
+
+type Measure<'graph, T> = Box<dyn Fn(&T) -> f64 + 'graph>;
+
+/// A start state and a goal in a graph. Every move costs 1 and the heuristic
+/// is 0 unless they're set.
+///
+/// ```
+/// use graph::{AdjacencyList, Problem, Strategy};
+/// use std::collections::HashMap;
+///
+/// let graph = AdjacencyList::new(HashMap::from([
+///     ("home", vec!["bus stop", "car"]),
+///     ("bus stop", vec!["office"]),
+///     ("car", vec!["office"]),
+///     ("office", vec![]),
+/// ]));
+/// let graph = &graph;
+///
+/// let search = Problem::new(&graph, &"home", |place| **place == "office")
+///     .cost(|(_, to)| if **to == "car" { 5.0 } else { 1.0 })
+///     .solve(Strategy::UniformCost);
+///
+/// let solution = search.solution.unwrap();
+/// assert_eq!(Vec::from(solution.path), vec![&"home", &"bus stop", &"office"]);
+/// assert_eq!(solution.cost, 2.0);
+/// ```
+pub struct Problem<'graph, G: Graph + ?Sized> {
+    graph: &'graph G,
+    start: G::V,
+    goal: Goal<'graph, G::V>,
+    cost: Measure<'graph, G::E>,
+    heuristic: Measure<'graph, G::V>,
+}
+
+impl<'graph, G: Graph + ?Sized> Problem<'graph, G>
+where
+    G::V: Clone + Eq + Hash,
+{
+    /// Any state matching `is_goal` will do
+    pub fn new(graph: &'graph G, start: G::V, is_goal: impl Fn(&G::V) -> bool + 'graph) -> Self {
+        Self::with_goal(graph, start, Goal::Matching(Box::new(is_goal)))
+    }
+
+    /// From `start` to exactly `goal`, which every strategy including
+    /// [`Strategy::Bidirectional`] can search for
+    pub fn between(graph: &'graph G, start: G::V, goal: G::V) -> Self {
+        Self::with_goal(graph, start, Goal::State(goal))
+    }
+
+    fn with_goal(graph: &'graph G, start: G::V, goal: Goal<'graph, G::V>) -> Self {
+        Self {
+            graph,
+            start,
+            goal,
+            cost: Box::new(|_| 1.0),
+            heuristic: Box::new(|_| 0.0),
+        }
+    }
+
+    /// Cost of taking an edge, must not be negative
+    pub fn cost(mut self, cost: impl Fn(&G::E) -> f64 + 'graph) -> Self {
+        self.cost = Box::new(cost);
+        self
+    }
+
+    /// Estimated cost from a state to the goal, only [`Strategy::AStar`] uses it
+    pub fn heuristic(mut self, heuristic: impl Fn(&G::V) -> f64 + 'graph) -> Self {
+        self.heuristic = Box::new(heuristic);
+        self
+    }
+
+    /// [`Strategy::Bidirectional`] finds no solution and expands nothing unless
+    /// the problem was made with [`Problem::between`]
+    pub fn solve(&self, strategy: Strategy) -> Search<G::V> {
+        let mut stats = Stats::default();
+
+        let solution = match strategy {
+            Strategy::BreadthFirst => self.breadth_first(&mut stats),
+            Strategy::IterativeDeepening => self.iterative_deepening(&mut stats),
+            Strategy::UniformCost => self.best_first(&|_| 0.0, &mut stats),
+            Strategy::AStar => self.best_first(&*self.heuristic, &mut stats),
+            Strategy::Bidirectional => self.bidirectional(&mut stats),
+        };
+
+        Search { solution, stats }
+    }
+
+    fn is_goal(&self, node: &G::V) -> bool {
+        match &self.goal {
+            Goal::State(goal) => node == goal,
+            Goal::Matching(is_goal) => is_goal(node),
+        }
+    }
+
+    /// (next state, cost of the move) for every move out of `node`
+    fn successors(&self, node: &G::V) -> Vec<(G::V, f64)> {
+        self.graph
+            .out_edges(node)
+            .into_iter()
+            .map(|edge| (self.graph.vertices(&edge).1, (self.cost)(&edge)))
+            .collect()
+    }
+
+    /// (previous state, cost of the move) for every move into `node`
+    fn predecessors(&self, node: &G::V) -> Vec<(G::V, f64)> {
+        self.graph
+            .in_edges(node)
+            .into_iter()
+            .map(|edge| (self.graph.vertices(&edge).0, (self.cost)(&edge)))
+            .collect()
+    }
+
+    fn breadth_first(&self, stats: &mut Stats) -> Option<Solution<G::V>> {
+        let mut frontier = VecDeque::from([self.start.clone()]);
+        let mut costs = HashMap::from([(self.start.clone(), 0.0)]);
+        let mut parents = HashMap::new();
+
+        loop {
+            stats.saw_frontier(frontier.len());
+            let node = frontier.pop_front()?;
+            let cost = costs[&node];
+
+            if self.is_goal(&node) {
+                let path = path_to(&parents, node);
+                return Some(Solution { path, cost });
+            }
+
+            stats.expanded += 1;
+            for (next, step) in self.successors(&node) {
+                if !costs.contains_key(&next) {
+                    costs.insert(next.clone(), cost + step);
+                    parents.insert(next.clone(), node.clone());
+                    frontier.push_back(next);
+                }
+            }
+        }
+    }
+
+    fn iterative_deepening(&self, stats: &mut Stats) -> Option<Solution<G::V>> {
+        let mut limit = 0;
+
+        loop {
+            let mut path = vec![(self.start.clone(), 0.0)];
+            let mut cut_off = false;
+
+            if self.depth_limited(&mut path, limit, &mut cut_off, stats) {
+                let cost = path.last().unwrap().1;
+                let path = path_from(path.into_iter().map(|(node, _)| node).collect());
+                return Some(Solution { path, cost });
+            }
+
+            // every route came to a dead end before the limit, a deeper search won't find more
+            if !cut_off {
+                return None;
+            }
+
+            limit += 1;
+        }
+    }
+
+    /// Depth first from the last state of `path`, at most `limit` more moves and never
+    /// through a state already on the path. Leaves the path to the goal in `path` if found.
+    fn depth_limited(
+        &self,
+        path: &mut Vec<(G::V, f64)>,
+        limit: usize,
+        cut_off: &mut bool,
+        stats: &mut Stats,
+    ) -> bool {
+        stats.saw_frontier(path.len());
+        let (node, cost) = path.last().unwrap().clone();
+
+        if self.is_goal(&node) {
+            return true;
+        }
+        if limit == 0 {
+            *cut_off = true;
+            return false;
+        }
+
+        stats.expanded += 1;
+        for (next, step) in self.successors(&node) {
+            if path.iter().any(|(on_path, _)| on_path == &next) {
+                continue;
+            }
+
+            path.push((next, cost + step));
+            if self.depth_limited(path, limit - 1, cut_off, stats) {
+                return true;
+            }
+            path.pop();
+        }
+
+        false
+    }
+
+    /// Uniform cost search, or A* when the heuristic isn't 0
+    fn best_first(
+        &self,
+        heuristic: &dyn Fn(&G::V) -> f64,
+        stats: &mut Stats,
+    ) -> Option<Solution<G::V>> {
+        let mut frontier = BinaryHeap::from([Frontier {
+            estimate: heuristic(&self.start),
+            cost: 0.0,
+            node: self.start.clone(),
+        }]);
+        let mut costs = HashMap::from([(self.start.clone(), 0.0)]);
+        let mut parents = HashMap::new();
+
+        loop {
+            stats.saw_frontier(frontier.len());
+            let Frontier { cost, node, .. } = frontier.pop()?;
+
+            // a cheaper way to this state was found after this entry was pushed
+            if cost > costs[&node] {
+                continue;
+            }
+
+            if self.is_goal(&node) {
+                let path = path_to(&parents, node);
+                return Some(Solution { path, cost });
+            }
+
+            stats.expanded += 1;
+            for (next, step) in self.successors(&node) {
+                let next_cost = cost + step;
+
+                if costs.get(&next).is_none_or(|c| next_cost < *c) {
+                    costs.insert(next.clone(), next_cost);
+                    parents.insert(next.clone(), node.clone());
+                    frontier.push(Frontier {
+                        estimate: next_cost + heuristic(&next),
+                        cost: next_cost,
+                        node: next,
+                    });
+                }
+            }
+        }
+    }
+
+    fn bidirectional(&self, stats: &mut Stats) -> Option<Solution<G::V>> {
+        let Goal::State(goal) = &self.goal else {
+            return None;
+        };
+
+        if self.start == *goal {
+            let path = path_from(vec![self.start.clone()]);
+            return Some(Solution { path, cost: 0.0 });
+        }
+
+        let mut forward = Side::new(self.start.clone());
+        let mut backward = Side::new(goal.clone());
+
+        while !forward.frontier.is_empty() && !backward.frontier.is_empty() {
+            stats.saw_frontier(forward.frontier.len() + backward.frontier.len());
+
+            // growing the smaller side keeps the two about the same size
+            let meeting = match forward.frontier.len() <= backward.frontier.len() {
+                true => forward.expand(|node| self.successors(node), &backward, stats),
+                false => backward.expand(|node| self.predecessors(node), &forward, stats),
+            };
+
+            if let Some(meeting) = meeting {
+                let cost = forward.reached[&meeting].1 + backward.reached[&meeting].1;
+
+                // the backward side's parents lead towards the goal
+                let mut nodes = Vec::from(path_to(&forward.parents, meeting.clone()));
+                let mut node = meeting;
+                while let Some(next) = backward.parents.get(&node) {
+                    nodes.push(next.clone());
+                    node = next.clone();
+                }
+
+                return Some(Solution {
+                    path: path_from(nodes),
+                    cost,
+                });
+            }
+        }
+
+        None
+    }
+}
+
+/// One half of a bidirectional search
+struct Side<Node> {
+    /// the states at the deepest layer reached so far
+    frontier: Vec<Node>,
+    /// (steps, cost) to every state reached from this side
+    reached: HashMap<Node, (usize, f64)>,
+    parents: HashMap<Node, Node>,
+}
+
+impl<Node: Clone + Eq + Hash> Side<Node> {
+    fn new(start: Node) -> Self {
+        Self {
+            frontier: vec![start.clone()],
+            reached: HashMap::from([(start, (0, 0.0))]),
+            parents: HashMap::new(),
+        }
+    }
+
+    /// Expands the whole frontier layer, returning the state where the fewest
+    /// steps in total meet the other side, if any
+    fn expand(
+        &mut self,
+        neighbors: impl Fn(&Node) -> Vec<(Node, f64)>,
+        other: &Self,
+        stats: &mut Stats,
+    ) -> Option<Node> {
+        let mut layer = vec![];
+        let mut meeting: Option<(usize, Node)> = None;
+
+        for node in std::mem::take(&mut self.frontier) {
+            stats.expanded += 1;
+            let (steps, cost) = self.reached[&node];
+
+            for (next, step) in neighbors(&node) {
+                if self.reached.contains_key(&next) {
+                    continue;
+                }
+                self.reached.insert(next.clone(), (steps + 1, cost + step));
+                self.parents.insert(next.clone(), node.clone());
+
+                // a meeting further down the layer can still take fewer steps on the other side
+                if let Some((other_steps, _)) = other.reached.get(&next) {
+                    let total = steps + 1 + other_steps;
+                    if meeting.as_ref().is_none_or(|(best, _)| total < *best) {
+                        meeting = Some((total, next.clone()));
+                    }
+                }
+
+                layer.push(next);
+            }
+        }
+
+        self.frontier = layer;
+        meeting.map(|(_, node)| node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AdjacencyList;
+
+    const STRATEGIES: [Strategy; 5] = [
+        Strategy::BreadthFirst,
+        Strategy::IterativeDeepening,
+        Strategy::UniformCost,
+        Strategy::AStar,
+        Strategy::Bidirectional,
+    ];
+
+    /// a ring of 10 with a shortcut from 0 to 5, in both directions
+    fn ring() -> AdjacencyList<u32> {
+        let mut graph: HashMap<u32, Vec<u32>> = HashMap::new();
+        let mut connect = |a: u32, b: u32| {
+            graph.entry(a).or_default().push(b);
+            graph.entry(b).or_default().push(a);
+        };
+        for i in 0..10 {
+            connect(i, (i + 1) % 10);
+        }
+        connect(0, 5);
+
+        AdjacencyList::new(graph)
+    }
+
+    #[test]
+    fn test_fewest_steps() {
+        let graph = ring();
+        let graph = &graph;
+
+        for strategy in STRATEGIES {
+            let search = Problem::between(&graph, &1, &6).solve(strategy);
+            let solution = search.solution.unwrap();
+
+            // 1 0 5 6 and 1 2 3 4 5 6 are both ways there, only the first is shortest
+            assert_eq!(
+                Vec::from(solution.path),
+                vec![&1, &0, &5, &6],
+                "{:?}",
+                strategy
+            );
+            assert_eq!(solution.cost, 3.0);
+            assert!(search.stats.expanded > 0);
+            assert!(search.stats.max_frontier > 0);
+        }
+    }
+
+    #[test]
+    fn test_cheapest() {
+        let graph = ring();
+        let graph = &graph;
+
+        // the shortcut is a long way round, and so is going backwards past 0
+        let problem = Problem::between(&graph, &1, &6).cost(|(from, to)| match (**from, **to) {
+            (0, 5) | (5, 0) => 10.0,
+            (0, 9) | (9, 0) => 2.0,
+            _ => 1.0,
+        });
+
+        for strategy in [Strategy::UniformCost, Strategy::AStar] {
+            let solution = problem.solve(strategy).solution.unwrap();
+            assert_eq!(
+                Vec::from(solution.path),
+                vec![&1, &2, &3, &4, &5, &6],
+                "{:?}",
+                strategy
+            );
+            assert_eq!(solution.cost, 5.0);
+        }
+
+        // fewest steps, whatever they cost
+        let solution = problem.solve(Strategy::BreadthFirst).solution.unwrap();
+        assert_eq!(solution.cost, 12.0);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let graph = ring();
+        let graph = &graph;
+
+        for strategy in STRATEGIES {
+            let search = Problem::between(&graph, &1, &42).solve(strategy);
+            assert!(search.solution.is_none(), "{:?}", strategy);
+        }
+
+        let search = Problem::new(&graph, &3, |_| false).solve(Strategy::BreadthFirst);
+        assert_eq!(search.stats.expanded, 10);
+
+        let search = Problem::new(&graph, &3, |v| **v == 3).solve(Strategy::IterativeDeepening);
+        assert_eq!(Vec::from(search.solution.unwrap().path), vec![&3]);
+    }
+
+    #[test]
+    fn test_bidirectional_needs_goal_state() {
+        let graph = ring();
+        let graph = &graph;
+
+        let search = Problem::new(&graph, &1, |v| **v == 6).solve(Strategy::Bidirectional);
+        assert!(search.solution.is_none());
+        assert_eq!(search.stats, Stats::default());
+
+        let search = Problem::between(&graph, &1, &6).solve(Strategy::Bidirectional);
+        assert!(search.solution.is_some());
+    }
 }
@@ -1,66 +1,407 @@
 use crate::Graph;
+use std::{collections::HashSet, hash::Hash, iter, mem};
 
+/// Entry point of a lazy, Gremlin style traversal over any [`Graph`].
+///
+/// ```
+/// use graph::{AdjacencyList, Query};
+/// use std::collections::HashMap;
+///
+/// let graph = AdjacencyList::new(HashMap::from([("ATL", vec!["BOS", "DFW"]), ("BOS", vec!["DFW"])]));
+/// let graph = &graph;
+///
+/// let mut into_dfw: Vec<_> = Query::new(&graph)
+///     .vertices()
+///     .out_edges()
+///     .filter(|(_, to)| to == &&"DFW")
+///     .from()
+///     .collect();
+/// into_dfw.sort();
+/// assert_eq!(into_dfw, vec![&"ATL", &"BOS"]);
+/// ```
+///
+/// Every step wraps the previous one in another iterator, so nothing is
+/// looked up until the query is iterated, and `limit` stops the work early.
 pub struct Query<'graph, V, E> {
-    graph: Box<&'graph dyn Graph<V = V, E = E>>,
+    graph: &'graph dyn Graph<V = V, E = E>,
 }
 
-impl<'graph, V, E> Query<'graph, V, E> {
+/// A vertex or an edge visited by a query, see [`VertexQuery::path`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Element<V, E> {
+    Vertex(V),
+    Edge(E),
+}
+
+/// One walker moving through the graph: where it is now and everywhere it's been
+struct Traverser<T, V, E> {
+    current: T,
+    path: Vec<Element<V, E>>,
+}
+
+type Traversers<'graph, T, V, E> = Box<dyn Iterator<Item = Traverser<T, V, E>> + 'graph>;
+
+/// The steps of a query so far. They're compiled into traversers once the query
+/// is consumed, which is when it's known whether they need to keep their paths
+type Steps<'graph, T, V, E> = Box<dyn FnOnce(bool) -> Traversers<'graph, T, V, E> + 'graph>;
+
+/// A query that hasn't been compiled yet, or one that's being iterated
+enum Stage<'graph, T, V, E> {
+    Steps(Steps<'graph, T, V, E>),
+    Running(Traversers<'graph, T, V, E>),
+}
+
+/// A query whose traversers are on vertices, iterating it gives those vertices
+pub struct VertexQuery<'graph, V, E> {
+    graph: &'graph dyn Graph<V = V, E = E>,
+    stage: Stage<'graph, V, V, E>,
+}
+
+/// A query whose traversers are on edges, iterating it gives those edges
+pub struct EdgeQuery<'graph, V, E> {
+    graph: &'graph dyn Graph<V = V, E = E>,
+    stage: Stage<'graph, E, V, E>,
+}
+
+/// A repeated sequence of steps, see [`VertexQuery::repeat`]
+pub struct Repeat<'graph, V, E, F> {
+    query: VertexQuery<'graph, V, E>,
+    step: F,
+}
+
+impl<'graph, V: Clone + 'graph, E: Clone + 'graph> Query<'graph, V, E> {
     pub fn new(graph: &'graph impl Graph<V = V, E = E>) -> Self {
-        Self {
-            graph: Box::new(graph),
+        Self { graph }
+    }
+
+    /// starts from every vertex of the graph
+    pub fn vertices(&self) -> VertexQuery<'graph, V, E> {
+        self.starting_at(self.graph.all_vertices())
+    }
+
+    /// starts from every edge of the graph
+    pub fn edges(&self) -> EdgeQuery<'graph, V, E> {
+        let edges = self.graph.all_edges();
+        EdgeQuery {
+            graph: self.graph,
+            stage: Stage::Steps(Box::new(move |record_paths| {
+                Box::new(edges.into_iter().map(move |e| Traverser {
+                    path: start_path(record_paths, Element::Edge(e.clone())),
+                    current: e,
+                }))
+            })),
+        }
+    }
+
+    /// starts from the given vertices only
+    pub fn starting_at(
+        &self,
+        vertices: impl IntoIterator<Item = V> + 'graph,
+    ) -> VertexQuery<'graph, V, E> {
+        VertexQuery {
+            graph: self.graph,
+            stage: Stage::Steps(Box::new(move |record_paths| {
+                Box::new(vertices.into_iter().map(move |v| Traverser {
+                    path: start_path(record_paths, Element::Vertex(v.clone())),
+                    current: v,
+                }))
+            })),
+        }
+    }
+}
+
+impl<'graph, V: Clone + 'graph, E: Clone + 'graph> VertexQuery<'graph, V, E> {
+    /// moves along outgoing edges to the vertices they point to
+    pub fn out(self) -> VertexQuery<'graph, V, E> {
+        let graph = self.graph;
+        VertexQuery {
+            graph,
+            stage: self.stage.then(move |traversers, record_paths| {
+                expand(
+                    traversers,
+                    graph,
+                    |g, v| g.out_vertices(v),
+                    Element::Vertex,
+                    record_paths,
+                )
+            }),
+        }
+    }
+
+    /// moves backwards along incoming edges to the vertices they come from
+    pub fn in_(self) -> VertexQuery<'graph, V, E> {
+        let graph = self.graph;
+        VertexQuery {
+            graph,
+            stage: self.stage.then(move |traversers, record_paths| {
+                expand(
+                    traversers,
+                    graph,
+                    |g, v| g.in_vertices(v),
+                    Element::Vertex,
+                    record_paths,
+                )
+            }),
         }
     }
 
-    pub fn vertices(&self) -> QueryIter<V> {
-        QueryIter {
-            items: self.graph.all_vertices(),
+    pub fn out_edges(self) -> EdgeQuery<'graph, V, E> {
+        let graph = self.graph;
+        EdgeQuery {
+            graph,
+            stage: self.stage.then(move |traversers, record_paths| {
+                expand(
+                    traversers,
+                    graph,
+                    |g, v| g.out_edges(v),
+                    Element::Edge,
+                    record_paths,
+                )
+            }),
         }
     }
 
-    pub fn edges(&self) -> QueryIter<E> {
-        QueryIter {
-            items: self.graph.all_edges(),
+    pub fn in_edges(self) -> EdgeQuery<'graph, V, E> {
+        let graph = self.graph;
+        EdgeQuery {
+            graph,
+            stage: self.stage.then(move |traversers, record_paths| {
+                expand(
+                    traversers,
+                    graph,
+                    |g, v| g.in_edges(v),
+                    Element::Edge,
+                    record_paths,
+                )
+            }),
         }
     }
+
+    /// keeps the traversers whose vertex matches `predicate`
+    pub fn filter(self, predicate: impl Fn(&V) -> bool + 'graph) -> VertexQuery<'graph, V, E> {
+        VertexQuery {
+            graph: self.graph,
+            stage: self.stage.then(move |traversers, _| {
+                Box::new(traversers.filter(move |t| predicate(&t.current)))
+            }),
+        }
+    }
+
+    /// keeps only the first traverser to reach each vertex
+    pub fn dedup(self) -> VertexQuery<'graph, V, E>
+    where
+        V: Eq + Hash,
+    {
+        VertexQuery {
+            graph: self.graph,
+            stage: self.stage.then(|traversers, _| {
+                let mut seen = HashSet::new();
+                Box::new(traversers.filter(move |t| seen.insert(t.current.clone())))
+            }),
+        }
+    }
+
+    /// stops after `n` traversers
+    pub fn limit(self, n: usize) -> VertexQuery<'graph, V, E> {
+        VertexQuery {
+            graph: self.graph,
+            stage: self
+                .stage
+                .then(move |traversers, _| Box::new(traversers.take(n))),
+        }
+    }
+
+    /// repeats the steps in `step`, as many times as given to [`Repeat::times`]
+    ///
+    /// `query.repeat(|q| q.out()).times(2)` is the same as `query.out().out()`
+    pub fn repeat<F>(self, step: F) -> Repeat<'graph, V, E, F>
+    where
+        F: Fn(VertexQuery<'graph, V, E>) -> VertexQuery<'graph, V, E>,
+    {
+        Repeat { query: self, step }
+    }
+
+    /// the vertices and edges each traverser went through, starting where the query started
+    ///
+    /// Only a query ending in `path` keeps paths, so one that's already been
+    /// partly iterated gives empty paths for the rest
+    pub fn path(self) -> impl Iterator<Item = Vec<Element<V, E>>> + 'graph {
+        self.stage.compile(true).map(|t| t.path)
+    }
 }
 
-#[allow(dead_code)]
-pub struct VertexIter<V> {
-    vertices: Vec<V>,
+impl<'graph, V: Clone + 'graph, E: Clone + 'graph> EdgeQuery<'graph, V, E> {
+    /// moves to the vertex each edge comes from
+    pub fn from(self) -> VertexQuery<'graph, V, E> {
+        let graph = self.graph;
+        VertexQuery {
+            graph,
+            stage: self.stage.then(move |traversers, record_paths| {
+                expand(
+                    traversers,
+                    graph,
+                    |g, e| vec![g.vertices(e).0],
+                    Element::Vertex,
+                    record_paths,
+                )
+            }),
+        }
+    }
+
+    /// moves to the vertex each edge points to
+    pub fn to(self) -> VertexQuery<'graph, V, E> {
+        let graph = self.graph;
+        VertexQuery {
+            graph,
+            stage: self.stage.then(move |traversers, record_paths| {
+                expand(
+                    traversers,
+                    graph,
+                    |g, e| vec![g.vertices(e).1],
+                    Element::Vertex,
+                    record_paths,
+                )
+            }),
+        }
+    }
+
+    /// keeps the traversers whose edge matches `predicate`
+    pub fn filter(self, predicate: impl Fn(&E) -> bool + 'graph) -> EdgeQuery<'graph, V, E> {
+        EdgeQuery {
+            graph: self.graph,
+            stage: self.stage.then(move |traversers, _| {
+                Box::new(traversers.filter(move |t| predicate(&t.current)))
+            }),
+        }
+    }
+
+    /// keeps only the first traverser to reach each edge
+    pub fn dedup(self) -> EdgeQuery<'graph, V, E>
+    where
+        E: Eq + Hash,
+    {
+        EdgeQuery {
+            graph: self.graph,
+            stage: self.stage.then(|traversers, _| {
+                let mut seen = HashSet::new();
+                Box::new(traversers.filter(move |t| seen.insert(t.current.clone())))
+            }),
+        }
+    }
+
+    /// stops after `n` traversers
+    pub fn limit(self, n: usize) -> EdgeQuery<'graph, V, E> {
+        EdgeQuery {
+            graph: self.graph,
+            stage: self
+                .stage
+                .then(move |traversers, _| Box::new(traversers.take(n))),
+        }
+    }
+
+    /// see [`VertexQuery::path`]
+    pub fn path(self) -> impl Iterator<Item = Vec<Element<V, E>>> + 'graph {
+        self.stage.compile(true).map(|t| t.path)
+    }
 }
 
-#[allow(dead_code)]
-pub struct EdgeIter<E> {
-    edges: Vec<E>,
+impl<'graph, V, E, F> Repeat<'graph, V, E, F>
+where
+    F: Fn(VertexQuery<'graph, V, E>) -> VertexQuery<'graph, V, E>,
+{
+    pub fn times(self, n: usize) -> VertexQuery<'graph, V, E> {
+        (0..n).fold(self.query, |query, _| (self.step)(query))
+    }
+}
+
+impl<'graph, T: 'graph, V: 'graph, E: 'graph> Stage<'graph, T, V, E> {
+    fn compile(self, record_paths: bool) -> Traversers<'graph, T, V, E> {
+        match self {
+            Stage::Steps(steps) => steps(record_paths),
+            Stage::Running(traversers) => traversers,
+        }
+    }
+
+    /// adds `step` after the steps so far
+    fn then<U>(
+        self,
+        step: impl FnOnce(Traversers<'graph, T, V, E>, bool) -> Traversers<'graph, U, V, E> + 'graph,
+    ) -> Stage<'graph, U, V, E> {
+        Stage::Steps(Box::new(move |record_paths| {
+            step(self.compile(record_paths), record_paths)
+        }))
+    }
+
+    /// the traversers, compiling the steps without paths the first time
+    fn running(&mut self) -> &mut Traversers<'graph, T, V, E> {
+        if let Stage::Steps(_) = self {
+            let stage = mem::replace(self, Stage::Running(Box::new(iter::empty())));
+            *self = Stage::Running(stage.compile(false));
+        }
+        match self {
+            Stage::Running(traversers) => traversers,
+            Stage::Steps(_) => unreachable!(),
+        }
+    }
 }
 
-pub struct QueryIter<T> {
-    items: Vec<T>,
+impl<'graph, V: 'graph, E: 'graph> Iterator for VertexQuery<'graph, V, E> {
+    type Item = V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.stage.running().next().map(|t| t.current)
+    }
 }
 
-impl<T> Iterator for QueryIter<T> {
-    type Item = T;
+impl<'graph, V: 'graph, E: 'graph> Iterator for EdgeQuery<'graph, V, E> {
+    type Item = E;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.items.pop()
+        self.stage.running().next().map(|t| t.current)
     }
 }
 
-impl<T> QueryIter<T> {
-    // pub fn in_edges<E>(&self) -> QueryIter<E> {
-    //     QueryIter { items: todo!() }
-    // }
+/// Replaces every traverser with one for each of its `next` elements
+fn expand<'graph, A, B, V, E>(
+    traversers: Traversers<'graph, A, V, E>,
+    graph: &'graph dyn Graph<V = V, E = E>,
+    next: impl Fn(&'graph dyn Graph<V = V, E = E>, &A) -> Vec<B> + 'graph,
+    element: fn(B) -> Element<V, E>,
+    record_paths: bool,
+) -> Traversers<'graph, B, V, E>
+where
+    A: 'graph,
+    B: Clone + 'graph,
+    V: Clone + 'graph,
+    E: Clone + 'graph,
+{
+    Box::new(traversers.flat_map(move |t| {
+        next(graph, &t.current).into_iter().map(move |b| {
+            let path = match record_paths {
+                true => [t.path.as_slice(), &[element(b.clone())]].concat(),
+                false => vec![],
+            };
+            Traverser { current: b, path }
+        })
+    }))
+}
+
+/// The path of a traverser starting on `element`, empty unless paths are recorded
+fn start_path<V, E>(record_paths: bool, element: Element<V, E>) -> Vec<Element<V, E>> {
+    match record_paths {
+        true => vec![element],
+        false => vec![],
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use super::*;
     use crate::AdjacencyList;
     use std::collections::HashMap;
 
-    #[test]
-    fn test_query() {
-        let _graph = AdjacencyList::new(HashMap::from([
+    fn airports() -> AdjacencyList<&'static str> {
+        AdjacencyList::new(HashMap::from([
             ("ATL", vec!["BOS", "DFW", "MOB"]),
             ("BOS", vec!["ATL", "DFW"]),
             ("MOB", vec!["ATL"]),
@@ -79,20 +420,132 @@ mod tests {
                     "ATL", "AUS", "BOS", "HOU", "LAX", "LIT", "MSY", "OKC", "SHV", "SFO",
                 ],
             ),
-        ]));
-
-        // let query = Query::new(&graph)
-        //     .vertices()
-        //     .filter(|v| v == &"asdf")
-        //     .in_edges()
-        //     .filter(|e| e.r_type = "parent")
-        //     .map(|e| e.from());
-
-        // let query = Query::new(&graph)
-        //     .edges()
-        //     .filter(|(from, to)| from == &"asdf")
-        //     .in_vertices()
-        //     .filter(|v| v == &"blah")
-        //     ;
+        ]))
+    }
+
+    fn sorted<'a>(items: impl Iterator<Item = &'a &'static str>) -> Vec<&'static str> {
+        let mut items: Vec<&str> = items.copied().collect();
+        items.sort();
+        items
+    }
+
+    #[test]
+    fn test_query() {
+        let graph = airports();
+        let graph = &graph;
+        let query = Query::new(&graph);
+
+        assert_eq!(query.vertices().count(), 14);
+        assert_eq!(
+            query.edges().filter(|(from, _)| from == &&"DFW").count(),
+            10
+        );
+    }
+
+    #[test]
+    fn test_steps() {
+        let graph = airports();
+        let graph = &graph;
+        let query = Query::new(&graph);
+
+        let out = query.vertices().filter(|v| v == &&"SFO").out();
+        assert_eq!(sorted(out), vec!["DFW", "LA"]);
+
+        let in_ = query.vertices().filter(|v| v == &&"LA").in_();
+        assert_eq!(sorted(in_), vec!["SFO"]);
+
+        // everyone flying into AUS, found through the edges
+        let from = query
+            .vertices()
+            .in_edges()
+            .filter(|(_, to)| to == &&"AUS")
+            .from()
+            .dedup();
+        assert_eq!(sorted(from), vec!["DFW", "HOU", "SAT"]);
+
+        let to = query
+            .starting_at([&"MOB"])
+            .out_edges()
+            .to()
+            .out_edges()
+            .dedup()
+            .count();
+        assert_eq!(to, 3);
+    }
+
+    #[test]
+    fn test_repeat_dedup_limit() {
+        let graph = airports();
+        let graph = &graph;
+        let query = Query::new(&graph);
+
+        let two_hops = query.starting_at([&"MOB"]).repeat(|q| q.out()).times(2);
+        assert_eq!(sorted(two_hops), vec!["BOS", "DFW", "MOB"]);
+
+        let reachable = query
+            .starting_at([&"MOB"])
+            .repeat(|q| q.out().dedup())
+            .times(3)
+            .dedup();
+        assert_eq!(reachable.count(), 11);
+
+        assert_eq!(query.vertices().out().limit(5).count(), 5);
+        assert_eq!(query.starting_at([&"LA"]).out().limit(5).count(), 0);
+        assert_eq!(
+            query
+                .starting_at([&"MOB"])
+                .repeat(|q| q.out())
+                .times(0)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_path() {
+        let graph = airports();
+        let graph = &graph;
+        let query = Query::new(&graph);
+
+        let paths: Vec<_> = query
+            .starting_at([&"MOB"])
+            .out()
+            .out_edges()
+            .filter(|(_, to)| to == &&"DFW")
+            .to()
+            .path()
+            .collect();
+
+        assert_eq!(
+            paths,
+            vec![vec![
+                Element::Vertex(&"MOB"),
+                Element::Vertex(&"ATL"),
+                Element::Edge((&"ATL", &"DFW")),
+                Element::Vertex(&"DFW"),
+            ]]
+        );
+
+        // the same steps without a path step still end up in the same places
+        let ends = sorted(query.starting_at([&"MOB"]).repeat(|q| q.out()).times(2));
+        let mut path_ends: Vec<_> = query
+            .starting_at([&"MOB"])
+            .repeat(|q| q.out())
+            .times(2)
+            .path()
+            .map(|path| match path.as_slice() {
+                [Element::Vertex(_), Element::Vertex(_), Element::Vertex(end)] => **end,
+                _ => panic!("{path:?} isn't two hops"),
+            })
+            .collect();
+        path_ends.sort();
+        assert_eq!(ends, path_ends);
+
+        let edge_paths: Vec<_> = query
+            .edges()
+            .filter(|(from, to)| from == &&"SFO" && to == &&"LA")
+            .path()
+            .collect();
+        assert_eq!(edge_paths, vec![vec![Element::Edge((&"SFO", &"LA"))]]);
     }
 }
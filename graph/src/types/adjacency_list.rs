@@ -1,5 +1,8 @@
 use crate::graph::Graph;
-use std::{collections::HashMap, hash::Hash};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
 
 pub struct AdjacencyList<Node> {
     graph: HashMap<Node, Vec<Node>>,
@@ -54,7 +57,7 @@ impl<'graph, Node: Hash + Eq> Graph for &'graph AdjacencyList<Node> {
         self.out_edges(from)
             .iter()
             .filter(|(_, t)| t == to)
-            .map(|a| *a)
+            .copied()
             .collect::<Vec<Self::E>>()
     }
 
@@ -63,11 +66,20 @@ impl<'graph, Node: Hash + Eq> Graph for &'graph AdjacencyList<Node> {
     }
 
     fn all_vertices(&self) -> Vec<Self::V> {
-        todo!()
+        // nodes that only appear as a target (dead ends) have no key of their own
+        let mut seen = HashSet::new();
+        self.graph
+            .iter()
+            .flat_map(|(node, adj)| std::iter::once(node).chain(adj))
+            .filter(|node| seen.insert(*node))
+            .collect()
     }
 
     fn all_edges(&self) -> Vec<Self::E> {
-        todo!()
+        self.graph
+            .iter()
+            .flat_map(|(node, adj)| adj.iter().map(move |next_node| (node, next_node)))
+            .collect()
     }
 }
 
@@ -102,5 +114,11 @@ mod tests {
         if let Some(path) = breadth_first_search(&&graph, &&"DFW", |airport| airport == &&"LA") {
             println!("{:?}", Vec::<&&str>::from(path));
         }
+
+        // "LA" is only ever a destination
+        let vertices = (&graph).all_vertices();
+        assert_eq!(vertices.len(), 14);
+        assert!(vertices.contains(&&"LA"));
+        assert_eq!((&graph).all_edges().len(), 32);
     }
 }
@@ -1,4 +1,22 @@
-use crate::graph::Graph;
+use crate::{
+    algorithm::{astar, Path},
+    graph::{Graph, Weighted},
+};
+use std::f64::consts::SQRT_2;
+
+mod ascii;
+mod image;
+
+/// Why a map couldn't be read into a grid
+#[derive(Debug)]
+pub enum GridError {
+    Syntax {
+        /// 1 based line number
+        line: usize,
+        message: String,
+    },
+    Png(png::DecodingError),
+}
 
 #[derive(Debug)]
 pub struct BinaryGrid {
@@ -39,16 +57,55 @@ impl BinaryGrid {
 
     /// converts binary number grid to boolean grid
     pub fn grid_to_bool(grid: Vec<Vec<u8>>) -> Vec<Vec<bool>> {
-        let to_bool = |val| match val {
-            0 => false,
-            _ => true,
-        };
+        let to_bool = |val| val != 0;
 
         grid.into_iter()
-            .map(|row| row.into_iter().map(|val| to_bool(val)).collect())
+            .map(|row| row.into_iter().map(to_bool).collect())
             .collect()
     }
 
+    /// grid distance when only horizontal and vertical moves are allowed
+    pub fn manhattan((r1, c1): &(usize, usize), (r2, c2): &(usize, usize)) -> f64 {
+        (r1.abs_diff(*r2) + c1.abs_diff(*c2)) as f64
+    }
+
+    /// grid distance when diagonal moves are allowed and cost √2
+    pub fn octile((r1, c1): &(usize, usize), (r2, c2): &(usize, usize)) -> f64 {
+        let (dr, dc) = (r1.abs_diff(*r2) as f64, c1.abs_diff(*c2) as f64);
+        dr + dc + (SQRT_2 - 2.0) * dr.min(dc)
+    }
+
+    /// A* from `start` to `goal` using `heuristic`, usually [`BinaryGrid::manhattan`] or [`BinaryGrid::octile`]
+    pub fn astar(
+        &self,
+        start: &(usize, usize),
+        goal: &(usize, usize),
+        heuristic: impl Fn(&(usize, usize), &(usize, usize)) -> f64,
+    ) -> Option<(Path<(usize, usize)>, f64)> {
+        astar(self, start, |rc| rc == goal, |rc| heuristic(rc, goal))
+    }
+
+    /// A* with octile distance if diagonal moves are allowed and manhattan distance otherwise
+    pub fn shortest_path(
+        &self,
+        start: &(usize, usize),
+        goal: &(usize, usize),
+    ) -> Option<(Path<(usize, usize)>, f64)> {
+        let diagonal = [(0, 0), (0, 2), (2, 0), (2, 2)]
+            .iter()
+            .any(|(dr, dc)| self.directions[*dr][*dc]);
+
+        match diagonal {
+            true => self.astar(start, goal, BinaryGrid::octile),
+            false => self.astar(start, goal, BinaryGrid::manhattan),
+        }
+    }
+
+    /// the cells along `path`, in order
+    fn path_cells(path: Option<&Path<(usize, usize)>>) -> Vec<(usize, usize)> {
+        path.map(Path::nodes).unwrap_or_default()
+    }
+
     fn nrc(&self, (r, c): &<BinaryGrid as Graph>::V) -> Vec<<BinaryGrid as Graph>::V> {
         let mut nrc = vec![];
 
@@ -161,6 +218,18 @@ impl Graph for BinaryGrid {
     }
 }
 
+impl Weighted for BinaryGrid {
+    type E = <BinaryGrid as Graph>::E;
+
+    /// 1 for horizontal and vertical moves, √2 for diagonal ones
+    fn weight(&self, ((r1, c1), (r2, c2)): &Self::E) -> f64 {
+        match r1 != r2 && c1 != c2 {
+            true => SQRT_2,
+            false => 1.0,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -183,4 +252,39 @@ mod tests {
 
         let _graph = BinaryGrid::new_hv(grid);
     }
+
+    #[test]
+    fn test_shortest_path() {
+        let grid = BinaryGrid::grid_to_bool(vec![
+            vec![1, 1, 1, 1, 1],
+            vec![0, 0, 0, 0, 1],
+            vec![1, 1, 1, 1, 1],
+            vec![1, 0, 0, 0, 0],
+            vec![1, 1, 1, 1, 1],
+        ]);
+
+        let graph = BinaryGrid::new_hv(grid.clone());
+        let (path, cost) = graph.shortest_path(&(0, 0), &(4, 4)).unwrap();
+        assert_eq!(cost, 16.0);
+        assert_eq!(Vec::from(path).len(), 17);
+        assert!(graph.shortest_path(&(0, 0), &(1, 0)).is_none());
+
+        // cutting the corners of the snake saves 4 × (2 - √2)
+        let graph = BinaryGrid::new_hvd(grid);
+        let (path, cost) = graph.shortest_path(&(0, 0), &(4, 4)).unwrap();
+        assert!((cost - (8.0 + 4.0 * SQRT_2)).abs() < 1e-9);
+        assert_eq!(Vec::from(path).len(), 13);
+
+        let (_, manhattan) = graph
+            .astar(&(0, 0), &(4, 4), BinaryGrid::manhattan)
+            .unwrap();
+        assert!(manhattan >= cost);
+    }
+
+    #[test]
+    fn test_heuristics() {
+        assert_eq!(BinaryGrid::manhattan(&(1, 5), &(4, 1)), 7.0);
+        assert!((BinaryGrid::octile(&(1, 5), &(4, 1)) - (1.0 + 3.0 * SQRT_2)).abs() < 1e-9);
+        assert_eq!(BinaryGrid::octile(&(2, 2), &(2, 2)), 0.0);
+    }
 }
@@ -0,0 +1,248 @@
+use super::{BinaryGrid, GridError};
+use crate::algorithm::Path;
+
+impl BinaryGrid {
+    /// Reads a map drawn with `.` for open cells and `#` for walls, a line per row.
+    ///
+    /// `S`, `G` and `*` are open too, so what [`BinaryGrid::render_ascii`] draws
+    /// reads back as the same grid. Blank lines around the map are skipped.
+    ///
+    /// ```
+    /// use graph::BinaryGrid;
+    ///
+    /// let grid = BinaryGrid::new_hv(BinaryGrid::ascii_to_bool("..#\n#..\n").unwrap());
+    /// let (path, _) = grid.shortest_path(&(0, 0), &(1, 2)).unwrap();
+    /// assert_eq!(grid.render_ascii(Some(&path)), "S*#\n#*G\n");
+    /// ```
+    pub fn ascii_to_bool(text: &str) -> Result<Vec<Vec<bool>>, GridError> {
+        let lines: Vec<(usize, &str)> = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim_end()))
+            .collect();
+        let first = lines.iter().position(|(_, row)| !row.is_empty());
+        let last = lines.iter().rposition(|(_, row)| !row.is_empty());
+
+        let (Some(first), Some(last)) = (first, last) else {
+            return Ok(vec![]);
+        };
+
+        let mut grid = vec![];
+        for (line, row) in &lines[first..=last] {
+            let row = parse_row(*line, row, |cell| match cell {
+                '.' | 'S' | 'G' | '*' => Some(true),
+                '#' => Some(false),
+                _ => None,
+            })?;
+
+            if let Some(width) = grid.first().map(Vec::len) {
+                if row.len() != width {
+                    return Err(syntax(
+                        *line,
+                        format!("expected {} cells, found {}", width, row.len()),
+                    ));
+                }
+            }
+
+            grid.push(row);
+        }
+
+        Ok(grid)
+    }
+
+    /// Reads a map in the Moving AI benchmark format, a `type`, `height` and `width`
+    /// header followed by `map` and the rows.
+    ///
+    /// `.` and `G` are ground and `S` is swamp, all open. `@` and `O` (out of bounds),
+    /// `T` (trees) and `W` (water) are walls. The `type` is read but not checked,
+    /// `octile` maps are meant to be searched with [`BinaryGrid::new_hvd`].
+    pub fn moving_ai_to_bool(text: &str) -> Result<Vec<Vec<bool>>, GridError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim_end()));
+        let (mut height, mut width) = (None, None);
+
+        let map_line = loop {
+            let Some((line, header)) = lines.next() else {
+                return Err(syntax(text.lines().count(), "missing map".to_string()));
+            };
+
+            let words: Vec<&str> = header.split_whitespace().collect();
+            match words[..] {
+                [] | ["type", _] => {}
+                ["height", value] => height = Some(number(line, value)?),
+                ["width", value] => width = Some(number(line, value)?),
+                ["map"] => break line,
+                _ => return Err(syntax(line, format!("unexpected {:?}", header))),
+            }
+        };
+
+        let (Some(height), Some(width)) = (height, width) else {
+            return Err(syntax(map_line, "height or width missing".to_string()));
+        };
+
+        let mut grid = Vec::with_capacity(height);
+        for (line, row) in lines.by_ref().take(height) {
+            let row = parse_row(line, row, |cell| match cell {
+                '.' | 'G' | 'S' => Some(true),
+                '@' | 'O' | 'T' | 'W' => Some(false),
+                _ => None,
+            })?;
+
+            if row.len() != width {
+                return Err(syntax(
+                    line,
+                    format!("expected {} cells, found {}", width, row.len()),
+                ));
+            }
+            grid.push(row);
+        }
+
+        if grid.len() < height {
+            return Err(syntax(
+                map_line + grid.len(),
+                format!("expected {} rows, found {}", height, grid.len()),
+            ));
+        }
+
+        Ok(grid)
+    }
+
+    /// Draws the grid with `#` for walls and `.` for open cells, and the path
+    /// over it with `S` at its start, `G` at its goal and `*` in between
+    pub fn render_ascii(&self, path: Option<&Path<(usize, usize)>>) -> String {
+        let mut rows: Vec<Vec<char>> = self
+            .grid
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|open| if *open { '.' } else { '#' })
+                    .collect()
+            })
+            .collect();
+
+        let cells = Self::path_cells(path);
+        for (i, (r, c)) in cells.iter().enumerate() {
+            if let Some(cell) = rows.get_mut(*r).and_then(|row| row.get_mut(*c)) {
+                *cell = match i {
+                    0 => 'S',
+                    i if i == cells.len() - 1 => 'G',
+                    _ => '*',
+                };
+            }
+        }
+
+        rows.into_iter()
+            .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+            .collect()
+    }
+}
+
+fn parse_row(
+    line: usize,
+    row: &str,
+    is_open: impl Fn(char) -> Option<bool>,
+) -> Result<Vec<bool>, GridError> {
+    row.chars()
+        .map(|cell| is_open(cell).ok_or_else(|| syntax(line, format!("unexpected {:?}", cell))))
+        .collect()
+}
+
+fn number(line: usize, value: &str) -> Result<usize, GridError> {
+    value
+        .parse()
+        .map_err(|_| syntax(line, format!("expected a number, found {:?}", value)))
+}
+
+fn syntax(line: usize, message: String) -> GridError {
+    GridError::Syntax { line, message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAZE: &str = "
+.....#....
+.###.#.##.
+.#...#..#.
+.#.####.#.
+.#......#.
+";
+
+    const MAZE_SOLVED: &str = "\
+S****#****
+.###*#*##*
+.#***#**#*
+.#*####*#*
+.#******#G
+";
+
+    #[test]
+    fn test_ascii() {
+        let grid = BinaryGrid::ascii_to_bool(MAZE).unwrap();
+        assert_eq!(grid.len(), 5);
+        assert!(grid.iter().all(|row| row.len() == 10));
+        assert!(grid[0][0] && !grid[0][5]);
+
+        let grid = BinaryGrid::new_hv(grid);
+        let (path, _) = grid.shortest_path(&(0, 0), &(4, 9)).unwrap();
+        let rendered = grid.render_ascii(Some(&path));
+        assert_eq!(rendered, MAZE_SOLVED);
+        assert_eq!(grid.render_ascii(None), MAZE.trim_start());
+
+        // a drawn path reads back as open cells
+        assert_eq!(
+            BinaryGrid::ascii_to_bool(&rendered).unwrap(),
+            BinaryGrid::ascii_to_bool(MAZE).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_ascii_errors() {
+        assert!(BinaryGrid::ascii_to_bool("\n\n").unwrap().is_empty());
+        assert!(matches!(
+            BinaryGrid::ascii_to_bool("..\n.x"),
+            Err(GridError::Syntax { line: 2, .. })
+        ));
+        assert!(matches!(
+            BinaryGrid::ascii_to_bool("\n..\n...\n"),
+            Err(GridError::Syntax { line: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_moving_ai() {
+        let text = "type octile\nheight 3\nwidth 4\nmap\n..@T\nG.S.\nWW..\n";
+        let grid = BinaryGrid::moving_ai_to_bool(text).unwrap();
+        assert_eq!(
+            grid,
+            vec![
+                vec![true, true, false, false],
+                vec![true, true, true, true],
+                vec![false, false, true, true],
+            ]
+        );
+
+        let grid = BinaryGrid::new_hvd(grid);
+        let (_, cost) = grid.shortest_path(&(0, 0), &(2, 3)).unwrap();
+        assert!((cost - (1.0 + 2.0 * std::f64::consts::SQRT_2)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_moving_ai_errors() {
+        let error = |text| match BinaryGrid::moving_ai_to_bool(text) {
+            Err(GridError::Syntax { line, .. }) => line,
+            other => panic!("{:?}", other),
+        };
+
+        assert_eq!(error("type octile\nheight 2\nwidth 2\n"), 3);
+        assert_eq!(error("type octile\nheight two\nwidth 2\nmap\n..\n..\n"), 2);
+        assert_eq!(error("type octile\nwidth 2\nmap\n..\n..\n"), 3);
+        assert_eq!(error("type octile\nheight 2\nwidth 2\nmap\n..\n.?\n"), 6);
+        assert_eq!(error("type octile\nheight 2\nwidth 2\nmap\n...\n..\n"), 5);
+        assert_eq!(error("type octile\nheight 3\nwidth 2\nmap\n..\n..\n"), 6);
+        assert_eq!(error("version 1\nmap\n"), 1);
+    }
+}
@@ -0,0 +1,132 @@
+use super::{BinaryGrid, GridError};
+use crate::algorithm::Path;
+use png::{BitDepth, ColorType, Decoder, Encoder, Transformations};
+use std::io::{self, Read, Write};
+
+const WALL: [u8; 3] = [0, 0, 0];
+const OPEN: [u8; 3] = [255, 255, 255];
+const PATH: [u8; 3] = [255, 0, 0];
+
+impl BinaryGrid {
+    /// Reads a PNG with a cell per pixel, dark pixels are walls and everything else is open.
+    ///
+    /// A pixel is dark when every color channel is below half, so the red path
+    /// [`BinaryGrid::write_png`] draws reads back as open. Transparency is ignored.
+    pub fn png_to_bool(reader: impl Read) -> Result<Vec<Vec<bool>>, GridError> {
+        let mut decoder = Decoder::new(reader);
+        // palettes and low bit depths become plain 8 bit gray or RGB
+        decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+        let mut reader = decoder.read_info().map_err(GridError::Png)?;
+
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut buffer).map_err(GridError::Png)?;
+
+        let (color_type, _) = reader.output_color_type();
+        let samples = color_type.samples();
+        let colors = match color_type {
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => 1,
+            _ => 3,
+        };
+
+        let grid = buffer
+            .chunks(frame.line_size)
+            .take(frame.height as usize)
+            .map(|line| {
+                line.chunks(samples)
+                    .take(frame.width as usize)
+                    .map(|pixel| pixel[..colors].iter().any(|sample| *sample >= 128))
+                    .collect()
+            })
+            .collect();
+
+        Ok(grid)
+    }
+
+    /// Writes the grid as an RGB PNG with a pixel per cell, walls black, open
+    /// cells white and the path red. Short rows are padded with walls.
+    pub fn write_png(
+        &self,
+        path: Option<&Path<(usize, usize)>>,
+        writer: impl Write,
+    ) -> io::Result<()> {
+        let height = self.grid.len();
+        let width = self.grid.iter().map(Vec::len).max().unwrap_or(0);
+
+        let mut pixels = vec![WALL; width * height];
+        for (r, row) in self.grid.iter().enumerate() {
+            for (c, open) in row.iter().enumerate() {
+                if *open {
+                    pixels[r * width + c] = OPEN;
+                }
+            }
+        }
+        for (r, c) in Self::path_cells(path) {
+            if r < height && c < width {
+                pixels[r * width + c] = PATH;
+            }
+        }
+
+        let mut encoder = Encoder::new(writer, width as u32, height as u32);
+        encoder.set_color(ColorType::Rgb);
+        encoder.set_depth(BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(pixels.as_flattened())?;
+        writer.finish()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> BinaryGrid {
+        BinaryGrid::new_hv(BinaryGrid::ascii_to_bool("...#\n.#..\n...#\n").unwrap())
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let grid = grid();
+        let (path, _) = grid.shortest_path(&(0, 0), &(1, 3)).unwrap();
+
+        let mut image = vec![];
+        grid.write_png(Some(&path), &mut image).unwrap();
+        assert_eq!(&image[1..4], b"PNG");
+
+        let read = BinaryGrid::png_to_bool(image.as_slice()).unwrap();
+        assert_eq!(read, grid.grid);
+
+        let mut decoder = Decoder::new(image.as_slice()).read_info().unwrap();
+        let mut pixels = vec![0; decoder.output_buffer_size()];
+        decoder.next_frame(&mut pixels).unwrap();
+        assert_eq!(&pixels[..3], &PATH);
+        assert_eq!(&pixels[9..12], &WALL);
+        assert_eq!(&pixels[12..15], &OPEN);
+    }
+
+    #[test]
+    fn test_grayscale() {
+        // a 1 bit image, 3 wide and 2 high: 101 / 010
+        let mut image = vec![];
+        let mut encoder = Encoder::new(&mut image, 3, 2);
+        encoder.set_color(ColorType::Grayscale);
+        encoder.set_depth(BitDepth::One);
+        let mut writer = encoder.write_header().unwrap();
+        writer
+            .write_image_data(&[0b1010_0000, 0b0100_0000])
+            .unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(
+            BinaryGrid::png_to_bool(image.as_slice()).unwrap(),
+            vec![vec![true, false, true], vec![false, true, false]]
+        );
+
+        assert!(matches!(
+            BinaryGrid::png_to_bool(&b"not a png"[..]),
+            Err(GridError::Png(_))
+        ));
+    }
+}
@@ -62,11 +62,11 @@ where
     }
 
     fn all_vertices(&self) -> Vec<Self::V> {
-        todo!()
+        self.vertices.iter().collect()
     }
 
     fn all_edges(&self) -> Vec<Self::E> {
-        todo!()
+        self.edges.iter().collect()
     }
 }
 
@@ -85,8 +85,32 @@ impl<V, E> Mutable for Classic<V, E> {
 
 #[cfg(test)]
 mod tests {
-    // use super::Classic;
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Road(&'static str, &'static str);
+
+    impl Edge for Road {
+        type V = &'static str;
+
+        fn from(&self) -> &Self::V {
+            &self.0
+        }
+
+        fn to(&self) -> &Self::V {
+            &self.1
+        }
+    }
 
     #[test]
-    fn test() {}
+    fn test() {
+        let mut graph = Classic::new(vec!["a", "b"], vec![Road("a", "b")]);
+        graph.add_vertex("c");
+        graph.add_edge(Road("b", "c"));
+
+        let graph = &graph;
+        assert_eq!(graph.all_vertices(), vec![&"a", &"b", &"c"]);
+        assert_eq!(graph.all_edges(), vec![&Road("a", "b"), &Road("b", "c")]);
+        assert_eq!(graph.out_vertices(&&"b"), vec![&"c"]);
+    }
 }
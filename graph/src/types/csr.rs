@@ -0,0 +1,229 @@
+use crate::graph::Graph;
+use std::{collections::HashMap, hash::Hash};
+
+/// A read-only directed graph in compressed sparse row form, for graphs too big
+/// to spend a `Vec` per node on.
+///
+/// Nodes are `0..node_count()`. The out neighbors of every node sit next to each
+/// other in one array, node `v`'s from `offsets[v]` up to `offsets[v + 1]`, and
+/// the in neighbors are kept the same way. An edge is its position in the out
+/// neighbor array.
+///
+/// ```
+/// use graph::Csr;
+///
+/// let graph = Csr::from_edges(4, [(0, 1), (0, 2), (2, 1), (3, 0)]);
+/// assert_eq!(graph.successors(0), &[1, 2]);
+/// assert_eq!(graph.predecessors(1), &[0, 2]);
+/// assert_eq!(graph.edge_count(), 4);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Csr {
+    out_offsets: Vec<usize>,
+    targets: Vec<usize>,
+    in_offsets: Vec<usize>,
+    sources: Vec<usize>,
+}
+
+impl Csr {
+    /// Builds the graph in two passes over the edges, counting then placing them.
+    /// Neighbors keep the order their edges came in.
+    ///
+    /// Panics if an edge has a node outside `0..node_count`
+    pub fn from_edges(node_count: usize, edges: impl IntoIterator<Item = (usize, usize)>) -> Self {
+        let edges: Vec<(usize, usize)> = edges.into_iter().collect();
+        if let Some((from, to)) = edges
+            .iter()
+            .find(|(from, to)| *from >= node_count || *to >= node_count)
+        {
+            panic!(
+                "edge {} -> {} outside a graph of {} nodes",
+                from, to, node_count
+            );
+        }
+
+        let (out_offsets, targets) = Self::rows(node_count, edges.iter().copied());
+        let (in_offsets, sources) =
+            Self::rows(node_count, edges.iter().map(|(from, to)| (*to, *from)));
+
+        Self {
+            out_offsets,
+            targets,
+            in_offsets,
+            sources,
+        }
+    }
+
+    /// Copies any graph, numbering its vertices in the order `all_vertices` lists them.
+    /// Returns the vertices too, the one at index `i` is node `i`
+    pub fn from_graph<G: Graph + ?Sized>(graph: &G) -> (Self, Vec<G::V>)
+    where
+        G::V: Clone + Eq + Hash,
+    {
+        let vertices = graph.all_vertices();
+        let index: HashMap<&G::V, usize> =
+            vertices.iter().enumerate().map(|(i, v)| (v, i)).collect();
+
+        let edges = vertices.iter().enumerate().flat_map(|(i, v)| {
+            graph
+                .out_vertices(v)
+                .into_iter()
+                .filter_map(|w| index.get(&w).map(|j| (i, *j)))
+                .collect::<Vec<_>>()
+        });
+
+        (Self::from_edges(vertices.len(), edges), vertices)
+    }
+
+    /// (offsets, neighbors) grouping `pairs` by their first node
+    fn rows(
+        node_count: usize,
+        pairs: impl Iterator<Item = (usize, usize)> + Clone,
+    ) -> (Vec<usize>, Vec<usize>) {
+        let mut offsets = vec![0; node_count + 1];
+        for (from, _) in pairs.clone() {
+            offsets[from + 1] += 1;
+        }
+        for v in 0..node_count {
+            offsets[v + 1] += offsets[v];
+        }
+
+        let mut next = offsets.clone();
+        let mut neighbors = vec![0; offsets[node_count]];
+        for (from, to) in pairs {
+            neighbors[next[from]] = to;
+            next[from] += 1;
+        }
+
+        (offsets, neighbors)
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.out_offsets.len() - 1
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.targets.len()
+    }
+
+    pub fn successors(&self, v: usize) -> &[usize] {
+        &self.targets[self.out_offsets[v]..self.out_offsets[v + 1]]
+    }
+
+    pub fn predecessors(&self, v: usize) -> &[usize] {
+        &self.sources[self.in_offsets[v]..self.in_offsets[v + 1]]
+    }
+
+    pub fn out_degree(&self, v: usize) -> usize {
+        self.out_offsets[v + 1] - self.out_offsets[v]
+    }
+
+    pub fn in_degree(&self, v: usize) -> usize {
+        self.in_offsets[v + 1] - self.in_offsets[v]
+    }
+
+    /// the node edge `e` starts at, found by a binary search over the offsets
+    fn source(&self, e: usize) -> usize {
+        self.out_offsets.partition_point(|offset| *offset <= e) - 1
+    }
+}
+
+impl Graph for Csr {
+    type V = usize;
+    type E = usize;
+
+    fn out_vertices(&self, v: &Self::V) -> Vec<Self::V> {
+        self.successors(*v).to_vec()
+    }
+
+    fn in_vertices(&self, v: &Self::V) -> Vec<Self::V> {
+        self.predecessors(*v).to_vec()
+    }
+
+    fn out_edges(&self, v: &Self::V) -> Vec<Self::E> {
+        (self.out_offsets[*v]..self.out_offsets[*v + 1]).collect()
+    }
+
+    /// edges are numbered by their source, so these need a search per in neighbor
+    fn in_edges(&self, v: &Self::V) -> Vec<Self::E> {
+        let mut edges: Vec<Self::E> = self
+            .predecessors(*v)
+            .iter()
+            .flat_map(|from| self.edges(from, v))
+            .collect();
+        edges.sort_unstable();
+        edges.dedup();
+        edges
+    }
+
+    fn edges(&self, from: &Self::V, to: &Self::V) -> Vec<Self::E> {
+        self.out_edges(from)
+            .into_iter()
+            .filter(|e| self.targets[*e] == *to)
+            .collect()
+    }
+
+    fn vertices(&self, e: &Self::E) -> (Self::V, Self::V) {
+        (self.source(*e), self.targets[*e])
+    }
+
+    fn all_vertices(&self) -> Vec<Self::V> {
+        (0..self.node_count()).collect()
+    }
+
+    fn all_edges(&self) -> Vec<Self::E> {
+        (0..self.edge_count()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AdjacencyList;
+
+    #[test]
+    fn test_from_edges() {
+        let graph = Csr::from_edges(5, [(3, 1), (0, 1), (3, 3), (0, 4), (3, 1)]);
+
+        assert_eq!(graph.node_count(), 5);
+        assert_eq!(graph.successors(3), &[1, 3, 1]);
+        assert_eq!(graph.successors(2), &[] as &[usize]);
+        assert_eq!(graph.predecessors(1), &[3, 0, 3]);
+        assert_eq!(graph.out_degree(0), 2);
+        assert_eq!(graph.in_degree(3), 1);
+
+        // parallel edges come back once
+        assert_eq!(graph.in_edges(&1), vec![0, 2, 4]);
+        assert_eq!(graph.edges(&3, &1), vec![2, 4]);
+        assert_eq!(graph.vertices(&3), (3, 3));
+        assert!(graph
+            .all_edges()
+            .iter()
+            .all(|e| graph.out_edges(&graph.vertices(e).0).contains(e)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_from_edges_out_of_range() {
+        Csr::from_edges(2, [(0, 2)]);
+    }
+
+    #[test]
+    fn test_from_graph() {
+        let graph = AdjacencyList::new(HashMap::from([
+            ("a", vec!["b", "c"]),
+            ("b", vec!["c"]),
+            ("c", vec![]),
+        ]));
+        let graph = &graph;
+
+        let (csr, vertices) = Csr::from_graph(&graph);
+        let name = |v: usize| *vertices[v];
+        let c = vertices.iter().position(|v| **v == "c").unwrap();
+
+        assert_eq!(csr.edge_count(), 3);
+        let mut into_c: Vec<&str> = csr.predecessors(c).iter().map(|v| name(*v)).collect();
+        into_c.sort();
+        assert_eq!(into_c, vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,459 @@
+use crate::graph::{Graph, Mutable, Weighted};
+
+/// Identifies a node of a [`DiGraph`]. Once the node is removed the id finds
+/// nothing, even after its slot holds a new node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId {
+    index: usize,
+    generation: u32,
+}
+
+/// Identifies an edge of a [`DiGraph`], see [`NodeId`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EdgeId {
+    index: usize,
+    generation: u32,
+}
+
+impl NodeId {
+    /// The slot the node is in, a later node can have the same one
+    pub fn index(self) -> usize {
+        self.index
+    }
+}
+
+impl EdgeId {
+    /// see [`NodeId::index`]
+    pub fn index(self) -> usize {
+        self.index
+    }
+}
+
+/// A node or edge, or a hole where one was removed. The generation goes up on
+/// every removal, so ids made for an earlier occupant don't match
+#[derive(Debug, Clone)]
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+impl<T> Slot<T> {
+    fn get(&self, generation: u32) -> Option<&T> {
+        self.value
+            .as_ref()
+            .filter(|_| self.generation == generation)
+    }
+
+    fn get_mut(&mut self, generation: u32) -> Option<&mut T> {
+        self.value
+            .as_mut()
+            .filter(|_| self.generation == generation)
+    }
+
+    fn take(&mut self, generation: u32) -> Option<T> {
+        self.get(generation)?;
+        self.generation = self.generation.wrapping_add(1);
+        self.value.take()
+    }
+}
+
+/// Puts `value` in a free slot or a new one, (index, generation) of where it went
+fn insert<T>(slots: &mut Vec<Slot<T>>, free: &mut Vec<usize>, value: T) -> (usize, u32) {
+    match free.pop() {
+        Some(index) => {
+            slots[index].value = Some(value);
+            (index, slots[index].generation)
+        }
+        None => {
+            slots.push(Slot {
+                generation: 0,
+                value: Some(value),
+            });
+            (slots.len() - 1, 0)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct NodeSlot<N> {
+    weight: N,
+    outgoing: Vec<EdgeId>,
+    incoming: Vec<EdgeId>,
+}
+
+#[derive(Debug, Clone)]
+struct EdgeSlot<E> {
+    weight: E,
+    from: NodeId,
+    to: NodeId,
+}
+
+/// An owned directed graph with node weights `N` and edge weights `E`.
+///
+/// Nodes and edges live in slots addressed by [`NodeId`] and [`EdgeId`].
+/// Removing one leaves a hole instead of shifting the others, so ids handed
+/// out earlier keep pointing at the same thing. Freed slots are reused by
+/// later insertions, but with a new generation, so the ids of removed nodes
+/// and edges find nothing instead of whatever took their place.
+///
+/// Every node keeps the ids of its outgoing and incoming edges, so neighbors
+/// in either direction are found without scanning the edge list.
+#[derive(Debug, Clone)]
+pub struct DiGraph<N, E> {
+    nodes: Vec<Slot<NodeSlot<N>>>,
+    edges: Vec<Slot<EdgeSlot<E>>>,
+    free_nodes: Vec<usize>,
+    free_edges: Vec<usize>,
+}
+
+impl<N, E> Default for DiGraph<N, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N, E> DiGraph<N, E> {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![],
+            edges: vec![],
+            free_nodes: vec![],
+            free_edges: vec![],
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len() - self.free_nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len() - self.free_edges.len()
+    }
+
+    pub fn add_node(&mut self, weight: N) -> NodeId {
+        let slot = NodeSlot {
+            weight,
+            outgoing: vec![],
+            incoming: vec![],
+        };
+
+        let (index, generation) = insert(&mut self.nodes, &mut self.free_nodes, slot);
+        NodeId { index, generation }
+    }
+
+    /// Adds an edge from `from` to `to`. Parallel edges and self loops are allowed
+    ///
+    /// Panics if either node is not in the graph
+    pub fn add_edge(&mut self, from: NodeId, to: NodeId, weight: E) -> EdgeId {
+        assert!(
+            self.contains_node(from) && self.contains_node(to),
+            "edge between missing nodes {:?} -> {:?}",
+            from,
+            to
+        );
+
+        let slot = EdgeSlot { weight, from, to };
+        let (index, generation) = insert(&mut self.edges, &mut self.free_edges, slot);
+        let id = EdgeId { index, generation };
+
+        self.node_slot_mut(from).unwrap().outgoing.push(id);
+        self.node_slot_mut(to).unwrap().incoming.push(id);
+
+        id
+    }
+
+    /// Removes a node along with every edge going into or out of it
+    pub fn remove_node(&mut self, id: NodeId) -> Option<N> {
+        let slot = self.nodes.get_mut(id.index)?.take(id.generation)?;
+        self.free_nodes.push(id.index);
+
+        // a self loop shows up in both lists, the second removal is a no-op
+        for edge in slot.outgoing.iter().chain(&slot.incoming) {
+            self.remove_edge(*edge);
+        }
+
+        Some(slot.weight)
+    }
+
+    pub fn remove_edge(&mut self, id: EdgeId) -> Option<E> {
+        let slot = self.edges.get_mut(id.index)?.take(id.generation)?;
+        self.free_edges.push(id.index);
+
+        // the endpoints are already gone when called from remove_node
+        if let Some(from) = self.node_slot_mut(slot.from) {
+            from.outgoing.retain(|edge| *edge != id);
+        }
+        if let Some(to) = self.node_slot_mut(slot.to) {
+            to.incoming.retain(|edge| *edge != id);
+        }
+
+        Some(slot.weight)
+    }
+
+    pub fn contains_node(&self, id: NodeId) -> bool {
+        self.node_slot(id).is_some()
+    }
+
+    pub fn contains_edge(&self, id: EdgeId) -> bool {
+        self.edge_slot(id).is_some()
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&N> {
+        self.node_slot(id).map(|slot| &slot.weight)
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut N> {
+        self.node_slot_mut(id).map(|slot| &mut slot.weight)
+    }
+
+    pub fn edge(&self, id: EdgeId) -> Option<&E> {
+        self.edge_slot(id).map(|slot| &slot.weight)
+    }
+
+    pub fn edge_mut(&mut self, id: EdgeId) -> Option<&mut E> {
+        let slot = self.edges.get_mut(id.index)?.get_mut(id.generation)?;
+        Some(&mut slot.weight)
+    }
+
+    /// (from, to) of an edge
+    pub fn endpoints(&self, id: EdgeId) -> Option<(NodeId, NodeId)> {
+        self.edge_slot(id).map(|slot| (slot.from, slot.to))
+    }
+
+    /// the first edge from `from` to `to`, if there is one
+    pub fn find_edge(&self, from: NodeId, to: NodeId) -> Option<EdgeId> {
+        self.outgoing(from)
+            .iter()
+            .copied()
+            .find(|edge| self.endpoints(*edge).map(|(_, t)| t) == Some(to))
+    }
+
+    pub fn outgoing(&self, id: NodeId) -> &[EdgeId] {
+        self.node_slot(id)
+            .map(|slot| slot.outgoing.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn incoming(&self, id: NodeId) -> &[EdgeId] {
+        self.node_slot(id)
+            .map(|slot| slot.incoming.as_slice())
+            .unwrap_or_default()
+    }
+
+    pub fn successors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.outgoing(id)
+            .iter()
+            .map(move |edge| self.edge_slot(*edge).unwrap().to)
+    }
+
+    pub fn predecessors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.incoming(id)
+            .iter()
+            .map(move |edge| self.edge_slot(*edge).unwrap().from)
+    }
+
+    pub fn node_ids(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.value.is_some())
+            .map(|(index, slot)| NodeId {
+                index,
+                generation: slot.generation,
+            })
+    }
+
+    pub fn edge_ids(&self) -> impl Iterator<Item = EdgeId> + '_ {
+        self.edges
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.value.is_some())
+            .map(|(index, slot)| EdgeId {
+                index,
+                generation: slot.generation,
+            })
+    }
+
+    fn node_slot(&self, id: NodeId) -> Option<&NodeSlot<N>> {
+        self.nodes.get(id.index)?.get(id.generation)
+    }
+
+    fn node_slot_mut(&mut self, id: NodeId) -> Option<&mut NodeSlot<N>> {
+        self.nodes.get_mut(id.index)?.get_mut(id.generation)
+    }
+
+    fn edge_slot(&self, id: EdgeId) -> Option<&EdgeSlot<E>> {
+        self.edges.get(id.index)?.get(id.generation)
+    }
+}
+
+impl<N, E> Graph for DiGraph<N, E> {
+    type V = NodeId;
+    type E = EdgeId;
+
+    fn out_vertices(&self, v: &Self::V) -> Vec<Self::V> {
+        self.successors(*v).collect()
+    }
+
+    fn in_vertices(&self, v: &Self::V) -> Vec<Self::V> {
+        self.predecessors(*v).collect()
+    }
+
+    fn out_edges(&self, v: &Self::V) -> Vec<Self::E> {
+        self.outgoing(*v).to_vec()
+    }
+
+    fn in_edges(&self, v: &Self::V) -> Vec<Self::E> {
+        self.incoming(*v).to_vec()
+    }
+
+    fn edges(&self, from: &Self::V, to: &Self::V) -> Vec<Self::E> {
+        self.outgoing(*from)
+            .iter()
+            .copied()
+            .filter(|edge| self.endpoints(*edge).map(|(_, t)| t) == Some(*to))
+            .collect()
+    }
+
+    /// Panics if the edge is not in the graph
+    fn vertices(&self, e: &Self::E) -> (Self::V, Self::V) {
+        self.endpoints(*e)
+            .unwrap_or_else(|| panic!("missing edge {:?}", e))
+    }
+
+    fn all_vertices(&self) -> Vec<Self::V> {
+        self.node_ids().collect()
+    }
+
+    fn all_edges(&self) -> Vec<Self::E> {
+        self.edge_ids().collect()
+    }
+}
+
+impl<N, E: Clone + Into<f64>> Weighted for DiGraph<N, E> {
+    type E = EdgeId;
+
+    /// Panics if the edge is not in the graph
+    fn weight(&self, e: &Self::E) -> f64 {
+        self.edge(*e)
+            .unwrap_or_else(|| panic!("missing edge {:?}", e))
+            .clone()
+            .into()
+    }
+}
+
+impl<N, E> Mutable for DiGraph<N, E> {
+    type V = N;
+    type E = (NodeId, NodeId, E);
+
+    fn add_vertex(&mut self, v: Self::V) {
+        self.add_node(v);
+    }
+
+    fn add_edge(&mut self, (from, to, weight): Self::E) {
+        DiGraph::add_edge(self, from, to, weight);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::breadth_first_search;
+
+    fn cities() -> (DiGraph<&'static str, f64>, Vec<NodeId>) {
+        let mut graph = DiGraph::new();
+        let ids: Vec<NodeId> = ["ATL", "BOS", "DFW", "LAX"]
+            .into_iter()
+            .map(|city| graph.add_node(city))
+            .collect();
+
+        graph.add_edge(ids[0], ids[1], 950.0);
+        graph.add_edge(ids[0], ids[2], 730.0);
+        graph.add_edge(ids[1], ids[2], 1550.0);
+        graph.add_edge(ids[2], ids[3], 1240.0);
+        graph.add_edge(ids[3], ids[2], 1240.0);
+
+        (graph, ids)
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let (graph, ids) = cities();
+        let [atl, bos, dfw, lax] = ids[..] else {
+            unreachable!()
+        };
+
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 5);
+        assert_eq!(graph.out_vertices(&atl), vec![bos, dfw]);
+        assert_eq!(graph.in_vertices(&dfw), vec![atl, bos, lax]);
+        assert_eq!(graph.in_vertices(&atl), vec![]);
+
+        let edge = graph.find_edge(dfw, lax).unwrap();
+        assert_eq!(graph.vertices(&edge), (dfw, lax));
+        assert_eq!(graph.weight(&edge), 1240.0);
+        assert_eq!(graph.edges(&bos, &atl), vec![]);
+
+        let path = breadth_first_search(&graph, &bos, |city| graph.node(*city) == Some(&"LAX"));
+        assert_eq!(Vec::from(path.unwrap()), vec![bos, dfw, lax]);
+    }
+
+    #[test]
+    fn test_removal_keeps_ids() {
+        let (mut graph, ids) = cities();
+        let [atl, bos, dfw, lax] = ids[..] else {
+            unreachable!()
+        };
+        let dfw_lax = graph.find_edge(dfw, lax).unwrap();
+
+        assert_eq!(graph.remove_node(bos), Some("BOS"));
+        assert_eq!(graph.remove_node(bos), None);
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 3);
+        assert_eq!(graph.out_vertices(&atl), vec![dfw]);
+        assert_eq!(graph.in_vertices(&dfw), vec![atl, lax]);
+
+        // the other ids still point at the same things
+        assert_eq!(graph.node(lax), Some(&"LAX"));
+        assert_eq!(graph.endpoints(dfw_lax), Some((dfw, lax)));
+        assert_eq!(graph.all_vertices(), vec![atl, dfw, lax]);
+
+        assert_eq!(graph.remove_edge(dfw_lax), Some(1240.0));
+        assert_eq!(graph.find_edge(dfw, lax), None);
+        assert_eq!(graph.in_vertices(&dfw), vec![atl, lax]);
+
+        // freed slots are reused, but the removed ids don't find the new occupants
+        let mia = graph.add_node("MIA");
+        assert_eq!(mia.index(), bos.index());
+        assert_ne!(mia, bos);
+        assert!(!graph.contains_node(bos));
+        assert_eq!(graph.node(bos), None);
+        assert_eq!(graph.remove_node(bos), None);
+        assert_eq!(graph.node(mia), Some(&"MIA"));
+
+        let self_loop = graph.add_edge(mia, mia, 0.0);
+        assert_eq!(self_loop.index(), dfw_lax.index());
+        assert_eq!(graph.edge(dfw_lax), None);
+        assert_eq!(graph.endpoints(dfw_lax), None);
+        assert_eq!(graph.edge_mut(dfw_lax), None);
+        assert_eq!(graph.out_vertices(&mia), vec![mia]);
+        assert_eq!(graph.remove_node(mia), Some("MIA"));
+        assert_eq!(graph.edge_count(), 2);
+    }
+
+    #[test]
+    fn test_mutable() {
+        let mut graph: DiGraph<char, u32> = DiGraph::default();
+        for c in ['a', 'b', 'c'] {
+            graph.add_vertex(c);
+        }
+        let ids = graph.all_vertices();
+        Mutable::add_edge(&mut graph, (ids[0], ids[2], 7));
+
+        let edges = graph.all_edges();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(graph.weight(&edges[0]), 7.0);
+        *graph.edge_mut(edges[0]).unwrap() = 9;
+        assert_eq!(graph.weight(&edges[0]), 9.0);
+    }
+}
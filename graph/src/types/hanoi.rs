@@ -0,0 +1,162 @@
+use crate::{algorithm::breadth_first_traverse, Graph, Planning, Problem};
+
+/// Towers of Hanoi with `N` disks on 3 pegs.
+///
+/// A state is the peg every disk is on, smallest disk first. Only the top
+/// disk of a peg can move, and never onto a smaller one.
+pub struct Hanoi<const N: usize> {
+    pub start: [u8; N],
+    pub target: [u8; N],
+}
+
+impl<const N: usize> Hanoi<N> {
+    /// The classic puzzle, every disk from the first peg to the last
+    pub fn new() -> Self {
+        Self {
+            start: [0; N],
+            target: [2; N],
+        }
+    }
+
+    /// disks not on their target peg, every one of them has at least one move left
+    pub fn misplaced(&self, state: &[u8; N]) -> f64 {
+        state
+            .iter()
+            .zip(self.target)
+            .filter(|(peg, target)| **peg != *target)
+            .count() as f64
+    }
+
+    /// the smallest disk on `peg`
+    fn top(state: &[u8; N], peg: u8) -> Option<usize> {
+        state.iter().position(|p| *p == peg)
+    }
+}
+
+impl<const N: usize> Default for Hanoi<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Graph for Hanoi<N> {
+    type V = [u8; N];
+    type E = (Self::V, Self::V);
+
+    fn out_vertices(&self, state: &Self::V) -> Vec<Self::V> {
+        let mut res = vec![];
+
+        for from in 0..3 {
+            let Some(disk) = Self::top(state, from) else {
+                continue;
+            };
+
+            for to in (0..3).filter(|to| *to != from) {
+                if Self::top(state, to).is_none_or(|top| top > disk) {
+                    let mut next = *state;
+                    next[disk] = to;
+                    res.push(next);
+                }
+            }
+        }
+
+        res
+    }
+
+    fn in_vertices(&self, state: &Self::V) -> Vec<Self::V> {
+        self.out_vertices(state)
+    }
+
+    fn out_edges(&self, state: &Self::V) -> Vec<Self::E> {
+        self.out_vertices(state)
+            .into_iter()
+            .map(|next| (*state, next))
+            .collect()
+    }
+
+    fn in_edges(&self, state: &Self::V) -> Vec<Self::E> {
+        self.in_vertices(state)
+            .into_iter()
+            .map(|previous| (previous, *state))
+            .collect()
+    }
+
+    fn edges(&self, from: &Self::V, to: &Self::V) -> Vec<Self::E> {
+        match self.out_vertices(from).contains(to) {
+            true => vec![(*from, *to)],
+            false => vec![],
+        }
+    }
+
+    fn vertices(&self, edge: &Self::E) -> (Self::V, Self::V) {
+        *edge
+    }
+
+    /// every legal state, all 3^N of them can be reached from any other
+    fn all_vertices(&self) -> Vec<Self::V> {
+        breadth_first_traverse(self, &self.start).collect()
+    }
+
+    fn all_edges(&self) -> Vec<Self::E> {
+        self.all_vertices()
+            .iter()
+            .flat_map(|state| self.out_edges(state))
+            .collect()
+    }
+}
+
+impl<const N: usize> Planning for Hanoi<N> {
+    fn problem(&self) -> Problem<'_, Self> {
+        Problem::between(self, self.start, self.target).heuristic(|state| self.misplaced(state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Strategy;
+
+    #[test]
+    fn test_solve() {
+        let hanoi = Hanoi::<5>::new();
+        assert_eq!(hanoi.all_vertices().len(), 243);
+
+        for strategy in [
+            Strategy::BreadthFirst,
+            Strategy::UniformCost,
+            Strategy::AStar,
+            Strategy::Bidirectional,
+        ] {
+            let search = hanoi.solve(strategy);
+            let solution = search.solution.unwrap();
+            assert_eq!(solution.cost, 31.0, "{:?}", strategy);
+            assert_eq!(Vec::from(solution.path).len(), 32);
+            assert!(search.stats.expanded <= 243);
+        }
+
+        let search = Hanoi::<3>::new().solve(Strategy::IterativeDeepening);
+        assert_eq!(search.solution.unwrap().cost, 7.0);
+        assert!(search.stats.max_frontier >= 8);
+    }
+
+    #[test]
+    fn test_moves() {
+        let hanoi = Hanoi::<3>::new();
+
+        // the smallest disk can go anywhere, the others have to stay under it
+        let mut moves = hanoi.out_vertices(&[0, 0, 0]);
+        moves.sort();
+        assert_eq!(moves, vec![[1, 0, 0], [2, 0, 0]]);
+
+        let mut moves = hanoi.out_vertices(&[1, 0, 0]);
+        moves.sort();
+        assert_eq!(moves, vec![[0, 0, 0], [1, 2, 0], [2, 0, 0]]);
+
+        let partway = Hanoi {
+            start: [2, 1, 0],
+            target: [1, 1, 1],
+        };
+        assert_eq!(partway.misplaced(&partway.start), 2.0);
+        assert!(partway.solve(Strategy::AStar).solution.is_some());
+    }
+}
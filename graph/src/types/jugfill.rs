@@ -1,4 +1,4 @@
-use crate::{algorithm::breadth_first_search, Graph, Planning};
+use crate::{algorithm::breadth_first_traverse, Graph, Planning, Problem};
 use std::vec;
 
 pub struct JugFill<const N: usize> {
@@ -46,8 +46,31 @@ impl<const N: usize> Graph for JugFill<N> {
         res
     }
 
+    /// pouring can't be undone, so these are found by trying every pour that could have ended in `node`
     fn in_vertices(&self, node: &Self::V) -> Vec<Self::V> {
-        self.out_vertices(node)
+        let mut res = vec![];
+
+        for i in 0..N {
+            for j in 0..N {
+                if i == j {
+                    continue;
+                }
+
+                for amount in 1..=node[j] {
+                    let mut old_water = *node;
+                    old_water[i] += amount;
+                    old_water[j] -= amount;
+
+                    if old_water[i] <= self.capacity[i]
+                        && self.out_vertices(&old_water).contains(node)
+                    {
+                        res.push(old_water);
+                    }
+                }
+            }
+        }
+
+        res
     }
 
     fn out_edges(&self, node: &Self::V) -> Vec<Self::E> {
@@ -81,26 +104,29 @@ impl<const N: usize> Graph for JugFill<N> {
         *edge
     }
 
+    /// the states reachable from `start`, the rest of the state space can never be visited
     fn all_vertices(&self) -> Vec<Self::V> {
-        todo!()
+        breadth_first_traverse(self, &self.start).collect()
     }
 
     fn all_edges(&self) -> Vec<Self::E> {
-        todo!()
+        self.all_vertices()
+            .iter()
+            .flat_map(|node| self.out_edges(node))
+            .collect()
     }
 }
 
 impl<const N: usize> Planning for JugFill<N> {
-    type Solution = Vec<[i32; N]>;
-
-    fn solve(&self) -> Option<Vec<[i32; N]>> {
-        breadth_first_search(self, &self.start, |node| node == &self.target).map(|path| path.into())
+    fn problem(&self) -> Problem<'_, Self> {
+        Problem::between(self, self.start, self.target)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Strategy;
 
     #[test]
     fn test() {
@@ -110,8 +136,43 @@ mod tests {
             target: [4, 4, 0],
         };
 
-        if let Some(path) = jugfill.solve() {
-            println!("{:?}", path);
+        for strategy in [
+            Strategy::BreadthFirst,
+            Strategy::IterativeDeepening,
+            Strategy::Bidirectional,
+        ] {
+            let path = Vec::from(jugfill.solve(strategy).solution.unwrap().path);
+            assert_eq!(path.len(), 8, "{:?}", strategy);
+            assert_eq!(path.first(), Some(&jugfill.start));
+            assert_eq!(path.last(), Some(&jugfill.target));
         }
+
+        let vertices = jugfill.all_vertices();
+        assert!(vertices.contains(&jugfill.start));
+        assert!(vertices.contains(&jugfill.target));
+        assert!(vertices.iter().all(|water| water.iter().sum::<i32>() == 8));
+        assert!(vertices.iter().all(|water| jugfill
+            .in_vertices(water)
+            .iter()
+            .all(|old_water| jugfill.out_vertices(old_water).contains(water))));
+        let mut old_water = jugfill.in_vertices(&[8, 0, 0]);
+        old_water.sort();
+        assert_eq!(
+            old_water,
+            vec![
+                [3, 5, 0],
+                [4, 4, 0],
+                [5, 0, 3],
+                [5, 3, 0],
+                [6, 0, 2],
+                [6, 2, 0],
+                [7, 0, 1],
+                [7, 1, 0]
+            ]
+        );
+        assert!(jugfill
+            .all_edges()
+            .iter()
+            .all(|(from, to)| vertices.contains(from) && vertices.contains(to)));
     }
 }
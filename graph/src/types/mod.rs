@@ -2,8 +2,18 @@ mod adjacency_list;
 pub mod ancestry;
 mod binary_grid;
 pub mod classic;
+mod csr;
+mod digraph;
+mod hanoi;
 mod jugfill;
+mod property_graph;
+mod sliding_puzzle;
 
 pub use adjacency_list::*;
 pub use binary_grid::*;
+pub use csr::*;
+pub use digraph::*;
+pub use hanoi::*;
 pub use jugfill::*;
+pub use property_graph::*;
+pub use sliding_puzzle::*;
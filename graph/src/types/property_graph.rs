@@ -0,0 +1,292 @@
+use crate::{
+    graph::{Graph, Mutable},
+    DiGraph, EdgeId, NodeId,
+};
+use std::{collections::HashMap, hash::Hash};
+
+/// A directed graph of labeled vertices connected by typed relations.
+///
+/// Vertices are looked up by their label, so every label is in the graph at most
+/// once, while any number of relations can connect the same two vertices.
+///
+/// ```
+/// use graph::PropertyGraph;
+///
+/// #[derive(PartialEq)]
+/// enum Link {
+///     Follows,
+///     Blocks,
+/// }
+///
+/// let mut graph = PropertyGraph::new();
+/// graph.add_relation("ann", "bob", Link::Follows);
+/// graph.add_relation("cat", "bob", Link::Follows);
+/// graph.add_relation("dan", "bob", Link::Blocks);
+///
+/// let mut followers = graph.in_related(&"bob", |link| link == &Link::Follows);
+/// followers.sort();
+/// assert_eq!(followers, vec![&"ann", &"cat"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PropertyGraph<V, R> {
+    graph: DiGraph<V, R>,
+    ids: HashMap<V, NodeId>,
+}
+
+impl<V, R> Default for PropertyGraph<V, R> {
+    fn default() -> Self {
+        Self {
+            graph: DiGraph::new(),
+            ids: HashMap::new(),
+        }
+    }
+}
+
+impl<V: Clone + Eq + Hash, R> PropertyGraph<V, R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the underlying graph, for the algorithms that work on node and edge ids
+    pub fn graph(&self) -> &DiGraph<V, R> {
+        &self.graph
+    }
+
+    /// Adds the vertex unless it's already there, either way returns its id
+    pub fn add_vertex(&mut self, v: V) -> NodeId {
+        match self.ids.get(&v) {
+            Some(id) => *id,
+            None => {
+                let id = self.graph.add_node(v.clone());
+                self.ids.insert(v, id);
+                id
+            }
+        }
+    }
+
+    /// Adds a relation between two vertices, adding the vertices first if they're new
+    pub fn add_relation(&mut self, from: V, to: V, relation: R) -> EdgeId {
+        let from = self.add_vertex(from);
+        let to = self.add_vertex(to);
+        self.graph.add_edge(from, to, relation)
+    }
+
+    /// Removes a vertex along with all its relations
+    pub fn remove_vertex(&mut self, v: &V) -> bool {
+        match self.ids.remove(v) {
+            Some(id) => self.graph.remove_node(id).is_some(),
+            None => false,
+        }
+    }
+
+    pub fn remove_relation(&mut self, id: EdgeId) -> Option<R> {
+        self.graph.remove_edge(id)
+    }
+
+    pub fn contains(&self, v: &V) -> bool {
+        self.ids.contains_key(v)
+    }
+
+    pub fn id(&self, v: &V) -> Option<NodeId> {
+        self.ids.get(v).copied()
+    }
+
+    pub fn vertex(&self, id: NodeId) -> Option<&V> {
+        self.graph.node(id)
+    }
+
+    pub fn relation(&self, id: EdgeId) -> Option<&R> {
+        self.graph.edge(id)
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    pub fn relation_count(&self) -> usize {
+        self.graph.edge_count()
+    }
+
+    pub fn vertices(&self) -> impl Iterator<Item = &V> + '_ {
+        self.graph.node_ids().map(|id| self.graph.node(id).unwrap())
+    }
+
+    /// every relation as (from, relation, to)
+    pub fn relations(&self) -> impl Iterator<Item = (&V, &R, &V)> + '_ {
+        self.graph.edge_ids().map(|id| self.describe(id))
+    }
+
+    /// vertices `v` has a matching relation to
+    pub fn out_related(&self, v: &V, is_relation: impl Fn(&R) -> bool) -> Vec<&V> {
+        self.related(v, is_relation, DiGraph::outgoing, |(_, _, to)| to)
+    }
+
+    /// vertices with a matching relation to `v`
+    pub fn in_related(&self, v: &V, is_relation: impl Fn(&R) -> bool) -> Vec<&V> {
+        self.related(v, is_relation, DiGraph::incoming, |(from, _, _)| from)
+    }
+
+    fn related<'a>(
+        &'a self,
+        v: &V,
+        is_relation: impl Fn(&R) -> bool,
+        edges: fn(&'a DiGraph<V, R>, NodeId) -> &'a [EdgeId],
+        other: fn((&'a V, &'a R, &'a V)) -> &'a V,
+    ) -> Vec<&'a V> {
+        let Some(id) = self.id(v) else {
+            return vec![];
+        };
+
+        edges(&self.graph, id)
+            .iter()
+            .map(|edge| self.describe(*edge))
+            .filter(|(_, relation, _)| is_relation(relation))
+            .map(other)
+            .collect()
+    }
+
+    fn describe(&self, id: EdgeId) -> (&V, &R, &V) {
+        let (from, to) = self.graph.endpoints(id).unwrap();
+        (
+            self.graph.node(from).unwrap(),
+            self.graph.edge(id).unwrap(),
+            self.graph.node(to).unwrap(),
+        )
+    }
+}
+
+impl<V, R> Graph for PropertyGraph<V, R> {
+    type V = NodeId;
+    type E = EdgeId;
+
+    fn out_vertices(&self, v: &Self::V) -> Vec<Self::V> {
+        self.graph.out_vertices(v)
+    }
+
+    fn in_vertices(&self, v: &Self::V) -> Vec<Self::V> {
+        self.graph.in_vertices(v)
+    }
+
+    fn out_edges(&self, v: &Self::V) -> Vec<Self::E> {
+        self.graph.out_edges(v)
+    }
+
+    fn in_edges(&self, v: &Self::V) -> Vec<Self::E> {
+        self.graph.in_edges(v)
+    }
+
+    fn edges(&self, from: &Self::V, to: &Self::V) -> Vec<Self::E> {
+        self.graph.edges(from, to)
+    }
+
+    fn vertices(&self, e: &Self::E) -> (Self::V, Self::V) {
+        self.graph.vertices(e)
+    }
+
+    fn all_vertices(&self) -> Vec<Self::V> {
+        self.graph.all_vertices()
+    }
+
+    fn all_edges(&self) -> Vec<Self::E> {
+        self.graph.all_edges()
+    }
+}
+
+impl<V: Clone + Eq + Hash, R> Mutable for PropertyGraph<V, R> {
+    type V = V;
+    type E = (V, V, R);
+
+    fn add_vertex(&mut self, v: Self::V) {
+        PropertyGraph::add_vertex(self, v);
+    }
+
+    fn add_edge(&mut self, (from, to, relation): Self::E) {
+        self.add_relation(from, to, relation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Query;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Dep {
+        Normal,
+        Dev,
+    }
+
+    fn crates() -> PropertyGraph<&'static str, Dep> {
+        let mut graph = PropertyGraph::new();
+        graph.add_relation("app", "serde", Dep::Normal);
+        graph.add_relation("app", "criterion", Dep::Dev);
+        graph.add_relation("app", "rayon", Dep::Normal);
+        graph.add_relation("rayon", "rayon-core", Dep::Normal);
+        graph.add_relation("criterion", "rayon", Dep::Normal);
+        graph.add_relation("criterion", "serde", Dep::Normal);
+        graph
+    }
+
+    fn sorted<'a>(mut items: Vec<&'a &'static str>) -> Vec<&'a &'static str> {
+        items.sort();
+        items
+    }
+
+    #[test]
+    fn test_relations() {
+        let graph = crates();
+        assert_eq!(graph.vertex_count(), 5);
+        assert_eq!(graph.relation_count(), 6);
+
+        assert_eq!(
+            sorted(graph.out_related(&"app", |dep| dep == &Dep::Normal)),
+            vec![&"rayon", &"serde"]
+        );
+        assert_eq!(
+            graph.out_related(&"app", |dep| dep == &Dep::Dev),
+            vec![&"criterion"]
+        );
+        assert_eq!(
+            sorted(graph.in_related(&"serde", |_| true)),
+            vec![&"app", &"criterion"]
+        );
+        assert!(graph.in_related(&"left-pad", |_| true).is_empty());
+
+        let dev: Vec<_> = graph
+            .relations()
+            .filter(|(_, dep, _)| **dep == Dep::Dev)
+            .collect();
+        assert_eq!(dev, vec![(&"app", &Dep::Dev, &"criterion")]);
+    }
+
+    #[test]
+    fn test_vertices_are_unique() {
+        let mut graph = crates();
+        let serde = graph.id(&"serde").unwrap();
+        assert_eq!(graph.add_vertex("serde"), serde);
+        assert_eq!(graph.vertex(serde), Some(&"serde"));
+
+        assert!(graph.remove_vertex(&"criterion"));
+        assert!(!graph.remove_vertex(&"criterion"));
+        assert!(!graph.contains(&"criterion"));
+        assert_eq!(graph.relation_count(), 3);
+        assert_eq!(graph.in_related(&"serde", |_| true), vec![&"app"]);
+    }
+
+    #[test]
+    fn test_query() {
+        let graph = crates();
+        let app = graph.id(&"app").unwrap();
+
+        // the crates exactly two hops out from app
+        let mut two_hops: Vec<&str> = Query::new(&graph)
+            .starting_at([app])
+            .repeat(|q| q.out())
+            .times(2)
+            .dedup()
+            .map(|id| *graph.vertex(id).unwrap())
+            .collect();
+        two_hops.sort();
+        assert_eq!(two_hops, vec!["rayon", "rayon-core", "serde"]);
+    }
+}
@@ -0,0 +1,244 @@
+use crate::{algorithm::breadth_first_traverse, Graph, Planning, Problem};
+
+/// The sliding tile puzzle on a `W` by `H` board, the 8 puzzle is `SlidingPuzzle<3, 3>`.
+///
+/// Tiles are numbered from 1 and 0 is the gap. The puzzle is solved when the
+/// tiles are in order row by row with the gap in the bottom right corner.
+pub struct SlidingPuzzle<const W: usize, const H: usize> {
+    start: [[u8; W]; H],
+}
+
+impl<const W: usize, const H: usize> SlidingPuzzle<W, H> {
+    /// None unless `start` has the gap and every tile from 1 to `W * H - 1` once
+    pub fn new(start: [[u8; W]; H]) -> Option<Self> {
+        let mut tiles: Vec<usize> = start.iter().flatten().map(|tile| *tile as usize).collect();
+        tiles.sort_unstable();
+
+        tiles.into_iter().eq(0..W * H).then_some(Self { start })
+    }
+
+    pub const fn start(&self) -> &[[u8; W]; H] {
+        &self.start
+    }
+
+    pub fn solved() -> [[u8; W]; H] {
+        let mut board = [[0; W]; H];
+        for (r, row) in board.iter_mut().enumerate() {
+            for (c, tile) in row.iter_mut().enumerate() {
+                *tile = ((r * W + c + 1) % (W * H)) as u8;
+            }
+        }
+        board
+    }
+
+    /// Only half of all boards can be slid into order, the other half is a tile swap away
+    pub fn is_solvable(&self) -> bool {
+        let tiles: Vec<u8> = self
+            .start
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|t| *t != 0)
+            .collect();
+        let inversions = (0..tiles.len())
+            .flat_map(|i| (i + 1..tiles.len()).map(move |j| (i, j)))
+            .filter(|(i, j)| tiles[*i] > tiles[*j])
+            .count();
+
+        // on an even width board every vertical move of the gap also flips the parity
+        let (gap_row, _) = Self::gap(&self.start);
+        match W % 2 {
+            0 => (inversions + H - 1 - gap_row).is_multiple_of(2),
+            _ => inversions.is_multiple_of(2),
+        }
+    }
+
+    /// How far every tile is from where it belongs, never more than the moves left
+    pub fn manhattan(board: &[[u8; W]; H]) -> f64 {
+        let mut distance = 0;
+        for (r, row) in board.iter().enumerate() {
+            for (c, tile) in row.iter().enumerate() {
+                if *tile != 0 {
+                    let home = *tile as usize - 1;
+                    distance += (home / W).abs_diff(r) + (home % W).abs_diff(c);
+                }
+            }
+        }
+        distance as f64
+    }
+
+    fn gap(board: &[[u8; W]; H]) -> (usize, usize) {
+        (0..H)
+            .flat_map(|r| (0..W).map(move |c| (r, c)))
+            .find(|(r, c)| board[*r][*c] == 0)
+            .expect("a board has a gap")
+    }
+}
+
+impl<const W: usize, const H: usize> Graph for SlidingPuzzle<W, H> {
+    type V = [[u8; W]; H];
+    type E = (Self::V, Self::V);
+
+    /// every board one tile slide into the gap away
+    fn out_vertices(&self, board: &Self::V) -> Vec<Self::V> {
+        let (r, c) = Self::gap(board);
+        let neighbors = [
+            (r.checked_sub(1), Some(c)),
+            (Some(r + 1).filter(|r| *r < H), Some(c)),
+            (Some(r), c.checked_sub(1)),
+            (Some(r), Some(c + 1).filter(|c| *c < W)),
+        ];
+
+        neighbors
+            .into_iter()
+            .filter_map(|(tile_r, tile_c)| Some((tile_r?, tile_c?)))
+            .map(|(tile_r, tile_c)| {
+                let mut next = *board;
+                next[r][c] = board[tile_r][tile_c];
+                next[tile_r][tile_c] = 0;
+                next
+            })
+            .collect()
+    }
+
+    fn in_vertices(&self, board: &Self::V) -> Vec<Self::V> {
+        self.out_vertices(board)
+    }
+
+    fn out_edges(&self, board: &Self::V) -> Vec<Self::E> {
+        self.out_vertices(board)
+            .into_iter()
+            .map(|next| (*board, next))
+            .collect()
+    }
+
+    fn in_edges(&self, board: &Self::V) -> Vec<Self::E> {
+        self.in_vertices(board)
+            .into_iter()
+            .map(|previous| (previous, *board))
+            .collect()
+    }
+
+    fn edges(&self, from: &Self::V, to: &Self::V) -> Vec<Self::E> {
+        match self.out_vertices(from).contains(to) {
+            true => vec![(*from, *to)],
+            false => vec![],
+        }
+    }
+
+    fn vertices(&self, edge: &Self::E) -> (Self::V, Self::V) {
+        *edge
+    }
+
+    /// the boards reachable from `start`, half of all of them
+    fn all_vertices(&self) -> Vec<Self::V> {
+        breadth_first_traverse(self, &self.start).collect()
+    }
+
+    fn all_edges(&self) -> Vec<Self::E> {
+        self.all_vertices()
+            .iter()
+            .flat_map(|board| self.out_edges(board))
+            .collect()
+    }
+}
+
+impl<const W: usize, const H: usize> Planning for SlidingPuzzle<W, H> {
+    fn problem(&self) -> Problem<'_, Self> {
+        Problem::between(self, self.start, Self::solved()).heuristic(Self::manhattan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Strategy;
+
+    #[test]
+    fn test_solve() {
+        let puzzle = SlidingPuzzle::new([[4, 1, 3], [7, 2, 6], [0, 5, 8]]).unwrap();
+        assert!(puzzle.is_solvable());
+        assert_eq!(SlidingPuzzle::<3, 3>::manhattan(puzzle.start()), 6.0);
+
+        let mut expanded = vec![];
+        for strategy in [
+            Strategy::BreadthFirst,
+            Strategy::IterativeDeepening,
+            Strategy::UniformCost,
+            Strategy::AStar,
+            Strategy::Bidirectional,
+        ] {
+            let search = puzzle.solve(strategy);
+            let path = Vec::from(search.solution.unwrap().path);
+            assert_eq!(path.len(), 7, "{:?}", strategy);
+            assert_eq!(path.last(), Some(&SlidingPuzzle::solved()));
+            expanded.push(search.stats.expanded);
+        }
+
+        // every tile is out of place by exactly the moves it takes, so A* walks straight there
+        assert_eq!(expanded[3], 6);
+        assert!(expanded[3] < expanded[0]);
+    }
+
+    #[test]
+    fn test_solvable() {
+        let swapped = SlidingPuzzle::new([[2, 1, 3], [4, 5, 6], [7, 8, 0]]).unwrap();
+        assert!(!swapped.is_solvable());
+
+        let wide = SlidingPuzzle::new([[1, 2, 3, 4], [5, 6, 0, 8], [9, 10, 7, 11]]).unwrap();
+        assert!(wide.is_solvable());
+        assert_eq!(
+            Vec::from(wide.solve(Strategy::AStar).solution.unwrap().path).len(),
+            3
+        );
+        assert_eq!(SlidingPuzzle::<4, 3>::solved()[2], [9, 10, 11, 0]);
+    }
+
+    #[test]
+    fn test_invalid_boards() {
+        assert!(SlidingPuzzle::new([[1, 2], [3, 4]]).is_none());
+        assert!(SlidingPuzzle::new([[1, 0], [3, 3]]).is_none());
+        assert!(SlidingPuzzle::new([[0; 16]; 16]).is_none());
+        assert!(SlidingPuzzle::new([[3, 1], [0, 2]]).is_some());
+    }
+
+    #[test]
+    fn test_unsolvable_small() {
+        // two tiles swapped, 360 boards are reachable and none of them is solved
+        let swapped = SlidingPuzzle::new([[2, 1, 3], [4, 5, 0]]).unwrap();
+        assert!(!swapped.is_solvable());
+        assert_eq!(swapped.all_vertices().len(), 360);
+
+        // iterative deepening only gives up once no path is cut off, which
+        // takes every path without repeats, so it gets the 12 boards of a 2x2 ring
+        for strategy in [
+            Strategy::BreadthFirst,
+            Strategy::UniformCost,
+            Strategy::AStar,
+            Strategy::Bidirectional,
+        ] {
+            assert!(swapped.solve(strategy).solution.is_none(), "{:?}", strategy);
+        }
+
+        let ring = SlidingPuzzle::new([[2, 1], [3, 0]]).unwrap();
+        assert!(!ring.is_solvable());
+        assert_eq!(ring.all_vertices().len(), 12);
+        for strategy in [
+            Strategy::BreadthFirst,
+            Strategy::IterativeDeepening,
+            Strategy::UniformCost,
+            Strategy::AStar,
+            Strategy::Bidirectional,
+        ] {
+            assert!(ring.solve(strategy).solution.is_none(), "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    #[ignore = "enumerates all 181,440 boards reachable from an unsolvable start"]
+    fn test_unsolvable() {
+        let swapped = SlidingPuzzle::new([[2, 1, 3], [4, 5, 6], [7, 8, 0]]).unwrap();
+        assert_eq!(swapped.all_vertices().len(), 181_440);
+        assert!(swapped.solve(Strategy::Bidirectional).solution.is_none());
+    }
+}
@@ -29,7 +29,7 @@ pub use abstract_declarator::AbstractDeclarator;
 pub use declaration::Declaration;
 pub use declaration_specifier::DeclarationSpecifier;
 pub use declarator::Declarator;
-pub use direct_abstract_declarator::DirectAbstractDeclarator;
+pub use direct_abstract_declarator::{DirectAbstractDeclarator, DirectAbstractDeclaratorTail};
 pub use direct_declarator::{DirectDeclarator, DirectDeclaratorTail};
 pub use external_declaration::ExternalDeclaration;
 pub use function_definition::FunctionDefinition;
@@ -16,6 +16,10 @@ enum Type<'text> {
     Function {
         return_ty: Box<Type<'text>>,
         param_tys: Vec<Type<'text>>,
+        variadic: bool,
+        /// `false` for old style declarations (`int f();`, K&R definitions)
+        /// which don't specify the parameter types for the callers
+        prototyped: bool,
     },
     Struct {
         name: &'text str,
@@ -66,6 +70,24 @@ enum SemanticError<'ast, 'text> {
     InvalidPostfixOperand(&'ast ast::PostfixExpr<'text>),
     NotAFunction(&'ast ast::PostfixExpr<'text>),
     InvalidFnCall(&'ast ast::PostfixExpr<'text>),
+    ArgumentCountMismatch {
+        call: &'ast ast::PostfixExpr<'text>,
+        expected: usize,
+        actual: usize,
+    },
+    ArgumentTypeMismatch {
+        call: &'ast ast::PostfixExpr<'text>,
+        expected: Type<'text>,
+        actual: Type<'text>,
+    },
+    IncompatibleRedeclaration {
+        name: &'text str,
+        previous: Type<'text>,
+        current: Type<'text>,
+    },
+    FunctionRedefinition(&'text str),
+    IdentifierListInDeclaration(&'ast ast::Declarator<'text>),
+    NotAParameter(&'text str),
     UndefinedMember {
         struct_name: &'text str,
         field: &'text str,
@@ -94,6 +116,7 @@ enum SemanticError<'ast, 'text> {
 
 enum Symbol<'text> {
     Var(Var<'text>),
+    Function(Function<'text>),
     Label(Label<'text>),
     Enum(Enum<'text>),
 }
@@ -103,6 +126,15 @@ struct Var<'text> {
     ty: Type<'text>,
 }
 
+struct Function<'text> {
+    name: &'text str,
+    ty: Type<'text>,
+    defined: bool,
+}
+
+/// parameter name (optional in prototypes) and its type
+type Param<'text> = (Option<&'text str>, Type<'text>);
+
 struct Label<'text>(&'text str);
 
 struct Enum<'text> {
@@ -202,15 +234,11 @@ impl<'text> SemanticContext<'text> {
         let scope = self.curr_scope_mut();
 
         // cannot redeclare variable
-        if scope
-            .symbols
-            .iter()
-            .filter_map(|s| match s {
-                Symbol::Var(v) => Some(v),
-                _ => None,
-            })
-            .any(|var_| var_.name == var.name)
-        {
+        if scope.symbols.iter().any(|s| match s {
+            Symbol::Var(var_) => var_.name == var.name,
+            Symbol::Function(f) => f.name == var.name,
+            _ => false,
+        }) {
             return false;
         }
 
@@ -230,6 +258,62 @@ impl<'text> SemanticContext<'text> {
             .find(|var| var.name == name)
     }
 
+    fn declare_fn<'ast>(&mut self, f: Function<'text>) -> Result<(), SemanticError<'ast, 'text>> {
+        if self
+            .curr_scope()
+            .symbols
+            .iter()
+            .any(|s| matches!(s, Symbol::Var(v) if v.name == f.name))
+        {
+            return Err(SemanticError::VariableRedeclaration(f.name));
+        }
+
+        // functions always have linkage, so every declaration of a name
+        // (even a prototype inside a block) refers to the same function.
+        // keep them in the file scope so that later declarations can be checked against it.
+        let file_scope = self
+            .symbol_table
+            .first_mut()
+            .expect("must have atleast one scope");
+
+        let prev = file_scope.symbols.iter_mut().find_map(|s| match s {
+            Symbol::Function(prev) if prev.name == f.name => Some(prev),
+            _ => None,
+        });
+
+        let Some(prev) = prev else {
+            file_scope.symbols.push(Symbol::Function(f));
+            return Ok(());
+        };
+
+        if !is_compatible(&prev.ty, &f.ty) {
+            return Err(SemanticError::IncompatibleRedeclaration {
+                name: f.name,
+                previous: prev.ty.clone(),
+                current: f.ty,
+            });
+        }
+
+        if prev.defined && f.defined {
+            return Err(SemanticError::FunctionRedefinition(f.name));
+        }
+
+        prev.ty = composite_fn_ty(prev.ty.clone(), f.ty);
+        prev.defined |= f.defined;
+        Ok(())
+    }
+
+    fn find_fn<'ctx>(&'ctx self, name: &'text str) -> Option<&'ctx Function<'text>> {
+        self.symbol_table
+            .iter()
+            .flat_map(|scope| scope.symbols.iter())
+            .filter_map(|s| match s {
+                Symbol::Function(f) => Some(f),
+                _ => None,
+            })
+            .find(|f| f.name == name)
+    }
+
     fn declare_label(&mut self, label: &'text str) -> bool {
         // labels are function scoped.
         // so checking for label just inside local scope is not enough
@@ -334,79 +418,143 @@ fn analyze_function_definition<'ast, 'text>(
     ctx: &mut SemanticContext<'text>,
 ) -> Result<(), SemanticError<'ast, 'text>> {
     use ast::StorageClassSpecifier as SCS;
-    use ast::TypeSpecifier as TS;
 
-    let mut pos = 0;
+    if f.declaration_specifiers.iter().any(|ds| {
+        matches!(
+            ds,
+            DeclarationSpecifier::StorageClassSpecifier(scs)
+                if scs != &SCS::Static && scs != &SCS::Extern
+        )
+    }) {
+        return Err(SemanticError::InvalidDSS(&f.declaration_specifiers));
+    }
 
-    let scs = match f.declaration_specifiers.get(pos) {
-        None => None,
-        Some(DeclarationSpecifier::StorageClassSpecifier(scs))
-            if scs == &SCS::Static || scs == &SCS::Extern =>
-        {
-            pos += 1;
-            Some(scs)
+    let ty = analyze_declaration_specifiers(&f.declaration_specifiers, ctx)?;
+    let (name, mut ty) = analyze_declarator(&f.declarator, ty, ctx)?;
+
+    let params = match fn_declarator_tail(&f.declarator) {
+        Some(ast::DirectDeclaratorTail::Function(ptl, _)) if f.declarations.is_empty() => {
+            // every parameter of a function definition must be named
+            analyze_parameter_type_list(ptl, ctx)?
+                .0
+                .into_iter()
+                .map(|(name, ty)| name.map(|name| (name, ty)))
+                .collect::<Option<Vec<_>>>()
+                .ok_or(SemanticError::InvalidFunctionDefinition(f))?
         }
-        _ => return Err(SemanticError::InvalidDSS(&f.declaration_specifiers)),
+        Some(ast::DirectDeclaratorTail::Parameters(idents, _)) => {
+            let params = analyze_kr_parameters(idents, &f.declarations, ctx)?;
+            if let Type::Function { param_tys, .. } = &mut ty {
+                *param_tys = params.iter().map(|(_, ty)| ty.clone()).collect();
+            }
+            params
+        }
+        _ => return Err(SemanticError::InvalidFunctionDefinition(f)),
     };
 
-    let mut tss = Vec::with_capacity(f.declaration_specifiers.len());
-    for ds in &f.declaration_specifiers[pos..] {
-        match ds {
-            DeclarationSpecifier::TypeSpecifier(ts) => tss.push(ts),
-            _ => return Err(SemanticError::InvalidDSS(&f.declaration_specifiers)),
+    let Type::Function { return_ty, .. } = &ty else {
+        return Err(SemanticError::InvalidFunctionDefinition(f));
+    };
+    let return_ty = return_ty.as_ref().clone();
+
+    ctx.declare_fn(Function {
+        name,
+        ty,
+        defined: true,
+    })?;
+
+    ctx.scoped(ScopeKind::Fn(return_ty), |ctx| {
+        for (name, ty) in params {
+            if !ctx.declare_var(Var { name, ty }) {
+                return Err(SemanticError::VariableRedeclaration(name));
+            }
         }
-    }
 
-    let a = match tss.as_slice() {
-        [TS::Void] => Ok(Type::Void),
-        [TS::Char] => Ok(Type::Char),
-        [TS::Signed, TS::Char] => Ok(Type::SignedChar),
-        [TS::UnSigned, TS::Char] => Ok(Type::UnSignedChar),
-        [TS::Short]
-        | [TS::Signed, TS::Short]
-        | [TS::Short, TS::Int]
-        | [TS::Signed, TS::Short, TS::Int] => Ok(Type::Short),
-        [TS::UnSigned, TS::Short] | [TS::UnSigned, TS::Short, TS::Int] => Ok(Type::UnSignedShort),
-        [TS::Int] | [TS::Signed] | [TS::Signed, TS::Int] => Ok(Type::Int),
-        [TS::UnSigned] | [TS::UnSigned, TS::Int] => Ok(Type::UnSigned),
-        [TS::Long]
-        | [TS::Signed, TS::Long]
-        | [TS::Long, TS::Int]
-        | [TS::Signed, TS::Long, TS::Int] => Ok(Type::Long),
-        [TS::UnSigned, TS::Long] | [TS::UnSigned, TS::Long, TS::Int] => Ok(Type::UnSignedLong),
-        [TS::Long, TS::Long]
-        | [TS::Signed, TS::Long, TS::Long]
-        | [TS::Long, TS::Long, TS::Int]
-        | [TS::Signed, TS::Long, TS::Long, TS::Int] => Ok(Type::LongLong),
-        [TS::UnSigned, TS::Long, TS::Long] | [TS::UnSigned, TS::Long, TS::Long, TS::Int] => {
-            Ok(Type::UnSignedLongLong)
-        }
-        [TS::Float] => Ok(Type::Float),
-        [TS::Double] => Ok(Type::Double),
-        [TS::Long, TS::Double] => Ok(Type::LongDouble),
-        [TS::StructOrUnionSpecifier(sou)] => analyze_struct_or_union_specifier(sou, ctx),
-        [TS::EnumSpecifier(e)] => analyze_enum_specifier(e, ctx),
-        [TS::TypeDefName(name)] => {
-            // ctx.find_typedef(name);
-            todo!()
+        // parameters share the scope of the outermost block of the body
+        f.body
+            .0
+            .iter()
+            .map(|item| match item {
+                ast::BlockItem::Declaration(d) => analyze_declaration(d, ctx),
+                ast::BlockItem::Statement(stmt) => analyze_stmt(stmt, ctx),
+            })
+            .find(Result::is_err)
+            .unwrap_or(Ok(()))
+    })
+}
+
+/// Types of the parameters of a K&R style function definition
+/// `int add(a, b) int a; int b; { ... }`
+fn analyze_kr_parameters<'ast, 'text>(
+    idents: &[&'text str],
+    declarations: &'ast [ast::Declaration<'text>],
+    ctx: &mut SemanticContext<'text>,
+) -> Result<Vec<(&'text str, Type<'text>)>, SemanticError<'ast, 'text>> {
+    let mut params = idents
+        .iter()
+        .map(|ident| (*ident, None))
+        .collect::<Vec<(&'text str, Option<Type<'text>>)>>();
+
+    for declaration in declarations {
+        let ty = analyze_declaration_specifiers(&declaration.declaration_specifiers, ctx)?;
+
+        for init_d in &declaration.init_declarators {
+            let ast::InitDeclarator::Declared(d) = init_d else {
+                return Err(SemanticError::InvalidInitializer);
+            };
+
+            let (name, ty) = analyze_declarator(d, ty.clone(), ctx)?;
+            match params.iter_mut().find(|(ident, _)| *ident == name) {
+                None => return Err(SemanticError::NotAParameter(name)),
+                Some((_, Some(_))) => return Err(SemanticError::VariableRedeclaration(name)),
+                Some((_, param_ty)) => *param_ty = Some(decay(ty)),
+            }
         }
-        _ => todo!(),
-    };
+    }
 
-    todo!()
+    // parameters missing from the declaration list are int
+    Ok(params
+        .into_iter()
+        .map(|(ident, ty)| (ident, ty.unwrap_or(Type::Int)))
+        .collect())
 }
 
 fn analyze_declaration<'ast, 'text>(
     declaration: &'ast ast::Declaration<'text>,
     ctx: &mut SemanticContext<'text>,
 ) -> Result<(), SemanticError<'ast, 'text>> {
+    // TODO: typedefs
+    if declaration.declaration_specifiers.iter().any(|ds| {
+        matches!(
+            ds,
+            DeclarationSpecifier::StorageClassSpecifier(ast::StorageClassSpecifier::TypeDef)
+        )
+    }) {
+        return Ok(());
+    }
+
     let ty = analyze_declaration_specifiers(&declaration.declaration_specifiers, ctx)?;
 
     for init_d in &declaration.init_declarators {
-        let d_ty = analyze_init_declarator(init_d, ctx)?;
-        // if ty != d_ty {
-        //     return Err(SemanticError::TypeMismatch(ty, d_ty));
-        // }
+        let (ast::InitDeclarator::Declared(d) | ast::InitDeclarator::Initialized(d, _)) = init_d;
+
+        // an identifier list is only allowed in a function definition
+        if let Some(ast::DirectDeclaratorTail::Parameters(_, _)) = fn_declarator_tail(d) {
+            return Err(SemanticError::IdentifierListInDeclaration(d));
+        }
+
+        match analyze_init_declarator(init_d, ty.clone(), ctx)? {
+            (name, ty @ Type::Function { .. }) => ctx.declare_fn(Function {
+                name,
+                ty,
+                defined: false,
+            })?,
+            (name, ty) => {
+                if !ctx.declare_var(Var { name, ty }) {
+                    return Err(SemanticError::VariableRedeclaration(name));
+                }
+            }
+        }
     }
 
     Ok(())
@@ -416,20 +564,35 @@ fn analyze_declaration_specifiers<'ast, 'text>(
     dss: &'ast [ast::DeclarationSpecifier<'text>],
     ctx: &mut SemanticContext<'text>,
 ) -> Result<Type<'text>, SemanticError<'ast, 'text>> {
-    todo!()
+    // TODO: check TypeQualifiers (const, volatile)
+
+    let tss = dss
+        .iter()
+        .filter_map(|ds| match ds {
+            DeclarationSpecifier::TypeSpecifier(ts) => Some(ts),
+            _ => None,
+        })
+        .collect::<Vec<&ast::TypeSpecifier<'text>>>();
+
+    analyze_type_specifiers(tss, SemanticError::InvalidDSS(dss), ctx)
 }
 
 fn analyze_init_declarator<'ast, 'text>(
     init_d: &'ast ast::InitDeclarator<'text>,
+    ty: Type<'text>,
     ctx: &mut SemanticContext<'text>,
-) -> Result<Type<'text>, SemanticError<'ast, 'text>> {
+) -> Result<(&'text str, Type<'text>), SemanticError<'ast, 'text>> {
     match init_d {
-        ast::InitDeclarator::Declared(d) => analyze_declarator(d, ctx),
+        ast::InitDeclarator::Declared(d) => analyze_declarator(d, ty, ctx),
         ast::InitDeclarator::Initialized(d, init) => {
-            let d_ty = analyze_declarator(d, ctx)?;
+            let (name, d_ty) = analyze_declarator(d, ty, ctx)?;
+            if let Type::Function { .. } = d_ty {
+                return Err(SemanticError::InvalidInitializer);
+            }
+
             let init_ty = analyze_initializer(init, ctx)?;
-            match d_ty == init_ty {
-                true => Ok(d_ty),
+            match is_compatible(&d_ty, &decay(init_ty.clone())) {
+                true => Ok((name, d_ty)),
                 false => Err(SemanticError::TypeMismatch(d_ty, init_ty)),
             }
         }
@@ -438,12 +601,25 @@ fn analyze_init_declarator<'ast, 'text>(
 
 fn analyze_declarator<'ast, 'text>(
     declarator: &'ast ast::Declarator<'text>,
+    ty: Type<'text>,
     ctx: &mut SemanticContext<'text>,
-) -> Result<Type<'text>, SemanticError<'ast, 'text>> {
-    if let Some(pointer) = &declarator.pointer {
-        analyze_pointer(pointer, ctx)?;
+) -> Result<(&'text str, Type<'text>), SemanticError<'ast, 'text>> {
+    let ty = match &declarator.pointer {
+        Some(pointer) => analyze_pointer(pointer, ty, ctx)?,
+        None => ty,
+    };
+    analyze_direct_declarator(&declarator.d_declarator, ty, ctx)
+}
+
+/// The tail attached directly to the declared identifier.
+/// for a function declarator that is its parameter list.
+fn fn_declarator_tail<'ast, 'text>(
+    declarator: &'ast ast::Declarator<'text>,
+) -> Option<&'ast ast::DirectDeclaratorTail<'text>> {
+    match &declarator.d_declarator {
+        ast::DirectDeclarator::Ident(_, tail) => tail.as_ref(),
+        ast::DirectDeclarator::Parens(d, tail) => fn_declarator_tail(d).or(tail.as_ref()),
     }
-    analyze_direct_declarator(&declarator.d_declarator, ctx)
 }
 
 fn analyze_initializer<'ast, 'text>(
@@ -458,36 +634,172 @@ fn analyze_initializer<'ast, 'text>(
 
 fn analyze_pointer<'ast, 'text>(
     pointer: &'ast ast::Pointer,
+    ty: Type<'text>,
     ctx: &mut SemanticContext<'text>,
-) -> Result<(), SemanticError<'ast, 'text>> {
-    Ok(())
+) -> Result<Type<'text>, SemanticError<'ast, 'text>> {
+    // TODO: check TypeQualifiers (const, volatile)
+    Ok(pointer
+        .into_iter()
+        .fold(ty, |ty, _| Type::Pointer(Box::new(ty))))
 }
 
 fn analyze_direct_declarator<'ast, 'text>(
     d_declarator: &'ast ast::DirectDeclarator<'text>,
+    ty: Type<'text>,
+    ctx: &mut SemanticContext<'text>,
+) -> Result<(&'text str, Type<'text>), SemanticError<'ast, 'text>> {
+    match d_declarator {
+        ast::DirectDeclarator::Ident(ident, tail) => {
+            let ty = match tail {
+                Some(tail) => analyze_direct_declarator_tail(tail, ty, ctx)?,
+                None => ty,
+            };
+            Ok((ident, ty))
+        }
+        ast::DirectDeclarator::Parens(d, tail) => {
+            let ty = match tail {
+                Some(tail) => analyze_direct_declarator_tail(tail, ty, ctx)?,
+                None => ty,
+            };
+            analyze_declarator(d, ty, ctx)
+        }
+    }
+}
+
+fn analyze_direct_declarator_tail<'ast, 'text>(
+    tail: &'ast ast::DirectDeclaratorTail<'text>,
+    ty: Type<'text>,
     ctx: &mut SemanticContext<'text>,
 ) -> Result<Type<'text>, SemanticError<'ast, 'text>> {
-    todo!()
+    match tail {
+        ast::DirectDeclaratorTail::Array(len, tail) => {
+            let ty = match tail {
+                Some(tail) => analyze_direct_declarator_tail(tail, ty, ctx)?,
+                None => ty,
+            };
+            Ok(Type::Array(Box::new(ty), array_len(len.as_ref())))
+        }
+        ast::DirectDeclaratorTail::Function(ptl, tail) => {
+            let return_ty = match tail {
+                Some(tail) => analyze_direct_declarator_tail(tail, ty, ctx)?,
+                None => ty,
+            };
+            analyze_fn_declarator(Some(ptl), return_ty, ctx)
+        }
+        ast::DirectDeclaratorTail::Parameters(idents, tail) => {
+            let return_ty = match tail {
+                Some(tail) => analyze_direct_declarator_tail(tail, ty, ctx)?,
+                None => ty,
+            };
+            // the parameter types come from the declaration list
+            // of the function definition. until then they are int
+            Ok(Type::Function {
+                return_ty: Box::new(return_ty),
+                param_tys: vec![Type::Int; idents.len()],
+                variadic: false,
+                prototyped: false,
+            })
+        }
+    }
 }
 
-fn analyze_type_name<'ast, 'text>(
-    type_name: &'ast ast::TypeName<'text>,
+fn analyze_fn_declarator<'ast, 'text>(
+    ptl: Option<&'ast ast::ParameterTypeList<'text>>,
+    return_ty: Type<'text>,
     ctx: &mut SemanticContext<'text>,
 ) -> Result<Type<'text>, SemanticError<'ast, 'text>> {
-    let base_ty = analyze_specifier_qualifiers(&type_name.specifier_qualifiers, ctx)?;
+    let (params, variadic) = match ptl {
+        Some(ptl) => analyze_parameter_type_list(ptl, ctx)?,
+        None => (vec![], false),
+    };
 
-    match type_name.abstract_declarator.as_ref() {
-        Some(ad) => {
-            let declarator_ty = analyze_abstract_declarator(ad, ctx)?;
+    // `f()` says nothing about the parameters, unlike `f(void)`
+    let prototyped = match ptl {
+        Some(ast::ParameterTypeList::ParameterList(params)) => !params.is_empty(),
+        Some(ast::ParameterTypeList::VariadicParameterList(_)) => true,
+        None => false,
+    };
 
-            // let a = match (base_ty, declarator_ty) {
-            // }
+    Ok(Type::Function {
+        return_ty: Box::new(return_ty),
+        param_tys: params.into_iter().map(|(_, ty)| ty).collect(),
+        variadic,
+        prototyped,
+    })
+}
 
-            todo!()
+fn analyze_parameter_type_list<'ast, 'text>(
+    ptl: &'ast ast::ParameterTypeList<'text>,
+    ctx: &mut SemanticContext<'text>,
+) -> Result<(Vec<Param<'text>>, bool), SemanticError<'ast, 'text>> {
+    let (params, variadic) = match ptl {
+        ast::ParameterTypeList::ParameterList(params) => (params, false),
+        ast::ParameterTypeList::VariadicParameterList(params) => (params, true),
+    };
 
-            // match (base_ty, declarator_ty) {
-            // }
+    let params = params
+        .iter()
+        .map(|param| analyze_parameter_declaration(param, ctx))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    match params.as_slice() {
+        [(None, Type::Void)] if !variadic => Ok((vec![], variadic)),
+        _ => Ok((params, variadic)),
+    }
+}
+
+fn analyze_parameter_declaration<'ast, 'text>(
+    param: &'ast ast::ParameterDeclaration<'text>,
+    ctx: &mut SemanticContext<'text>,
+) -> Result<Param<'text>, SemanticError<'ast, 'text>> {
+    let (name, ty) = match param {
+        ast::ParameterDeclaration::WithDeclarator(dss, d) => {
+            let ty = analyze_declaration_specifiers(dss, ctx)?;
+            let (name, ty) = analyze_declarator(d, ty, ctx)?;
+            (Some(name), ty)
+        }
+        ast::ParameterDeclaration::WithAbstractDeclarator(dss, ad) => {
+            let ty = analyze_declaration_specifiers(dss, ctx)?;
+            (None, analyze_abstract_declarator(ad, ty, ctx)?)
+        }
+        ast::ParameterDeclaration::OnlySpecifiers(dss) => {
+            (None, analyze_declaration_specifiers(dss, ctx)?)
         }
+    };
+
+    // array and function parameters are adjusted to pointers
+    Ok((name, decay(ty)))
+}
+
+/// Array sizes are only known for integer literals (`int nums[3]`).
+/// everything else is treated like an incomplete array (`int nums[]`)
+fn array_len(expr: Option<&ast::ConstantExpr>) -> usize {
+    match expr {
+        Some(ast::ConditionalExpr::LogicalOrExpr(ast::LogicalOrExpr::LogicalAndExpr(
+            ast::LogicalAndExpr::BitOrExpr(ast::BitOrExpr::XORExpr(ast::XORExpr::BitAndExpr(
+                ast::BitAndExpr::EqualityExpr(ast::EqualityExpr::ComparisionExpr(
+                    ast::ComparisionExpr::ShiftExpr(ast::ShiftExpr::AdditiveExpr(
+                        ast::AdditiveExpr::MultiplicativeExpr(ast::MultiplicativeExpr::CastExpr(
+                            ast::CastExpr::UnaryExpr(ast::UnaryExpr::PostfixExpr(
+                                ast::PostfixExpr::Primary(ast::Primary::Int(len)),
+                            )),
+                        )),
+                    )),
+                )),
+            ))),
+        ))) if *len >= 0 => *len as usize,
+        _ => 0,
+    }
+}
+
+fn analyze_type_name<'ast, 'text>(
+    type_name: &'ast ast::TypeName<'text>,
+    ctx: &mut SemanticContext<'text>,
+) -> Result<Type<'text>, SemanticError<'ast, 'text>> {
+    let base_ty = analyze_specifier_qualifiers(&type_name.specifier_qualifiers, ctx)?;
+
+    match type_name.abstract_declarator.as_ref() {
+        Some(ad) => analyze_abstract_declarator(ad, base_ty, ctx),
         None => Ok(base_ty),
     }
 }
@@ -498,9 +810,7 @@ fn analyze_specifier_qualifiers<'ast, 'text>(
 ) -> Result<Type<'text>, SemanticError<'ast, 'text>> {
     // TODO: check TypeQualifiers (const, volatile)
 
-    use ast::TypeSpecifier as TS;
-
-    let mut tss = sqs
+    let tss = sqs
         .iter()
         .filter_map(|sq| match sq {
             ast::SpecifierQualifier::TypeSpecifier(ts) => Some(ts),
@@ -508,6 +818,16 @@ fn analyze_specifier_qualifiers<'ast, 'text>(
         })
         .collect::<Vec<&ast::TypeSpecifier<'text>>>();
 
+    analyze_type_specifiers(tss, SemanticError::InvalidSpecifierQualifiers(sqs), ctx)
+}
+
+fn analyze_type_specifiers<'ast, 'text>(
+    mut tss: Vec<&'ast ast::TypeSpecifier<'text>>,
+    err: SemanticError<'ast, 'text>,
+    ctx: &mut SemanticContext<'text>,
+) -> Result<Type<'text>, SemanticError<'ast, 'text>> {
+    use ast::TypeSpecifier as TS;
+
     tss.sort_by_key(|ts| match ts {
         TS::Void => 2,
         TS::Signed => 3,
@@ -556,7 +876,7 @@ fn analyze_specifier_qualifiers<'ast, 'text>(
             // ctx.find_typedef(name);
             todo!()
         }
-        _ => Err(SemanticError::InvalidSpecifierQualifiers(sqs)),
+        _ => Err(err),
     }
 }
 
@@ -575,13 +895,198 @@ fn analyze_enum_specifier<'ast, 'text>(
 }
 
 fn analyze_abstract_declarator<'ast, 'text>(
-    ad: &ast::AbstractDeclarator<'text>,
+    ad: &'ast ast::AbstractDeclarator<'text>,
+    ty: Type<'text>,
     ctx: &mut SemanticContext<'text>,
 ) -> Result<Type<'text>, SemanticError<'ast, 'text>> {
     match ad {
-        ast::AbstractDeclarator::Pointer(p) => todo!(),
-        ast::AbstractDeclarator::PointerWithDirect(p, dad) => todo!(),
-        ast::AbstractDeclarator::Direct(dad) => todo!(),
+        ast::AbstractDeclarator::Pointer(p) => analyze_pointer(p, ty, ctx),
+        ast::AbstractDeclarator::PointerWithDirect(p, dad) => {
+            let ty = analyze_pointer(p, ty, ctx)?;
+            analyze_direct_abstract_declarator(dad, ty, ctx)
+        }
+        ast::AbstractDeclarator::Direct(dad) => analyze_direct_abstract_declarator(dad, ty, ctx),
+    }
+}
+
+fn analyze_direct_abstract_declarator<'ast, 'text>(
+    dad: &'ast ast::DirectAbstractDeclarator<'text>,
+    ty: Type<'text>,
+    ctx: &mut SemanticContext<'text>,
+) -> Result<Type<'text>, SemanticError<'ast, 'text>> {
+    match dad {
+        ast::DirectAbstractDeclarator::Parens(ad, tail) => {
+            let ty = match tail {
+                Some(tail) => analyze_direct_abstract_declarator_tail(tail, ty, ctx)?,
+                None => ty,
+            };
+            analyze_abstract_declarator(ad, ty, ctx)
+        }
+        ast::DirectAbstractDeclarator::Array(len, tail) => {
+            let ty = match tail {
+                Some(tail) => analyze_direct_abstract_declarator_tail(tail, ty, ctx)?,
+                None => ty,
+            };
+            Ok(Type::Array(Box::new(ty), array_len(len.as_ref())))
+        }
+        ast::DirectAbstractDeclarator::Function(ptl, tail) => {
+            let return_ty = match tail {
+                Some(tail) => analyze_direct_abstract_declarator_tail(tail, ty, ctx)?,
+                None => ty,
+            };
+            analyze_fn_declarator(ptl.as_ref(), return_ty, ctx)
+        }
+    }
+}
+
+fn analyze_direct_abstract_declarator_tail<'ast, 'text>(
+    tail: &'ast ast::DirectAbstractDeclaratorTail<'text>,
+    ty: Type<'text>,
+    ctx: &mut SemanticContext<'text>,
+) -> Result<Type<'text>, SemanticError<'ast, 'text>> {
+    match tail {
+        ast::DirectAbstractDeclaratorTail::Array(len, tail) => {
+            let ty = match tail {
+                Some(tail) => analyze_direct_abstract_declarator_tail(tail, ty, ctx)?,
+                None => ty,
+            };
+            Ok(Type::Array(Box::new(ty), array_len(len.as_ref())))
+        }
+        ast::DirectAbstractDeclaratorTail::Function(ptl, tail) => {
+            let return_ty = match tail {
+                Some(tail) => analyze_direct_abstract_declarator_tail(tail, ty, ctx)?,
+                None => ty,
+            };
+            analyze_fn_declarator(ptl.as_ref(), return_ty, ctx)
+        }
+    }
+}
+
+/// Array to pointer and function to pointer conversions.
+/// also the adjustment applied to array and function parameters
+fn decay(ty: Type) -> Type {
+    match ty {
+        Type::Array(ty, _) => Type::Pointer(ty),
+        Type::String => Type::Pointer(Box::new(Type::Char)),
+        ty @ Type::Function { .. } => Type::Pointer(Box::new(ty)),
+        ty => ty,
+    }
+}
+
+/// Default argument promotions. applied to the arguments matched by `...`
+/// and to all arguments of a function called without a prototype
+fn promote(ty: Type) -> Type {
+    match decay(ty) {
+        Type::Char | Type::SignedChar | Type::UnSignedChar | Type::Short | Type::UnSignedShort => {
+            Type::Int
+        }
+        Type::Float => Type::Double,
+        ty => ty,
+    }
+}
+
+fn is_compatible(lhs: &Type, rhs: &Type) -> bool {
+    match (lhs, rhs) {
+        (Type::Pointer(lhs), Type::Pointer(rhs)) => is_compatible(lhs, rhs),
+        (Type::Array(lhs, lhs_len), Type::Array(rhs, rhs_len)) => {
+            is_compatible(lhs, rhs) && (lhs_len == rhs_len || *lhs_len == 0 || *rhs_len == 0)
+        }
+        (
+            Type::Function {
+                return_ty: lhs_return_ty,
+                param_tys: lhs_param_tys,
+                variadic: lhs_variadic,
+                prototyped: true,
+            },
+            Type::Function {
+                return_ty: rhs_return_ty,
+                param_tys: rhs_param_tys,
+                variadic: rhs_variadic,
+                prototyped: true,
+            },
+        ) => {
+            is_compatible(lhs_return_ty, rhs_return_ty)
+                && lhs_variadic == rhs_variadic
+                && lhs_param_tys.len() == rhs_param_tys.len()
+                && lhs_param_tys
+                    .iter()
+                    .zip(rhs_param_tys)
+                    .all(|(lhs, rhs)| is_compatible(lhs, rhs))
+        }
+        (
+            Type::Function {
+                return_ty,
+                param_tys,
+                variadic,
+                prototyped: true,
+            },
+            Type::Function {
+                return_ty: old_return_ty,
+                param_tys: old_param_tys,
+                prototyped: false,
+                ..
+            },
+        )
+        | (
+            Type::Function {
+                return_ty: old_return_ty,
+                param_tys: old_param_tys,
+                prototyped: false,
+                ..
+            },
+            Type::Function {
+                return_ty,
+                param_tys,
+                variadic,
+                prototyped: true,
+            },
+        ) => {
+            // the prototype must agree with the parameters the old style
+            // declaration receives after the default argument promotions
+            let params_match = match old_param_tys.is_empty() {
+                true => param_tys
+                    .iter()
+                    .all(|ty| is_compatible(ty, &promote(ty.clone()))),
+                false => {
+                    param_tys.len() == old_param_tys.len()
+                        && param_tys
+                            .iter()
+                            .zip(old_param_tys)
+                            .all(|(ty, old_ty)| is_compatible(ty, &promote(old_ty.clone())))
+                }
+            };
+            is_compatible(return_ty, old_return_ty) && !variadic && params_match
+        }
+        (
+            Type::Function {
+                return_ty: lhs_return_ty,
+                prototyped: false,
+                ..
+            },
+            Type::Function {
+                return_ty: rhs_return_ty,
+                prototyped: false,
+                ..
+            },
+        ) => is_compatible(lhs_return_ty, rhs_return_ty),
+        _ => lhs == rhs,
+    }
+}
+
+/// The type of a function after a compatible redeclaration.
+/// a prototype replaces an old style declaration and the parameters
+/// of a K&R definition replace an empty `()`
+fn composite_fn_ty<'text>(prev: Type<'text>, curr: Type<'text>) -> Type<'text> {
+    match (&prev, &curr) {
+        (
+            Type::Function {
+                param_tys: prev_param_tys,
+                prototyped: false,
+                ..
+            },
+            Type::Function { prototyped, .. },
+        ) if *prototyped || prev_param_tys.is_empty() => curr,
+        _ => prev,
     }
 }
 
@@ -853,7 +1358,7 @@ fn analyze_logicalor_expr<'ast, 'text>(
     ctx: &mut SemanticContext<'text>,
 ) -> Result<Type<'text>, SemanticError<'ast, 'text>> {
     match expr {
-        ast::LogicalOrExpr::LogicalAndExpr(expr) => todo!(),
+        ast::LogicalOrExpr::LogicalAndExpr(expr) => analyze_logicaland_expr(expr, ctx),
         ast::LogicalOrExpr::LogicalOr(lhs, rhs) => match (
             analyze_logicalor_expr(lhs, ctx)?,
             analyze_logicaland_expr(rhs, ctx)?,
@@ -1142,34 +1647,20 @@ fn analyze_postfix_expr<'ast, 'text>(
                 Type::Function {
                     return_ty,
                     param_tys,
+                    variadic,
+                    prototyped,
                 } => {
-                    if args.len() != param_tys.len() {
-                        return Err(SemanticError::InvalidFnCall(expr));
-                    }
-
-                    for (arg, param_ty) in args.iter().zip(param_tys.iter()) {
-                        let arg_ty = analyze_assignment_expr(arg, ctx)?;
-                        if &arg_ty != param_ty {
-                            return Err(SemanticError::InvalidFnCall(expr));
-                        }
-                    }
+                    analyze_fn_args(expr, &param_tys, variadic, prototyped, args, ctx)?;
                     Ok(*return_ty)
                 }
                 Type::Pointer(ty) => match *ty {
                     Type::Function {
                         return_ty,
                         param_tys,
+                        variadic,
+                        prototyped,
                     } => {
-                        if args.len() != param_tys.len() {
-                            return Err(SemanticError::InvalidFnCall(expr));
-                        }
-
-                        for (arg, param_ty) in args.iter().zip(param_tys.iter()) {
-                            let arg_ty = analyze_assignment_expr(arg, ctx)?;
-                            if &arg_ty != param_ty {
-                                return Err(SemanticError::InvalidFnCall(expr));
-                            }
-                        }
+                        analyze_fn_args(expr, &param_tys, variadic, prototyped, args, ctx)?;
                         Ok(*return_ty)
                     }
                     _ => Err(SemanticError::NotAFunction(inner_expr)),
@@ -1217,6 +1708,58 @@ fn analyze_postfix_expr<'ast, 'text>(
     }
 }
 
+fn analyze_fn_args<'ast, 'text>(
+    call: &'ast ast::PostfixExpr<'text>,
+    param_tys: &[Type<'text>],
+    variadic: bool,
+    prototyped: bool,
+    args: &'ast [ast::AssignmentExpr<'text>],
+    ctx: &mut SemanticContext<'text>,
+) -> Result<(), SemanticError<'ast, 'text>> {
+    // without a prototype the parameters are only known
+    // if the function was defined with a K&R identifier list
+    let arity_known = prototyped || !param_tys.is_empty();
+    let arity_matches = match variadic {
+        true => args.len() >= param_tys.len(),
+        false => args.len() == param_tys.len(),
+    };
+
+    if arity_known && !arity_matches {
+        return Err(SemanticError::ArgumentCountMismatch {
+            call,
+            expected: param_tys.len(),
+            actual: args.len(),
+        });
+    }
+
+    for (idx, arg) in args.iter().enumerate() {
+        let arg_ty = analyze_assignment_expr(arg, ctx)?;
+
+        let (expected, actual) = match param_tys.get(idx) {
+            Some(param_ty) if prototyped => (param_ty.clone(), decay(arg_ty.clone())),
+            Some(param_ty) => (promote(param_ty.clone()), promote(arg_ty.clone())),
+            None => {
+                // matched by `...` (or unknown parameters)
+                // anything goes after the default argument promotions
+                if promote(arg_ty) == Type::Void {
+                    return Err(SemanticError::InvalidFnCall(call));
+                }
+                continue;
+            }
+        };
+
+        if !is_compatible(&expected, &actual) {
+            return Err(SemanticError::ArgumentTypeMismatch {
+                call,
+                expected,
+                actual: arg_ty,
+            });
+        }
+    }
+
+    Ok(())
+}
+
 fn analyze_primary_expr<'ast, 'text>(
     expr: &'ast ast::Primary<'text>,
     ctx: &mut SemanticContext<'text>,
@@ -1224,7 +1767,10 @@ fn analyze_primary_expr<'ast, 'text>(
     match expr {
         ast::Primary::Ident(ident) => match ctx.find_var(ident) {
             Some(var) => Ok(var.ty.clone()),
-            None => Err(SemanticError::UndefinedVariable(ident)),
+            None => match ctx.find_fn(ident) {
+                Some(f) => Ok(f.ty.clone()),
+                None => Err(SemanticError::UndefinedVariable(ident)),
+            },
         },
         ast::Primary::Int(_) => Ok(Type::Int),
         ast::Primary::Char(_) => Ok(Type::Char),
@@ -1987,3 +2533,167 @@ fn analyze_primary_expr<'ast, 'text>(
 
 //     Ok(())
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::lex;
+
+    macro_rules! check {
+        ($src:expr, $expected:pat) => {
+            let tokens = lex($src).expect("** LEX ERROR");
+            let tu = ast::parse(&tokens).expect("** Unable to parse translation unit");
+            let result = analyze_translation_unit(&tu, &mut SemanticContext::new());
+            assert!(matches!(result, $expected), "{}\n{:?}", $src, result);
+        };
+    }
+
+    #[test]
+    fn test_prototypes() {
+        check!(
+            "int add(int a, int b); int main(void) { return add(1, 2); }",
+            Ok(())
+        );
+        check!(
+            "int add(int, int); int add(int a, int b) { return a + b; }",
+            Ok(())
+        );
+        check!(
+            "int main(void) { int add(int, int); return add(1, 2); }",
+            Ok(())
+        );
+        check!(
+            "int apply(int (*f)(int), int x) { return f(x); }",
+            Ok(())
+        );
+        check!(
+            "int first(int nums[3]); int first(int *nums) { return nums[0]; }",
+            Ok(())
+        );
+        check!(
+            "int add(int a, int b); int main(void) { return add(1); }",
+            Err(SemanticError::ArgumentCountMismatch {
+                expected: 2,
+                actual: 1,
+                ..
+            })
+        );
+        check!(
+            "int add(int a, int b); int main(void) { return add(1, 2.0); }",
+            Err(SemanticError::ArgumentTypeMismatch {
+                expected: Type::Int,
+                actual: Type::Float,
+                ..
+            })
+        );
+        check!(
+            "int f(void); int main(void) { return f(1); }",
+            Err(SemanticError::ArgumentCountMismatch {
+                expected: 0,
+                actual: 1,
+                ..
+            })
+        );
+    }
+
+    #[test]
+    fn test_variadic_calls() {
+        check!(
+            r#"int printf(const char *fmt, ...);
+            int main(void) { char c; float f; printf("%c %f", c, f); return 0; }"#,
+            Ok(())
+        );
+        check!(
+            "int printf(const char *fmt, ...); int main(void) { return printf(); }",
+            Err(SemanticError::ArgumentCountMismatch {
+                expected: 1,
+                actual: 0,
+                ..
+            })
+        );
+        check!(
+            "int printf(const char *fmt, ...); int main(void) { return printf(1); }",
+            Err(SemanticError::ArgumentTypeMismatch { .. })
+        );
+        check!(
+            r#"int printf(const char *fmt, ...); void g(void);
+            int main(void) { return printf("%d", g()); }"#,
+            Err(SemanticError::InvalidFnCall(_))
+        );
+    }
+
+    #[test]
+    fn test_kr_functions() {
+        check!(
+            "int add(a, b) int a; int b; { return a + b; } int main(void) { return add(1, 2); }",
+            Ok(())
+        );
+        check!(
+            "int twice(a) { return a + a; } int main(void) { return twice(2); }",
+            Ok(())
+        );
+        check!(
+            "int add(a, b) int a; int b; { return a + b; } int main(void) { return add(1); }",
+            Err(SemanticError::ArgumentCountMismatch {
+                expected: 2,
+                actual: 1,
+                ..
+            })
+        );
+        check!(
+            "int add(a, b) int a; int b; { return a + b; } int main(void) { return add(1, 2.0); }",
+            Err(SemanticError::ArgumentTypeMismatch { .. })
+        );
+        check!(
+            "int add(a, b) int a; int c; { return a; }",
+            Err(SemanticError::NotAParameter("c"))
+        );
+        check!(
+            "int add(a, b);",
+            Err(SemanticError::IdentifierListInDeclaration(_))
+        );
+        check!("int f(); int main(void) { return f(1, 2.0); }", Ok(()));
+    }
+
+    #[test]
+    fn test_redeclarations() {
+        check!(
+            "int add(int a, int b); int add(int a, float b) { return a; }",
+            Err(SemanticError::IncompatibleRedeclaration { name: "add", .. })
+        );
+        check!(
+            "int f(int x); double f(int x);",
+            Err(SemanticError::IncompatibleRedeclaration { name: "f", .. })
+        );
+        check!(
+            "int f(int x, ...); int f(int x);",
+            Err(SemanticError::IncompatibleRedeclaration { name: "f", .. })
+        );
+        check!("int f(); int f(int x);", Ok(()));
+        check!(
+            "int f(); int f(float x);",
+            Err(SemanticError::IncompatibleRedeclaration { name: "f", .. })
+        );
+        check!(
+            "int f(); int f(int x); int main(void) { return f(); }",
+            Err(SemanticError::ArgumentCountMismatch {
+                expected: 1,
+                actual: 0,
+                ..
+            })
+        );
+        check!("int scale(double); int scale(x) float x; { return 0; }", Ok(()));
+        check!(
+            "int scale(float); int scale(x) float x; { return 0; }",
+            Err(SemanticError::IncompatibleRedeclaration { name: "scale", .. })
+        );
+        check!(
+            "int f(void) { return 0; } int f(void) { return 1; }",
+            Err(SemanticError::FunctionRedefinition("f"))
+        );
+        check!(
+            "int f; int f(void);",
+            Err(SemanticError::VariableRedeclaration("f"))
+        );
+    }
+}
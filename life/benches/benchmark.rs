@@ -1,7 +1,11 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, Criterion};
+use life::{HashLife, Pattern};
+use std::hint::black_box;
 
 const GRID_SIZE: usize = 10_000;
 
+const GOSPER_GLIDER_GUN: &str = include_str!("../patterns/gosper_glider_gun.rle");
+
 fn stack_benchmark(c: &mut Criterion) {
     let start = [[true; GRID_SIZE]; GRID_SIZE];
     let mut game = life::Game::new(Box::new(start));
@@ -14,5 +18,41 @@ fn stack_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, stack_benchmark);
+/// A glider gun run for 1024 generations, cell by cell on a grid big enough
+/// that no glider wraps around, and in jumps with HashLife
+fn hashlife_benchmark(c: &mut Criterion) {
+    let gun = Pattern::from_rle(GOSPER_GLIDER_GUN).unwrap();
+    let mut group = c.benchmark_group("glider gun");
+    group.sample_size(10);
+
+    group.bench_function("stack 1024 generations", |b| {
+        b.iter(|| {
+            let mut game = life::Game::<640, 640>::from_pattern(&gun).unwrap();
+            for _ in 0..1024 {
+                game.update();
+            }
+            black_box(&game);
+        });
+    });
+
+    group.bench_function("hashlife 1024 generations", |b| {
+        b.iter(|| {
+            let mut life = HashLife::from_pattern(&gun).unwrap();
+            life.step(1024);
+            black_box(life.population());
+        });
+    });
+
+    group.bench_function("hashlife 2^40 generations", |b| {
+        b.iter(|| {
+            let mut life = HashLife::from_pattern(&gun).unwrap();
+            life.step_pow2(40);
+            black_box(life.population());
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, stack_benchmark, hashlife_benchmark);
 criterion_main!(benches);
@@ -0,0 +1,20 @@
+//! Patterns and helpers shared by the tests of the different engines
+
+use crate::Game;
+
+/// 36 cells that add a glider every 30 generations, as Golly writes it
+pub(crate) const GOSPER_GLIDER_GUN: &str = include_str!("../patterns/gosper_glider_gun.rle");
+
+/// the live cells of a dense game that nothing has wrapped around yet, moved
+/// by `(-dr, -dc)`
+pub(crate) fn dense_cells<const R: usize, const C: usize>(
+    game: &Game<R, C>,
+    dr: usize,
+    dc: usize,
+) -> Vec<(i64, i64)> {
+    (0..R)
+        .flat_map(|r| (0..C).map(move |c| (r, c)))
+        .filter(|(r, c)| game.grid()[*r][*c])
+        .map(|(r, c)| (r as i64 - dr as i64, c as i64 - dc as i64))
+        .collect()
+}
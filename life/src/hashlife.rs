@@ -0,0 +1,527 @@
+use crate::{Pattern, Rule};
+use std::collections::HashMap;
+
+/// Index of a node in the cache
+type Id = usize;
+
+const DEAD: Id = 0;
+const ALIVE: Id = 1;
+
+/// Nodes kept before [`HashLife::step`] collects garbage
+const NODE_LIMIT: usize = 1 << 22;
+
+/// A square of `2^level` by `2^level` cells, a single cell at level 0
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    level: u8,
+    /// nw, ne, sw and se quarters, unused for cells
+    children: [Id; 4],
+    population: u64,
+}
+
+/// Life on an unbounded plane with Gosper's HashLife.
+///
+/// The plane is a quadtree where equal squares are the same node, so repeating
+/// patterns cost almost nothing to store. The result of running a node is
+/// remembered too, which lets it jump `2^k` generations at once in time
+/// that grows with how much the pattern varies, not with `k`.
+///
+/// Rows and columns are `i64` and can be negative. Only life-like rules without
+/// `B0` work, a plane full of dead cells has to stay dead.
+///
+/// ```
+/// use life::{HashLife, Pattern};
+///
+/// let glider = Pattern::from_cells(".O\n..O\nOOO\n").unwrap();
+/// let mut life = HashLife::from_pattern(&glider).unwrap();
+///
+/// // a glider moves a cell down and right every 4 generations
+/// life.step(4_000_000_000);
+/// assert_eq!(life.generation(), 4_000_000_000);
+/// assert_eq!(life.population(), 5);
+/// assert!(life.get(1_000_000_000, 1_000_000_001));
+/// ```
+#[derive(Debug, Clone)]
+pub struct HashLife {
+    rule: Rule,
+    nodes: Vec<Node>,
+    /// the canonical node with these children
+    index: HashMap<[Id; 4], Id>,
+    /// (node, k) to its center half `2^k` generations on
+    results: HashMap<(Id, u8), Id>,
+    /// the empty node at every level found so far
+    empty: Vec<Id>,
+    root: Id,
+    generation: u64,
+    node_limit: usize,
+}
+
+impl HashLife {
+    /// An empty plane under Conway's rule
+    pub fn new() -> Self {
+        Self::with_rule(Rule::CONWAY).unwrap()
+    }
+
+    /// None for Generations rules and rules with `B0`
+    pub fn with_rule(rule: Rule) -> Option<Self> {
+        if rule.states() > 2 || rule.is_born(0) {
+            return None;
+        }
+
+        let cell = |population| Node {
+            level: 0,
+            children: [DEAD; 4],
+            population,
+        };
+
+        let mut life = Self {
+            rule,
+            nodes: vec![cell(0), cell(1)],
+            index: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![],
+            root: DEAD,
+            generation: 0,
+            node_limit: NODE_LIMIT,
+        };
+        life.root = life.empty(3);
+
+        Some(life)
+    }
+
+    /// The pattern with its top left at `(0, 0)`, under its rule or Conway's.
+    /// None if the rule isn't supported, see [`HashLife::with_rule`]
+    pub fn from_pattern(pattern: &Pattern) -> Option<Self> {
+        let mut life = Self::with_rule(pattern.rule().copied().unwrap_or_default())?;
+        for (r, c) in pattern.cells() {
+            life.set(*r as i64, *c as i64, true);
+        }
+        Some(life)
+    }
+
+    /// The live cells moved so the top left one's row and column are 0, with the
+    /// rule. None if they're spread over more rows or columns than a `usize` counts
+    pub fn to_pattern(&self) -> Option<Pattern> {
+        Some(Pattern::from_plane(&self.cells())?.with_rule(self.rule))
+    }
+
+    pub const fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    pub const fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn population(&self) -> u64 {
+        self.nodes[self.root].population
+    }
+
+    /// Nodes in the cache, live or not
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// How many nodes to keep before collecting garbage. It's checked before
+    /// every `2^k` jump, a single jump can go over it.
+    pub fn set_node_limit(&mut self, limit: usize) {
+        self.node_limit = limit;
+    }
+
+    pub fn get(&self, r: i64, c: i64) -> bool {
+        let (r, c) = (r as i128, c as i128);
+        if !self.contains(r, c) {
+            return false;
+        }
+
+        let half = self.half();
+        let (mut r, mut c) = ((r + half) as u128, (c + half) as u128);
+        let mut id = self.root;
+        while self.nodes[id].level > 0 {
+            let half = 1 << (self.nodes[id].level - 1);
+            id = self.nodes[id].children[quarter(r, c, half)];
+            (r, c) = (r % half, c % half);
+        }
+        id == ALIVE
+    }
+
+    pub fn set(&mut self, r: i64, c: i64, alive: bool) {
+        let (r, c) = (r as i128, c as i128);
+        while !self.contains(r, c) {
+            self.root = self.expand(self.root);
+        }
+
+        let half = self.half();
+        self.root = self.set_in(self.root, (r + half) as u128, (c + half) as u128, alive);
+    }
+
+    /// Every live cell as `(row, column)`, sorted
+    pub fn cells(&self) -> Vec<(i64, i64)> {
+        let mut cells = vec![];
+        let half = self.half();
+        self.collect(self.root, -half, -half, &mut cells);
+        cells.sort_unstable();
+        cells
+    }
+
+    /// Runs any number of generations as a `2^k` jump per set bit
+    pub fn step(&mut self, generations: u64) {
+        for k in 0..u64::BITS as u8 {
+            if generations >> k & 1 == 1 {
+                self.step_pow2(k);
+            }
+        }
+    }
+
+    /// Runs `2^k` generations at once
+    pub fn step_pow2(&mut self, k: u8) {
+        if self.nodes.len() > self.node_limit {
+            self.collect_garbage();
+        }
+
+        // with every cell in the middle quarter, nothing can reach past the
+        // middle half in the `2^(level - 3)` generations a jump can be
+        loop {
+            let root = self.nodes[self.root];
+            if root.level >= k + 3 {
+                let middle = self.center(self.root);
+                let middle = self.center(middle);
+                if self.nodes[middle].population == root.population {
+                    break;
+                }
+            }
+            self.root = self.expand(self.root);
+        }
+
+        self.root = self.successor(self.root, k);
+        self.generation += 1 << k;
+    }
+
+    /// Drops every node the current pattern doesn't use and the results
+    /// remembered for them
+    pub fn collect_garbage(&mut self) {
+        let old = std::mem::take(&mut self.nodes);
+        let mut moved = HashMap::new();
+
+        self.nodes = old[..2].to_vec();
+        self.root = self.keep(&old, self.root, &mut moved);
+
+        self.index = self
+            .nodes
+            .iter()
+            .enumerate()
+            .skip(2)
+            .map(|(id, node)| (node.children, id))
+            .collect();
+        let moved_to = |id: &Id| match *id {
+            DEAD | ALIVE => Some(*id),
+            id => moved.get(&id).copied(),
+        };
+        self.results = self
+            .results
+            .iter()
+            .filter_map(|((id, k), result)| Some(((moved_to(id)?, *k), moved_to(result)?)))
+            .collect();
+        self.empty.clear();
+    }
+
+    /// copies a node and everything under it from `old` unless it was already
+    fn keep(&mut self, old: &[Node], id: Id, moved: &mut HashMap<Id, Id>) -> Id {
+        if id == DEAD || id == ALIVE {
+            return id;
+        }
+        if let Some(id) = moved.get(&id) {
+            return *id;
+        }
+
+        let mut node = old[id];
+        for child in &mut node.children {
+            *child = self.keep(old, *child, moved);
+        }
+        self.nodes.push(node);
+        moved.insert(id, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    /// half the root's side, the root's top left is `(-half, -half)`
+    fn half(&self) -> i128 {
+        1 << (self.nodes[self.root].level - 1)
+    }
+
+    fn contains(&self, r: i128, c: i128) -> bool {
+        let half = self.half();
+        (-half..half).contains(&r) && (-half..half).contains(&c)
+    }
+
+    fn set_in(&mut self, id: Id, r: u128, c: u128, alive: bool) -> Id {
+        let node = self.nodes[id];
+        if node.level == 0 {
+            return if alive { ALIVE } else { DEAD };
+        }
+
+        let half = 1 << (node.level - 1);
+        let mut children = node.children;
+        let i = quarter(r, c, half);
+        children[i] = self.set_in(children[i], r % half, c % half, alive);
+        self.join(children)
+    }
+
+    fn collect(&self, id: Id, top: i128, left: i128, cells: &mut Vec<(i64, i64)>) {
+        let node = self.nodes[id];
+        match node.level {
+            _ if node.population == 0 => {}
+            0 => cells.push((top as i64, left as i64)),
+            level => {
+                let half = 1 << (level - 1);
+                for (i, child) in node.children.iter().enumerate() {
+                    let (dr, dc) = ((i / 2) as i128 * half, (i % 2) as i128 * half);
+                    self.collect(*child, top + dr, left + dc, cells);
+                }
+            }
+        }
+    }
+
+    /// the canonical node with these children, made if it's new
+    fn join(&mut self, children: [Id; 4]) -> Id {
+        if let Some(id) = self.index.get(&children) {
+            return *id;
+        }
+
+        let node = Node {
+            level: self.nodes[children[0]].level + 1,
+            children,
+            population: children.iter().map(|id| self.nodes[*id].population).sum(),
+        };
+        self.nodes.push(node);
+        self.index.insert(children, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn empty(&mut self, level: u8) -> Id {
+        while self.empty.len() <= level as usize {
+            let id = match self.empty.last() {
+                None => DEAD,
+                Some(smaller) => self.join([*smaller; 4]),
+            };
+            self.empty.push(id);
+        }
+        self.empty[level as usize]
+    }
+
+    /// the same cells in a node twice the size, in its middle
+    fn expand(&mut self, id: Id) -> Id {
+        let node = self.nodes[id];
+        let e = self.empty(node.level - 1);
+        let [nw, ne, sw, se] = node.children;
+
+        let nw = self.join([e, e, e, nw]);
+        let ne = self.join([e, e, ne, e]);
+        let sw = self.join([e, sw, e, e]);
+        let se = self.join([se, e, e, e]);
+        self.join([nw, ne, sw, se])
+    }
+
+    /// the middle half of a node
+    fn center(&mut self, id: Id) -> Id {
+        let [nw, ne, sw, se] = self.nodes[id].children.map(|id| self.nodes[id].children);
+        self.join([nw[3], ne[2], sw[1], se[0]])
+    }
+
+    /// The middle half of a node at least at level 2, `2^k` generations on,
+    /// with `k` at most `level - 2`.
+    ///
+    /// The node is split into 9 overlapping squares of half its size. Each is run
+    /// (or just cut to its middle when `k` is smaller), joined into 4 squares that
+    /// are run again, and those 4 results are the answer.
+    fn successor(&mut self, id: Id, k: u8) -> Id {
+        let node = self.nodes[id];
+        if node.population == 0 {
+            return self.empty(node.level - 1);
+        }
+        if let Some(result) = self.results.get(&(id, k)) {
+            return *result;
+        }
+
+        let result = match node.level {
+            2 => self.base(id),
+            level => {
+                let full_speed = k == level - 2;
+
+                let mut parts = [DEAD; 9];
+                for (part, square) in parts.iter_mut().zip(self.nine(id)) {
+                    *part = match full_speed {
+                        true => self.successor(square, k - 1),
+                        false => self.center(square),
+                    };
+                }
+
+                let next_k = if full_speed { k - 1 } else { k };
+                let mut quarters = [DEAD; 4];
+                for (quarter, [a, b, c, d]) in quarters.iter_mut().zip([
+                    [0, 1, 3, 4],
+                    [1, 2, 4, 5],
+                    [3, 4, 6, 7],
+                    [4, 5, 7, 8],
+                ]) {
+                    let square = self.join([parts[a], parts[b], parts[c], parts[d]]);
+                    *quarter = self.successor(square, next_k);
+                }
+                self.join(quarters)
+            }
+        };
+
+        self.results.insert((id, k), result);
+        result
+    }
+
+    /// the 9 overlapping squares half the size of a node, row by row
+    fn nine(&mut self, id: Id) -> [Id; 9] {
+        let children = self.nodes[id].children;
+        let [nw, ne, sw, se] = children.map(|id| self.nodes[id].children);
+
+        [
+            children[0],
+            self.join([nw[1], ne[0], nw[3], ne[2]]),
+            children[1],
+            self.join([nw[2], nw[3], sw[0], sw[1]]),
+            self.join([nw[3], ne[2], sw[1], se[0]]),
+            self.join([ne[2], ne[3], se[0], se[1]]),
+            children[2],
+            self.join([sw[1], se[0], sw[3], se[2]]),
+            children[3],
+        ]
+    }
+
+    /// the middle 2 by 2 of a 4 by 4 node a generation on, cell by cell
+    fn base(&mut self, id: Id) -> Id {
+        let mut cells = [[false; 4]; 4];
+        for (i, child) in self.nodes[id].children.iter().enumerate() {
+            for (j, cell) in self.nodes[*child].children.iter().enumerate() {
+                cells[i / 2 * 2 + j / 2][i % 2 * 2 + j % 2] = *cell == ALIVE;
+            }
+        }
+
+        let rule = self.rule;
+        let next = |r: usize, c: usize| {
+            let live_neighbors = (r - 1..=r + 1)
+                .flat_map(|nr| (c - 1..=c + 1).map(move |nc| (nr, nc)))
+                .filter(|(nr, nc)| (*nr, *nc) != (r, c) && cells[*nr][*nc])
+                .count();
+            match rule.next(cells[r][c] as u8, live_neighbors as u8) {
+                1 => ALIVE,
+                _ => DEAD,
+            }
+        };
+
+        let quarters = [next(1, 1), next(1, 2), next(2, 1), next(2, 2)];
+        self.join(quarters)
+    }
+}
+
+impl Default for HashLife {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// which of a node's children `(r, c)` is in, with `half` its side
+fn quarter(r: u128, c: u128, half: u128) -> usize {
+    (r >= half) as usize * 2 + (c >= half) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures::{dense_cells, GOSPER_GLIDER_GUN},
+        Game,
+    };
+
+    #[test]
+    fn test_against_game() {
+        let gun = Pattern::from_rle(GOSPER_GLIDER_GUN).unwrap();
+        let r_pentomino = Pattern::from_cells(".OO\nOO.\n.O.\n")
+            .unwrap()
+            .with_rule("B36/S23".parse().unwrap());
+
+        for pattern in [gun, r_pentomino] {
+            let mut game = Game::<64, 64>::from_pattern(&pattern).unwrap();
+            let (dr, dc) = ((64 - pattern.height()) / 2, (64 - pattern.width()) / 2);
+            let mut life = HashLife::from_pattern(&pattern).unwrap();
+            let mut single = life.clone();
+
+            for _ in 0..30 {
+                game.update();
+                single.step(1);
+            }
+            life.step(30);
+
+            assert_eq!(life.cells(), dense_cells(&game, dr, dc));
+            assert_eq!(single.cells(), life.cells());
+            assert_eq!(life.generation(), 30);
+        }
+    }
+
+    #[test]
+    fn test_far_ahead() {
+        let glider = Pattern::from_cells(".O\n..O\nOOO\n").unwrap();
+        let mut life = HashLife::from_pattern(&glider).unwrap();
+        life.set(-5, -5, true);
+        assert_eq!(life.population(), 6);
+        life.set(-5, -5, false);
+
+        life.step_pow2(40);
+        let moved = 1 << 38;
+        let expected: Vec<(i64, i64)> = glider
+            .cells()
+            .iter()
+            .map(|(r, c)| (*r as i64 + moved, *c as i64 + moved))
+            .collect();
+        assert_eq!(life.cells(), expected);
+        assert_eq!(
+            life.to_pattern(),
+            Some(glider.clone().with_rule(Rule::CONWAY))
+        );
+
+        // a gun keeps adding a glider every 30 generations
+        let mut gun =
+            HashLife::from_pattern(&Pattern::from_rle(GOSPER_GLIDER_GUN).unwrap()).unwrap();
+        gun.step(30 * 1_000_000);
+        assert_eq!(gun.population(), 36 + 5 * 1_000_000);
+
+        // as far apart as cells get
+        let mut corners = HashLife::new();
+        corners.set(i64::MIN, 0, true);
+        corners.set(i64::MAX - 1, i64::MIN, true);
+        let pattern = corners.to_pattern().unwrap();
+        assert_eq!(pattern.cells(), &[(0, 1 << 63), (usize::MAX - 1, 0)]);
+        corners.set(i64::MAX, 0, true);
+        assert_eq!(corners.to_pattern(), None);
+    }
+
+    #[test]
+    fn test_collect_garbage() {
+        let gun = Pattern::from_rle(GOSPER_GLIDER_GUN).unwrap();
+        let mut life = HashLife::from_pattern(&gun).unwrap();
+        let mut collected = life.clone();
+        collected.set_node_limit(0);
+
+        for _ in 0..10 {
+            life.step(100);
+            collected.step(100);
+        }
+        assert!(collected.node_count() < life.node_count());
+        assert_eq!(collected.cells(), life.cells());
+    }
+
+    #[test]
+    fn test_rules() {
+        assert!(HashLife::with_rule("/2/3".parse().unwrap()).is_none());
+        assert!(HashLife::with_rule("B03/S23".parse().unwrap()).is_none());
+
+        let mut life = HashLife::new();
+        assert!(!life.get(i64::MAX, i64::MIN));
+        life.step(1 << 50);
+        assert_eq!(life.population(), 0);
+    }
+}
@@ -1,6 +1,18 @@
+#[cfg(test)]
+mod fixtures;
+mod hashlife;
 mod life;
 mod life_bitvec;
-mod life_vec2d;
 mod life_vec1d;
+mod life_vec2d;
+mod pattern;
+mod rule;
+mod sparse;
+mod topology;
 
+pub use hashlife::*;
 pub use life::*;
+pub use pattern::*;
+pub use rule::*;
+pub use sparse::*;
+pub use topology::*;
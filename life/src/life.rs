@@ -1,61 +1,232 @@
+use crate::{
+    topology::{self, BOUNDED, KLEIN_BOTTLE, TORUS},
+    Pattern, Rule, Topology,
+};
 use rayon::prelude::*;
 
 pub struct Game<const R: usize, const C: usize> {
     grid: Box<[[bool; C]; R]>,
+    rule: Rule,
+    topology: Topology,
+    /// the state of every cell row by row, only kept for rules with dying states
+    states: Vec<u8>,
 }
 
 impl<const R: usize, const C: usize> Game<R, C> {
     pub fn new(start: Box<[[bool; C]; R]>) -> Self {
-        Self { grid: start }
+        Self::with_rule(start, Rule::CONWAY)
+    }
+
+    pub fn with_rule(start: Box<[[bool; C]; R]>, rule: Rule) -> Self {
+        let states = match rule.states() {
+            2 => vec![],
+            _ => start.iter().flatten().map(|alive| *alive as u8).collect(),
+        };
+
+        Self {
+            grid: start,
+            rule,
+            topology: Topology::Torus,
+            states,
+        }
+    }
+
+    /// The pattern in the middle of the grid, under its rule or Conway's.
+    /// None if it doesn't fit
+    pub fn from_pattern(pattern: &Pattern) -> Option<Self> {
+        let r = R.checked_sub(pattern.height())? / 2;
+        let c = C.checked_sub(pattern.width())? / 2;
+        let rule = pattern.rule().copied().unwrap_or_default();
+
+        Some(Self::with_rule(pattern.grid_at(r, c)?, rule))
     }
 
     pub const fn grid(&self) -> &[[bool; C]; R] {
         &self.grid
     }
 
+    pub const fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    pub const fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    /// 0 for dead, 1 for alive and from 2 up for dying, see [`Rule`]
+    pub fn state(&self, r: usize, c: usize) -> u8 {
+        match self.states.get(r * C + c) {
+            Some(state) => *state,
+            None => self.grid[r][c] as u8,
+        }
+    }
+
     pub fn update(&mut self) {
+        match self.topology {
+            Topology::Torus => self.update_in::<TORUS>(),
+            Topology::Bounded => self.update_in::<BOUNDED>(),
+            Topology::KleinBottle => self.update_in::<KLEIN_BOTTLE>(),
+        }
+    }
+
+    fn update_in<const TOPOLOGY: u8>(&mut self) {
+        if !self.states.is_empty() {
+            return self.update_states::<TOPOLOGY>();
+        }
+
         let mut next = Box::new([[false; C]; R]);
 
         next.par_iter_mut().enumerate().for_each(|(r, row)| {
             row.par_iter_mut().enumerate().for_each(|(c, cell)| {
-                *cell = self.next(r, c);
+                *cell = self.next::<TOPOLOGY>(r, c);
             });
         });
 
         std::mem::swap(&mut self.grid, &mut next);
     }
 
-    #[inline]
-    const fn next(&self, r: usize, c: usize) -> bool {
-        match (self.grid[r][c], self.live_neighbors(r, c)) {
-            (true, n) if n < 2 => false,
-            (true, n) if n == 2 || n == 3 => true,
-            (true, n) if n > 3 => false,
-            (false, n) if n == 3 => true,
-            _ => self.grid[r][c],
-        }
+    /// dying cells aren't alive, so the grid still counts live neighbors
+    fn update_states<const TOPOLOGY: u8>(&mut self) {
+        let states: Vec<u8> = (0..R * C)
+            .into_par_iter()
+            .map(|i| {
+                self.rule.next(
+                    self.states[i],
+                    self.live_neighbors::<TOPOLOGY>(i / C, i % C),
+                )
+            })
+            .collect();
+
+        let mut next = Box::new([[false; C]; R]);
+        next.par_iter_mut().enumerate().for_each(|(r, row)| {
+            for (c, cell) in row.iter_mut().enumerate() {
+                *cell = states[r * C + c] == 1;
+            }
+        });
+
+        self.grid = next;
+        self.states = states;
     }
 
     #[inline]
-    const fn live_neighbors(&self, r: usize, c: usize) -> u8 {
-        self.grid[Self::diff::<R>(r, -1)][Self::diff::<C>(c, -1)] as u8
-            + self.grid[Self::diff::<R>(r, -1)][c] as u8
-            + self.grid[Self::diff::<R>(r, -1)][Self::diff::<C>(c, 1)] as u8
-            + self.grid[r][Self::diff::<C>(c, -1)] as u8
-            + self.grid[r][Self::diff::<C>(c, 1)] as u8
-            + self.grid[Self::diff::<R>(r, 1)][Self::diff::<C>(c, -1)] as u8
-            + self.grid[Self::diff::<R>(r, 1)][c] as u8
-            + self.grid[Self::diff::<R>(r, 1)][Self::diff::<C>(c, 1)] as u8
+    fn next<const TOPOLOGY: u8>(&self, r: usize, c: usize) -> bool {
+        self.rule
+            .next(self.grid[r][c] as u8, self.live_neighbors::<TOPOLOGY>(r, c))
+            == 1
     }
 
     #[inline]
-    const fn diff<const I: usize>(i: usize, diff: isize) -> usize {
-        match (i, diff) {
-            (0, -1) => I - 1,
-            (_, 1) if i == I - 1 => 0,
-            (_, -1) => i - 1,
-            (_, 1) => i + 1,
-            _ => unreachable!(),
+    fn live_neighbors<const TOPOLOGY: u8>(&self, r: usize, c: usize) -> u8 {
+        topology::live_neighbors::<TOPOLOGY>(R, C, (r, c), |r, c| self.grid[r][c])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alive<const R: usize, const C: usize>(game: &Game<R, C>) -> Vec<(usize, usize)> {
+        (0..R)
+            .flat_map(|r| (0..C).map(move |c| (r, c)))
+            .filter(|(r, c)| game.grid()[*r][*c])
+            .collect()
+    }
+
+    #[test]
+    fn test_conway() {
+        let mut grid = Box::new([[false; 5]; 5]);
+        for c in 1..4 {
+            grid[2][c] = true;
         }
+        let mut game = Game::new(grid);
+
+        game.update();
+        assert_eq!(alive(&game), vec![(1, 2), (2, 2), (3, 2)]);
+        game.update();
+        assert_eq!(alive(&game), vec![(2, 1), (2, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn test_from_pattern() {
+        let glider = Pattern::from_rle("x = 3, y = 3, rule = B36/S23\nbo$2bo$3o!").unwrap();
+        let game = Game::<5, 7>::from_pattern(&glider).unwrap();
+        assert_eq!(game.rule(), &"B36/S23".parse().unwrap());
+        assert_eq!(alive(&game), vec![(1, 3), (2, 4), (3, 2), (3, 3), (3, 4)]);
+
+        assert!(Game::<2, 7>::from_pattern(&glider).is_none());
+    }
+
+    #[test]
+    fn test_topology() {
+        let glider = Pattern::from_cells(".O\n..O\nOOO\n").unwrap();
+        let start = glider.grid_at::<8, 8>(0, 0).unwrap();
+
+        // 32 generations take a glider 8 cells down and right, once round a torus.
+        // On a Klein bottle coming back in from the top mirrors it
+        let mut mirrored: Vec<(usize, usize)> =
+            glider.cells().iter().map(|(r, c)| (*r, 7 - c)).collect();
+        mirrored.sort_unstable();
+
+        for (topology, expected) in [
+            (Topology::Torus, glider.cells().to_vec()),
+            (Topology::KleinBottle, mirrored),
+        ] {
+            let mut game = Game::new(start.clone());
+            game.set_topology(topology);
+            for _ in 0..32 {
+                game.update();
+            }
+            assert_eq!(alive(&game), expected);
+        }
+
+        // the glider ends up as a block in the corner
+        let mut game = Game::new(start);
+        game.set_topology(Topology::Bounded);
+        for _ in 0..32 {
+            game.update();
+        }
+        assert_eq!(alive(&game), vec![(6, 6), (6, 7), (7, 6), (7, 7)]);
+    }
+
+    #[test]
+    fn test_highlife() {
+        // a dead cell with 6 live neighbors is only born in HighLife
+        let mut grid = Box::new([[false; 5]; 5]);
+        for (r, c) in [(1, 1), (1, 2), (1, 3), (3, 1), (3, 2), (3, 3)] {
+            grid[r][c] = true;
+        }
+
+        let mut conway = Game::new(grid.clone());
+        conway.update();
+        assert!(!conway.grid()[2][2]);
+
+        let mut highlife = Game::with_rule(grid, "B36/S23".parse().unwrap());
+        highlife.update();
+        assert!(highlife.grid()[2][2]);
+    }
+
+    #[test]
+    fn test_brians_brain() {
+        let mut grid = Box::new([[false; 6]; 6]);
+        grid[2][2] = true;
+        grid[2][3] = true;
+        let mut game = Game::with_rule(grid, "/2/3".parse().unwrap());
+
+        game.update();
+        assert_eq!(game.state(2, 2), 2);
+        assert_eq!(alive(&game), vec![(1, 2), (1, 3), (3, 2), (3, 3)]);
+
+        // the dying cells don't count as neighbors, and can't be born again yet
+        game.update();
+        assert_eq!(game.state(2, 2), 0);
+        assert_eq!(game.state(1, 2), 2);
+        assert_eq!(
+            alive(&game),
+            vec![(0, 2), (0, 3), (2, 1), (2, 4), (4, 2), (4, 3)]
+        );
     }
 }
@@ -1,26 +1,78 @@
 #![allow(dead_code)]
 
+use crate::{
+    topology::{self, BOUNDED, KLEIN_BOTTLE, TORUS},
+    Rule, Topology,
+};
 use bitvec::prelude::*;
 
 pub struct Game<const N: usize> {
     grid: BitVec,
+    rule: Rule,
+    topology: Topology,
+    /// the state of every cell, only kept for rules with dying states
+    states: Vec<u8>,
 }
 
 impl<const N: usize> Game<N> {
-    pub const fn new(start: BitVec) -> Self {
-        Self { grid: start }
+    pub fn new(start: BitVec) -> Self {
+        Self::with_rule(start, Rule::CONWAY)
+    }
+
+    pub fn with_rule(start: BitVec, rule: Rule) -> Self {
+        let states = match rule.states() {
+            2 => vec![],
+            _ => start.iter().map(|alive| *alive as u8).collect(),
+        };
+
+        Self {
+            grid: start,
+            rule,
+            topology: Topology::Torus,
+            states,
+        }
     }
 
     pub const fn grid(&self) -> &BitVec {
         &self.grid
     }
 
+    pub const fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
     pub fn update(&mut self) {
+        match self.topology {
+            Topology::Torus => self.update_in::<TORUS>(),
+            Topology::Bounded => self.update_in::<BOUNDED>(),
+            Topology::KleinBottle => self.update_in::<KLEIN_BOTTLE>(),
+        }
+    }
+
+    fn update_in<const TOPOLOGY: u8>(&mut self) {
+        if !self.states.is_empty() {
+            let states: Vec<u8> = (0..N * N)
+                .map(|i| {
+                    self.rule.next(
+                        self.states[i],
+                        self.live_neighbors::<TOPOLOGY>(i / N, i % N),
+                    )
+                })
+                .collect();
+            self.grid = states.iter().map(|state| *state == 1).collect();
+            self.states = states;
+            return;
+        }
+
         let mut next = BitVec::with_capacity(N * N);
 
         for r in 0..N {
             for c in 0..N {
-                next.push(self.next(r, c));
+                next.push(self.next::<TOPOLOGY>(r, c));
             }
         }
 
@@ -28,43 +80,22 @@ impl<const N: usize> Game<N> {
     }
 
     #[inline]
-    fn next(&self, r: usize, c: usize) -> bool {
-        match (self.grid[Self::i(r, c)], self.live_neighbors(r, c)) {
-            (true, n) if n < 2 => false,
-            (true, n) if n == 2 || n == 3 => true,
-            (true, n) if n > 3 => false,
-            (false, n) if n == 3 => true,
-            _ => self.grid[Self::i(r, c)],
-        }
+    fn next<const TOPOLOGY: u8>(&self, r: usize, c: usize) -> bool {
+        self.rule.next(
+            self.grid[Self::i(r, c)] as u8,
+            self.live_neighbors::<TOPOLOGY>(r, c),
+        ) == 1
     }
 
     #[inline]
-    fn live_neighbors(&self, r: usize, c: usize) -> u8 {
-        self.grid[Self::i(Self::wrap(r, -1), Self::wrap(c, -1))] as u8
-            + self.grid[Self::i(Self::wrap(r, -1), c)] as u8
-            + self.grid[Self::i(Self::wrap(r, -1), Self::wrap(c, 1))] as u8
-            + self.grid[Self::i(r, Self::wrap(c, -1))] as u8
-            + self.grid[Self::i(r, Self::wrap(c, 1))] as u8
-            + self.grid[Self::i(Self::wrap(r, 1), Self::wrap(c, -1))] as u8
-            + self.grid[Self::i(Self::wrap(r, 1), c)] as u8
-            + self.grid[Self::i(Self::wrap(r, 1), Self::wrap(c, 1))] as u8
+    fn live_neighbors<const TOPOLOGY: u8>(&self, r: usize, c: usize) -> u8 {
+        topology::live_neighbors::<TOPOLOGY>(N, N, (r, c), |r, c| self.grid[Self::i(r, c)])
     }
 
     #[inline]
     const fn i(r: usize, c: usize) -> usize {
         r * N + c
     }
-
-    #[inline]
-    const fn wrap(i: usize, diff: isize) -> usize {
-        match (i, diff) {
-            (0, -1) => N - 1,
-            (_, 1) if i == N - 1 => 0,
-            (_, -1) => i - 1,
-            (_, 1) => i + 1,
-            _ => unreachable!(),
-        }
-    }
 }
 
 // const LIVE: char = '⬜';
@@ -1,43 +1,112 @@
 #![allow(dead_code)]
 
+use crate::{
+    topology::{self, BOUNDED, KLEIN_BOTTLE, TORUS},
+    Rule, Topology,
+};
 use rayon::prelude::*;
 
 pub struct Game<const R: usize, const C: usize> {
     grid: Vec<bool>,
+    rule: Rule,
+    topology: Topology,
+    /// the state of every cell, only kept for rules with dying states
+    states: Vec<u8>,
 }
 
 impl<const R: usize, const C: usize> Game<R, C> {
     pub fn empty() -> Self {
+        Self::with_rule(Rule::CONWAY)
+    }
+
+    /// an empty game
+    pub fn with_rule(rule: Rule) -> Self {
+        let states = match rule.states() {
+            2 => vec![],
+            _ => vec![0; R * C],
+        };
+
         Self {
             grid: vec![false; R * C],
+            rule,
+            topology: Topology::Torus,
+            states,
         }
     }
 
+    pub const fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
     pub fn kill(&mut self, r: usize, c: usize) {
-        self.grid[Self::i(r, c)] = false;
+        self.set(Self::i(r, c), 0);
     }
 
     pub fn revive(&mut self, r: usize, c: usize) {
-        self.grid[Self::i(r, c)] = true;
+        self.set(Self::i(r, c), 1);
+    }
+
+    fn set(&mut self, i: usize, state: u8) {
+        self.grid[i] = state == 1;
+        if let Some(cell) = self.states.get_mut(i) {
+            *cell = state;
+        }
     }
 
     pub fn par_update(&mut self) {
+        match self.topology {
+            Topology::Torus => self.par_update_in::<TORUS>(),
+            Topology::Bounded => self.par_update_in::<BOUNDED>(),
+            Topology::KleinBottle => self.par_update_in::<KLEIN_BOTTLE>(),
+        }
+    }
+
+    fn par_update_in<const TOPOLOGY: u8>(&mut self) {
+        if !self.states.is_empty() {
+            let states: Vec<u8> = (0..R * C)
+                .into_par_iter()
+                .map(|i| self.next_state::<TOPOLOGY>(i))
+                .collect();
+            self.grid = states.par_iter().map(|state| *state == 1).collect();
+            self.states = states;
+            return;
+        }
+
         let mut next: Vec<bool> = vec![false; R * C];
 
         next.par_iter_mut().enumerate().for_each(|(i, cell)| {
             let (r, c) = Self::rc(i);
-            *cell = self.next(r, c);
+            *cell = self.next::<TOPOLOGY>(r, c);
         });
 
         std::mem::swap(&mut self.grid, &mut next);
     }
 
     pub fn update(&mut self) {
+        match self.topology {
+            Topology::Torus => self.update_in::<TORUS>(),
+            Topology::Bounded => self.update_in::<BOUNDED>(),
+            Topology::KleinBottle => self.update_in::<KLEIN_BOTTLE>(),
+        }
+    }
+
+    fn update_in<const TOPOLOGY: u8>(&mut self) {
+        if !self.states.is_empty() {
+            let states: Vec<u8> = (0..R * C).map(|i| self.next_state::<TOPOLOGY>(i)).collect();
+            self.grid = states.iter().map(|state| *state == 1).collect();
+            self.states = states;
+            return;
+        }
+
         let mut next: Vec<bool> = Vec::with_capacity(R * C);
 
         for r in 0..R {
             for c in 0..C {
-                next.push(self.next(r, c));
+                next.push(self.next::<TOPOLOGY>(r, c));
             }
         }
 
@@ -45,51 +114,23 @@ impl<const R: usize, const C: usize> Game<R, C> {
     }
 
     #[inline]
-    fn next(&self, r: usize, c: usize) -> bool {
-        match (self.grid[Self::i(r, c)], self.live_neighbors(r, c)) {
-            (true, n) if n < 2 => false,
-            (true, n) if n == 2 || n == 3 => true,
-            (true, n) if n > 3 => false,
-            (false, n) if n == 3 => true,
-            _ => self.grid[Self::i(r, c)],
-        }
+    fn next<const TOPOLOGY: u8>(&self, r: usize, c: usize) -> bool {
+        self.rule.next(
+            self.grid[Self::i(r, c)] as u8,
+            self.live_neighbors::<TOPOLOGY>(r, c),
+        ) == 1
     }
 
     #[inline]
-    fn live_neighbors(&self, r: usize, c: usize) -> u8 {
-        let left_r = Self::diff::<R>(r, -1);
-        let right_r = Self::diff::<R>(r, 1);
-        let top_c = Self::diff::<C>(c, -1);
-        let bottom_c = Self::diff::<C>(c, 1);
-
-        let top_left = Self::i(left_r, top_c);
-        let top = Self::i(r, top_c);
-        let top_right = Self::i(right_r, top_c);
-        let left = Self::i(left_r, c);
-        let right = Self::i(right_r, c);
-        let bottom_left = Self::i(left_r, bottom_c);
-        let bottom = Self::i(r, bottom_c);
-        let bottom_right = Self::i(right_r, bottom_c);
-
-        self.grid[top_left] as u8
-            + self.grid[top] as u8
-            + self.grid[top_right] as u8
-            + self.grid[left] as u8
-            + self.grid[right] as u8
-            + self.grid[bottom_left] as u8
-            + self.grid[bottom] as u8
-            + self.grid[bottom_right] as u8
+    fn next_state<const TOPOLOGY: u8>(&self, i: usize) -> u8 {
+        let (r, c) = Self::rc(i);
+        self.rule
+            .next(self.states[i], self.live_neighbors::<TOPOLOGY>(r, c))
     }
 
     #[inline]
-    const fn diff<const I: usize>(i: usize, diff: i8) -> usize {
-        match (i, diff) {
-            (0, -1) => I - 1,
-            (_, 1) if i == I - 1 => 0,
-            (_, -1) => i - 1,
-            (_, 1) => i + 1,
-            _ => unreachable!(),
-        }
+    fn live_neighbors<const TOPOLOGY: u8>(&self, r: usize, c: usize) -> u8 {
+        topology::live_neighbors::<TOPOLOGY>(R, C, (r, c), |r, c| self.grid[Self::i(r, c)])
     }
 
     #[inline]
@@ -1,18 +1,22 @@
 #![allow(dead_code)]
 
-use std::collections::HashSet;
-
+use crate::{
+    topology::{self, BOUNDED, KLEIN_BOTTLE, TORUS},
+    Pattern, PatternError, Rule, Topology,
+};
 use rayon::prelude::*;
 
 pub struct Game {
     grid: Vec<Vec<bool>>,
+    rule: Rule,
+    topology: Topology,
+    /// the state of every cell, only kept for rules with dying states
+    states: Vec<Vec<u8>>,
 }
 
 impl Game {
     pub fn empty(r: usize, c: usize) -> Self {
-        Self {
-            grid: vec![vec![false; c]; r],
-        }
+        Self::new(vec![vec![false; c]; r])
     }
 
     pub fn nr(&self) -> usize {
@@ -20,23 +24,88 @@ impl Game {
     }
 
     pub fn nc(&self) -> usize {
-        self.grid[0].len()
+        self.grid.first().map_or(0, Vec::len)
     }
 
-    pub const fn new(start: Vec<Vec<bool>>) -> Self {
-        Self { grid: start }
+    pub fn new(start: Vec<Vec<bool>>) -> Self {
+        Self::with_rule(start, Rule::CONWAY)
+    }
+
+    pub fn with_rule(start: Vec<Vec<bool>>, rule: Rule) -> Self {
+        let states = match rule.states() {
+            2 => vec![],
+            _ => start
+                .iter()
+                .map(|row| row.iter().map(|alive| *alive as u8).collect())
+                .collect(),
+        };
+
+        Self {
+            grid: start,
+            rule,
+            topology: Topology::Torus,
+            states,
+        }
     }
 
     pub const fn grid(&self) -> &Vec<Vec<bool>> {
         &self.grid
     }
 
+    pub const fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    pub const fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+    }
+
+    /// 0 for dead, 1 for alive and from 2 up for dying, see [`Rule`]
+    pub fn state(&self, r: usize, c: usize) -> u8 {
+        match self.states.get(r) {
+            Some(row) => row[c],
+            None => self.grid[r][c] as u8,
+        }
+    }
+
     pub fn update(&mut self) {
+        match self.topology {
+            Topology::Torus => self.update_in::<TORUS>(),
+            Topology::Bounded => self.update_in::<BOUNDED>(),
+            Topology::KleinBottle => self.update_in::<KLEIN_BOTTLE>(),
+        }
+    }
+
+    fn update_in<const TOPOLOGY: u8>(&mut self) {
+        if !self.states.is_empty() {
+            let states: Vec<Vec<u8>> = (0..self.nr())
+                .into_par_iter()
+                .map(|r| {
+                    (0..self.nc())
+                        .map(|c| {
+                            self.rule
+                                .next(self.states[r][c], self.live_neighbors::<TOPOLOGY>(r, c))
+                        })
+                        .collect()
+                })
+                .collect();
+            self.grid = states
+                .iter()
+                .map(|row| row.iter().map(|state| *state == 1).collect())
+                .collect();
+            self.states = states;
+            return;
+        }
+
         let mut next = vec![vec![false; self.grid[0].len()]; self.grid.len()];
 
         next.par_iter_mut().enumerate().for_each(|(r, row)| {
             row.par_iter_mut().enumerate().for_each(|(c, cell)| {
-                *cell = self.next(r, c);
+                *cell = self.next::<TOPOLOGY>(r, c);
             });
         });
 
@@ -44,72 +113,75 @@ impl Game {
     }
 
     #[inline]
-    fn next(&self, r: usize, c: usize) -> bool {
-        match (self.grid[r][c], self.live_neighbors(r, c)) {
-            (true, n) if n < 2 => false,
-            (true, n) if n == 2 || n == 3 => true,
-            (true, n) if n > 3 => false,
-            (false, n) if n == 3 => true,
-            _ => self.grid[r][c],
-        }
+    fn next<const TOPOLOGY: u8>(&self, r: usize, c: usize) -> bool {
+        self.rule
+            .next(self.grid[r][c] as u8, self.live_neighbors::<TOPOLOGY>(r, c))
+            == 1
     }
 
     #[inline]
-    fn live_neighbors(&self, r: usize, c: usize) -> u8 {
-        self.grid[Self::diff(self.nr(), r, -1)][Self::diff(self.nc(), c, -1)] as u8
-            + self.grid[Self::diff(self.nr(), r, -1)][c] as u8
-            + self.grid[Self::diff(self.nr(), r, -1)][Self::diff(self.nc(), c, 1)] as u8
-            + self.grid[r][Self::diff(self.nc(), c, -1)] as u8
-            + self.grid[r][Self::diff(self.nc(), c, 1)] as u8
-            + self.grid[Self::diff(self.nr(), r, 1)][Self::diff(self.nc(), c, -1)] as u8
-            + self.grid[Self::diff(self.nr(), r, 1)][c] as u8
-            + self.grid[Self::diff(self.nr(), r, 1)][Self::diff(self.nc(), c, 1)] as u8
-    }
-
-    #[inline]
-    const fn diff(max_i: usize, i: usize, diff: isize) -> usize {
-        match (i, diff) {
-            (0, -1) => max_i - 1,
-            (_, 1) if i == max_i - 1 => 0,
-            (_, -1) => i - 1,
-            (_, 1) => i + 1,
-            _ => unreachable!(),
-        }
+    fn live_neighbors<const TOPOLOGY: u8>(&self, r: usize, c: usize) -> u8 {
+        topology::live_neighbors::<TOPOLOGY>(self.nr(), self.nc(), (r, c), |r, c| self.grid[r][c])
     }
 }
 
 const LIVE: char = '⬜';
 const DEAD: char = '⬛';
 
-impl From<&str> for Game {
-    fn from(text: &str) -> Self {
+impl TryFrom<&str> for Game {
+    type Error = PatternError;
+
+    /// Reads rows of ⬜ and ⬛, all the same length
+    fn try_from(text: &str) -> Result<Self, Self::Error> {
         let mut grid: Vec<Vec<bool>> = Vec::new();
+        let syntax = |line, message| PatternError::Syntax { line, message };
 
-        for line in text.trim().lines() {
+        for (i, line) in text.lines().enumerate() {
             let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
 
             let mut row = vec![];
             for char in line.chars() {
                 match char {
                     LIVE => row.push(true),
                     DEAD => row.push(false),
-                    _ => panic!("only {} and {} allowed", LIVE, DEAD),
+                    _ => return Err(syntax(i + 1, format!("only {} and {} allowed", LIVE, DEAD))),
                 }
             }
+
+            if grid.first().is_some_and(|first| first.len() != row.len()) {
+                return Err(syntax(i + 1, "variable length columns!".to_string()));
+            }
             grid.push(row);
         }
 
-        if grid
-            .iter()
-            .map(|row| row.len())
-            .collect::<HashSet<usize>>()
-            .len()
-            != 1
-        {
-            panic!("variable length columns!");
+        if grid.is_empty() {
+            return Err(syntax(1, "no rows".to_string()));
+        }
+
+        Ok(Game::new(grid))
+    }
+}
+
+impl TryFrom<&Pattern> for Game {
+    type Error = PatternError;
+
+    /// Just the pattern's bounding box, under its rule or Conway's. A grid needs
+    /// at least one cell, so an empty bounding box is an error
+    fn try_from(pattern: &Pattern) -> Result<Self, Self::Error> {
+        if pattern.height() == 0 || pattern.width() == 0 {
+            return Err(PatternError::Syntax {
+                line: 1,
+                message: "no cells".to_string(),
+            });
         }
 
-        Game::new(grid)
+        Ok(Game::with_rule(
+            pattern.rows(),
+            pattern.rule().copied().unwrap_or_default(),
+        ))
     }
 }
 
@@ -121,7 +193,7 @@ pub fn draw(game: &Game) {
                 false => print!("{}", DEAD),
             }
         }
-        println!("");
+        println!();
     }
 }
 
@@ -141,7 +213,7 @@ pub fn run() {
     //     ",
     // );
 
-    let mut game: Game = Game::from(
+    let mut game = Game::try_from(
         "
         ⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛
         ⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛
@@ -160,7 +232,8 @@ pub fn run() {
         ⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛
         ⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛⬛
         ",
-    );
+    )
+    .unwrap();
 
     loop {
         draw(&game);
@@ -169,3 +242,28 @@ pub fn run() {
         game.update();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_pattern() {
+        let blinker = Pattern::from_rle("x = 3, y = 3\n$3o!").unwrap();
+        let mut game = Game::try_from(&blinker).unwrap();
+        game.set_topology(Topology::Bounded);
+        game.update();
+        assert_eq!(game.grid(), &vec![vec![false, true, false]; 3]);
+
+        for empty in [
+            Pattern::new([]),
+            Pattern::from_rle("x = 0, y = 0\n!").unwrap(),
+        ] {
+            assert!(matches!(
+                Game::try_from(&empty),
+                Err(PatternError::Syntax { .. })
+            ));
+        }
+        assert_eq!(Game::new(vec![]).nc(), 0);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{Rule, RuleError};
+
+mod cells;
+mod life106;
+mod rle;
+
+/// Why a pattern file couldn't be read
+#[derive(Debug)]
+pub enum PatternError {
+    Syntax {
+        /// 1 based line number
+        line: usize,
+        message: String,
+    },
+    Rule {
+        line: usize,
+        error: RuleError,
+    },
+}
+
+/// The live cells of a pattern, as read from or written to RLE, plaintext
+/// `.cells` and Life 1.06 files.
+///
+/// Cells are `(row, column)` inside a `height` by `width` bounding box with the
+/// top left at `(0, 0)`. Comment lines are kept without their leading `#` or `!`.
+///
+/// ```
+/// use life::Pattern;
+///
+/// let glider = Pattern::from_rle("x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+/// assert_eq!((glider.height(), glider.width()), (3, 3));
+/// assert_eq!(glider.to_cells(), ".O.\n..O\nOOO\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Pattern {
+    height: usize,
+    width: usize,
+    /// sorted by row and then column
+    cells: Vec<(usize, usize)>,
+    rule: Option<Rule>,
+    comments: Vec<String>,
+}
+
+impl Pattern {
+    /// The bounding box is the smallest around the cells. Repeated cells count once
+    pub fn new(cells: impl IntoIterator<Item = (usize, usize)>) -> Self {
+        let mut cells: Vec<(usize, usize)> = cells.into_iter().collect();
+        cells.sort_unstable();
+        cells.dedup();
+
+        Self {
+            height: cells.iter().map(|(r, _)| r + 1).max().unwrap_or(0),
+            width: cells.iter().map(|(_, c)| c + 1).max().unwrap_or(0),
+            cells,
+            rule: None,
+            comments: vec![],
+        }
+    }
+
+    /// Cells on the plane moved so the topmost row and leftmost column are 0, None
+    /// if they're too far apart for a `usize` bounding box
+    pub(crate) fn from_plane(cells: &[(i64, i64)]) -> Option<Self> {
+        let top = cells.iter().map(|(r, _)| *r).min().unwrap_or(0);
+        let left = cells.iter().map(|(_, c)| *c).min().unwrap_or(0);
+        // the height or width is one more than the farthest offset
+        let offset = |x: i64, start: i64| {
+            usize::try_from(x.abs_diff(start))
+                .ok()
+                .filter(|offset| *offset < usize::MAX)
+        };
+
+        let cells = cells
+            .iter()
+            .map(|(r, c)| Some((offset(*r, top)?, offset(*c, left)?)))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self::new(cells))
+    }
+
+    /// Every live cell of a grid, keeping the grid's size as the bounding box
+    pub fn from_rows<Row: AsRef<[bool]>>(rows: &[Row]) -> Self {
+        let cells = rows.iter().enumerate().flat_map(|(r, row)| {
+            row.as_ref()
+                .iter()
+                .enumerate()
+                .filter(|(_, alive)| **alive)
+                .map(move |(c, _)| (r, c))
+        });
+
+        Self {
+            height: rows.len(),
+            width: rows.iter().map(|row| row.as_ref().len()).max().unwrap_or(0),
+            ..Self::new(cells)
+        }
+    }
+
+    pub fn with_rule(mut self, rule: Rule) -> Self {
+        self.rule = Some(rule);
+        self
+    }
+
+    pub fn with_comments(mut self, comments: Vec<String>) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn cells(&self) -> &[(usize, usize)] {
+        &self.cells
+    }
+
+    /// the rule the file asked for, only RLE headers have one
+    pub fn rule(&self) -> Option<&Rule> {
+        self.rule.as_ref()
+    }
+
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    /// The bounding box as a grid, `true` for live cells
+    pub fn rows(&self) -> Vec<Vec<bool>> {
+        let mut rows = vec![vec![false; self.width]; self.height];
+        for (r, c) in &self.cells {
+            rows[*r][*c] = true;
+        }
+        rows
+    }
+
+    /// Draws the pattern into an `R` by `C` grid with its top left at `(r, c)`,
+    /// None if it doesn't fit
+    pub fn grid_at<const R: usize, const C: usize>(
+        &self,
+        r: usize,
+        c: usize,
+    ) -> Option<Box<[[bool; C]; R]>> {
+        if r + self.height > R || c + self.width > C {
+            return None;
+        }
+
+        let mut grid = Box::new([[false; C]; R]);
+        for (dr, dc) in &self.cells {
+            grid[r + dr][c + dc] = true;
+        }
+        Some(grid)
+    }
+}
+
+/// The most cells the bounding box of a pattern read from a file can cover, so
+/// that its rows and the grids made from it fit in memory
+pub const MAX_AREA: usize = 1 << 28;
+
+/// A syntax error at `line` unless a `height` by `width` box fits in [`MAX_AREA`]
+fn check_area(line: usize, height: usize, width: usize) -> Result<(), PatternError> {
+    match height.checked_mul(width) {
+        Some(area) if area <= MAX_AREA => Ok(()),
+        _ => Err(syntax(
+            line,
+            format!("{} by {} is more than {} cells", width, height, MAX_AREA),
+        )),
+    }
+}
+
+fn syntax(line: usize, message: String) -> PatternError {
+    PatternError::Syntax { line, message }
+}
+
+/// (1 based line number, line without trailing whitespace)
+fn numbered(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim_end()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows() {
+        let pattern = Pattern::from_rows(&[[false, true, false], [false, false, false]]);
+        assert_eq!((pattern.height(), pattern.width()), (2, 3));
+        assert_eq!(pattern.cells(), &[(0, 1)]);
+        assert_eq!(
+            pattern.rows(),
+            vec![vec![false, true, false], vec![false; 3]]
+        );
+
+        let pattern = Pattern::new([(2, 1), (0, 0), (2, 1)]);
+        assert_eq!((pattern.height(), pattern.width()), (3, 2));
+        assert_eq!(pattern.cells(), &[(0, 0), (2, 1)]);
+
+        let grid = pattern.grid_at::<4, 4>(1, 2).unwrap();
+        assert!(grid[1][2] && grid[3][3]);
+        assert_eq!(grid.iter().flatten().filter(|alive| **alive).count(), 2);
+        assert!(pattern.grid_at::<4, 4>(2, 2).is_none());
+    }
+}
@@ -0,0 +1,90 @@
+use super::{numbered, syntax, Pattern, PatternError};
+
+impl Pattern {
+    /// Reads the plaintext `.cells` format, `!` comment lines and then a row per
+    /// line with `.` for dead cells and `O` for live ones. `*` is read as live too.
+    ///
+    /// Rows can be shorter than the widest one, the rest of the row is dead.
+    pub fn from_cells(text: &str) -> Result<Self, PatternError> {
+        let mut comments = vec![];
+        let mut rows: Vec<Vec<bool>> = vec![];
+
+        for (line, row) in numbered(text) {
+            if let Some(comment) = row.strip_prefix('!') {
+                if rows.is_empty() {
+                    comments.push(comment.to_string());
+                    continue;
+                }
+            }
+            if row.is_empty() && rows.is_empty() {
+                continue;
+            }
+
+            let row = row
+                .chars()
+                .map(|cell| match cell {
+                    '.' => Ok(false),
+                    'O' | '*' => Ok(true),
+                    _ => Err(syntax(line, format!("unexpected {:?}", cell))),
+                })
+                .collect::<Result<_, _>>()?;
+            rows.push(row);
+        }
+
+        // blank lines only count as rows between other rows
+        while rows.last().is_some_and(Vec::is_empty) {
+            rows.pop();
+        }
+
+        Ok(Self::from_rows(&rows).with_comments(comments))
+    }
+
+    /// Writes the comments after `!` and every row of the bounding box in full
+    pub fn to_cells(&self) -> String {
+        let comments = self
+            .comments
+            .iter()
+            .map(|comment| format!("!{}\n", comment));
+
+        let rows = self.rows().into_iter().map(|row| {
+            row.into_iter()
+                .map(|alive| if alive { 'O' } else { '.' })
+                .chain(['\n'])
+                .collect::<String>()
+        });
+
+        comments.chain(rows).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cells() {
+        let text = "!Name: Glider\n!\n.O\n..O\nOOO\n\n";
+        let glider = Pattern::from_cells(text).unwrap();
+        assert_eq!(glider.comments(), &["Name: Glider", ""]);
+        assert_eq!((glider.height(), glider.width()), (3, 3));
+        assert_eq!(glider.cells(), &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        assert_eq!(glider.to_cells(), "!Name: Glider\n!\n.O.\n..O\nOOO\n");
+
+        // blank rows in the middle are kept
+        let pattern = Pattern::from_cells("O\n\n*.\n").unwrap();
+        assert_eq!((pattern.height(), pattern.width()), (3, 2));
+        assert_eq!(Pattern::from_cells(&pattern.to_cells()).unwrap(), pattern);
+    }
+
+    #[test]
+    fn test_cells_errors() {
+        assert!(matches!(
+            Pattern::from_cells("!comment\n.O\n.o\n"),
+            Err(PatternError::Syntax { line: 3, .. })
+        ));
+        assert!(matches!(
+            Pattern::from_cells(".O\n!late comment\n"),
+            Err(PatternError::Syntax { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+use super::{check_area, numbered, syntax, Pattern, PatternError};
+
+const HEADER: &str = "#Life 1.06";
+
+impl Pattern {
+    /// Reads Life 1.06, a `#Life 1.06` line and then an `x y` pair per live cell,
+    /// `x` the column and `y` the row. Other `#` lines are comments.
+    ///
+    /// Coordinates can be negative, the pattern is moved so its top left live
+    /// cell's row and column are 0. Cells spread over more than
+    /// [`MAX_AREA`](super::MAX_AREA) are an error.
+    pub fn from_life_106(text: &str) -> Result<Self, PatternError> {
+        let mut lines = numbered(text).filter(|(_, line)| !line.trim().is_empty());
+        match lines.next() {
+            Some((_, header)) if header.trim() == HEADER => {}
+            found => {
+                return Err(syntax(
+                    found.map_or(1, |(line, _)| line),
+                    format!("expected {:?}", HEADER),
+                ))
+            }
+        }
+
+        let mut comments = vec![];
+        // (line, row, column)
+        let mut cells: Vec<(usize, i64, i64)> = vec![];
+        for (line, text) in lines {
+            if let Some(comment) = text.trim_start().strip_prefix('#') {
+                comments.push(comment.to_string());
+                continue;
+            }
+
+            let numbers: Vec<&str> = text.split_whitespace().collect();
+            let [x, y] = numbers[..] else {
+                return Err(syntax(line, format!("expected x y, found {:?}", text)));
+            };
+            let coordinate = |value: &str| {
+                value
+                    .parse()
+                    .map_err(|_| syntax(line, format!("expected a number, found {:?}", value)))
+            };
+            cells.push((line, coordinate(y)?, coordinate(x)?));
+        }
+
+        let top = cells.iter().map(|(_, r, _)| *r).min().unwrap_or(0);
+        let left = cells.iter().map(|(_, _, c)| *c).min().unwrap_or(0);
+        let cells = cells
+            .into_iter()
+            .map(|(line, r, c)| {
+                let offset = |i: i64, min: i64| i.checked_sub(min)?.try_into().ok();
+                match (offset(r, top), offset(c, left)) {
+                    (Some(r), Some(c)) => Ok((line, r, c)),
+                    _ => Err(syntax(line, "too far from the other cells".to_string())),
+                }
+            })
+            .collect::<Result<Vec<(usize, usize, usize)>, _>>()?;
+
+        // the farthest cell from the top left one gets the blame for a box that's too big
+        if let Some((line, _, _)) = cells.iter().max_by_key(|(_, r, c)| *r.max(c)) {
+            let (height, width) = cells
+                .iter()
+                .fold((0, 0), |(h, w), (_, r, c)| (h.max(r + 1), w.max(c + 1)));
+            check_area(*line, height, width)?;
+        }
+
+        Ok(Self::new(cells.into_iter().map(|(_, r, c)| (r, c))).with_comments(comments))
+    }
+
+    /// Writes the header, the comments and a line per live cell, column first
+    pub fn to_life_106(&self) -> String {
+        let comments = self
+            .comments
+            .iter()
+            .map(|comment| format!("#{}\n", comment));
+        let cells = self.cells.iter().map(|(r, c)| format!("{} {}\n", c, r));
+
+        [format!("{}\n", HEADER)]
+            .into_iter()
+            .chain(comments)
+            .chain(cells)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_life_106() {
+        let text = "#Life 1.06\n#D Glider\n0 -1\n1 0\n-1 1\n0 1\n1 1\n";
+        let glider = Pattern::from_life_106(text).unwrap();
+        assert_eq!(glider.comments(), &["D Glider"]);
+        assert_eq!(glider.cells(), &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        assert_eq!(
+            glider,
+            Pattern::from_cells(".O\n..O\nOOO\n")
+                .unwrap()
+                .with_comments(vec!["D Glider".to_string()])
+        );
+
+        let written = glider.to_life_106();
+        assert_eq!(written, "#Life 1.06\n#D Glider\n1 0\n2 1\n0 2\n1 2\n2 2\n");
+        assert_eq!(Pattern::from_life_106(&written).unwrap(), glider);
+    }
+
+    #[test]
+    fn test_life_106_errors() {
+        let line = |text| match Pattern::from_life_106(text) {
+            Err(PatternError::Syntax { line, .. }) => line,
+            other => panic!("{:?}", other),
+        };
+
+        assert_eq!(line(""), 1);
+        assert_eq!(line("\n#Life 1.05\n"), 2);
+        assert_eq!(line("#Life 1.06\n0 0\n1\n"), 3);
+        assert_eq!(line("#Life 1.06\n0 x\n"), 2);
+        assert_eq!(line("#Life 1.06\n0 -1\n0 9223372036854775807\n"), 3);
+        assert_eq!(line("#Life 1.06\n-9223372036854775808 0\n1 0\n"), 3);
+        assert_eq!(line("#Life 1.06\n0 0\n5 -9000000000000000000\n1 1\n"), 4);
+        assert_eq!(line("#Life 1.06\n100000 0\n0 100000\n"), 3);
+        assert!(Pattern::from_life_106("#Life 1.06\n100000 0\n").is_ok());
+    }
+}
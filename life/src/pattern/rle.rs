@@ -0,0 +1,247 @@
+use super::{check_area, numbered, syntax, Pattern, PatternError};
+
+/// Golly keeps RLE lines under 70 characters
+const LINE_LENGTH: usize = 70;
+
+impl Pattern {
+    /// Reads Golly's run length encoding, `#` comment lines, an `x = 3, y = 3, rule = B3/S23`
+    /// header (the rule is optional) and then runs of `b` for dead cells, `o` for
+    /// live cells and `$` for ends of rows, up to a `!`.
+    ///
+    /// `.` and `A`, the dead and live states of multistate RLE, are read too.
+    /// A header bigger than [`MAX_AREA`](super::MAX_AREA) cells is an error.
+    pub fn from_rle(text: &str) -> Result<Self, PatternError> {
+        let mut lines = numbered(text);
+        let mut comments = vec![];
+
+        let (line, header) = loop {
+            match lines.next() {
+                None => {
+                    return Err(syntax(
+                        text.lines().count().max(1),
+                        "missing the x = , y = header".to_string(),
+                    ))
+                }
+                Some((_, line)) if line.trim().is_empty() => {}
+                Some((_, line)) if line.trim_start().starts_with('#') => {
+                    comments.push(line.trim_start()[1..].to_string());
+                }
+                Some(header) => break header,
+            }
+        };
+        let (width, height, rule) = parse_header(line, header)?;
+        check_area(line, height, width)?;
+
+        let mut cells = vec![];
+        let (mut r, mut c): (usize, usize) = (0, 0);
+        let mut count: Option<usize> = None;
+
+        'body: for (line, text) in lines {
+            for tag in text.chars() {
+                let too_big = || syntax(line, "run or position too big".to_string());
+
+                if let Some(digit) = tag.to_digit(10) {
+                    let run = count.unwrap_or(0).checked_mul(10);
+                    count = Some(
+                        run.and_then(|run| run.checked_add(digit as usize))
+                            .ok_or_else(too_big)?,
+                    );
+                    continue;
+                }
+
+                let run = count.take().unwrap_or(1);
+                match tag {
+                    'b' | '.' => c = c.checked_add(run).ok_or_else(too_big)?,
+                    'o' | 'A' => {
+                        let end = c.checked_add(run).ok_or_else(too_big)?;
+                        if r >= height || end > width {
+                            return Err(syntax(
+                                line,
+                                format!("cells outside the {} by {} header", width, height),
+                            ));
+                        }
+                        cells.extend((c..end).map(|c| (r, c)));
+                        c = end;
+                    }
+                    '$' => {
+                        r = r.checked_add(run).ok_or_else(too_big)?;
+                        c = 0;
+                    }
+                    '!' => break 'body,
+                    tag if tag.is_whitespace() => {}
+                    tag => return Err(syntax(line, format!("unexpected {:?}", tag))),
+                }
+            }
+        }
+
+        Ok(Self {
+            height,
+            width,
+            rule,
+            comments,
+            ..Self::new(cells)
+        })
+    }
+
+    /// Writes the comments, the header with the rule (Conway's if there is none)
+    /// and the runs, wrapped at 70 characters
+    pub fn to_rle(&self) -> String {
+        let mut text: String = self
+            .comments
+            .iter()
+            .map(|comment| format!("#{}\n", comment))
+            .collect();
+        text += &format!(
+            "x = {}, y = {}, rule = {}\n",
+            self.width,
+            self.height,
+            self.rule.unwrap_or_default()
+        );
+
+        let mut runs = vec![];
+        let mut last_row = 0;
+        for row in self.cells.chunk_by(|a, b| a.0 == b.0) {
+            let r = row[0].0;
+            if r > last_row {
+                runs.push(run(r - last_row, '$'));
+            }
+            last_row = r;
+
+            let mut c = 0;
+            for alive in row.chunk_by(|a, b| a.1 + 1 == b.1) {
+                let start = alive[0].1;
+                if start > c {
+                    runs.push(run(start - c, 'b'));
+                }
+                runs.push(run(alive.len(), 'o'));
+                c = start + alive.len();
+            }
+        }
+        runs.push("!".to_string());
+
+        let mut line = String::new();
+        for run in runs {
+            if line.len() + run.len() > LINE_LENGTH {
+                text += &line;
+                text.push('\n');
+                line.clear();
+            }
+            line += &run;
+        }
+        text += &line;
+        text.push('\n');
+
+        text
+    }
+}
+
+fn run(length: usize, tag: char) -> String {
+    match length {
+        1 => tag.to_string(),
+        _ => format!("{}{}", length, tag),
+    }
+}
+
+/// (width, height, rule) from `x = 3, y = 3, rule = B3/S23`
+fn parse_header(
+    line: usize,
+    header: &str,
+) -> Result<(usize, usize, Option<crate::Rule>), PatternError> {
+    let (mut width, mut height, mut rule) = (None, None, None);
+
+    for field in header.split(',') {
+        let Some((key, value)) = field.split_once('=') else {
+            return Err(syntax(
+                line,
+                format!("expected key = value, found {:?}", field),
+            ));
+        };
+        let (key, value) = (key.trim(), value.trim());
+
+        let number = || {
+            value
+                .parse()
+                .map_err(|_| syntax(line, format!("expected a number, found {:?}", value)))
+        };
+
+        match key {
+            "x" => width = Some(number()?),
+            "y" => height = Some(number()?),
+            "rule" => {
+                rule = Some(
+                    value
+                        .parse()
+                        .map_err(|error| PatternError::Rule { line, error })?,
+                )
+            }
+            _ => return Err(syntax(line, format!("unknown key {:?}", key))),
+        }
+    }
+
+    match (width, height) {
+        (Some(width), Some(height)) => Ok((width, height, rule)),
+        _ => Err(syntax(line, "x or y missing".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::GOSPER_GLIDER_GUN, Rule};
+
+    #[test]
+    fn test_rle() {
+        let gun = Pattern::from_rle(GOSPER_GLIDER_GUN).unwrap();
+        assert_eq!((gun.width(), gun.height()), (36, 9));
+        assert_eq!(gun.cells().len(), 36);
+        assert_eq!(gun.rule(), Some(&Rule::CONWAY));
+        assert_eq!(gun.comments(), &["N Gosper glider gun", "O Bill Gosper"]);
+        assert!(gun.cells().contains(&(0, 24)) && gun.cells().contains(&(8, 13)));
+
+        assert_eq!(gun.to_rle(), GOSPER_GLIDER_GUN);
+    }
+
+    #[test]
+    fn test_rle_runs() {
+        // blank rows, a missing rule and a body split anywhere
+        let pattern = Pattern::from_rle("x=4,y=5\n2o2$\n\n3bo\n$ .A!\n").unwrap();
+        assert_eq!(pattern.rule(), None);
+        assert_eq!(pattern.cells(), &[(0, 0), (0, 1), (2, 3), (3, 1)]);
+        assert_eq!(pattern.height(), 5);
+        assert_eq!(
+            pattern.to_rle(),
+            "x = 4, y = 5, rule = B3/S23\n2o2$3bo$bo!\n"
+        );
+
+        let rule = "B36/S23".parse().unwrap();
+        let written = Pattern::new([(1, 1)]).with_rule(rule).to_rle();
+        assert_eq!(written, "x = 2, y = 2, rule = B36/S23\n$bo!\n");
+        assert_eq!(Pattern::from_rle(&written).unwrap().rule(), Some(&rule));
+    }
+
+    #[test]
+    fn test_rle_errors() {
+        let line = |text| match Pattern::from_rle(text) {
+            Err(PatternError::Syntax { line, .. } | PatternError::Rule { line, .. }) => line,
+            other => panic!("{:?}", other),
+        };
+
+        assert_eq!(line("#C nothing else\n"), 1);
+        assert_eq!(line("#C\nx = 3\nbo!"), 2);
+        assert_eq!(line("x = 3, y = three\nbo!"), 1);
+        assert_eq!(line("x = 3, y = 1\nbo$\nboz!"), 3);
+        assert_eq!(line("x = 3, y = 1\n4o!"), 2);
+        assert_eq!(line("x = 3, y = 1\n$o!"), 2);
+        assert_eq!(line("x = 3, y = 1\n99999999999999999999999b!"), 2);
+        assert_eq!(line("x = 3, y = 1\nbo18446744073709551615b!"), 2);
+        assert_eq!(line("x = 3, y = 1\nbo\n18446744073709551615o!"), 3);
+        assert_eq!(line("x = 3, y = 1\n$18446744073709551615$o!"), 2);
+        assert_eq!(line("x = 100000000000, y = 100000000000\no!"), 1);
+        assert_eq!(line("#C wide\nx = 268435457, y = 1\no!"), 2);
+        assert!(Pattern::from_rle("x = 16384, y = 16384\no!").is_ok());
+        assert!(matches!(
+            Pattern::from_rle("x = 1, y = 1, rule = B9/S\no!"),
+            Err(PatternError::Rule { line: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,228 @@
+use std::{fmt, str::FromStr};
+
+/// Which cells are born, survive and die, parsed from a rulestring.
+///
+/// Life-like rules have 2 states, dead (0) and alive (1). Generations rules have
+/// more, a live cell that doesn't survive goes through the dying states 2, 3, ...
+/// and only then dies, and dying cells don't count as live neighbors.
+///
+/// ```
+/// use life::Rule;
+///
+/// let highlife: Rule = "B36/S23".parse().unwrap();
+/// assert!(highlife.is_born(6));
+/// assert_eq!("23/36".parse::<Rule>().unwrap(), highlife);
+///
+/// let brians_brain: Rule = "/2/3".parse().unwrap();
+/// assert_eq!(brians_brain.states(), 3);
+/// assert_eq!(brians_brain.to_string(), "B2/S/C3");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rule {
+    /// bit `n` is set if a dead cell with `n` live neighbors is born
+    birth: u16,
+    /// bit `n` is set if a live cell with `n` live neighbors survives
+    survival: u16,
+    states: u8,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RuleError {
+    /// not `B3/S23`, `23/3` or a Generations rule like `/2/3` or `B2/S/C3`
+    Malformed(String),
+    /// a neighbor count that isn't a digit from 0 to 8
+    InvalidCount(char),
+    /// Generations rules have 2 to 255 states
+    InvalidStates(String),
+}
+
+impl Rule {
+    /// B3/S23
+    pub const CONWAY: Rule = Rule {
+        birth: 1 << 3,
+        survival: 1 << 2 | 1 << 3,
+        states: 2,
+    };
+
+    /// Panics if a count is above 8 or there are fewer than 2 states
+    pub fn new(birth: &[u8], survival: &[u8], states: u8) -> Self {
+        assert!(states >= 2, "a rule needs at least 2 states");
+
+        let mask = |counts: &[u8]| {
+            counts.iter().fold(0, |mask, n| {
+                assert!(*n <= 8, "a cell has at most 8 neighbors, not {}", n);
+                mask | 1 << n
+            })
+        };
+
+        Self {
+            birth: mask(birth),
+            survival: mask(survival),
+            states,
+        }
+    }
+
+    pub const fn states(&self) -> u8 {
+        self.states
+    }
+
+    pub const fn is_born(&self, live_neighbors: u8) -> bool {
+        self.birth >> live_neighbors & 1 == 1
+    }
+
+    pub const fn survives(&self, live_neighbors: u8) -> bool {
+        self.survival >> live_neighbors & 1 == 1
+    }
+
+    /// The state after `state` for a cell with `live_neighbors` live neighbors
+    #[inline]
+    pub const fn next(&self, state: u8, live_neighbors: u8) -> u8 {
+        match state {
+            0 if self.is_born(live_neighbors) => 1,
+            0 => 0,
+            1 if self.survives(live_neighbors) => 1,
+            // with 2 states a cell that doesn't survive dies right away
+            dying => (dying + 1) % self.states,
+        }
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::CONWAY
+    }
+}
+
+impl FromStr for Rule {
+    type Err = RuleError;
+
+    /// Reads `B36/S23` (either way round), `23/36` (survival first) and the
+    /// Generations forms `/2/3` and `B2/S/C3`, with the number of states last
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let malformed = || RuleError::Malformed(text.to_string());
+        let parts: Vec<(Option<char>, &str)> = text.trim().split('/').map(letter).collect();
+
+        let (birth, survival, states) = match parts[..] {
+            [(Some('B'), birth), (Some('S'), survival)]
+            | [(Some('S'), survival), (Some('B'), birth)] => (birth, survival, None),
+            [(Some('B'), birth), (Some('S'), survival), (Some('C' | 'G'), states)]
+            | [(Some('S'), survival), (Some('B'), birth), (Some('C' | 'G'), states)] => {
+                (birth, survival, Some(states))
+            }
+            [(None, survival), (None, birth)] => (birth, survival, None),
+            [(None, survival), (None, birth), (None, states)] => (birth, survival, Some(states)),
+            _ => return Err(malformed()),
+        };
+
+        let states = match states {
+            None => 2,
+            Some(states) => match states.parse() {
+                Ok(states) if states >= 2 => states,
+                _ => return Err(RuleError::InvalidStates(states.to_string())),
+            },
+        };
+
+        Ok(Self {
+            birth: counts(birth)?,
+            survival: counts(survival)?,
+            states,
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = |mask: u16| -> String {
+            (0..=8)
+                .filter(|n| mask >> n & 1 == 1)
+                .map(|n| char::from(b'0' + n as u8))
+                .collect()
+        };
+
+        write!(f, "B{}/S{}", digits(self.birth), digits(self.survival))?;
+        if self.states > 2 {
+            write!(f, "/C{}", self.states)?;
+        }
+        Ok(())
+    }
+}
+
+/// splits a leading `B`, `S`, `C` or `G` off a part of a rulestring
+fn letter(part: &str) -> (Option<char>, &str) {
+    let part = part.trim();
+    match part.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => (Some(c.to_ascii_uppercase()), &part[1..]),
+        _ => (None, part),
+    }
+}
+
+fn counts(digits: &str) -> Result<u16, RuleError> {
+    digits.chars().try_fold(0, |mask, c| match c.to_digit(10) {
+        Some(n) if n <= 8 => Ok(mask | 1 << n),
+        _ => Err(RuleError::InvalidCount(c)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let conway: Rule = "B3/S23".parse().unwrap();
+        assert_eq!(conway, Rule::CONWAY);
+        assert_eq!("b3/s23".parse(), Ok(conway));
+        assert_eq!("S23/B3".parse(), Ok(conway));
+        assert_eq!("23/3".parse(), Ok(conway));
+        assert_eq!(Rule::new(&[3], &[2, 3], 2), conway);
+
+        let day_and_night: Rule = "B3678/S34678".parse().unwrap();
+        assert_eq!(day_and_night.to_string(), "B3678/S34678");
+        assert!(day_and_night.is_born(8) && !day_and_night.is_born(5));
+
+        let star_wars: Rule = "345/2/4".parse().unwrap();
+        assert_eq!(star_wars, "B2/S345/C4".parse().unwrap());
+        assert_eq!(star_wars, "B2/S345/G4".parse().unwrap());
+        assert_eq!(star_wars.states(), 4);
+
+        // seeds: nothing survives
+        assert_eq!("B2/S".parse::<Rule>().unwrap().to_string(), "B2/S");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!("B9/S23".parse::<Rule>(), Err(RuleError::InvalidCount('9')));
+        assert_eq!("B3/Sx".parse::<Rule>(), Err(RuleError::InvalidCount('x')));
+        assert_eq!(
+            "/2/1".parse::<Rule>(),
+            Err(RuleError::InvalidStates("1".to_string()))
+        );
+        assert_eq!(
+            "B2/S/C300".parse::<Rule>(),
+            Err(RuleError::InvalidStates("300".to_string()))
+        );
+        assert!(matches!("B3".parse::<Rule>(), Err(RuleError::Malformed(_))));
+        assert!(matches!(
+            "B3/B3".parse::<Rule>(),
+            Err(RuleError::Malformed(_))
+        ));
+        assert!(matches!(
+            "B3/23".parse::<Rule>(),
+            Err(RuleError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_next() {
+        let brians_brain: Rule = "/2/3".parse().unwrap();
+        assert_eq!(brians_brain.next(0, 2), 1);
+        assert_eq!(brians_brain.next(0, 3), 0);
+        // alive always starts dying, and dying cells always move on
+        assert_eq!(brians_brain.next(1, 2), 2);
+        assert_eq!(brians_brain.next(2, 2), 0);
+
+        assert_eq!(Rule::CONWAY.next(1, 2), 1);
+        assert_eq!(Rule::CONWAY.next(1, 4), 0);
+        assert_eq!(Rule::CONWAY.next(0, 2), 0);
+    }
+}
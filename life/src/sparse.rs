@@ -0,0 +1,264 @@
+use crate::{topology::NEIGHBORS, Pattern, Rule};
+use std::collections::HashMap;
+
+/// Life on an unbounded plane that only stores the cells that aren't dead.
+///
+/// Every generation counts the live neighbors of the cells around live ones, so
+/// the time per generation grows with the population, not with the area the
+/// pattern spreads over. Generations rules work, the dying cells are stored
+/// too. Rules with `B0` don't, a plane full of dead cells has to stay dead.
+/// Rows and columns wrap around from `i64::MAX` to `i64::MIN`.
+///
+/// ```
+/// use life::{Pattern, SparseLife};
+///
+/// let glider = Pattern::from_cells(".O\n..O\nOOO\n").unwrap();
+/// let mut life = SparseLife::from_pattern(&glider).unwrap();
+///
+/// life.step(400);
+/// assert_eq!(life.population(), 5);
+/// assert_eq!(life.bounds(), Some(((100, 100), (102, 102))));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SparseLife {
+    rule: Rule,
+    /// (row, column) to the state of every cell that isn't dead
+    cells: HashMap<(i64, i64), u8>,
+    generation: u64,
+}
+
+impl SparseLife {
+    /// An empty plane under Conway's rule
+    pub fn new() -> Self {
+        Self::with_rule(Rule::CONWAY).unwrap()
+    }
+
+    /// None for rules with `B0`
+    pub fn with_rule(rule: Rule) -> Option<Self> {
+        if rule.is_born(0) {
+            return None;
+        }
+
+        Some(Self {
+            rule,
+            cells: HashMap::new(),
+            generation: 0,
+        })
+    }
+
+    /// The pattern with its top left at `(0, 0)`, under its rule or Conway's.
+    /// None if the rule has `B0`
+    pub fn from_pattern(pattern: &Pattern) -> Option<Self> {
+        let mut life = Self::with_rule(pattern.rule().copied().unwrap_or_default())?;
+        for (r, c) in pattern.cells() {
+            life.set(*r as i64, *c as i64, true);
+        }
+        Some(life)
+    }
+
+    /// The live cells moved so the top left one's row and column are 0, with the
+    /// rule. None if they're spread over more rows or columns than a `usize` counts
+    pub fn to_pattern(&self) -> Option<Pattern> {
+        Some(Pattern::from_plane(&self.cells())?.with_rule(self.rule))
+    }
+
+    pub const fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    pub const fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Live cells, dying ones don't count
+    pub fn population(&self) -> usize {
+        self.cells.values().filter(|state| **state == 1).count()
+    }
+
+    /// The top left and bottom right corners around every live cell, None if
+    /// there are none
+    pub fn bounds(&self) -> Option<((i64, i64), (i64, i64))> {
+        let mut live = self.live();
+        let first = live.next()?;
+
+        Some(
+            live.fold((first, first), |((top, left), (bottom, right)), (r, c)| {
+                ((top.min(r), left.min(c)), (bottom.max(r), right.max(c)))
+            }),
+        )
+    }
+
+    pub fn get(&self, r: i64, c: i64) -> bool {
+        self.state(r, c) == 1
+    }
+
+    /// 0 for dead, 1 for alive and from 2 up for dying, see [`Rule`]
+    pub fn state(&self, r: i64, c: i64) -> u8 {
+        self.cells.get(&(r, c)).copied().unwrap_or(0)
+    }
+
+    pub fn set(&mut self, r: i64, c: i64, alive: bool) {
+        match alive {
+            true => self.cells.insert((r, c), 1),
+            false => self.cells.remove(&(r, c)),
+        };
+    }
+
+    /// Every live cell as `(row, column)`, sorted
+    pub fn cells(&self) -> Vec<(i64, i64)> {
+        let mut cells: Vec<(i64, i64)> = self.live().collect();
+        cells.sort_unstable();
+        cells
+    }
+
+    pub fn update(&mut self) {
+        let mut live_neighbors: HashMap<(i64, i64), u8> = HashMap::new();
+        for (r, c) in self.live() {
+            for (dr, dc) in NEIGHBORS {
+                *live_neighbors
+                    .entry((r.wrapping_add(dr as i64), c.wrapping_add(dc as i64)))
+                    .or_default() += 1;
+            }
+        }
+
+        // a dead cell without live neighbors stays dead, so only these can be
+        // anything else next generation
+        let mut next: HashMap<(i64, i64), u8> = HashMap::new();
+        for cell in live_neighbors.keys().chain(self.cells.keys()) {
+            let state = self.rule.next(
+                self.state(cell.0, cell.1),
+                live_neighbors.get(cell).copied().unwrap_or(0),
+            );
+            if state != 0 {
+                next.insert(*cell, state);
+            }
+        }
+
+        self.cells = next;
+        self.generation += 1;
+    }
+
+    pub fn step(&mut self, generations: u64) {
+        for _ in 0..generations {
+            self.update();
+        }
+    }
+
+    fn live(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.cells
+            .iter()
+            .filter(|(_, state)| **state == 1)
+            .map(|(cell, _)| *cell)
+    }
+}
+
+impl Default for SparseLife {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures::{dense_cells, GOSPER_GLIDER_GUN},
+        Game, HashLife, Topology,
+    };
+
+    #[test]
+    fn test_against_hashlife() {
+        let gun = Pattern::from_rle(GOSPER_GLIDER_GUN).unwrap();
+        let mut sparse = SparseLife::from_pattern(&gun).unwrap();
+        let mut hashlife = HashLife::from_pattern(&gun).unwrap();
+
+        sparse.step(300);
+        hashlife.step(300);
+        assert_eq!(sparse.cells(), hashlife.cells());
+        assert_eq!(sparse.population(), 36 + 5 * 10);
+        assert_eq!(sparse.to_pattern(), hashlife.to_pattern());
+    }
+
+    #[test]
+    fn test_generations() {
+        // Brian's Brain, a pattern that stays away from the edges of the dense grid
+        let rule: Rule = "/2/3".parse().unwrap();
+        let pattern = Pattern::from_cells("OO\n..\nOO\n").unwrap().with_rule(rule);
+
+        let mut sparse = SparseLife::from_pattern(&pattern).unwrap();
+        let mut dense = Game::<32, 32>::with_rule(pattern.grid_at(14, 15).unwrap(), rule);
+        dense.set_topology(Topology::Bounded);
+
+        for _ in 0..6 {
+            sparse.update();
+            dense.update();
+
+            assert_eq!(sparse.cells(), dense_cells(&dense, 14, 15));
+            assert_eq!(sparse.state(-1, 0), dense.state(13, 15));
+        }
+    }
+
+    #[test]
+    fn test_bounded() {
+        // a bounded grid is the plane with everything past its edges killed every generation
+        let r_pentomino = Pattern::from_cells(".OO\nOO.\n.O.\n").unwrap();
+        let mut sparse = SparseLife::from_pattern(&r_pentomino).unwrap();
+        let mut dense = Game::<12, 16>::from_pattern(&r_pentomino).unwrap();
+        dense.set_topology(Topology::Bounded);
+        let (top, left) = ((12 - 3) / 2, (16 - 3) / 2);
+
+        for _ in 0..40 {
+            sparse.update();
+            dense.update();
+            for (r, c) in sparse.cells() {
+                if !(0..12).contains(&(r + top as i64)) || !(0..16).contains(&(c + left as i64)) {
+                    sparse.set(r, c, false);
+                }
+            }
+
+            assert_eq!(sparse.cells(), dense_cells(&dense, top, left));
+        }
+    }
+
+    #[test]
+    fn test_rules() {
+        assert!(SparseLife::with_rule("B0/S".parse().unwrap()).is_none());
+
+        let mut life = SparseLife::new();
+        assert_eq!(life.bounds(), None);
+        assert_eq!(life.to_pattern().unwrap().cells(), &[]);
+        life.set(-3, 7, true);
+        life.set(-3, 8, true);
+        assert_eq!(life.bounds(), Some(((-3, 7), (-3, 8))));
+        life.update();
+        assert_eq!(life.population(), 0);
+        assert_eq!(life.generation(), 1);
+    }
+
+    #[test]
+    fn test_edges() {
+        // a blinker across the last and first rows, which are neighbors
+        let mut life = SparseLife::new();
+        for r in [i64::MAX, i64::MIN, i64::MIN + 1] {
+            life.set(r, 0, true);
+        }
+        life.update();
+        assert_eq!(
+            life.cells(),
+            vec![(i64::MIN, -1), (i64::MIN, 0), (i64::MIN, 1)]
+        );
+        assert_eq!(
+            life.to_pattern().unwrap(),
+            Pattern::new([(0, 0), (0, 1), (0, 2)]).with_rule(Rule::CONWAY)
+        );
+
+        life.set(i64::MAX, 0, true);
+        assert_eq!(life.to_pattern(), None);
+        life.set(i64::MIN, -1, false);
+        life.set(i64::MIN, 1, false);
+        life.set(i64::MAX, 0, false);
+        life.set(i64::MAX - 1, 0, true);
+        let far = life.to_pattern().unwrap();
+        assert_eq!(far.cells(), &[(0, 0), (usize::MAX - 1, 0)]);
+    }
+}
@@ -0,0 +1,179 @@
+/// What lies past the edges of a grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Topology {
+    /// off one side is back in from the opposite side
+    #[default]
+    Torus,
+    /// everything past the edges is dead
+    Bounded,
+    /// the left and right edges join like a torus, but off the top or bottom
+    /// is back in from the other one with the columns mirrored
+    KleinBottle,
+}
+
+/// the 8 (row, column) offsets of a cell's neighbors
+pub(crate) const NEIGHBORS: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+impl Topology {
+    /// The cell `(dr, dc)` away from `(r, c)` on a `rows` by `columns` grid, None
+    /// if that's past the edge of a bounded grid. Steps of more than a grid's
+    /// size aren't supported.
+    ///
+    /// ```
+    /// use life::Topology;
+    ///
+    /// assert_eq!(Topology::Torus.neighbor(4, 5, (0, 1), (-1, 0)), Some((3, 1)));
+    /// assert_eq!(Topology::Bounded.neighbor(4, 5, (0, 1), (-1, 0)), None);
+    /// assert_eq!(Topology::KleinBottle.neighbor(4, 5, (0, 1), (-1, 0)), Some((3, 3)));
+    /// ```
+    #[inline]
+    pub const fn neighbor(
+        &self,
+        rows: usize,
+        columns: usize,
+        (r, c): (usize, usize),
+        (dr, dc): (isize, isize),
+    ) -> Option<(usize, usize)> {
+        let (r, c) = (r as isize + dr, c as isize + dc);
+        let (rows, columns) = (rows as isize, columns as isize);
+
+        match self {
+            Topology::Bounded if !inside(r, rows) || !inside(c, columns) => None,
+            Topology::Bounded => Some((r as usize, c as usize)),
+            Topology::Torus => Some((r.rem_euclid(rows) as usize, c.rem_euclid(columns) as usize)),
+            Topology::KleinBottle => {
+                let c = c.rem_euclid(columns);
+                let c = match inside(r, rows) {
+                    true => c,
+                    false => columns - 1 - c,
+                };
+                Some((r.rem_euclid(rows) as usize, c as usize))
+            }
+        }
+    }
+}
+
+// topologies as const generic parameters, so the dense grids match on their
+// topology once per generation rather than once per neighbor
+pub(crate) const TORUS: u8 = Topology::Torus as u8;
+pub(crate) const BOUNDED: u8 = Topology::Bounded as u8;
+pub(crate) const KLEIN_BOTTLE: u8 = Topology::KleinBottle as u8;
+
+/// Live neighbors of `(r, c)` on a `rows` by `columns` grid with the
+/// `TOPOLOGY`, one of the consts above. Same as counting the live cells
+/// [`Topology::neighbor`] finds, but wraps with branches instead of remainders
+#[inline]
+pub(crate) fn live_neighbors<const TOPOLOGY: u8>(
+    rows: usize,
+    columns: usize,
+    (r, c): (usize, usize),
+    alive: impl Fn(usize, usize) -> bool,
+) -> u8 {
+    let alive = |r, c| alive(r, c) as u8;
+
+    if TOPOLOGY == BOUNDED {
+        let mut live = 0;
+        for r in r.saturating_sub(1)..(r + 2).min(rows) {
+            for c in c.saturating_sub(1)..(c + 2).min(columns) {
+                live += alive(r, c);
+            }
+        }
+        return live - alive(r, c);
+    }
+
+    let up = if r == 0 { rows - 1 } else { r - 1 };
+    let down = if r == rows - 1 { 0 } else { r + 1 };
+    let left = if c == 0 { columns - 1 } else { c - 1 };
+    let right = if c == columns - 1 { 0 } else { c + 1 };
+
+    // the three cells of a row above or below, a Klein bottle mirrors the columns
+    // of the row past the top or bottom edge
+    let row = |r, edge: bool| match TOPOLOGY == KLEIN_BOTTLE && edge {
+        true => {
+            alive(r, columns - 1 - left) + alive(r, columns - 1 - c) + alive(r, columns - 1 - right)
+        }
+        false => alive(r, left) + alive(r, c) + alive(r, right),
+    };
+
+    row(up, r == 0) + alive(r, left) + alive(r, right) + row(down, r == rows - 1)
+}
+
+const fn inside(i: isize, n: isize) -> bool {
+    0 <= i && i < n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neighbor() {
+        let neighbor = |topology: Topology, cell, step| topology.neighbor(3, 4, cell, step);
+
+        assert_eq!(neighbor(Topology::Torus, (2, 3), (1, 1)), Some((0, 0)));
+        assert_eq!(neighbor(Topology::Torus, (0, 0), (-1, -1)), Some((2, 3)));
+        assert_eq!(neighbor(Topology::Torus, (1, 1), (1, -1)), Some((2, 0)));
+
+        assert_eq!(neighbor(Topology::Bounded, (2, 3), (1, 0)), None);
+        assert_eq!(neighbor(Topology::Bounded, (2, 3), (0, 1)), None);
+        assert_eq!(neighbor(Topology::Bounded, (1, 1), (1, -1)), Some((2, 0)));
+
+        // sideways is a torus, up and down mirror the columns
+        assert_eq!(
+            neighbor(Topology::KleinBottle, (1, 3), (0, 1)),
+            Some((1, 0))
+        );
+        assert_eq!(
+            neighbor(Topology::KleinBottle, (2, 0), (1, 0)),
+            Some((0, 3))
+        );
+        assert_eq!(
+            neighbor(Topology::KleinBottle, (2, 3), (1, 1)),
+            Some((0, 3))
+        );
+        assert_eq!(
+            neighbor(Topology::KleinBottle, (0, 1), (-1, 1)),
+            Some((2, 1))
+        );
+    }
+
+    #[test]
+    fn test_live_neighbors() {
+        // every cell of a few small grids with a scattering of live cells, against `neighbor`
+        for (rows, columns) in [(1, 1), (1, 4), (3, 1), (3, 4), (5, 6)] {
+            let alive = |r: usize, c: usize| (r * 7 + c * 3) % 5 < 2;
+
+            let counted = |topology, cell| match topology {
+                Topology::Torus => live_neighbors::<TORUS>(rows, columns, cell, alive),
+                Topology::Bounded => live_neighbors::<BOUNDED>(rows, columns, cell, alive),
+                Topology::KleinBottle => live_neighbors::<KLEIN_BOTTLE>(rows, columns, cell, alive),
+            };
+
+            for topology in [Topology::Torus, Topology::Bounded, Topology::KleinBottle] {
+                for cell in (0..rows).flat_map(|r| (0..columns).map(move |c| (r, c))) {
+                    let expected = NEIGHBORS
+                        .iter()
+                        .filter_map(|step| topology.neighbor(rows, columns, cell, *step))
+                        .filter(|(r, c)| alive(*r, *c))
+                        .count() as u8;
+                    assert_eq!(
+                        counted(topology, cell),
+                        expected,
+                        "{:?} {:?}",
+                        topology,
+                        cell
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1115 @@
+use crate::{RingBuffer, SaveState, SaveStateError, StateReader, StateWriter};
+
+/// NTSC CPU clock in Hz
+pub const CPU_CLOCK: u32 = 1_789_773;
+
+#[rustfmt::skip]
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14,
+    12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+#[rustfmt::skip]
+const TRIANGLE_TABLE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0,
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+/// in CPU cycles
+const NOISE_PERIODS: [u16; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+
+/// in CPU cycles
+const DMC_RATES: [u16; 16] = [
+    428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+];
+
+#[derive(Default)]
+struct LengthCounter {
+    enabled: bool,
+    halt: bool,
+    counter: u8,
+}
+
+impl LengthCounter {
+    fn load(&mut self, index: u8) {
+        if self.enabled {
+            self.counter = LENGTH_TABLE[(index & 0x1F) as usize];
+        }
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.counter = 0;
+        }
+    }
+
+    /// half frame
+    fn clock(&mut self) {
+        if !self.halt && self.counter > 0 {
+            self.counter -= 1;
+        }
+    }
+
+    fn active(&self) -> bool {
+        self.counter > 0
+    }
+}
+
+#[derive(Default)]
+struct Envelope {
+    start: bool,
+    looping: bool,
+    constant: bool,
+    volume: u8,
+    divider: u8,
+    decay: u8,
+}
+
+impl Envelope {
+    fn write(&mut self, data: u8) {
+        self.looping = data & 0x20 != 0;
+        self.constant = data & 0x10 != 0;
+        self.volume = data & 0x0F;
+    }
+
+    /// quarter frame
+    fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.volume;
+        } else if self.divider == 0 {
+            self.divider = self.volume;
+            if self.decay > 0 {
+                self.decay -= 1;
+            } else if self.looping {
+                self.decay = 15;
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        match self.constant {
+            true => self.volume,
+            false => self.decay,
+        }
+    }
+}
+
+struct Pulse {
+    /// pulse 1 negates with ones' complement, pulse 2 with twos' complement
+    ones_complement: bool,
+
+    duty: u8,
+    sequence: u8,
+    timer: u16,
+    period: u16,
+
+    envelope: Envelope,
+    length: LengthCounter,
+
+    sweep_enabled: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_divider: u8,
+    sweep_reload: bool,
+}
+
+impl Pulse {
+    fn new(ones_complement: bool) -> Self {
+        Self {
+            ones_complement,
+            duty: 0,
+            sequence: 0,
+            timer: 0,
+            period: 0,
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+            sweep_enabled: false,
+            sweep_period: 0,
+            sweep_negate: false,
+            sweep_shift: 0,
+            sweep_divider: 0,
+            sweep_reload: false,
+        }
+    }
+
+    fn write(&mut self, reg: u16, data: u8) {
+        match reg {
+            0 => {
+                self.duty = data >> 6;
+                self.length.halt = data & 0x20 != 0;
+                self.envelope.write(data);
+            }
+            1 => {
+                self.sweep_enabled = data & 0x80 != 0;
+                self.sweep_period = (data >> 4) & 0x07;
+                self.sweep_negate = data & 0x08 != 0;
+                self.sweep_shift = data & 0x07;
+                self.sweep_reload = true;
+            }
+            2 => self.period = (self.period & 0x0700) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((data as u16 & 0x07) << 8);
+                self.length.load(data >> 3);
+                self.sequence = 0;
+                self.envelope.start = true;
+            }
+        }
+    }
+
+    /// every APU cycle (two CPU cycles)
+    fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            self.sequence = (self.sequence + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn sweep_target(&self) -> u16 {
+        let change = self.period >> self.sweep_shift;
+        match (self.sweep_negate, self.ones_complement) {
+            (false, _) => self.period + change,
+            (true, true) => self.period.saturating_sub(change + 1),
+            (true, false) => self.period.saturating_sub(change),
+        }
+    }
+
+    /// the sweep unit silences the channel even when it's disabled
+    fn muted(&self) -> bool {
+        self.period < 8 || self.sweep_target() > 0x07FF
+    }
+
+    /// half frame
+    fn clock_sweep(&mut self) {
+        if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift > 0 && !self.muted() {
+            self.period = self.sweep_target();
+        }
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.muted()
+            || !self.length.active()
+            || DUTY_TABLE[self.duty as usize][self.sequence as usize] == 0
+        {
+            return 0;
+        }
+        self.envelope.output()
+    }
+}
+
+#[derive(Default)]
+struct Triangle {
+    sequence: u8,
+    timer: u16,
+    period: u16,
+
+    length: LengthCounter,
+
+    linear_control: bool,
+    linear_reload_value: u8,
+    linear_counter: u8,
+    linear_reload: bool,
+}
+
+impl Triangle {
+    fn write(&mut self, reg: u16, data: u8) {
+        match reg {
+            0 => {
+                self.linear_control = data & 0x80 != 0;
+                self.length.halt = self.linear_control;
+                self.linear_reload_value = data & 0x7F;
+            }
+            1 => {}
+            2 => self.period = (self.period & 0x0700) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((data as u16 & 0x07) << 8);
+                self.length.load(data >> 3);
+                self.linear_reload = true;
+            }
+        }
+    }
+
+    /// every CPU cycle
+    fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            if self.length.active() && self.linear_counter > 0 {
+                self.sequence = (self.sequence + 1) % 32;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    /// quarter frame
+    fn clock_linear(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+        if !self.linear_control {
+            self.linear_reload = false;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        TRIANGLE_TABLE[self.sequence as usize]
+    }
+}
+
+struct Noise {
+    mode: bool,
+    shift: u16,
+    timer: u16,
+    period: u16,
+
+    envelope: Envelope,
+    length: LengthCounter,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self {
+            mode: false,
+            shift: 1,
+            timer: 0,
+            period: NOISE_PERIODS[0],
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+        }
+    }
+}
+
+impl Noise {
+    fn write(&mut self, reg: u16, data: u8) {
+        match reg {
+            0 => {
+                self.length.halt = data & 0x20 != 0;
+                self.envelope.write(data);
+            }
+            1 => {}
+            2 => {
+                self.mode = data & 0x80 != 0;
+                self.period = NOISE_PERIODS[(data & 0x0F) as usize];
+            }
+            _ => {
+                self.length.load(data >> 3);
+                self.envelope.start = true;
+            }
+        }
+    }
+
+    /// every CPU cycle
+    fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period - 1;
+            let tap = match self.mode {
+                true => 6,
+                false => 1,
+            };
+            let feedback = (self.shift ^ (self.shift >> tap)) & 0x01;
+            self.shift = (self.shift >> 1) | (feedback << 14);
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.shift & 0x01 != 0 || !self.length.active() {
+            return 0;
+        }
+        self.envelope.output()
+    }
+}
+
+struct Dmc {
+    irq_enabled: bool,
+    looping: bool,
+    rate: u16,
+    timer: u16,
+    level: u8,
+
+    sample_address: u16,
+    sample_length: u16,
+    current_address: u16,
+    bytes_remaining: u16,
+    sample_buffer: Option<u8>,
+
+    shift: u8,
+    bits_remaining: u8,
+    silence: bool,
+
+    irq: bool,
+}
+
+impl Default for Dmc {
+    fn default() -> Self {
+        Self {
+            irq_enabled: false,
+            looping: false,
+            rate: DMC_RATES[0],
+            timer: 0,
+            level: 0,
+            sample_address: 0xC000,
+            sample_length: 1,
+            current_address: 0xC000,
+            bytes_remaining: 0,
+            sample_buffer: None,
+            shift: 0,
+            bits_remaining: 8,
+            silence: true,
+            irq: false,
+        }
+    }
+}
+
+impl Dmc {
+    fn write(&mut self, reg: u16, data: u8) {
+        match reg {
+            0 => {
+                self.irq_enabled = data & 0x80 != 0;
+                self.looping = data & 0x40 != 0;
+                self.rate = DMC_RATES[(data & 0x0F) as usize];
+                if !self.irq_enabled {
+                    self.irq = false;
+                }
+            }
+            1 => self.level = data & 0x7F,
+            2 => self.sample_address = 0xC000 | ((data as u16) << 6),
+            _ => self.sample_length = ((data as u16) << 4) | 1,
+        }
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    /// every CPU cycle
+    fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.rate - 1;
+
+        if !self.silence {
+            match self.shift & 0x01 {
+                1 if self.level <= 125 => self.level += 2,
+                0 if self.level >= 2 => self.level -= 2,
+                _ => {}
+            }
+        }
+        self.shift >>= 1;
+
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(sample) => {
+                    self.silence = false;
+                    self.shift = sample;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    /// the address the memory reader wants to fetch next, if any
+    fn request(&self) -> Option<u16> {
+        match (self.sample_buffer, self.bytes_remaining) {
+            (None, 1..) => Some(self.current_address),
+            _ => None,
+        }
+    }
+
+    fn fill(&mut self, data: u8) {
+        self.sample_buffer = Some(data);
+        self.current_address = match self.current_address {
+            0xFFFF => 0x8000,
+            addr => addr + 1,
+        };
+        self.bytes_remaining -= 1;
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq = true;
+            }
+        }
+    }
+}
+
+/// One pole filter, used to model the NES's output stage
+struct Filter {
+    high_pass: bool,
+    alpha: f32,
+    prev_in: f32,
+    prev_out: f32,
+}
+
+impl Filter {
+    fn new(high_pass: bool, cutoff: f32, sample_rate: u32) -> Self {
+        let rc = 1.0 / (2.0 * std::f32::consts::PI * cutoff);
+        let dt = 1.0 / sample_rate as f32;
+        let alpha = match high_pass {
+            true => rc / (rc + dt),
+            false => dt / (rc + dt),
+        };
+        Self {
+            high_pass,
+            alpha,
+            prev_in: 0.0,
+            prev_out: 0.0,
+        }
+    }
+
+    fn process(&mut self, sample: f32) -> f32 {
+        let out = match self.high_pass {
+            true => self.alpha * (self.prev_out + sample - self.prev_in),
+            false => self.prev_out + self.alpha * (sample - self.prev_out),
+        };
+        self.prev_in = sample;
+        self.prev_out = out;
+        out
+    }
+}
+
+/// The 2A03's audio processing unit.
+///
+/// Clocked once per CPU cycle. Mixed output is averaged down to `sample_rate`
+/// and pushed into `samples` for the frontend to drain.
+pub struct Apu2A03 {
+    pulse1: Pulse,
+    pulse2: Pulse,
+    triangle: Triangle,
+    noise: Noise,
+    dmc: Dmc,
+
+    /// CPU cycles into the current frame counter sequence
+    frame_cycle: u32,
+    five_step: bool,
+    irq_inhibit: bool,
+    frame_irq: bool,
+    odd_cycle: bool,
+
+    sample_rate: u32,
+    sample_clock: u32,
+    sample_sum: f32,
+    sample_count: u32,
+    filters: [Filter; 3],
+    pub samples: RingBuffer<f32>,
+}
+
+impl Default for Apu2A03 {
+    fn default() -> Self {
+        Self::new(44100)
+    }
+}
+
+impl Apu2A03 {
+    /// The rate is clamped to between 1Hz and the CPU clock, at most one sample per cycle
+    pub fn new(sample_rate: u32) -> Self {
+        let sample_rate = sample_rate.clamp(1, CPU_CLOCK);
+        Self {
+            pulse1: Pulse::new(true),
+            pulse2: Pulse::new(false),
+            triangle: Triangle::default(),
+            noise: Noise::default(),
+            dmc: Dmc::default(),
+            frame_cycle: 0,
+            five_step: false,
+            irq_inhibit: false,
+            frame_irq: false,
+            odd_cycle: false,
+            sample_rate,
+            sample_clock: 0,
+            sample_sum: 0.0,
+            sample_count: 0,
+            filters: Self::filters(sample_rate),
+            // one second of audio
+            samples: RingBuffer::new(sample_rate as usize),
+        }
+    }
+
+    /// two high passes at 90Hz and 440Hz and a low pass at 14kHz
+    fn filters(sample_rate: u32) -> [Filter; 3] {
+        [
+            Filter::new(true, 90.0, sample_rate),
+            Filter::new(true, 440.0, sample_rate),
+            Filter::new(false, 14000.0, sample_rate),
+        ]
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Changes the output rate, clamped like in [`Apu2A03::new`]. Drops any samples not yet drained
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        let sample_rate = sample_rate.clamp(1, CPU_CLOCK);
+        self.sample_rate = sample_rate;
+        self.sample_clock = 0;
+        self.sample_sum = 0.0;
+        self.sample_count = 0;
+        self.filters = Self::filters(sample_rate);
+        self.samples = RingBuffer::new(sample_rate as usize);
+    }
+
+    pub fn cpu_read(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x4015 => {
+                let mut status = 0;
+                for (bit, active) in [
+                    self.pulse1.length.active(),
+                    self.pulse2.length.active(),
+                    self.triangle.length.active(),
+                    self.noise.length.active(),
+                    self.dmc.bytes_remaining > 0,
+                ]
+                .into_iter()
+                .enumerate()
+                {
+                    status |= (active as u8) << bit;
+                }
+                status |= (self.frame_irq as u8) << 6;
+                status |= (self.dmc.irq as u8) << 7;
+                self.frame_irq = false;
+                status
+            }
+            _ => 0,
+        }
+    }
+
+    pub fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x4000..=0x4003 => self.pulse1.write(addr & 0x03, data),
+            0x4004..=0x4007 => self.pulse2.write(addr & 0x03, data),
+            0x4008..=0x400B => self.triangle.write(addr & 0x03, data),
+            0x400C..=0x400F => self.noise.write(addr & 0x03, data),
+            0x4010..=0x4013 => self.dmc.write(addr & 0x03, data),
+            0x4015 => {
+                self.pulse1.length.set_enabled(data & 0x01 != 0);
+                self.pulse2.length.set_enabled(data & 0x02 != 0);
+                self.triangle.length.set_enabled(data & 0x04 != 0);
+                self.noise.length.set_enabled(data & 0x08 != 0);
+                match data & 0x10 {
+                    0 => self.dmc.bytes_remaining = 0,
+                    _ if self.dmc.bytes_remaining == 0 => self.dmc.restart(),
+                    _ => {}
+                }
+                self.dmc.irq = false;
+            }
+            0x4017 => {
+                self.five_step = data & 0x80 != 0;
+                self.irq_inhibit = data & 0x40 != 0;
+                if self.irq_inhibit {
+                    self.frame_irq = false;
+                }
+                self.frame_cycle = 0;
+                if self.five_step {
+                    self.quarter_frame();
+                    self.half_frame();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn irq_pending(&self) -> bool {
+        self.frame_irq || self.dmc.irq
+    }
+
+    /// address the DMC wants a sample byte from. The bus answers with [`Apu2A03::dmc_fill`]
+    pub fn dmc_request(&self) -> Option<u16> {
+        self.dmc.request()
+    }
+
+    pub fn dmc_fill(&mut self, data: u8) {
+        self.dmc.fill(data);
+    }
+
+    fn quarter_frame(&mut self) {
+        self.pulse1.envelope.clock();
+        self.pulse2.envelope.clock();
+        self.noise.envelope.clock();
+        self.triangle.clock_linear();
+    }
+
+    fn half_frame(&mut self) {
+        self.pulse1.length.clock();
+        self.pulse2.length.clock();
+        self.triangle.length.clock();
+        self.noise.length.clock();
+        self.pulse1.clock_sweep();
+        self.pulse2.clock_sweep();
+    }
+
+    fn clock_frame_counter(&mut self) {
+        self.frame_cycle += 1;
+        match (self.frame_cycle, self.five_step) {
+            (7457, _) | (22371, _) => self.quarter_frame(),
+            (14913, _) | (29829, false) | (37281, true) => {
+                self.quarter_frame();
+                self.half_frame();
+            }
+            _ => {}
+        }
+
+        if !self.five_step && (29828..=29830).contains(&self.frame_cycle) && !self.irq_inhibit {
+            self.frame_irq = true;
+        }
+
+        let period = match self.five_step {
+            true => 37282,
+            false => 29830,
+        };
+        if self.frame_cycle >= period {
+            self.frame_cycle = 0;
+        }
+    }
+
+    /// Advances by one CPU cycle
+    pub fn clock(&mut self) {
+        self.clock_frame_counter();
+
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+        if self.odd_cycle {
+            self.pulse1.clock_timer();
+            self.pulse2.clock_timer();
+        }
+        self.odd_cycle = !self.odd_cycle;
+
+        self.sample_sum += self.mix();
+        self.sample_count += 1;
+        self.sample_clock += self.sample_rate;
+        if self.sample_clock >= CPU_CLOCK {
+            self.sample_clock -= CPU_CLOCK;
+            let mut sample = self.sample_sum / self.sample_count as f32;
+            for filter in &mut self.filters {
+                sample = filter.process(sample);
+            }
+            self.samples.push(sample);
+            self.sample_sum = 0.0;
+            self.sample_count = 0;
+        }
+    }
+
+    /// The nonlinear DAC approximation from the nesdev wiki. 0.0 to ~1.0
+    fn mix(&self) -> f32 {
+        let pulse = (self.pulse1.output() + self.pulse2.output()) as f32;
+        let pulse_out = match pulse {
+            0.0 => 0.0,
+            _ => 95.88 / (8128.0 / pulse + 100.0),
+        };
+
+        let tnd = self.triangle.output() as f32 / 8227.0
+            + self.noise.output() as f32 / 12241.0
+            + self.dmc.level as f32 / 22638.0;
+        let tnd_out = match tnd {
+            0.0 => 0.0,
+            _ => 159.79 / (1.0 / tnd + 100.0),
+        };
+
+        pulse_out + tnd_out
+    }
+}
+
+impl SaveState for LengthCounter {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.bool(self.enabled);
+        state.bool(self.halt);
+        state.u8(self.counter);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.enabled = state.bool()?;
+        self.halt = state.bool()?;
+        self.counter = state.u8()?;
+        Ok(())
+    }
+}
+
+impl SaveState for Envelope {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.bool(self.start);
+        state.bool(self.looping);
+        state.bool(self.constant);
+        state.u8(self.volume);
+        state.u8(self.divider);
+        state.u8(self.decay);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.start = state.bool()?;
+        self.looping = state.bool()?;
+        self.constant = state.bool()?;
+        self.volume = state.u8()?;
+        self.divider = state.u8()?;
+        self.decay = state.u8()?;
+        Ok(())
+    }
+}
+
+impl SaveState for Pulse {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.u8(self.duty);
+        state.u8(self.sequence);
+        state.u16(self.timer);
+        state.u16(self.period);
+        self.envelope.save_state(state);
+        self.length.save_state(state);
+        state.bool(self.sweep_enabled);
+        state.u8(self.sweep_period);
+        state.bool(self.sweep_negate);
+        state.u8(self.sweep_shift);
+        state.u8(self.sweep_divider);
+        state.bool(self.sweep_reload);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.duty = state.u8()?;
+        self.sequence = state.u8()?;
+        self.timer = state.u16()?;
+        self.period = state.u16()?;
+        self.envelope.load_state(state)?;
+        self.length.load_state(state)?;
+        self.sweep_enabled = state.bool()?;
+        self.sweep_period = state.u8()?;
+        self.sweep_negate = state.bool()?;
+        self.sweep_shift = state.u8()?;
+        self.sweep_divider = state.u8()?;
+        self.sweep_reload = state.bool()?;
+        if self.duty > 3 || self.sequence > 7 {
+            return Err(SaveStateError::InvalidData("pulse sequencer"));
+        }
+        Ok(())
+    }
+}
+
+impl SaveState for Triangle {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.u8(self.sequence);
+        state.u16(self.timer);
+        state.u16(self.period);
+        self.length.save_state(state);
+        state.bool(self.linear_control);
+        state.u8(self.linear_reload_value);
+        state.u8(self.linear_counter);
+        state.bool(self.linear_reload);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.sequence = state.u8()?;
+        self.timer = state.u16()?;
+        self.period = state.u16()?;
+        self.length.load_state(state)?;
+        self.linear_control = state.bool()?;
+        self.linear_reload_value = state.u8()?;
+        self.linear_counter = state.u8()?;
+        self.linear_reload = state.bool()?;
+        if self.sequence > 31 {
+            return Err(SaveStateError::InvalidData("triangle sequencer"));
+        }
+        Ok(())
+    }
+}
+
+impl SaveState for Noise {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.bool(self.mode);
+        state.u16(self.shift);
+        state.u16(self.timer);
+        state.u16(self.period);
+        self.envelope.save_state(state);
+        self.length.save_state(state);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.mode = state.bool()?;
+        self.shift = state.u16()?;
+        self.timer = state.u16()?;
+        self.period = state.u16()?;
+        self.envelope.load_state(state)?;
+        self.length.load_state(state)?;
+        if !NOISE_PERIODS.contains(&self.period) {
+            return Err(SaveStateError::InvalidData("noise period"));
+        }
+        Ok(())
+    }
+}
+
+impl SaveState for Dmc {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.bool(self.irq_enabled);
+        state.bool(self.looping);
+        state.u16(self.rate);
+        state.u16(self.timer);
+        state.u8(self.level);
+        state.u16(self.sample_address);
+        state.u16(self.sample_length);
+        state.u16(self.current_address);
+        state.u16(self.bytes_remaining);
+        state.bool(self.sample_buffer.is_some());
+        state.u8(self.sample_buffer.unwrap_or(0));
+        state.u8(self.shift);
+        state.u8(self.bits_remaining);
+        state.bool(self.silence);
+        state.bool(self.irq);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.irq_enabled = state.bool()?;
+        self.looping = state.bool()?;
+        self.rate = state.u16()?;
+        self.timer = state.u16()?;
+        self.level = state.u8()?;
+        self.sample_address = state.u16()?;
+        self.sample_length = state.u16()?;
+        self.current_address = state.u16()?;
+        self.bytes_remaining = state.u16()?;
+        let buffered = state.bool()?;
+        let sample = state.u8()?;
+        self.sample_buffer = buffered.then_some(sample);
+        self.shift = state.u8()?;
+        self.bits_remaining = state.u8()?;
+        self.silence = state.bool()?;
+        self.irq = state.bool()?;
+        if !DMC_RATES.contains(&self.rate) || !(1..=8).contains(&self.bits_remaining) {
+            return Err(SaveStateError::InvalidData("dmc timer"));
+        }
+        Ok(())
+    }
+}
+
+impl SaveState for Apu2A03 {
+    fn save_state(&self, state: &mut StateWriter) {
+        self.pulse1.save_state(state);
+        self.pulse2.save_state(state);
+        self.triangle.save_state(state);
+        self.noise.save_state(state);
+        self.dmc.save_state(state);
+
+        state.u32(self.frame_cycle);
+        state.bool(self.five_step);
+        state.bool(self.irq_inhibit);
+        state.bool(self.frame_irq);
+        state.bool(self.odd_cycle);
+
+        state.u32(self.sample_clock);
+        state.f32(self.sample_sum);
+        state.u32(self.sample_count);
+        for filter in &self.filters {
+            state.f32(filter.prev_in);
+            state.f32(filter.prev_out);
+        }
+    }
+
+    /// Pending output in `samples` is dropped, it belongs to the timeline being left
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.pulse1.load_state(state)?;
+        self.pulse2.load_state(state)?;
+        self.triangle.load_state(state)?;
+        self.noise.load_state(state)?;
+        self.dmc.load_state(state)?;
+
+        self.frame_cycle = state.u32()?;
+        self.five_step = state.bool()?;
+        self.irq_inhibit = state.bool()?;
+        self.frame_irq = state.bool()?;
+        self.odd_cycle = state.bool()?;
+
+        self.sample_clock = state.u32()? % CPU_CLOCK;
+        self.sample_sum = state.f32()?;
+        self.sample_count = state.u32()?;
+        for filter in &mut self.filters {
+            filter.prev_in = state.f32()?;
+            filter.prev_out = state.f32()?;
+        }
+
+        self.samples.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(apu: &mut Apu2A03, cycles: u32) {
+        for _ in 0..cycles {
+            apu.clock();
+        }
+    }
+
+    /// counts rising zero crossings
+    fn crossings(samples: &[f32]) -> usize {
+        samples
+            .windows(2)
+            .filter(|pair| pair[0] < 0.0 && pair[1] >= 0.0)
+            .count()
+    }
+
+    #[test]
+    fn test_pulse_tone() {
+        let mut apu = Apu2A03::new(44100);
+        apu.cpu_write(0x4015, 0x01);
+        // 50% duty, constant volume 15
+        apu.cpu_write(0x4000, 0b1011_1111);
+        // 440Hz: 1789773 / (16 * 440) - 1
+        apu.cpu_write(0x4002, 0xFD);
+        apu.cpu_write(0x4003, 0x00);
+
+        run(&mut apu, CPU_CLOCK / 2);
+        let samples: Vec<f32> = apu.samples.drain().collect();
+        assert!((22049..=22051).contains(&samples.len()));
+
+        // skip the filters settling. 0.4s at 440Hz
+        let crossings = crossings(&samples[4410..]);
+        assert!((174..=178).contains(&crossings), "{}", crossings);
+    }
+
+    #[test]
+    fn test_length_counter_and_status() {
+        let mut apu = Apu2A03::default();
+        apu.cpu_write(0x4015, 0x0F);
+        apu.cpu_write(0x4000, 0b0011_1111);
+        apu.cpu_write(0x4002, 0xFD);
+        // length index 1: 254 half frames
+        apu.cpu_write(0x4003, 0b0000_1000);
+        // length index 3: 2 half frames
+        apu.cpu_write(0x400F, 0b0001_1000);
+        assert_eq!(apu.cpu_read(0x4015) & 0x0F, 0b1001);
+
+        // two half frames
+        run(&mut apu, 29830);
+        assert_eq!(apu.cpu_read(0x4015) & 0x0F, 0b0001);
+
+        apu.cpu_write(0x4015, 0x00);
+        assert_eq!(apu.cpu_read(0x4015) & 0x0F, 0);
+    }
+
+    #[test]
+    fn test_sweep_mutes() {
+        let mut apu = Apu2A03::default();
+        apu.cpu_write(0x4015, 0x01);
+        apu.cpu_write(0x4000, 0b1011_1111);
+        apu.cpu_write(0x4002, 0xFF);
+        apu.cpu_write(0x4003, 0x07);
+        // the target period overflows $7FF even with the sweep disabled
+        apu.cpu_write(0x4001, 0x01);
+        assert!(apu.pulse1.muted());
+
+        apu.cpu_write(0x4001, 0x09);
+        assert_eq!(apu.pulse1.sweep_target(), 0x7FF - 0x3FF - 1);
+        assert_eq!(apu.pulse2.sweep_target(), 0);
+    }
+
+    #[test]
+    fn test_frame_irq() {
+        let mut apu = Apu2A03::default();
+        run(&mut apu, 29827);
+        assert!(!apu.irq_pending());
+        run(&mut apu, 1);
+        assert!(apu.irq_pending());
+        assert_ne!(apu.cpu_read(0x4015) & 0x40, 0);
+        assert_eq!(apu.cpu_read(0x4015) & 0x40, 0);
+
+        // five step mode never raises it
+        let mut apu = Apu2A03::default();
+        apu.cpu_write(0x4017, 0x80);
+        run(&mut apu, 40000);
+        assert!(!apu.irq_pending());
+    }
+
+    #[test]
+    fn test_dmc() {
+        let mut apu = Apu2A03::default();
+        apu.cpu_write(0x4010, 0x8F);
+        apu.cpu_write(0x4012, 0x01);
+        apu.cpu_write(0x4013, 0x00);
+        apu.cpu_write(0x4015, 0x10);
+
+        assert_eq!(apu.dmc_request(), Some(0xC040));
+        apu.dmc_fill(0xFF);
+        assert_eq!(apu.dmc_request(), None);
+        assert!(apu.irq_pending());
+        assert_eq!(apu.cpu_read(0x4015) & 0x10, 0);
+
+        // the sample byte only starts playing once the current (silent) byte is out
+        run(&mut apu, 54 * 16);
+        assert_eq!(apu.dmc.level, 16);
+
+        apu.cpu_write(0x4015, 0x00);
+        assert!(!apu.irq_pending());
+    }
+
+    #[test]
+    fn test_sample_rate_limits() {
+        let mut apu = Apu2A03::new(0);
+        assert_eq!(apu.sample_rate(), 1);
+        run(&mut apu, CPU_CLOCK + 1);
+        assert_eq!(apu.samples.len(), 1);
+
+        apu.set_sample_rate(u32::MAX);
+        assert_eq!(apu.sample_rate(), CPU_CLOCK);
+        run(&mut apu, 1000);
+        assert_eq!(apu.samples.len(), 1000);
+    }
+
+    #[test]
+    fn test_load_state_errors() {
+        // the state of `channel` with the bytes at `zeroed` cleared
+        fn corrupted(channel: &impl SaveState, zeroed: std::ops::Range<usize>) -> Vec<u8> {
+            let mut state = StateWriter::default();
+            channel.save_state(&mut state);
+            let mut bytes = state.into_bytes();
+            bytes[zeroed].fill(0);
+            bytes
+        }
+        let invalid = |result| matches!(result, Err(SaveStateError::InvalidData(_)));
+
+        let mut noise = Noise::default();
+        let bytes = corrupted(&noise, 0..0);
+        assert!(noise.load_state(&mut StateReader::new(&bytes)).is_ok());
+        // mode, shift, timer and then the period
+        let bytes = corrupted(&noise, 5..7);
+        assert!(invalid(noise.load_state(&mut StateReader::new(&bytes))));
+
+        let mut dmc = Dmc::default();
+        let bytes = corrupted(&dmc, 0..0);
+        assert!(dmc.load_state(&mut StateReader::new(&bytes)).is_ok());
+        // irq_enabled, looping and then the rate
+        let bytes = corrupted(&dmc, 2..4);
+        assert!(invalid(dmc.load_state(&mut StateReader::new(&bytes))));
+        // bits_remaining is the third byte from the end
+        let end = corrupted(&dmc, 0..0).len();
+        let bytes = corrupted(&dmc, end - 3..end - 2);
+        assert!(invalid(dmc.load_state(&mut StateReader::new(&bytes))));
+    }
+}
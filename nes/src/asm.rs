@@ -0,0 +1,1013 @@
+use crate::{AddrMode, Bus, Mem64K, OLC6502};
+use std::collections::HashMap;
+
+/// Errors carry the 1 based source line they were found on
+#[derive(Debug, PartialEq, Eq)]
+pub enum AsmError {
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    UnknownMnemonic {
+        line: usize,
+        mnemonic: String,
+    },
+    /// the instruction exists, but not with the operand syntax used
+    InvalidAddressingMode {
+        line: usize,
+        mnemonic: String,
+    },
+    UnknownDirective {
+        line: usize,
+        directive: String,
+    },
+    UndefinedLabel {
+        line: usize,
+        label: String,
+    },
+    DuplicateLabel {
+        line: usize,
+        label: String,
+    },
+    /// an operand, `.byte` or `.word` value that doesn't fit, or code running past $FFFF
+    OutOfRange {
+        line: usize,
+        value: i64,
+    },
+    BranchOutOfRange {
+        line: usize,
+        offset: i64,
+    },
+}
+
+/// A contiguous run of assembled bytes
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Segment {
+    pub origin: u16,
+    pub bytes: Vec<u8>,
+}
+
+/// The output of [`assemble`]
+#[derive(Debug, Clone)]
+pub struct Program {
+    segments: Vec<Segment>,
+    symbols: HashMap<String, i64>,
+}
+
+impl Program {
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// the address of a label, or the value of a constant if it fits in 16 bits
+    pub fn label(&self, name: &str) -> Option<u16> {
+        self.symbols
+            .get(name)
+            .and_then(|value| u16::try_from(*value).ok())
+    }
+
+    /// Writes every segment to the bus. Later segments win where they overlap
+    pub fn load(&self, bus: &mut dyn Bus) {
+        for segment in &self.segments {
+            for (offset, byte) in segment.bytes.iter().enumerate() {
+                bus.write(segment.origin.wrapping_add(offset as u16), *byte);
+            }
+        }
+    }
+
+    /// 64K of RAM holding the program and zeroes elsewhere
+    pub fn to_mem(&self) -> Mem64K {
+        let mut mem = Mem64K::default();
+        self.load(&mut mem);
+        mem
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+    Neg,
+    Not,
+    Low,
+    High,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Number(i64),
+    Symbol(String),
+    /// `*`, the address of the current statement
+    Pc,
+    Unary(Op, Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+enum EvalError {
+    Undefined(String),
+    DivideByZero,
+}
+
+impl Expr {
+    fn eval(&self, symbols: &HashMap<String, i64>, pc: u16) -> Result<i64, EvalError> {
+        Ok(match self {
+            Expr::Number(n) => *n,
+            Expr::Symbol(name) => *symbols
+                .get(name)
+                .ok_or_else(|| EvalError::Undefined(name.clone()))?,
+            Expr::Pc => pc as i64,
+            Expr::Unary(op, expr) => {
+                let value = expr.eval(symbols, pc)?;
+                match op {
+                    Op::Neg => value.wrapping_neg(),
+                    Op::Not => !value,
+                    Op::Low => value & 0xFF,
+                    Op::High => (value >> 8) & 0xFF,
+                    _ => unreachable!("not a unary operator"),
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(symbols, pc)?, rhs.eval(symbols, pc)?);
+                match op {
+                    Op::Add => lhs.wrapping_add(rhs),
+                    Op::Sub => lhs.wrapping_sub(rhs),
+                    Op::Mul => lhs.wrapping_mul(rhs),
+                    Op::Div | Op::Mod if rhs == 0 => return Err(EvalError::DivideByZero),
+                    Op::Div => lhs.wrapping_div(rhs),
+                    Op::Mod => lhs.wrapping_rem(rhs),
+                    Op::And => lhs & rhs,
+                    Op::Or => lhs | rhs,
+                    Op::Xor => lhs ^ rhs,
+                    Op::Shl => lhs.wrapping_shl(rhs as u32),
+                    Op::Shr => lhs.wrapping_shr(rhs as u32),
+                    _ => unreachable!("not a binary operator"),
+                }
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i64),
+    Ident(String),
+    Str(String),
+    Op(char),
+    Shl,
+    Shr,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    let digits = |i: &mut usize, radix: u32| -> Result<i64, String> {
+        let start = *i;
+        while *i < chars.len() && (chars[*i].is_digit(radix) || chars[*i] == '_') {
+            *i += 1;
+        }
+        let digits: String = chars[start..*i].iter().filter(|c| **c != '_').collect();
+        i64::from_str_radix(&digits, radix).map_err(|_| format!("invalid number in `{}`", text))
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ' ' | '\t' => i += 1,
+            '$' => {
+                i += 1;
+                tokens.push(Token::Number(digits(&mut i, 16)?));
+            }
+            '%' if chars.get(i + 1).is_some_and(|c| *c == '0' || *c == '1') => {
+                i += 1;
+                tokens.push(Token::Number(digits(&mut i, 2)?));
+            }
+            '0'..='9' => tokens.push(Token::Number(digits(&mut i, 10)?)),
+            '\'' => match (chars.get(i + 1), chars.get(i + 2)) {
+                (Some(c), Some('\'')) => {
+                    tokens.push(Token::Number(*c as i64));
+                    i += 3;
+                }
+                _ => return Err(format!("unterminated character in `{}`", text)),
+            },
+            '"' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|c| *c == '"')
+                    .ok_or_else(|| format!("unterminated string in `{}`", text))?;
+                tokens.push(Token::Str(chars[i + 1..i + 1 + end].iter().collect()));
+                i += end + 2;
+            }
+            '<' | '>' if chars.get(i + 1) == Some(&c) => {
+                tokens.push(match c {
+                    '<' => Token::Shl,
+                    _ => Token::Shr,
+                });
+                i += 2;
+            }
+            '+' | '-' | '*' | '/' | '%' | '&' | '|' | '^' | '~' | '<' | '>' | '(' | ')' | ','
+            | '#' => {
+                tokens.push(Token::Op(c));
+                i += 1;
+            }
+            _ if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            _ => return Err(format!("unexpected `{}`", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the operator precedence levels, loosest first:
+/// `|`, `^`, `&`, `<< >>`, `+ -`, `* / %`, then the unary `- ~ < >`.
+/// `<` and `>` take the low and high byte of everything after them
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1)
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Op(op)) if *op == c => Ok(()),
+            _ => Err(format!("expected `{}`", c)),
+        }
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[(Token, Op)],
+        operand: fn(&mut Self) -> Result<Expr, String>,
+    ) -> Result<Expr, String> {
+        let mut lhs = operand(self)?;
+        while let Some((_, op)) = ops.iter().find(|(token, _)| Some(token) == self.peek()) {
+            self.pos += 1;
+            let rhs = operand(self)?;
+            lhs = Expr::Binary(*op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        self.binary(&[(Token::Op('|'), Op::Or)], Self::xor)
+    }
+
+    fn xor(&mut self) -> Result<Expr, String> {
+        self.binary(&[(Token::Op('^'), Op::Xor)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        self.binary(&[(Token::Op('&'), Op::And)], Self::shift)
+    }
+
+    fn shift(&mut self) -> Result<Expr, String> {
+        self.binary(&[(Token::Shl, Op::Shl), (Token::Shr, Op::Shr)], Self::sum)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        self.binary(
+            &[(Token::Op('+'), Op::Add), (Token::Op('-'), Op::Sub)],
+            Self::product,
+        )
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        self.binary(
+            &[
+                (Token::Op('*'), Op::Mul),
+                (Token::Op('/'), Op::Div),
+                (Token::Op('%'), Op::Mod),
+            ],
+            Self::unary,
+        )
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        let op = match self.peek() {
+            Some(Token::Op('-')) => Op::Neg,
+            Some(Token::Op('~')) => Op::Not,
+            Some(Token::Op('<')) => Op::Low,
+            Some(Token::Op('>')) => Op::High,
+            _ => return self.primary(),
+        };
+        self.pos += 1;
+        let operand = match op {
+            Op::Low | Op::High => self.expr()?,
+            _ => self.unary()?,
+        };
+        Ok(Expr::Unary(op, Box::new(operand)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next().cloned() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Ident(name)) => Ok(Expr::Symbol(name)),
+            Some(Token::Op('*')) => Ok(Expr::Pc),
+            Some(Token::Op('(')) => {
+                let expr = self.expr()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("expected an expression".to_string()),
+        }
+    }
+}
+
+fn parse_expr(text: &str) -> Result<Expr, String> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+    };
+    let expr = parser.expr()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(format!("unexpected {:?} in `{}`", token, text)),
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Implied,
+    Immediate(Expr),
+    Direct(Expr),
+    DirectX(Expr),
+    DirectY(Expr),
+    Indirect(Expr),
+    IndirectX(Expr),
+    IndirectY(Expr),
+}
+
+#[derive(Debug, Clone)]
+enum ByteItem {
+    Expr(Expr),
+    Str(Vec<u8>),
+}
+
+#[derive(Debug, Clone)]
+enum Statement {
+    Org(Expr),
+    Byte(Vec<ByteItem>),
+    Word(Vec<Expr>),
+    Constant(String, Expr),
+    Instruction(String, Operand),
+}
+
+struct Line {
+    number: usize,
+    label: Option<String>,
+    statement: Option<Statement>,
+}
+
+/// the index of the `)` closing the `(` at `open`
+fn closing_paren(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices().skip(open) {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// splits on commas outside of parentheses and quotes
+fn split_args(text: &str) -> Vec<&str> {
+    let mut args = Vec::new();
+    let (mut depth, mut quoted, mut start) = (0, false, 0);
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                args.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(text[start..].trim());
+    args
+}
+
+fn index_register(text: &str) -> Option<char> {
+    match text.trim().to_ascii_uppercase().as_str() {
+        "X" => Some('X'),
+        "Y" => Some('Y'),
+        _ => None,
+    }
+}
+
+fn parse_operand(mnemonic: &str, text: &str) -> Result<Operand, String> {
+    if text.is_empty() {
+        return Ok(Operand::Implied);
+    }
+    if text.eq_ignore_ascii_case("A") && matches!(mnemonic, "ASL" | "LSR" | "ROL" | "ROR") {
+        return Ok(Operand::Implied);
+    }
+    if let Some(value) = text.strip_prefix('#') {
+        return Ok(Operand::Immediate(parse_expr(value)?));
+    }
+
+    if text.starts_with('(') {
+        if let Some(close) = closing_paren(text, 0) {
+            let inner = &text[1..close];
+            let rest = text[close + 1..].trim();
+            if rest.is_empty() {
+                return match split_args(inner)[..] {
+                    [expr, x] if index_register(x) == Some('X') => {
+                        Ok(Operand::IndirectX(parse_expr(expr)?))
+                    }
+                    [expr] => Ok(Operand::Indirect(parse_expr(expr)?)),
+                    _ => Err(format!("invalid operand `{}`", text)),
+                };
+            }
+            if let Some(y) = rest.strip_prefix(',') {
+                if index_register(y) == Some('Y') {
+                    return Ok(Operand::IndirectY(parse_expr(inner)?));
+                }
+            }
+        }
+    }
+
+    match split_args(text)[..] {
+        [expr] => Ok(Operand::Direct(parse_expr(expr)?)),
+        [expr, index] => match index_register(index) {
+            Some('X') => Ok(Operand::DirectX(parse_expr(expr)?)),
+            Some('Y') => Ok(Operand::DirectY(parse_expr(expr)?)),
+            _ => Err(format!("expected X or Y after the comma in `{}`", text)),
+        },
+        _ => Err(format!("invalid operand `{}`", text)),
+    }
+}
+
+fn is_ident(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// strips a `;` comment, ignoring semicolons in strings and character literals
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = None;
+    for (i, c) in line.char_indices() {
+        match (c, quoted) {
+            ('"' | '\'', None) => quoted = Some(c),
+            (c, Some(q)) if c == q => quoted = None,
+            (';', None) => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_line(number: usize, text: &str) -> Result<Line, AsmError> {
+    let syntax = |message: String| AsmError::Syntax {
+        line: number,
+        message,
+    };
+    let mut text = strip_comment(text).trim();
+
+    let mut label = None;
+    if let Some(colon) = text.find(':') {
+        if is_ident(&text[..colon]) {
+            label = Some(text[..colon].to_string());
+            text = text[colon + 1..].trim();
+        }
+    }
+
+    let (word, rest) = match text.find(char::is_whitespace) {
+        Some(i) => (&text[..i], text[i..].trim()),
+        None => (text, ""),
+    };
+
+    let statement = if text.is_empty() {
+        None
+    } else if let Some((name, value)) = text
+        .split_once('=')
+        .filter(|(name, _)| is_ident(name.trim()))
+    {
+        Some(Statement::Constant(
+            name.trim().to_string(),
+            parse_expr(value).map_err(syntax)?,
+        ))
+    } else if let Some(directive) = word.strip_prefix('.') {
+        let args = || {
+            split_args(rest)
+                .into_iter()
+                .map(|arg| parse_expr(arg).map_err(syntax))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Some(match directive.to_ascii_lowercase().as_str() {
+            "org" => Statement::Org(parse_expr(rest).map_err(syntax)?),
+            "word" => Statement::Word(args()?),
+            "byte" => Statement::Byte(
+                split_args(rest)
+                    .into_iter()
+                    .map(
+                        |arg| match arg.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+                            Some(string) => Ok(ByteItem::Str(string.as_bytes().to_vec())),
+                            None => Ok(ByteItem::Expr(parse_expr(arg).map_err(syntax)?)),
+                        },
+                    )
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            _ => {
+                return Err(AsmError::UnknownDirective {
+                    line: number,
+                    directive: word.to_string(),
+                })
+            }
+        })
+    } else {
+        let mnemonic = word.to_ascii_uppercase();
+        if !(0..=255).any(|opcode| {
+            let instruction = OLC6502::lookup(opcode);
+            instruction.is_official() && instruction.name() == mnemonic
+        }) {
+            return Err(AsmError::UnknownMnemonic {
+                line: number,
+                mnemonic: word.to_string(),
+            });
+        }
+        let operand = parse_operand(&mnemonic, rest).map_err(syntax)?;
+        Some(Statement::Instruction(mnemonic, operand))
+    };
+
+    Ok(Line {
+        number,
+        label,
+        statement,
+    })
+}
+
+fn opcode(mnemonic: &str, mode: AddrMode) -> Option<u8> {
+    (0..=255).find(|opcode| {
+        let instruction = OLC6502::lookup(*opcode);
+        instruction.is_official()
+            && instruction.name() == mnemonic
+            && instruction.addrmode() == mode
+    })
+}
+
+/// Picks the addressing mode for an instruction. Zero page forms are used when
+/// the operand is already known to fit in a byte, so forward references always
+/// get the absolute form. Both passes have to agree, so pass 1 decides
+fn select_mode(mnemonic: &str, operand: &Operand, known: Option<i64>) -> Option<AddrMode> {
+    let zero_page = known.is_some_and(|value| (0..=0xFF).contains(&value));
+    let either = |zp: AddrMode, abs: AddrMode| match zero_page && opcode(mnemonic, zp).is_some() {
+        true => zp,
+        false => abs,
+    };
+    let mode = match operand {
+        Operand::Implied => AddrMode::IMP,
+        Operand::Immediate(_) => AddrMode::IMM,
+        Operand::Direct(_) if opcode(mnemonic, AddrMode::REL).is_some() => AddrMode::REL,
+        Operand::Direct(_) => either(AddrMode::ZP0, AddrMode::ABS),
+        Operand::DirectX(_) => either(AddrMode::ZPX, AddrMode::ABX),
+        Operand::DirectY(_) => either(AddrMode::ZPY, AddrMode::ABY),
+        Operand::Indirect(_) => AddrMode::IND,
+        Operand::IndirectX(_) => AddrMode::IZX,
+        Operand::IndirectY(_) => AddrMode::IZY,
+    };
+    opcode(mnemonic, mode).map(|_| mode)
+}
+
+fn operand_expr(operand: &Operand) -> Option<&Expr> {
+    match operand {
+        Operand::Implied => None,
+        Operand::Immediate(expr)
+        | Operand::Direct(expr)
+        | Operand::DirectX(expr)
+        | Operand::DirectY(expr)
+        | Operand::Indirect(expr)
+        | Operand::IndirectX(expr)
+        | Operand::IndirectY(expr) => Some(expr),
+    }
+}
+
+/// Assembles 6502 source into a [`Program`].
+///
+/// One statement per line, optionally preceded by a `label:`. Comments start with `;`.
+///
+/// - instructions use the usual operand syntax: `#imm`, `zp`, `zp,X`, `abs,Y`,
+///   `(ind)`, `(zp,X)`, `(zp),Y`, `A` for the accumulator, and a target address for branches
+/// - `.org expr` moves the output address, `.byte` and `.word` emit data. `.byte`
+///   also takes `"strings"`
+/// - `name = expr` defines a constant
+/// - expressions can use `$hex`, `%binary`, decimal and `'c'` literals, labels,
+///   `*` for the address of the current statement, `< >` for the low and high
+///   byte, and `+ - * / % & | ^ << >> ~` with parentheses
+///
+/// The first pass assigns addresses to labels, the second emits bytes.
+pub fn assemble(source: &str) -> Result<Program, AsmError> {
+    let lines = source
+        .lines()
+        .enumerate()
+        .map(|(i, text)| parse_line(i + 1, text))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // pass 1: addresses and addressing modes
+    let mut symbols = HashMap::new();
+    let mut modes = vec![None; lines.len()];
+    let mut pc: i64 = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let number = line.number;
+        if let Some(label) = &line.label {
+            if symbols.insert(label.clone(), pc).is_some() {
+                return Err(AsmError::DuplicateLabel {
+                    line: number,
+                    label: label.clone(),
+                });
+            }
+        }
+
+        pc += match &line.statement {
+            None => 0,
+            Some(Statement::Org(expr)) => {
+                pc = eval(expr, &symbols, pc, number)?;
+                0
+            }
+            Some(Statement::Constant(name, expr)) => {
+                let value = eval(expr, &symbols, pc, number)?;
+                if symbols.insert(name.clone(), value).is_some() {
+                    return Err(AsmError::DuplicateLabel {
+                        line: number,
+                        label: name.clone(),
+                    });
+                }
+                0
+            }
+            Some(Statement::Byte(items)) => items
+                .iter()
+                .map(|item| match item {
+                    ByteItem::Expr(_) => 1,
+                    ByteItem::Str(bytes) => bytes.len() as i64,
+                })
+                .sum(),
+            Some(Statement::Word(exprs)) => 2 * exprs.len() as i64,
+            Some(Statement::Instruction(mnemonic, operand)) => {
+                let known =
+                    operand_expr(operand).and_then(|expr| expr.eval(&symbols, pc as u16).ok());
+                let mode = select_mode(mnemonic, operand, known).ok_or_else(|| {
+                    AsmError::InvalidAddressingMode {
+                        line: number,
+                        mnemonic: mnemonic.clone(),
+                    }
+                })?;
+                modes[i] = Some(mode);
+                1 + mode.operand_bytes() as i64
+            }
+        };
+        if !(0..=0x10000).contains(&pc) {
+            return Err(AsmError::OutOfRange {
+                line: number,
+                value: pc,
+            });
+        }
+    }
+
+    // pass 2: bytes
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut pc: i64 = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let number = line.number;
+        let mut bytes = Vec::new();
+        match &line.statement {
+            None | Some(Statement::Constant(..)) => {}
+            Some(Statement::Org(expr)) => {
+                pc = eval(expr, &symbols, pc, number)?;
+                continue;
+            }
+            Some(Statement::Byte(items)) => {
+                for item in items {
+                    match item {
+                        ByteItem::Expr(expr) => {
+                            bytes.push(byte(eval(expr, &symbols, pc, number)?, number)?)
+                        }
+                        ByteItem::Str(string) => bytes.extend_from_slice(string),
+                    }
+                }
+            }
+            Some(Statement::Word(exprs)) => {
+                for expr in exprs {
+                    let value = eval(expr, &symbols, pc, number)?;
+                    bytes.extend_from_slice(&word(value, number)?.to_le_bytes());
+                }
+            }
+            Some(Statement::Instruction(mnemonic, operand)) => {
+                let mode = modes[i].expect("pass 1 selects a mode for every instruction");
+                bytes.push(opcode(mnemonic, mode).expect("pass 1 checked the opcode exists"));
+                if let Some(expr) = operand_expr(operand) {
+                    let value = eval(expr, &symbols, pc, number)?;
+                    match mode {
+                        AddrMode::REL => {
+                            let offset = value - (pc + 2);
+                            if !(-128..=127).contains(&offset) {
+                                return Err(AsmError::BranchOutOfRange {
+                                    line: number,
+                                    offset,
+                                });
+                            }
+                            bytes.push(offset as u8);
+                        }
+                        _ if mode.operand_bytes() == 1 => bytes.push(byte(value, number)?),
+                        _ => bytes.extend_from_slice(&word(value, number)?.to_le_bytes()),
+                    }
+                }
+            }
+        }
+
+        if bytes.is_empty() {
+            continue;
+        }
+        let len = bytes.len() as i64;
+        match segments.last_mut() {
+            Some(segment) if segment.origin as i64 + segment.bytes.len() as i64 == pc => {
+                segment.bytes.extend(bytes)
+            }
+            _ => segments.push(Segment {
+                origin: pc as u16,
+                bytes,
+            }),
+        }
+        pc += len;
+    }
+
+    Ok(Program { segments, symbols })
+}
+
+fn eval(
+    expr: &Expr,
+    symbols: &HashMap<String, i64>,
+    pc: i64,
+    line: usize,
+) -> Result<i64, AsmError> {
+    expr.eval(symbols, pc as u16).map_err(|e| match e {
+        EvalError::Undefined(label) => AsmError::UndefinedLabel { line, label },
+        EvalError::DivideByZero => AsmError::Syntax {
+            line,
+            message: "division by zero".to_string(),
+        },
+    })
+}
+
+/// accepts signed and unsigned bytes
+fn byte(value: i64, line: usize) -> Result<u8, AsmError> {
+    match value {
+        -0x80..=0xFF => Ok(value as u8),
+        _ => Err(AsmError::OutOfRange { line, value }),
+    }
+}
+
+fn word(value: i64, line: usize) -> Result<u16, AsmError> {
+    match value {
+        -0x8000..=0xFFFF => Ok(value as u16),
+        _ => Err(AsmError::OutOfRange { line, value }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disassemble_range;
+
+    fn bytes(source: &str) -> Vec<u8> {
+        let program = assemble(source).unwrap();
+        assert_eq!(program.segments().len(), 1);
+        program.segments()[0].bytes.clone()
+    }
+
+    #[test]
+    fn test_addressing_modes() {
+        let source = "
+            .org $8000
+            start:
+                nop
+                asl a
+                lda #$10
+                lda $20
+                lda $20,x
+                ldx $20,y
+                bne start
+                lda $1234
+                lda $1234,X
+                lda $1234,Y
+                lda $20,Y       ; no zero page,Y form for LDA
+                jmp ($1234)
+                lda ($20,X)
+                lda ($20),Y
+        ";
+        let program = assemble(source).unwrap();
+        let mut mem = program.to_mem();
+        let lines: Vec<String> = disassemble_range(&mut mem, 0x8000, 14)
+            .iter()
+            .map(|disassembly| disassembly.to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "8000  EA        NOP",
+                "8001  0A        ASL A",
+                "8002  A9 10     LDA #$10",
+                "8004  A5 20     LDA $20",
+                "8006  B5 20     LDA $20,X",
+                "8008  B6 20     LDX $20,Y",
+                "800A  D0 F4     BNE $8000",
+                "800C  AD 34 12  LDA $1234",
+                "800F  BD 34 12  LDA $1234,X",
+                "8012  B9 34 12  LDA $1234,Y",
+                "8015  B9 20 00  LDA $0020,Y",
+                "8018  6C 34 12  JMP ($1234)",
+                "801B  A1 20     LDA ($20,X)",
+                "801D  B1 20     LDA ($20),Y",
+            ]
+        );
+        assert_eq!(program.label("start"), Some(0x8000));
+    }
+
+    #[test]
+    fn test_every_official_opcode_round_trips() {
+        let mut mem = Mem64K::default();
+        for opcode in 0..=255u8 {
+            let instruction = OLC6502::lookup(opcode);
+            if !instruction.is_official() {
+                continue;
+            }
+            // operands that select the zero page forms where they exist
+            let program = [opcode, 0x12, 0x34];
+            for (offset, byte) in program.iter().enumerate() {
+                mem.write(0x0400 + offset as u16, *byte);
+            }
+            let disassembly = crate::disassemble(&mut mem, 0x0400);
+            let source = format!(
+                ".org $0400\n{} {}",
+                disassembly.mnemonic, disassembly.operand
+            );
+            assert_eq!(bytes(&source), disassembly.bytes, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_labels_and_forward_references() {
+        let program = assemble(
+            "
+            .org $C000
+            reset:  ldx #0
+            loop:   lda message,x
+                    beq done
+                    sta $0200,x
+                    inx
+                    bne loop
+            done:   jmp done
+            message:
+                    .byte \"HI\", 0
+            zp:     lda later      ; forward references get the absolute form
+                    .org $00F0
+            later:  .byte 1
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(program.label("loop"), Some(0xC002));
+        assert_eq!(program.label("done"), Some(0xC00D));
+        assert_eq!(program.label("message"), Some(0xC010));
+        assert_eq!(
+            program.segments(),
+            &[
+                Segment {
+                    origin: 0xC000,
+                    bytes: vec![
+                        0xA2, 0x00, 0xBD, 0x10, 0xC0, 0xF0, 0x06, 0x9D, 0x00, 0x02, 0xE8, 0xD0,
+                        0xF5, 0x4C, 0x0D, 0xC0, b'H', b'I', 0x00, 0xAD, 0xF0, 0x00,
+                    ],
+                },
+                Segment {
+                    origin: 0x00F0,
+                    bytes: vec![0x01],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expressions_and_data() {
+        let source = "
+            PPUCTRL = $2000
+            count = 3 * (2 + 1)
+            .org $8000
+            table:
+                .byte count, -1, %1010, 'A', count << 2 | 1
+                .word table, table + 2, PPUCTRL
+                lda #<vector
+                ldx #>vector
+                lda #>$1234 + 1    ; high byte of everything after `>`
+                sta PPUCTRL + 1
+                jmp *
+            vector = $ABCD
+        ";
+        assert_eq!(
+            bytes(source),
+            vec![
+                9, 0xFF, 0b1010, b'A', 37, // .byte
+                0x00, 0x80, 0x02, 0x80, 0x00, 0x20, // .word
+                0xA9, 0xCD, // LDA #<vector
+                0xA2, 0xAB, // LDX #>vector
+                0xA9, 0x12, // LDA #>$1235
+                0x8D, 0x01, 0x20, // STA $2001
+                0x4C, 0x14, 0x80, // JMP *
+            ]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |source: &str| assemble(source).unwrap_err();
+
+        assert_eq!(
+            error("nop\nfoo #1"),
+            AsmError::UnknownMnemonic {
+                line: 2,
+                mnemonic: "foo".to_string()
+            }
+        );
+        assert_eq!(
+            error("jmp ($10),y"),
+            AsmError::InvalidAddressingMode {
+                line: 1,
+                mnemonic: "JMP".to_string()
+            }
+        );
+        assert_eq!(
+            error("lda missing"),
+            AsmError::UndefinedLabel {
+                line: 1,
+                label: "missing".to_string()
+            }
+        );
+        assert_eq!(
+            error("a: nop\na: nop"),
+            AsmError::DuplicateLabel {
+                line: 2,
+                label: "a".to_string()
+            }
+        );
+        assert_eq!(
+            error("lda #$100"),
+            AsmError::OutOfRange {
+                line: 1,
+                value: 0x100
+            }
+        );
+        assert_eq!(
+            error("start: .org 200\nbne start"),
+            AsmError::BranchOutOfRange {
+                line: 2,
+                offset: -202
+            }
+        );
+        assert!(matches!(
+            error(".fill 3"),
+            AsmError::UnknownDirective { .. }
+        ));
+        assert!(matches!(error("lda (1"), AsmError::Syntax { line: 1, .. }));
+        assert!(matches!(error("lda 1,z"), AsmError::Syntax { line: 1, .. }));
+        assert!(matches!(
+            error(".org $FFFF\nnop\nnop"),
+            AsmError::OutOfRange { line: 3, .. }
+        ));
+    }
+}
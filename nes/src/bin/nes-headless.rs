@@ -0,0 +1,89 @@
+use clap::Parser;
+use nes::{write_png, write_ppm, Bus16, Cartridge, Movie, SCREEN_HEIGHT, SCREEN_WIDTH};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// Run a ROM without a window, feeding it recorded input and saving screenshots.
+///
+/// Prints the frame hash of the last frame, so a regression run can be compared
+/// against a known good one without keeping screenshots around.
+#[derive(Parser)]
+struct Cli {
+    /// iNES or NES 2.0 ROM
+    rom: PathBuf,
+
+    /// number of frames to run, defaults to the length of the movie or 60
+    #[arg(short, long)]
+    frames: Option<usize>,
+
+    /// controller input, one line of buttons per frame
+    #[arg(short, long)]
+    movie: Option<PathBuf>,
+
+    /// screenshot of the last frame, written as PPM if the extension is .ppm and PNG otherwise
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// also take a screenshot every N frames, named after `output` with the frame number appended
+    #[arg(short, long, requires = "output")]
+    every: Option<usize>,
+}
+
+fn screenshot(nes: &Bus16, path: &Path) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let frame = nes.ppu.frame();
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("ppm") => write_ppm(&mut file, SCREEN_WIDTH, SCREEN_HEIGHT, frame),
+        _ => write_png(&mut file, SCREEN_WIDTH, SCREEN_HEIGHT, frame),
+    }
+}
+
+/// `shot.png` -> `shot-000120.png`
+fn numbered(path: &Path, frame: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}-{:06}.{}", stem, frame, ext.to_string_lossy()),
+        None => format!("{}-{:06}", stem, frame),
+    };
+    path.with_file_name(name)
+}
+
+fn fail(what: &Path, error: impl std::fmt::Debug) -> ! {
+    eprintln!("** {} -- {:?}", what.display(), error);
+    std::process::exit(1);
+}
+
+fn main() {
+    let args = Cli::parse();
+
+    let cartridge = Cartridge::load(&args.rom).unwrap_or_else(|e| fail(&args.rom, e));
+    let movie = match &args.movie {
+        Some(path) => Movie::load(path).unwrap_or_else(|e| fail(path, e)),
+        None => Movie::default(),
+    };
+    let frames = args.frames.unwrap_or(match movie.is_empty() {
+        true => 60,
+        false => movie.len(),
+    });
+
+    let mut nes = Bus16::default();
+    nes.insert_cartridge(cartridge);
+    nes.reset();
+
+    for frame in 0..frames {
+        movie.play_frame(&mut nes, frame);
+
+        if let (Some(output), Some(every)) = (&args.output, args.every) {
+            if every > 0 && (frame + 1) % every == 0 {
+                let path = numbered(output, frame + 1);
+                screenshot(&nes, &path).unwrap_or_else(|e| fail(&path, e));
+            }
+        }
+    }
+
+    if let Some(output) = &args.output {
+        screenshot(&nes, output).unwrap_or_else(|e| fail(output, e));
+    }
+    println!("{} frames, hash {:016x}", frames, nes.ppu.frame_hash());
+}
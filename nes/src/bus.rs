@@ -1,16 +1,904 @@
-use crate::{Mem64K, OLC6502};
+use crate::{
+    Apu2A03, Cartridge, Controller, Ppu2C02, SaveState, SaveStateError, StateReader, StateWriter,
+    OLC6502,
+};
 
+/// Everything the CPU can see on its address lines.
+///
+/// The CPU borrows the bus for the duration of a single `clock`, so it never owns
+/// the devices behind it and can be driven against flat RAM in tests just as well
+/// as against the full memory map.
+pub trait Bus {
+    fn read(&mut self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, data: u8);
+
+    /// Reads without side effects, for debuggers and disassemblers.
+    /// Only differs from `read` on buses with memory mapped I/O
+    fn peek(&mut self, addr: u16) -> u8 {
+        self.read(addr)
+    }
+
+    /// Called once per CPU cycle, after the cycle's memory accesses.
+    /// Lets devices that run in lockstep with the CPU catch up.
+    fn tick(&mut self) {}
+}
+
+/// The NES memory map.
+///
+/// CPU: 2K of RAM mirrored up to $1FFF, PPU registers at $2000-$3FFF,
+/// APU and I/O at $4000-$401F, and the cartridge from $4020 up.
+///
+/// The PPU has its own address space, see [`Ppu2C02::read`].
 pub struct Bus16 {
-    cpu: OLC6502,
-    ram: Mem64K,
+    pub cpu: OLC6502,
+    pub ppu: Ppu2C02,
+    pub apu: Apu2A03,
+    pub controllers: [Controller; 2],
+    ram: [u8; 2 * 1024],
+    cartridge: Option<Cartridge>,
+
+    /// CPU cycles since power on
+    cycles: u64,
+    /// CPU cycles left in the current OAM DMA
+    dma_stall: u16,
+}
+
+impl Default for Bus16 {
+    fn default() -> Self {
+        Self {
+            cpu: OLC6502::default(),
+            ppu: Ppu2C02::default(),
+            apu: Apu2A03::default(),
+            controllers: [Controller::default(); 2],
+            ram: [0; 2 * 1024],
+            cartridge: None,
+            cycles: 0,
+            dma_stall: 0,
+        }
+    }
 }
 
 impl Bus16 {
-    pub fn write(&mut self, addr: u16, data: u8) {
-        self.ram.write(addr, data);
+    pub fn insert_cartridge(&mut self, cartridge: Cartridge) {
+        self.cartridge = Some(cartridge);
+    }
+
+    pub fn cartridge(&self) -> Option<&Cartridge> {
+        self.cartridge.as_ref()
+    }
+
+    pub fn cartridge_mut(&mut self) -> Option<&mut Cartridge> {
+        self.cartridge.as_mut()
+    }
+
+    pub fn reset(&mut self) {
+        if let Some(cartridge) = &mut self.cartridge {
+            cartridge.reset();
+        }
+        self.dma_stall = 0;
+        let mut cpu = std::mem::take(&mut self.cpu);
+        cpu.reset(self);
+        self.cpu = cpu;
+    }
+
+    /// true while an OAM DMA holds the CPU off the bus
+    pub fn dma_stalled(&self) -> bool {
+        self.dma_stall > 0
+    }
+
+    /// Advances the system by one CPU cycle (three PPU dots)
+    pub fn clock(&mut self) {
+        self.clock_with(&mut |cpu, bus| cpu.clock(bus));
+    }
+
+    /// Like `clock`, but lets the caller stand between the CPU and the bus,
+    /// e.g. to observe every memory access
+    pub fn clock_with(&mut self, clock_cpu: &mut dyn FnMut(&mut OLC6502, &mut dyn Bus)) {
+        // the CPU is moved out for the duration of the clock so the rest of the bus can be lent to it
+        let mut cpu = std::mem::take(&mut self.cpu);
+
+        if self.dma_stall > 0 {
+            self.dma_stall -= 1;
+            self.tick();
+        } else {
+            clock_cpu(&mut cpu, self);
+        }
+
+        // interrupts are only taken between instructions
+        if cpu.complete() && self.dma_stall == 0 {
+            if self.ppu.take_nmi() {
+                cpu.nmi(self);
+            } else if self.apu.irq_pending()
+                || self
+                    .cartridge
+                    .as_ref()
+                    .is_some_and(|cart| cart.irq_pending())
+            {
+                cpu.irq(self);
+            }
+        }
+
+        self.cpu = cpu;
+    }
+
+    /// Runs until the PPU finishes the current frame
+    pub fn run_frame(&mut self) {
+        self.ppu.frame_complete = false;
+        while !self.ppu.frame_complete {
+            self.clock();
+        }
+    }
+
+    pub fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.ppu.read(&mut self.cartridge, addr)
+    }
+
+    pub fn ppu_write(&mut self, addr: u16, data: u8) {
+        self.ppu.write(&mut self.cartridge, addr, data);
+    }
+
+    /// Copies a page of CPU memory into OAM. The CPU is halted for 513 cycles, 514 on odd cycles
+    fn oam_dma(&mut self, page: u8) {
+        for offset in 0..=0xFF {
+            let data = self.read(u16::from_be_bytes([page, offset]));
+            self.ppu.write_oam(data);
+        }
+        self.dma_stall = 513 + (self.cycles & 1) as u16;
+    }
+}
+
+impl Bus for Bus16 {
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x1FFF => self.ram[(addr & 0x07FF) as usize],
+            0x2000..=0x3FFF => self.ppu.cpu_read(&mut self.cartridge, addr),
+            0x4015 => self.apu.cpu_read(addr),
+            0x4016 => self.controllers[0].read(),
+            0x4017 => self.controllers[1].read(),
+            0x4000..=0x401F => 0,
+            _ => match &mut self.cartridge {
+                Some(cartridge) => cartridge.cpu_read(addr).unwrap_or(0),
+                None => 0,
+            },
+        }
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram[(addr & 0x07FF) as usize] = data,
+            0x2000..=0x3FFF => self.ppu.cpu_write(&mut self.cartridge, addr, data),
+            0x4014 => self.oam_dma(data),
+            // one strobe line goes to both ports
+            0x4016 => self.controllers.iter_mut().for_each(|c| c.write(data)),
+            0x4000..=0x4013 | 0x4015 | 0x4017 => self.apu.cpu_write(addr, data),
+            0x4000..=0x401F => {}
+            _ => {
+                if let Some(cartridge) = &mut self.cartridge {
+                    cartridge.cpu_write(addr, data);
+                }
+            }
+        }
+    }
+
+    fn peek(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x1FFF => self.ram[(addr & 0x07FF) as usize],
+            0x2000..=0x401F => 0,
+            _ => match &mut self.cartridge {
+                Some(cartridge) => cartridge.cpu_read(addr).unwrap_or(0),
+                None => 0,
+            },
+        }
+    }
+
+    fn tick(&mut self) {
+        self.cycles += 1;
+        for _ in 0..3 {
+            self.ppu.clock(&mut self.cartridge);
+        }
+
+        self.apu.clock();
+        // the DMC's sample fetches steal cycles from the CPU
+        if let Some(addr) = self.apu.dmc_request() {
+            let data = self.read(addr);
+            self.apu.dmc_fill(data);
+            self.dma_stall += 4;
+        }
+    }
+}
+
+impl SaveState for Bus16 {
+    fn save_state(&self, state: &mut StateWriter) {
+        self.cpu.save_state(state);
+        self.ppu.save_state(state);
+        self.apu.save_state(state);
+        for controller in &self.controllers {
+            controller.save_state(state);
+        }
+        state.bytes(&self.ram);
+        state.u64(self.cycles);
+        state.u16(self.dma_stall);
+
+        state.bool(self.cartridge.is_some());
+        if let Some(cartridge) = &self.cartridge {
+            cartridge.save_state(state);
+        }
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.cpu.load_state(state)?;
+        self.ppu.load_state(state)?;
+        self.apu.load_state(state)?;
+        for controller in &mut self.controllers {
+            controller.load_state(state)?;
+        }
+        state.bytes(&mut self.ram)?;
+        self.cycles = state.u64()?;
+        self.dma_stall = state.u16()?;
+
+        match (state.bool()?, &mut self.cartridge) {
+            (true, Some(cartridge)) => cartridge.load_state(state),
+            (false, None) => Ok(()),
+            _ => Err(SaveStateError::CartridgeMismatch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::tests::rom;
+    use crate::{write_wav, Button, Mirroring, CPU_CLOCK};
+
+    #[test]
+    fn test_cpu_memory_map() {
+        let mut bus = Bus16::default();
+        bus.insert_cartridge(Cartridge::from_bytes(&rom(0, 2, 1, 0)).unwrap());
+
+        bus.write(0x0001, 0x42);
+        assert_eq!(bus.read(0x0801), 0x42);
+        assert_eq!(bus.read(0x1801), 0x42);
+        assert_eq!(bus.read(0x8000), 0);
+        assert_eq!(bus.read(0xC000), 1);
+
+        bus.write(0x6000, 0x24);
+        assert_eq!(bus.read(0x6000), 0x24);
+    }
+
+    #[test]
+    fn test_ppu_memory_map() {
+        let mut bus = Bus16::default();
+        // vertical mirroring
+        bus.insert_cartridge(Cartridge::from_bytes(&rom(0, 1, 1, 0x01)).unwrap());
+
+        assert_eq!(bus.ppu_read(0x0000), 0x80);
+        bus.ppu_write(0x2001, 0x11);
+        assert_eq!(bus.ppu_read(0x2801), 0x11);
+        assert_eq!(bus.ppu_read(0x3001), 0x11);
+        assert_eq!(bus.ppu_read(0x2401), 0x00);
+
+        bus.ppu_write(0x3F10, 0x0F);
+        assert_eq!(bus.ppu_read(0x3F00), 0x0F);
+        assert_eq!(bus.ppu_read(0x3F30), 0x0F);
+    }
+
+    #[test]
+    fn test_runs_program_from_cartridge() {
+        let mut bytes = rom(0, 1, 1, 0);
+        #[rustfmt::skip]
+        let program = [
+            0xA9, 0x42,       // LDA #$42
+            0x8D, 0x00, 0x07, // STA $0700
+        ];
+        bytes[16..16 + program.len()].copy_from_slice(&program);
+        // reset vector at $FFFC -> $8000 (the 16K bank is mirrored at $C000)
+        bytes[16 + 0x3FFC] = 0x00;
+        bytes[16 + 0x3FFD] = 0x80;
+
+        let mut bus = Bus16::default();
+        bus.insert_cartridge(Cartridge::from_bytes(&bytes).unwrap());
+        bus.reset();
+        for _ in 0..7 + 2 + 4 {
+            bus.clock();
+        }
+        assert_eq!(bus.read(0x0700), 0x42);
+    }
+
+    /// an NROM cartridge with `program` at $8000, and `nmi` at $8100 for both NMI and IRQ
+    fn cartridge(program: &[u8], nmi: &[u8]) -> Cartridge {
+        let mut bytes = rom(0, 1, 1, 0);
+        bytes[16..16 + program.len()].copy_from_slice(program);
+        bytes[16 + 0x100..16 + 0x100 + nmi.len()].copy_from_slice(nmi);
+        bytes[16 + 0x3FFA..16 + 0x4000].copy_from_slice(&[0x00, 0x81, 0x00, 0x80, 0x00, 0x81]);
+        Cartridge::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_oam_dma() {
+        #[rustfmt::skip]
+        let program = [
+            0xA9, 0x02,       // LDA #$02
+            0x8D, 0x14, 0x40, // STA $4014
+            0x4C, 0x05, 0x80, // JMP $8005
+        ];
+        let mut bus = Bus16::default();
+        bus.insert_cartridge(cartridge(&program, &[]));
+        for i in 0..=0xFF {
+            bus.write(0x0200 + i, i as u8);
+        }
+        bus.reset();
+
+        while bus.dma_stall == 0 {
+            bus.clock();
+        }
+        assert!((513..=514).contains(&bus.dma_stall));
+        assert_eq!(bus.ppu.oam()[0x00], 0x00);
+        assert_eq!(bus.ppu.oam()[0xFF], 0xFF);
+
+        for _ in 0..bus.dma_stall {
+            assert!(bus.dma_stall > 0);
+            bus.clock();
+        }
+        assert_eq!(bus.dma_stall, 0);
     }
 
-    pub fn read(&self, addr: u16) -> u8 {
-        self.ram.read(addr)
+    #[test]
+    fn test_controller_ports() {
+        let mut bus = Bus16::default();
+        bus.controllers[0].set_buttons(Button::A | Button::UP);
+        bus.controllers[1].set_buttons(Button::B);
+
+        bus.write(0x4016, 1);
+        bus.write(0x4016, 0);
+        let pad1: Vec<u8> = (0..8).map(|_| bus.read(0x4016) & 0x01).collect();
+        let pad2: Vec<u8> = (0..8).map(|_| bus.read(0x4017) & 0x01).collect();
+        assert_eq!(pad1, vec![1, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(pad2, vec![0, 1, 0, 0, 0, 0, 0, 0]);
+
+        // $4017 writes go to the APU frame counter, not the controllers
+        bus.write(0x4016, 1);
+        bus.write(0x4017, 0);
+        assert_eq!(bus.read(0x4016) & 0x01, 1);
+        assert_eq!(bus.read(0x4016) & 0x01, 1);
+    }
+
+    #[test]
+    fn test_vblank_nmi() {
+        #[rustfmt::skip]
+        let program = [
+            0xA9, 0x80,       // LDA #$80
+            0x8D, 0x00, 0x20, // STA $2000
+            0x4C, 0x05, 0x80, // JMP $8005
+        ];
+        #[rustfmt::skip]
+        let nmi = [
+            0xE6, 0x00, // INC $00
+            0x40,       // RTI
+        ];
+        let mut bus = Bus16::default();
+        bus.insert_cartridge(cartridge(&program, &nmi));
+        bus.reset();
+
+        for _ in 0..3 {
+            bus.run_frame();
+        }
+        assert_eq!(bus.read(0x0000), 3);
+    }
+
+    #[test]
+    fn test_apu_frame_irq() {
+        #[rustfmt::skip]
+        let program = [
+            0x58,             // CLI
+            0x4C, 0x01, 0x80, // JMP $8001
+        ];
+        #[rustfmt::skip]
+        let irq = [
+            0xE6, 0x00,       // INC $00
+            0xAD, 0x15, 0x40, // LDA $4015, acknowledges the frame IRQ
+            0x40,             // RTI
+        ];
+        let mut bus = Bus16::default();
+        bus.insert_cartridge(cartridge(&program, &irq));
+        bus.reset();
+
+        // the 4 step frame counter raises its IRQ 29828 cycles in and then every
+        // 29830. The handler's INC lands a few cycles later, once the JMP in
+        // flight, the 7 cycle interrupt sequence and the INC itself are done
+        let mut taken = vec![];
+        for cycle in 0..90_000 {
+            bus.clock();
+            if bus.read(0x0000) as usize != taken.len() {
+                taken.push(cycle);
+            }
+        }
+        assert_eq!(taken, vec![29836, 29836 + 29830, 29836 + 2 * 29830]);
+    }
+
+    /// a 32K NROM cartridge with CHR RAM running `source`, assembled from $8000.
+    /// The source sets its own vectors at $FFFA
+    fn assembled(source: &str) -> Bus16 {
+        assembled_on(rom(0, 2, 0, 0), source)
+    }
+
+    /// the cartridge in `bytes` running `source`, which goes at the end of PRG
+    /// ROM, where mappers keep their fixed bank
+    fn assembled_on(mut bytes: Vec<u8>, source: &str) -> Bus16 {
+        let program = crate::assemble(&format!(".org $8000\n{}", source)).unwrap();
+        let prg_end = 16 + bytes[4] as usize * 0x4000;
+        for segment in program.segments() {
+            let start = prg_end - (0x10000 - segment.origin as usize);
+            bytes[start..start + segment.bytes.len()].copy_from_slice(&segment.bytes);
+        }
+
+        let mut bus = Bus16::default();
+        bus.insert_cartridge(Cartridge::from_bytes(&bytes).unwrap());
+        bus.reset();
+        bus
+    }
+
+    /// the CPU cycles between the first and the 61st vblank NMI with `mask` in $2001
+    fn sixty_frames(mask: u8) -> usize {
+        let mut bus = assembled(&format!(
+            "
+            reset:  lda #$80
+                    sta $2000
+                    lda #{}
+                    sta $2001
+            loop:   jmp loop
+            nmi:    inc $00
+                    rti
+                    .org $FFFA
+                    .word nmi, reset, reset
+            ",
+            mask
+        ));
+
+        let mut nmis = vec![];
+        for cycle in 0.. {
+            bus.clock();
+            if bus.read(0x0000) as usize != nmis.len() {
+                nmis.push(cycle);
+                if nmis.len() == 61 {
+                    break;
+                }
+            }
+        }
+        nmis[60] - nmis[0]
+    }
+
+    #[test]
+    fn test_frame_timing() {
+        // 262 lines of 341 dots, 3 dots to a CPU cycle. The NMI waits for the
+        // JMP in flight, so it can come up to 3 cycles late
+        let frames = 60 * 262 * 341 / 3;
+        assert!(sixty_frames(0x00).abs_diff(frames) <= 3);
+
+        // with rendering on, every odd frame is a dot shorter
+        let frames = (60 * 262 * 341 - 30) / 3;
+        assert!(sixty_frames(0x18).abs_diff(frames) <= 3);
+    }
+
+    #[test]
+    fn test_sprite_flag_timing() {
+        let mut bus = assembled(
+            "
+            reset:  lda #0
+                    sta $2000
+                    sta $2001
+                    ; tile 1 is solid colour 1
+                    sta $2006
+                    lda #$10
+                    sta $2006
+                    ldx #8
+                    lda #$FF
+            low:    sta $2007
+                    dex
+                    bne low
+                    ldx #8
+                    lda #0
+            high:   sta $2007
+                    dex
+                    bne high
+                    ; at row 2, column 2 of the background
+                    lda #$20
+                    sta $2006
+                    lda #$42
+                    sta $2006
+                    lda #1
+                    sta $2007
+
+                    ; sprites off screen but for the ones in the table
+                    ldx #0
+                    lda #$FF
+            clear:  sta $0200,x
+                    inx
+                    bne clear
+            copy:   lda sprites,x
+                    sta $0200,x
+                    inx
+                    cpx #40
+                    bne copy
+                    lda #$02
+                    sta $4014
+
+                    lda #0
+                    sta $2005
+                    sta $2005
+                    lda #$1E
+                    sta $2001
+
+                    ; skip to a frame that starts with the flags clear
+            vblank: bit $2002
+                    bpl vblank
+            stale:  bit $2002
+                    bvs stale
+            hit:    bit $2002
+                    bvc hit
+                    inc $00
+            over:   lda $2002
+                    and #$20
+                    beq over
+                    inc $01
+            done:   jmp done
+
+            ; y, tile, attributes, x. Sprite 0 overlaps the background tile
+            ; and 9 sprites share the lines from 101
+            sprites:
+                    .byte 15, 1, 0, 20
+                    .byte 100, 1, 0, 0, 100, 1, 0, 10, 100, 1, 0, 20
+                    .byte 100, 1, 0, 30, 100, 1, 0, 40, 100, 1, 0, 50
+                    .byte 100, 1, 0, 60, 100, 1, 0, 70, 100, 1, 0, 80
+                    .org $FFFA
+                    .word reset, reset, reset
+            ",
+        );
+
+        let mut seen = [None; 2];
+        for _ in 0..3 * 29781 {
+            bus.clock();
+            for (addr, seen) in seen.iter_mut().enumerate() {
+                if seen.is_none() && bus.read(addr as u16) != 0 {
+                    *seen = Some((bus.ppu.scanline(), bus.ppu.cycle()));
+                }
+            }
+        }
+
+        // the polling loops notice a flag within 25 CPU cycles, 75 dots. The hit is
+        // at x = 20 on line 16, drawn on dot 21, and the overflow is found on
+        // dot 257 of line 100 while picking the sprites for the next line
+        let (line, dot) = seen[0].expect("sprite 0 hit");
+        assert_eq!(line, 16);
+        assert!((21..21 + 75).contains(&dot), "hit seen on dot {}", dot);
+        let (line, dot) = seen[1].expect("sprite overflow");
+        assert_eq!(line, 100);
+        assert!(
+            (257..257 + 75).contains(&dot),
+            "overflow seen on dot {}",
+            dot
+        );
+    }
+
+    /// runs `cycles` CPU cycles and gives the first one after which $00 and $01
+    /// are non zero, for programs that mark events there
+    fn marks(bus: &mut Bus16, cycles: usize) -> [Option<usize>; 2] {
+        let mut marks = [None; 2];
+        for cycle in 0..cycles {
+            bus.clock();
+            for (addr, mark) in marks.iter_mut().enumerate() {
+                if mark.is_none() && bus.read(addr as u16) != 0 {
+                    *mark = Some(cycle);
+                }
+            }
+        }
+        marks
+    }
+
+    /// the CPU cycles from writing `frame_counter` to $4017 until a length
+    /// counter loaded with 2 runs out
+    fn length_expiry(frame_counter: u8) -> usize {
+        let mut bus = assembled(&format!(
+            "
+            reset:  lda #$01
+                    sta $4015
+                    lda #$10        ; length counter not halted
+                    sta $4000
+                    lda #$18        ; length index 3, 2 half frames
+                    sta $4003
+                    lda #${:02X}
+                    sta $4017
+                    inc $01
+            wait:   lda $4015
+                    and #$01
+                    bne wait
+                    inc $00
+            done:   jmp done
+                    .org $FFFA
+                    .word reset, reset, reset
+            ",
+            frame_counter
+        ));
+
+        let [Some(expired), Some(written)] = marks(&mut bus, 40_000) else {
+            panic!("the length counter never ran out");
+        };
+        // the INC marking the write lands 5 cycles after it
+        expired - (written - 5)
+    }
+
+    #[test]
+    fn test_apu_length_counter() {
+        // the 4 step sequence clocks length counters 14913 and 29829 cycles in.
+        // A poll of $4015 every 9 cycles and the INC after it take up to 20
+        // cycles to notice
+        assert!((29829..29829 + 20).contains(&length_expiry(0x00)));
+
+        // 5 step mode clocks them right away when $4017 is written, and then
+        // 14913 cycles in
+        assert!((14913..14913 + 20).contains(&length_expiry(0x80)));
+    }
+
+    #[test]
+    fn test_apu_dmc_fetches_and_irq() {
+        let mut bus = assembled(
+            "
+            reset:  lda #$40        ; no frame counter IRQs
+                    sta $4017
+                    lda #$8F        ; IRQ at the end, 432 cycles a byte
+                    sta $4010
+                    lda #$00        ; from $C000
+                    sta $4012
+                    lda #$01        ; 17 bytes
+                    sta $4013
+                    lda #$10
+                    sta $4015
+                    cli
+            loop:   jmp loop
+
+            ; the first IRQ marks $01 and plays the sample again, the second marks $00
+            irq:    lda $01
+                    bne second
+                    inc $01
+                    lda #$10        ; acknowledges the IRQ too
+                    sta $4015
+                    rti
+            second: inc $00
+                    lda #$00
+                    sta $4015
+                    rti
+                    .org $C000
+                    .byte $FF, $FF, $FF, $FF, $FF, $FF, $FF, $FF
+                    .byte $FF, $FF, $FF, $FF, $FF, $FF, $FF, $FF, $FF
+                    .org $FFFA
+                    .word reset, reset, irq
+            ",
+        );
+
+        // the IRQ comes with the fetch of the last byte. Played again, the
+        // first byte is fetched once the 16th is out and the buffer is free, so
+        // each byte of the second run is fetched 432 cycles after the last
+        let [Some(second), Some(first)] = marks(&mut bus, 20_000) else {
+            panic!("fewer than two DMC IRQs");
+        };
+        let elapsed = second - first;
+        assert!(elapsed.abs_diff(17 * 432) <= 10, "{}", elapsed);
+        assert_eq!(bus.read(0x0000), 1);
+    }
+
+    #[test]
+    fn test_apu_pulse_tone() {
+        let mut bus = assembled(
+            "
+            reset:  lda #$01
+                    sta $4015
+                    lda #$BF        ; 50% duty, halted, constant volume 15
+                    sta $4000
+                    lda #$FD        ; period 253, 1789773 / 16 / 254 = 440.4Hz
+                    sta $4002
+                    lda #$00
+                    sta $4003
+            done:   jmp done
+                    .org $FFFA
+                    .word reset, reset, reset
+            ",
+        );
+        for _ in 0..CPU_CLOCK / 4 {
+            bus.clock();
+        }
+
+        // skip the first 50ms while the filters settle, then count zero crossings
+        let rate = bus.apu.sample_rate() as f32;
+        let samples: Vec<f32> = bus.apu.samples.drain().skip(rate as usize / 20).collect();
+        let crossings = samples
+            .windows(2)
+            .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+            .count();
+        let frequency = crossings as f32 / 2.0 / (samples.len() as f32 / rate);
+        assert!((frequency - 440.4).abs() < 5.0, "{}Hz", frequency);
+    }
+
+    /// the first byte of the 8 PRG banks a program switched to in turn and
+    /// stored at $0300
+    fn banks_read(bus: &mut Bus16) -> Vec<u8> {
+        for _ in 0..2000 {
+            bus.clock();
+        }
+        (0..8).map(|i| bus.read(0x0300 + i)).collect()
+    }
+
+    #[test]
+    fn test_uxrom_program() {
+        let mut bus = assembled_on(
+            rom(2, 8, 0, 0),
+            "
+                    .org $C100
+            reset:  ldy #0
+            next:   tya
+                    sta banks,y     ; over the same value, so no bus conflict
+                    lda $8000
+                    sta $0300,y
+                    iny
+                    cpy #8
+                    bne next
+            done:   jmp done
+            banks:  .byte 0, 1, 2, 3, 4, 5, 6, 7
+                    .org $FFFA
+                    .word reset, reset, reset
+            ",
+        );
+        assert_eq!(banks_read(&mut bus), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_mmc1_program() {
+        let mut bus = assembled_on(
+            rom(1, 8, 0, 0),
+            "
+                    .org $C100
+            reset:  lda #$80        ; resets the shift register
+                    sta $8000
+                    ldy #0
+            next:   tya
+                    jsr prg_bank
+                    lda $8000
+                    sta $0300,y
+                    iny
+                    cpy #8
+                    bne next
+                    lda #%01110     ; vertical mirroring, same PRG mode
+                    jsr control
+            done:   jmp done
+
+            ; the registers take 5 writes, one bit at a time from bit 0
+            prg_bank:
+                    ldx #5
+            prg_bit:
+                    sta $E000
+                    lsr a
+                    dex
+                    bne prg_bit
+                    rts
+            control:
+                    ldx #5
+            control_bit:
+                    sta $8000
+                    lsr a
+                    dex
+                    bne control_bit
+                    rts
+                    .org $FFFA
+                    .word reset, reset, reset
+            ",
+        );
+        assert_eq!(banks_read(&mut bus), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(bus.cartridge().unwrap().mirroring(), Mirroring::Vertical);
+    }
+
+    #[test]
+    fn test_mmc3_program() {
+        let mut bus = assembled_on(
+            rom(4, 4, 0, 0),
+            "
+                    .org $E100
+            reset:  lda #$40        ; no frame counter IRQs
+                    sta $4017
+                    ldy #0
+            next:   lda #6          ; R6 picks the 8K bank at $8000
+                    sta $8000
+                    sty $8001
+                    lda $8000
+                    sta $0300,y
+                    iny
+                    cpy #8
+                    bne next
+
+                    ; from the next frame, an IRQ 100 rendered lines in
+            vblank: bit $2002
+                    bpl vblank
+                    lda #100
+                    sta $C000
+                    sta $C001
+                    sta $E001
+                    lda #$18
+                    sta $2001
+                    cli
+            done:   jmp done
+            irq:    sta $E000       ; acknowledges and disables it
+                    inc $00
+                    rti
+                    .org $FFFA
+                    .word reset, reset, irq
+            ",
+        );
+
+        // 8K banks of the 16K ones filled with their number
+        assert_eq!(banks_read(&mut bus), vec![0, 0, 1, 1, 2, 2, 3, 3]);
+
+        let mut irq = None;
+        for _ in 0..2 * 29781 {
+            bus.clock();
+            if irq.is_none() && bus.read(0x0000) != 0 {
+                irq = Some((bus.ppu.scanline(), bus.ppu.cycle()));
+            }
+        }
+        // the counter is reloaded on the pre-render line and reaches 0 at dot
+        // 260 of line 99. The handler's INC lands within 20 cycles, 60 dots
+        let (line, dot) = irq.expect("MMC3 IRQ");
+        assert_eq!(line, 99);
+        assert!((260..260 + 60).contains(&dot), "IRQ seen on dot {}", dot);
+        assert_eq!(bus.read(0x0000), 1);
+    }
+
+    /// Runs every ROM in assets/nes/ppu for 60 frames and compares the frame hash
+    /// against the hex value in the .hash file next to it
+    #[test]
+    #[ignore = "requires test ROMs and hashes in assets/nes/ppu"]
+    fn test_frame_hashes() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/nes/ppu");
+        for entry in std::fs::read_dir(dir).expect(dir) {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "nes") {
+                continue;
+            }
+
+            let expected = std::fs::read_to_string(path.with_extension("hash")).expect("hash");
+            let expected = u64::from_str_radix(expected.trim(), 16).expect("hash");
+
+            let mut bus = Bus16::default();
+            bus.insert_cartridge(Cartridge::load(&path).unwrap());
+            bus.reset();
+            for _ in 0..60 {
+                bus.run_frame();
+            }
+            assert_eq!(bus.ppu.frame_hash(), expected, "{}", path.display());
+        }
+    }
+
+    /// Renders 120 frames of every ROM in assets/nes/apu to WAV and compares
+    /// it byte for byte with the .wav reference next to it
+    #[test]
+    #[ignore = "requires test ROMs and reference WAVs in assets/nes/apu"]
+    fn test_audio_references() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/nes/apu");
+        for entry in std::fs::read_dir(dir).expect(dir) {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "nes") {
+                continue;
+            }
+
+            let mut bus = Bus16::default();
+            bus.insert_cartridge(Cartridge::load(&path).unwrap());
+            bus.reset();
+            let mut samples = Vec::new();
+            for _ in 0..120 {
+                bus.run_frame();
+                samples.extend(bus.apu.samples.drain());
+            }
+
+            let mut wav = Vec::new();
+            write_wav(&mut wav, bus.apu.sample_rate(), &samples).unwrap();
+            let expected = std::fs::read(path.with_extension("wav")).expect("reference wav");
+            assert!(wav == expected, "{}", path.display());
+        }
     }
 }
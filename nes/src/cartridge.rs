@@ -0,0 +1,536 @@
+use crate::mapper::{self, CpuMapped, Mapper};
+use crate::{SaveState, SaveStateError, StateReader, StateWriter};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"NES\x1A";
+const HEADER_SIZE: usize = 16;
+const TRAINER_SIZE: usize = 512;
+const PRG_BANK_SIZE: usize = 16 * 1024;
+const CHR_BANK_SIZE: usize = 8 * 1024;
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(std::io::Error),
+    InvalidMagic,
+    /// the file is shorter than its header says
+    Truncated {
+        expected: usize,
+        actual: usize,
+    },
+    UnsupportedMapper(u16),
+    /// the header declares no PRG ROM, so there's nothing for the CPU to run
+    MissingPrgRom,
+    /// a ROM size in the header is too big to address
+    SizeOverflow,
+}
+
+impl From<std::io::Error> for CartridgeError {
+    fn from(value: std::io::Error) -> Self {
+        CartridgeError::Io(value)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+    SingleScreenLow,
+    SingleScreenHigh,
+    /// the cartridge provides the extra 2K of nametable memory
+    FourScreen,
+}
+
+impl Mirroring {
+    /// maps a nametable address ($2000-$2FFF) to an offset into 2K of VRAM
+    pub fn vram_offset(&self, addr: u16) -> usize {
+        let addr = (addr & 0x0FFF) as usize;
+        let table = addr / 0x400;
+        let offset = addr % 0x400;
+        match self {
+            Mirroring::Horizontal => (table / 2) * 0x400 + offset,
+            Mirroring::Vertical => (table % 2) * 0x400 + offset,
+            Mirroring::SingleScreenLow => offset,
+            Mirroring::SingleScreenHigh => 0x400 + offset,
+            Mirroring::FourScreen => addr,
+        }
+    }
+}
+
+impl SaveState for Mirroring {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.u8(match self {
+            Mirroring::Horizontal => 0,
+            Mirroring::Vertical => 1,
+            Mirroring::SingleScreenLow => 2,
+            Mirroring::SingleScreenHigh => 3,
+            Mirroring::FourScreen => 4,
+        });
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        *self = match state.u8()? {
+            0 => Mirroring::Horizontal,
+            1 => Mirroring::Vertical,
+            2 => Mirroring::SingleScreenLow,
+            3 => Mirroring::SingleScreenHigh,
+            4 => Mirroring::FourScreen,
+            _ => return Err(SaveStateError::InvalidData("mirroring")),
+        };
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+    INes,
+    Nes20,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Header {
+    pub format: Format,
+    pub mapper: u16,
+    pub submapper: u8,
+    pub prg_rom_size: usize,
+    pub chr_rom_size: usize,
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+    pub mirroring: Mirroring,
+    pub battery: bool,
+    pub trainer: bool,
+}
+
+impl Header {
+    pub fn parse(bytes: &[u8]) -> Result<Header, CartridgeError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(CartridgeError::Truncated {
+                expected: HEADER_SIZE,
+                actual: bytes.len(),
+            });
+        }
+        if &bytes[0..4] != MAGIC {
+            return Err(CartridgeError::InvalidMagic);
+        }
+
+        let flags6 = bytes[6];
+        let flags7 = bytes[7];
+        let format = match flags7 & 0x0C {
+            0x08 => Format::Nes20,
+            _ => Format::INes,
+        };
+
+        let mirroring = match (flags6 & 0x08 != 0, flags6 & 0x01 != 0) {
+            (true, _) => Mirroring::FourScreen,
+            (false, true) => Mirroring::Vertical,
+            (false, false) => Mirroring::Horizontal,
+        };
+        let battery = flags6 & 0x02 != 0;
+        let trainer = flags6 & 0x04 != 0;
+        let mapper_lo = ((flags7 & 0xF0) | (flags6 >> 4)) as u16;
+
+        let header = match format {
+            Format::INes => Header {
+                format,
+                mapper: mapper_lo,
+                submapper: 0,
+                prg_rom_size: bytes[4] as usize * PRG_BANK_SIZE,
+                chr_rom_size: bytes[5] as usize * CHR_BANK_SIZE,
+                // 0 means 8K for compatibility. iNES can't tell volatile and battery backed RAM apart
+                prg_ram_size: match battery {
+                    true => 0,
+                    false => bytes[8].max(1) as usize * 8 * 1024,
+                },
+                prg_nvram_size: match battery {
+                    true => bytes[8].max(1) as usize * 8 * 1024,
+                    false => 0,
+                },
+                chr_ram_size: match bytes[5] {
+                    0 => CHR_BANK_SIZE,
+                    _ => 0,
+                },
+                chr_nvram_size: 0,
+                mirroring,
+                battery,
+                trainer,
+            },
+            Format::Nes20 => Header {
+                format,
+                mapper: mapper_lo | ((bytes[8] & 0x0F) as u16) << 8,
+                submapper: bytes[8] >> 4,
+                prg_rom_size: nes20_rom_size(bytes[4], bytes[9] & 0x0F, PRG_BANK_SIZE)
+                    .ok_or(CartridgeError::SizeOverflow)?,
+                chr_rom_size: nes20_rom_size(bytes[5], bytes[9] >> 4, CHR_BANK_SIZE)
+                    .ok_or(CartridgeError::SizeOverflow)?,
+                prg_ram_size: nes20_ram_size(bytes[10] & 0x0F),
+                prg_nvram_size: nes20_ram_size(bytes[10] >> 4),
+                chr_ram_size: nes20_ram_size(bytes[11] & 0x0F),
+                chr_nvram_size: nes20_ram_size(bytes[11] >> 4),
+                mirroring,
+                battery,
+                trainer,
+            },
+        };
+
+        Ok(header)
+    }
+}
+
+/// NES 2.0 sizes are either a bank count split across two bytes,
+/// or when the high nibble is $F, an exponent-multiplier pair: 2^E * (MM * 2 + 1).
+/// None if that doesn't fit in a `usize`
+fn nes20_rom_size(lsb: u8, msb: u8, bank_size: usize) -> Option<usize> {
+    match msb {
+        0x0F => {
+            let exponent = (lsb >> 2) as u32;
+            let multiplier = (lsb & 0x03) as usize * 2 + 1;
+            2usize.checked_pow(exponent)?.checked_mul(multiplier)
+        }
+        _ => (((msb as usize) << 8) | lsb as usize).checked_mul(bank_size),
+    }
+}
+
+/// NES 2.0 RAM sizes are shift counts: 64 << shift, 0 meaning none
+fn nes20_ram_size(shift: u8) -> usize {
+    match shift {
+        0 => 0,
+        shift => 64 << shift,
+    }
+}
+
+pub struct Cartridge {
+    pub header: Header,
+    prg_rom: Vec<u8>,
+    /// CHR ROM, or CHR RAM when the cartridge has no CHR ROM
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    /// volatile and battery backed PRG RAM, mapped at $6000-$7FFF
+    prg_ram: Vec<u8>,
+    mapper: Box<dyn Mapper>,
+}
+
+impl Cartridge {
+    pub fn load(path: impl AsRef<Path>) -> Result<Cartridge, CartridgeError> {
+        let bytes = std::fs::read(path)?;
+        Cartridge::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Cartridge, CartridgeError> {
+        let header = Header::parse(bytes)?;
+        if header.prg_rom_size == 0 {
+            return Err(CartridgeError::MissingPrgRom);
+        }
+
+        let trainer_size = match header.trainer {
+            true => TRAINER_SIZE,
+            false => 0,
+        };
+        let prg_start = HEADER_SIZE + trainer_size;
+        let chr_start = prg_start
+            .checked_add(header.prg_rom_size)
+            .ok_or(CartridgeError::SizeOverflow)?;
+        let chr_end = chr_start
+            .checked_add(header.chr_rom_size)
+            .ok_or(CartridgeError::SizeOverflow)?;
+        if bytes.len() < chr_end {
+            return Err(CartridgeError::Truncated {
+                expected: chr_end,
+                actual: bytes.len(),
+            });
+        }
+
+        let mapper = mapper::new(&header)?;
+
+        let prg_rom = bytes[prg_start..chr_start].to_vec();
+        let chr_is_ram = header.chr_rom_size == 0;
+        let chr = match chr_is_ram {
+            true => vec![0; (header.chr_ram_size + header.chr_nvram_size).max(CHR_BANK_SIZE)],
+            false => bytes[chr_start..chr_end].to_vec(),
+        };
+        let mut prg_ram = vec![0; (header.prg_ram_size + header.prg_nvram_size).max(8 * 1024)];
+
+        // the trainer lives at $7000-$71FF
+        if header.trainer {
+            prg_ram[0x1000..0x1000 + TRAINER_SIZE]
+                .copy_from_slice(&bytes[HEADER_SIZE..HEADER_SIZE + TRAINER_SIZE]);
+        }
+
+        Ok(Cartridge {
+            header,
+            prg_rom,
+            chr,
+            chr_is_ram,
+            prg_ram,
+            mapper,
+        })
+    }
+
+    /// Reads from the cartridge's part of the CPU address space ($4020-$FFFF).
+    /// `None` when nothing on the cartridge responds (open bus).
+    pub fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match self.mapper.cpu_map_read(addr) {
+            CpuMapped::PrgRom(offset) => Some(self.prg_rom[offset % self.prg_rom.len()]),
+            CpuMapped::PrgRam(offset) => Some(self.prg_ram[offset % self.prg_ram.len()]),
+            CpuMapped::None => None,
+        }
+    }
+
+    pub fn cpu_write(&mut self, addr: u16, data: u8) {
+        match self.mapper.cpu_map_write(addr, data) {
+            CpuMapped::PrgRam(offset) => {
+                let len = self.prg_ram.len();
+                self.prg_ram[offset % len] = data;
+            }
+            CpuMapped::PrgRom(_) | CpuMapped::None => {}
+        }
+    }
+
+    /// Reads from the pattern tables ($0000-$1FFF of the PPU address space)
+    pub fn ppu_read(&mut self, addr: u16) -> u8 {
+        let offset = self.mapper.ppu_map(addr);
+        self.chr[offset % self.chr.len()]
+    }
+
+    pub fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_is_ram {
+            let offset = self.mapper.ppu_map(addr) % self.chr.len();
+            self.chr[offset] = data;
+        }
+    }
+
+    /// the mapper's current nametable arrangement, falling back to the one soldered on the board
+    pub fn mirroring(&self) -> Mirroring {
+        match self.header.mirroring {
+            Mirroring::FourScreen => Mirroring::FourScreen,
+            soldered => self.mapper.mirroring().unwrap_or(soldered),
+        }
+    }
+
+    pub fn irq_pending(&self) -> bool {
+        self.mapper.irq_pending()
+    }
+
+    pub fn irq_clear(&mut self) {
+        self.mapper.irq_clear();
+    }
+
+    /// Called by the PPU at the end of every visible scanline while rendering
+    pub fn scanline(&mut self) {
+        self.mapper.scanline();
+    }
+
+    pub fn reset(&mut self) {
+        self.mapper.reset();
+    }
+
+    /// PRG RAM contents worth persisting between sessions
+    pub fn battery_ram(&self) -> Option<&[u8]> {
+        match self.header.battery {
+            true => Some(&self.prg_ram),
+            false => None,
+        }
+    }
+
+    pub fn load_battery_ram(&mut self, data: &[u8]) {
+        let len = data.len().min(self.prg_ram.len());
+        self.prg_ram[..len].copy_from_slice(&data[..len]);
+    }
+
+    /// FNV-1a over the ROM contents, so a save state can't be loaded into another game
+    fn fingerprint(&self) -> u64 {
+        let rom = match self.chr_is_ram {
+            true => &[][..],
+            false => &self.chr[..],
+        };
+        self.prg_rom
+            .iter()
+            .chain(rom)
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+}
+
+/// RAM and mapper registers. ROM is only fingerprinted
+impl SaveState for Cartridge {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.u64(self.fingerprint());
+        state.bytes(&self.prg_ram);
+        if self.chr_is_ram {
+            state.bytes(&self.chr);
+        }
+        self.mapper.save_state(state);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        if state.u64()? != self.fingerprint() {
+            return Err(SaveStateError::CartridgeMismatch);
+        }
+        state.bytes(&mut self.prg_ram)?;
+        if self.chr_is_ram {
+            state.bytes(&mut self.chr)?;
+        }
+        self.mapper.load_state(state)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// builds an iNES image with every PRG bank filled with its bank number
+    /// and every CHR bank filled with $80 + its bank number
+    pub(crate) fn rom(mapper: u8, prg_banks: u8, chr_banks: u8, flags6: u8) -> Vec<u8> {
+        let mut bytes = vec![
+            b'N',
+            b'E',
+            b'S',
+            0x1A,
+            prg_banks,
+            chr_banks,
+            (mapper << 4) | flags6,
+            mapper & 0xF0,
+        ];
+        bytes.resize(HEADER_SIZE, 0);
+        for bank in 0..prg_banks {
+            bytes.extend(std::iter::repeat_n(bank, PRG_BANK_SIZE));
+        }
+        for bank in 0..chr_banks {
+            bytes.extend(std::iter::repeat_n(0x80 + bank, CHR_BANK_SIZE));
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_ines_header() {
+        let header = Header::parse(&rom(4, 8, 16, 0x03)).unwrap();
+        assert_eq!(
+            header,
+            Header {
+                format: Format::INes,
+                mapper: 4,
+                submapper: 0,
+                prg_rom_size: 128 * 1024,
+                chr_rom_size: 128 * 1024,
+                prg_ram_size: 0,
+                prg_nvram_size: 8 * 1024,
+                chr_ram_size: 0,
+                chr_nvram_size: 0,
+                mirroring: Mirroring::Vertical,
+                battery: true,
+                trainer: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_nes20_header() {
+        let mut bytes = rom(1, 2, 0, 0x02);
+        bytes[7] |= 0x08;
+        bytes[8] = 0x51; // submapper 5, mapper $1xx
+        bytes[10] = 0x70; // 8K battery backed PRG RAM
+        bytes[11] = 0x07; // 8K CHR RAM
+
+        let header = Header::parse(&bytes).unwrap();
+        assert_eq!(header.format, Format::Nes20);
+        assert_eq!(header.mapper, 0x101);
+        assert_eq!(header.submapper, 5);
+        assert_eq!(header.prg_rom_size, 32 * 1024);
+        assert_eq!(header.prg_ram_size, 0);
+        assert_eq!(header.prg_nvram_size, 8 * 1024);
+        assert_eq!(header.chr_ram_size, 8 * 1024);
+
+        // exponent-multiplier notation: 2^4 * 3
+        assert_eq!(nes20_rom_size(0b0001_0001, 0x0F, PRG_BANK_SIZE), Some(48));
+        assert_eq!(nes20_rom_size(0xFF, 0x0F, PRG_BANK_SIZE), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            Cartridge::from_bytes(b"NES"),
+            Err(CartridgeError::Truncated { .. })
+        ));
+        assert!(matches!(
+            Cartridge::from_bytes(&[0; 16]),
+            Err(CartridgeError::InvalidMagic)
+        ));
+
+        let mut bytes = rom(0, 2, 1, 0);
+        bytes.pop();
+        assert!(matches!(
+            Cartridge::from_bytes(&bytes),
+            Err(CartridgeError::Truncated { .. })
+        ));
+
+        assert!(matches!(
+            Cartridge::from_bytes(&rom(7, 2, 1, 0)),
+            Err(CartridgeError::UnsupportedMapper(7))
+        ));
+
+        assert!(matches!(
+            Cartridge::from_bytes(&rom(0, 0, 1, 0)),
+            Err(CartridgeError::MissingPrgRom)
+        ));
+
+        // NES 2.0 exponent-multiplier sizes: 2^63 * 7 of PRG ROM, and 2^63 each of PRG and CHR ROM
+        let mut bytes = rom(0, 1, 1, 0);
+        bytes[7] |= 0x08;
+        bytes[4] = 0xFF;
+        bytes[9] = 0x0F;
+        assert!(matches!(
+            Cartridge::from_bytes(&bytes),
+            Err(CartridgeError::SizeOverflow)
+        ));
+        bytes[4] = 0xFC;
+        bytes[5] = 0xFC;
+        bytes[9] = 0xFF;
+        assert!(matches!(
+            Cartridge::from_bytes(&bytes),
+            Err(CartridgeError::SizeOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_trainer_and_battery_ram() {
+        let mut bytes = rom(0, 1, 1, 0x06);
+        let trainer: Vec<u8> = (0..TRAINER_SIZE).map(|i| i as u8).collect();
+        bytes.splice(HEADER_SIZE..HEADER_SIZE, trainer);
+
+        let mut cart = Cartridge::from_bytes(&bytes).unwrap();
+        assert_eq!(cart.cpu_read(0x7000), Some(0x00));
+        assert_eq!(cart.cpu_read(0x71FF), Some(0xFF));
+        assert_eq!(cart.cpu_read(0x8000), Some(0x00));
+        assert_eq!(cart.ppu_read(0x0000), 0x80);
+
+        cart.cpu_write(0x6000, 0x42);
+        let saved = cart.battery_ram().unwrap().to_vec();
+        assert_eq!(saved[0], 0x42);
+
+        let mut cart = Cartridge::from_bytes(&bytes).unwrap();
+        cart.load_battery_ram(&saved);
+        assert_eq!(cart.cpu_read(0x6000), Some(0x42));
+    }
+
+    #[test]
+    fn test_chr_ram() {
+        let mut cart = Cartridge::from_bytes(&rom(2, 2, 0, 0)).unwrap();
+        cart.ppu_write(0x1234, 0x55);
+        assert_eq!(cart.ppu_read(0x1234), 0x55);
+
+        let mut cart = Cartridge::from_bytes(&rom(0, 1, 1, 0)).unwrap();
+        cart.ppu_write(0x1234, 0x55);
+        assert_eq!(cart.ppu_read(0x1234), 0x80);
+    }
+
+    #[test]
+    fn test_mirroring() {
+        assert_eq!(Mirroring::Horizontal.vram_offset(0x2400), 0x000);
+        assert_eq!(Mirroring::Horizontal.vram_offset(0x2800), 0x400);
+        assert_eq!(Mirroring::Vertical.vram_offset(0x2400), 0x400);
+        assert_eq!(Mirroring::Vertical.vram_offset(0x2C01), 0x401);
+        assert_eq!(Mirroring::SingleScreenHigh.vram_offset(0x2000), 0x400);
+        assert_eq!(Mirroring::FourScreen.vram_offset(0x2C00), 0xC00);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{SaveState, SaveStateError, StateReader, StateWriter};
+
+/// Button bits, in the order the controller shifts them out
+pub struct Button;
+
+impl Button {
+    pub const A: u8 = 1 << 0;
+    pub const B: u8 = 1 << 1;
+    pub const SELECT: u8 = 1 << 2;
+    pub const START: u8 = 1 << 3;
+    pub const UP: u8 = 1 << 4;
+    pub const DOWN: u8 = 1 << 5;
+    pub const LEFT: u8 = 1 << 6;
+    pub const RIGHT: u8 = 1 << 7;
+}
+
+/// A standard controller: a parallel-in, serial-out shift register.
+///
+/// While the strobe bit written to $4016 is high the register keeps reloading
+/// the buttons, so reads return A. After the strobe drops every read returns
+/// the next button, then 1s once all eight have been shifted out.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Controller {
+    buttons: u8,
+    shift: u8,
+    strobe: bool,
+}
+
+impl Controller {
+    /// the buttons currently held down, a combination of [`Button`] bits
+    pub fn buttons(&self) -> u8 {
+        self.buttons
+    }
+
+    pub fn set_buttons(&mut self, buttons: u8) {
+        self.buttons = buttons;
+        if self.strobe {
+            self.shift = buttons;
+        }
+    }
+
+    pub fn write(&mut self, data: u8) {
+        self.strobe = data & 0x01 != 0;
+        if self.strobe {
+            self.shift = self.buttons;
+        }
+    }
+
+    pub fn read(&mut self) -> u8 {
+        if self.strobe {
+            self.shift = self.buttons;
+        }
+        let bit = self.shift & 0x01;
+        self.shift = (self.shift >> 1) | 0x80;
+        // the upper bits are open bus, which is usually the $40 of the address
+        0x40 | bit
+    }
+}
+
+impl SaveState for Controller {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.u8(self.buttons);
+        state.u8(self.shift);
+        state.bool(self.strobe);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.buttons = state.u8()?;
+        self.shift = state.u8()?;
+        self.strobe = state.bool()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(controller: &mut Controller) -> Vec<u8> {
+        (0..10).map(|_| controller.read() & 0x01).collect()
+    }
+
+    #[test]
+    fn test_serial_shift() {
+        let mut controller = Controller::default();
+        controller.set_buttons(Button::A | Button::START | Button::RIGHT);
+
+        controller.write(1);
+        controller.write(0);
+        assert_eq!(
+            read_all(&mut controller),
+            vec![1, 0, 0, 1, 0, 0, 0, 1, 1, 1]
+        );
+
+        // without a new strobe the register stays empty
+        controller.set_buttons(Button::B);
+        assert_eq!(controller.read() & 0x01, 1);
+        controller.write(1);
+        controller.write(0);
+        assert_eq!(
+            read_all(&mut controller),
+            vec![0, 1, 0, 0, 0, 0, 0, 0, 1, 1]
+        );
+    }
+
+    #[test]
+    fn test_strobe_high_reads_a() {
+        let mut controller = Controller::default();
+        controller.write(1);
+        controller.set_buttons(Button::A | Button::B);
+        assert_eq!(read_all(&mut controller), vec![1; 10]);
+
+        controller.set_buttons(Button::B);
+        assert_eq!(controller.read(), 0x40);
+    }
+}
@@ -1,9 +1,8 @@
-use crate::Bus16;
-use std::{cell::RefCell, rc::Rc};
+use crate::{Bus, SaveState, SaveStateError, StateReader, StateWriter};
+use std::fmt;
 
+#[derive(Default)]
 pub struct OLC6502 {
-    bus: Option<Rc<RefCell<Bus16>>>,
-
     acc: u8,
     x: u8,
     y: u8,
@@ -17,58 +16,229 @@ pub struct OLC6502 {
     addr_rel: u16,
     opcode: u8,
     cycles: u8,
+
+    /// cycles since power on
+    total_cycles: u64,
+}
+
+/// A snapshot of the programmer visible registers
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Registers {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub pc: u16,
+    pub status: u8,
+}
+
+impl fmt::Display for Registers {
+    /// e.g. `A:00 X:00 Y:00 SP:FD PC:C000 P:24 nv-bdIzc`, set flags in uppercase
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags: String = "NV-BDIZC"
+            .chars()
+            .enumerate()
+            .map(|(i, flag)| match self.status & (0x80 >> i) {
+                0 => flag.to_ascii_lowercase(),
+                _ => flag,
+            })
+            .collect();
+        write!(
+            f,
+            "A:{:02X} X:{:02X} Y:{:02X} SP:{:02X} PC:{:04X} P:{:02X} {}",
+            self.a, self.x, self.y, self.sp, self.pc, self.status, flags
+        )
+    }
 }
 
 pub struct Instruction {
     name: &'static str,
-    operation: Box<dyn Fn() -> u8>,
-    addrmode: Box<dyn Fn() -> u8>,
+    operation: fn(&mut OLC6502, &mut dyn Bus) -> u8,
+    addrmode: AddrMode,
     cycles: u8,
 }
 
+impl Instruction {
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn addrmode(&self) -> AddrMode {
+        self.addrmode
+    }
+
+    /// base cycle count, before page crossing and branch penalties
+    pub fn cycles(&self) -> u8 {
+        self.cycles
+    }
+
+    /// false for the unofficial opcodes, which all execute as NOPs
+    pub fn is_official(&self) -> bool {
+        self.name != "???"
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AddrMode {
+    IMP,
+    IMM,
+    ZP0,
+    ZPX,
+    ZPY,
+    REL,
+    ABS,
+    ABX,
+    ABY,
+    IND,
+    IZX,
+    IZY,
+}
+
+impl AddrMode {
+    /// number of bytes following the opcode
+    pub fn operand_bytes(&self) -> u16 {
+        match self {
+            AddrMode::IMP => 0,
+            AddrMode::IMM
+            | AddrMode::ZP0
+            | AddrMode::ZPX
+            | AddrMode::ZPY
+            | AddrMode::REL
+            | AddrMode::IZX
+            | AddrMode::IZY => 1,
+            AddrMode::ABS | AddrMode::ABX | AddrMode::ABY | AddrMode::IND => 2,
+        }
+    }
+}
+
 pub struct FlagsOLC6502;
 
 impl FlagsOLC6502 {
-    const CARRY: u8 = 1 << 0;
-    const ZERO: u8 = 1 << 1;
-    const DISABLE_INTERRUPTS: u8 = 1 << 2;
-    const DECIMAL_MODE: u8 = 1 << 3;
-    const BREAK: u8 = 1 << 4;
-    const UNUSED: u8 = 1 << 5;
-    const OVERFLOW: u8 = 1 << 6;
-    const NEGATIVE: u8 = 1 << 7;
+    pub const CARRY: u8 = 1 << 0;
+    pub const ZERO: u8 = 1 << 1;
+    pub const DISABLE_INTERRUPTS: u8 = 1 << 2;
+    pub const DECIMAL_MODE: u8 = 1 << 3;
+    pub const BREAK: u8 = 1 << 4;
+    pub const UNUSED: u8 = 1 << 5;
+    pub const OVERFLOW: u8 = 1 << 6;
+    pub const NEGATIVE: u8 = 1 << 7;
 }
 
 impl OLC6502 {
-    fn bus(&self) -> std::cell::RefMut<'_, Bus16> {
-        self.bus
-            .as_ref()
-            .expect("Bus not connected to CPU!")
-            .borrow_mut()
+    const STACK_BASE: u16 = 0x0100;
+    const NMI_VECTOR: u16 = 0xFFFA;
+    const RESET_VECTOR: u16 = 0xFFFC;
+    const IRQ_VECTOR: u16 = 0xFFFE;
+
+    pub fn lookup(opcode: u8) -> &'static Instruction {
+        &LOOKUP[opcode as usize]
+    }
+
+    pub fn registers(&self) -> Registers {
+        Registers {
+            a: self.acc,
+            x: self.x,
+            y: self.y,
+            sp: self.stkp,
+            pc: self.pc,
+            status: self.status,
+        }
     }
 
-    pub fn write(&self, addr: u16, data: u8) {
-        self.bus().write(addr, data);
+    pub fn set_registers(&mut self, registers: Registers) {
+        self.acc = registers.a;
+        self.x = registers.x;
+        self.y = registers.y;
+        self.stkp = registers.sp;
+        self.pc = registers.pc;
+        self.status = registers.status;
     }
 
-    pub fn read(&self, addr: u16) -> u8 {
-        self.bus().read(addr)
+    pub fn total_cycles(&self) -> u64 {
+        self.total_cycles
     }
 
     pub fn get_flag(&self, flag: u8) -> u8 {
-        todo!()
+        match self.status & flag {
+            0 => 0,
+            _ => 1,
+        }
     }
 
-    pub fn set_flag(&mut self, flag: u8, val: bool) {}
+    pub fn set_flag(&mut self, flag: u8, val: bool) {
+        match val {
+            true => self.status |= flag,
+            false => self.status &= !flag,
+        }
+    }
+
+    fn set_zn(&mut self, val: u8) {
+        self.set_flag(FlagsOLC6502::ZERO, val == 0);
+        self.set_flag(FlagsOLC6502::NEGATIVE, val & 0x80 != 0);
+    }
+
+    fn push(&mut self, bus: &mut dyn Bus, data: u8) {
+        bus.write(Self::STACK_BASE + self.stkp as u16, data);
+        self.stkp = self.stkp.wrapping_sub(1);
+    }
+
+    fn pop(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.stkp = self.stkp.wrapping_add(1);
+        bus.read(Self::STACK_BASE + self.stkp as u16)
+    }
+
+    fn push_u16(&mut self, bus: &mut dyn Bus, data: u16) {
+        self.push(bus, (data >> 8) as u8);
+        self.push(bus, data as u8);
+    }
+
+    fn pop_u16(&mut self, bus: &mut dyn Bus) -> u16 {
+        let lo = self.pop(bus) as u16;
+        let hi = self.pop(bus) as u16;
+        (hi << 8) | lo
+    }
+}
+
+impl SaveState for OLC6502 {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.u8(self.acc);
+        state.u8(self.x);
+        state.u8(self.y);
+        state.u8(self.stkp);
+        state.u16(self.pc);
+        state.u8(self.status);
+        state.u8(self.fetched);
+        state.u16(self.addr_abs);
+        state.u16(self.addr_rel);
+        state.u8(self.opcode);
+        state.u8(self.cycles);
+        state.u64(self.total_cycles);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.acc = state.u8()?;
+        self.x = state.u8()?;
+        self.y = state.u8()?;
+        self.stkp = state.u8()?;
+        self.pc = state.u16()?;
+        self.status = state.u8()?;
+        self.fetched = state.u8()?;
+        self.addr_abs = state.u16()?;
+        self.addr_rel = state.u16()?;
+        self.opcode = state.u8()?;
+        self.cycles = state.u8()?;
+        self.total_cycles = state.u64()?;
+        Ok(())
+    }
 }
 
 impl OLC6502 {
-    pub fn clock(&mut self) {
+    pub fn clock(&mut self, bus: &mut dyn Bus) {
         if self.cycles == 0 {
-            let opcode = self.read(self.pc);
-            self.pc += 1;
+            let opcode = bus.read(self.pc);
+            self.pc = self.pc.wrapping_add(1);
 
-            let additional_cycles_required = self.execute(opcode);
+            let additional_cycles_required = self.execute(bus, opcode);
 
             if additional_cycles_required {
                 self.cycles += 1;
@@ -76,288 +246,1299 @@ impl OLC6502 {
         }
 
         self.cycles -= 1;
+        self.total_cycles += 1;
+        bus.tick();
+    }
+
+    /// true when the current instruction (or interrupt) has used up all its cycles
+    pub fn complete(&self) -> bool {
+        self.cycles == 0
     }
 
-    pub fn reset() {}
-    pub fn irq() {}
-    pub fn nmi() {}
+    pub fn reset(&mut self, bus: &mut dyn Bus) {
+        self.pc = read_u16(bus, Self::RESET_VECTOR);
 
-    pub fn fetch() -> u8 {
-        0
+        self.acc = 0;
+        self.x = 0;
+        self.y = 0;
+        self.stkp = 0xFD;
+        self.status = FlagsOLC6502::UNUSED | FlagsOLC6502::DISABLE_INTERRUPTS;
+
+        self.fetched = 0;
+        self.addr_abs = 0;
+        self.addr_rel = 0;
+
+        self.cycles = 7;
+    }
+
+    /// Interrupt request. ignored while interrupts are disabled
+    pub fn irq(&mut self, bus: &mut dyn Bus) {
+        if self.get_flag(FlagsOLC6502::DISABLE_INTERRUPTS) == 0 {
+            self.interrupt(bus, Self::IRQ_VECTOR);
+        }
+    }
+
+    /// Non maskable interrupt
+    pub fn nmi(&mut self, bus: &mut dyn Bus) {
+        self.interrupt(bus, Self::NMI_VECTOR);
+    }
+
+    fn interrupt(&mut self, bus: &mut dyn Bus, vector: u16) {
+        self.push_u16(bus, self.pc);
+        self.push(
+            bus,
+            (self.status | FlagsOLC6502::UNUSED) & !FlagsOLC6502::BREAK,
+        );
+        self.set_flag(FlagsOLC6502::DISABLE_INTERRUPTS, true);
+
+        self.pc = read_u16(bus, vector);
+        self.cycles = 7;
+    }
+
+    pub fn fetch(&mut self, bus: &mut dyn Bus) -> u8 {
+        if LOOKUP[self.opcode as usize].addrmode != AddrMode::IMP {
+            self.fetched = bus.read(self.addr_abs);
+        }
+        self.fetched
     }
 }
 
 impl OLC6502 {
-    pub fn execute(&mut self, opcode: u8) -> bool {
-        true
-    }
-
-    // pub fn lookup() -> [Instruction; 3] {
-    //     [
-    // Instruction {
-    //     name: "brk",
-    //     operation: Box::new(Self::brk),
-    //     addrmode: Box::new(Self::imm),
-    //     cycles: 7,
-    // },
-    //         Instruction {
-    //             name: todo!(),
-    //             operation: todo!(),
-    //             addrmode: todo!(),
-    //             cycles: todo!(),
-    //         },
-    //         Instruction {
-    //             name: todo!(),
-    //             operation: todo!(),
-    //             addrmode: todo!(),
-    //             cycles: todo!(),
-    //         },
-    //     ]
-    // }
+    pub fn execute(&mut self, bus: &mut dyn Bus, opcode: u8) -> bool {
+        let instruction = &LOOKUP[opcode as usize];
+
+        self.opcode = opcode;
+        self.cycles = instruction.cycles;
+        self.set_flag(FlagsOLC6502::UNUSED, true);
+
+        let additional_cycle_addrmode = match instruction.addrmode {
+            AddrMode::IMP => self.imp(bus),
+            AddrMode::IMM => self.imm(bus),
+            AddrMode::ZP0 => self.zp0(bus),
+            AddrMode::ZPX => self.zpx(bus),
+            AddrMode::ZPY => self.zpy(bus),
+            AddrMode::REL => self.rel(bus),
+            AddrMode::ABS => self.abs(bus),
+            AddrMode::ABX => self.abx(bus),
+            AddrMode::ABY => self.aby(bus),
+            AddrMode::IND => self.ind(bus),
+            AddrMode::IZX => self.izx(bus),
+            AddrMode::IZY => self.izy(bus),
+        };
+        let additional_cycle_operation = (instruction.operation)(self, bus);
+
+        self.set_flag(FlagsOLC6502::UNUSED, true);
+
+        // the page crossing penalty only applies to instructions that
+        // just read their operand. stores and read-modify-write
+        // instructions always take the longer path
+        additional_cycle_addrmode & additional_cycle_operation != 0
+    }
 }
 
 /// Addressing Modes
 impl OLC6502 {
-    pub fn imp() -> u8 {
+    pub fn imp(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.fetched = self.acc;
         0
     }
-    pub fn imm() -> u8 {
+    pub fn imm(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.addr_abs = self.pc;
+        self.pc = self.pc.wrapping_add(1);
         0
     }
-    pub fn zp0() -> u8 {
+    pub fn zp0(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.addr_abs = bus.read(self.pc) as u16;
+        self.pc = self.pc.wrapping_add(1);
         0
     }
-    pub fn zpx() -> u8 {
+    pub fn zpx(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.addr_abs = bus.read(self.pc).wrapping_add(self.x) as u16;
+        self.pc = self.pc.wrapping_add(1);
         0
     }
-    pub fn zpy() -> u8 {
+    pub fn zpy(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.addr_abs = bus.read(self.pc).wrapping_add(self.y) as u16;
+        self.pc = self.pc.wrapping_add(1);
         0
     }
-    pub fn rel() -> u8 {
+    pub fn rel(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.addr_rel = bus.read(self.pc) as i8 as u16;
+        self.pc = self.pc.wrapping_add(1);
         0
     }
-    pub fn abs() -> u8 {
+    pub fn abs(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.addr_abs = read_u16(bus, self.pc);
+        self.pc = self.pc.wrapping_add(2);
         0
     }
-    pub fn abx() -> u8 {
-        0
+    pub fn abx(&mut self, bus: &mut dyn Bus) -> u8 {
+        let base = read_u16(bus, self.pc);
+        self.pc = self.pc.wrapping_add(2);
+        self.addr_abs = base.wrapping_add(self.x as u16);
+        page_crossed(base, self.addr_abs)
     }
-    pub fn aby() -> u8 {
-        0
+    pub fn aby(&mut self, bus: &mut dyn Bus) -> u8 {
+        let base = read_u16(bus, self.pc);
+        self.pc = self.pc.wrapping_add(2);
+        self.addr_abs = base.wrapping_add(self.y as u16);
+        page_crossed(base, self.addr_abs)
     }
-    pub fn ind() -> u8 {
+    pub fn ind(&mut self, bus: &mut dyn Bus) -> u8 {
+        let ptr = read_u16(bus, self.pc);
+        self.pc = self.pc.wrapping_add(2);
+
+        // hardware bug: the high byte is not fetched from the next page
+        // when the pointer sits on a page boundary. JMP ($10FF) reads $10FF and $1000
+        let lo = bus.read(ptr) as u16;
+        let hi = bus.read((ptr & 0xFF00) | (ptr.wrapping_add(1) & 0x00FF)) as u16;
+        self.addr_abs = (hi << 8) | lo;
         0
     }
-    pub fn izx() -> u8 {
+    pub fn izx(&mut self, bus: &mut dyn Bus) -> u8 {
+        let ptr = bus.read(self.pc).wrapping_add(self.x);
+        self.pc = self.pc.wrapping_add(1);
+
+        let lo = bus.read(ptr as u16) as u16;
+        let hi = bus.read(ptr.wrapping_add(1) as u16) as u16;
+        self.addr_abs = (hi << 8) | lo;
         0
     }
-    pub fn izy() -> u8 {
-        0
+    pub fn izy(&mut self, bus: &mut dyn Bus) -> u8 {
+        let ptr = bus.read(self.pc);
+        self.pc = self.pc.wrapping_add(1);
+
+        let lo = bus.read(ptr as u16) as u16;
+        let hi = bus.read(ptr.wrapping_add(1) as u16) as u16;
+        let base = (hi << 8) | lo;
+        self.addr_abs = base.wrapping_add(self.y as u16);
+        page_crossed(base, self.addr_abs)
+    }
+}
+
+fn read_u16(bus: &mut dyn Bus, addr: u16) -> u16 {
+    let lo = bus.read(addr) as u16;
+    let hi = bus.read(addr.wrapping_add(1)) as u16;
+    (hi << 8) | lo
+}
+
+fn page_crossed(from: u16, to: u16) -> u8 {
+    match from & 0xFF00 == to & 0xFF00 {
+        true => 0,
+        false => 1,
     }
 }
 
 /// OP codes
 impl OLC6502 {
-    pub fn adc() -> u8 {
+    fn branch(&mut self, condition: bool) -> u8 {
+        if condition {
+            self.cycles += 1;
+            self.addr_abs = self.pc.wrapping_add(self.addr_rel);
+            self.cycles += page_crossed(self.pc, self.addr_abs);
+            self.pc = self.addr_abs;
+        }
         0
     }
 
-    pub fn and() -> u8 {
-        0
+    fn compare(&mut self, bus: &mut dyn Bus, reg: u8) {
+        let data = self.fetch(bus);
+        self.set_flag(FlagsOLC6502::CARRY, reg >= data);
+        self.set_zn(reg.wrapping_sub(data));
     }
 
-    pub fn asl() -> u8 {
-        0
+    /// writes the result of a shift/rotate back to where the operand came from
+    fn write_back(&mut self, bus: &mut dyn Bus, data: u8) {
+        match LOOKUP[self.opcode as usize].addrmode {
+            AddrMode::IMP => self.acc = data,
+            _ => bus.write(self.addr_abs, data),
+        }
     }
 
-    pub fn bcc() -> u8 {
-        0
+    fn add_with_carry(&mut self, data: u8) {
+        let sum = self.acc as u16 + data as u16 + self.get_flag(FlagsOLC6502::CARRY) as u16;
+        let result = sum as u8;
+
+        self.set_flag(FlagsOLC6502::CARRY, sum > 0xFF);
+        self.set_flag(
+            FlagsOLC6502::OVERFLOW,
+            (!(self.acc ^ data) & (self.acc ^ result)) & 0x80 != 0,
+        );
+        self.set_zn(result);
+        self.acc = result;
     }
-    pub fn bcs() -> u8 {
-        0
+
+    pub fn adc(&mut self, bus: &mut dyn Bus) -> u8 {
+        // the 2A03 has no decimal mode. the D flag is ignored
+        let data = self.fetch(bus);
+        self.add_with_carry(data);
+        1
     }
 
-    pub fn beq() -> u8 {
-        0
+    pub fn and(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.acc &= self.fetch(bus);
+        self.set_zn(self.acc);
+        1
     }
 
-    pub fn bit() -> u8 {
+    pub fn asl(&mut self, bus: &mut dyn Bus) -> u8 {
+        let data = self.fetch(bus);
+        self.set_flag(FlagsOLC6502::CARRY, data & 0x80 != 0);
+        let result = data << 1;
+        self.set_zn(result);
+        self.write_back(bus, result);
         0
     }
 
-    pub fn bmi() -> u8 {
-        0
+    pub fn bcc(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.branch(self.get_flag(FlagsOLC6502::CARRY) == 0)
+    }
+    pub fn bcs(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.branch(self.get_flag(FlagsOLC6502::CARRY) == 1)
     }
 
-    pub fn bne() -> u8 {
-        0
+    pub fn beq(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.branch(self.get_flag(FlagsOLC6502::ZERO) == 1)
     }
 
-    pub fn bpl() -> u8 {
+    pub fn bit(&mut self, bus: &mut dyn Bus) -> u8 {
+        let data = self.fetch(bus);
+        self.set_flag(FlagsOLC6502::ZERO, self.acc & data == 0);
+        self.set_flag(FlagsOLC6502::NEGATIVE, data & (1 << 7) != 0);
+        self.set_flag(FlagsOLC6502::OVERFLOW, data & (1 << 6) != 0);
         0
     }
 
-    pub fn brk() -> u8 {
-        0
+    pub fn bmi(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.branch(self.get_flag(FlagsOLC6502::NEGATIVE) == 1)
     }
 
-    pub fn bvc() -> u8 {
-        0
+    pub fn bne(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.branch(self.get_flag(FlagsOLC6502::ZERO) == 0)
+    }
+
+    pub fn bpl(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.branch(self.get_flag(FlagsOLC6502::NEGATIVE) == 0)
     }
-    pub fn bvs() -> u8 {
+
+    pub fn brk(&mut self, bus: &mut dyn Bus) -> u8 {
+        // the padding byte after BRK was already skipped by the IMM addressing mode
+        self.push_u16(bus, self.pc);
+        self.push(
+            bus,
+            self.status | FlagsOLC6502::BREAK | FlagsOLC6502::UNUSED,
+        );
+        self.set_flag(FlagsOLC6502::DISABLE_INTERRUPTS, true);
+
+        self.pc = read_u16(bus, Self::IRQ_VECTOR);
         0
     }
 
-    pub fn clc() -> u8 {
+    pub fn bvc(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.branch(self.get_flag(FlagsOLC6502::OVERFLOW) == 0)
+    }
+    pub fn bvs(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.branch(self.get_flag(FlagsOLC6502::OVERFLOW) == 1)
+    }
+
+    pub fn clc(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.set_flag(FlagsOLC6502::CARRY, false);
         0
     }
-    pub fn cld() -> u8 {
+    pub fn cld(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.set_flag(FlagsOLC6502::DECIMAL_MODE, false);
         0
     }
-    pub fn cli() -> u8 {
+    pub fn cli(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.set_flag(FlagsOLC6502::DISABLE_INTERRUPTS, false);
         0
     }
-    pub fn clv() -> u8 {
+    pub fn clv(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.set_flag(FlagsOLC6502::OVERFLOW, false);
         0
     }
 
-    pub fn cmp() -> u8 {
-        0
+    pub fn cmp(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.compare(bus, self.acc);
+        1
     }
 
-    pub fn cpx() -> u8 {
+    pub fn cpx(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.compare(bus, self.x);
         0
     }
-    pub fn cpy() -> u8 {
+    pub fn cpy(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.compare(bus, self.y);
         0
     }
 
-    pub fn dec() -> u8 {
+    pub fn dec(&mut self, bus: &mut dyn Bus) -> u8 {
+        let result = self.fetch(bus).wrapping_sub(1);
+        bus.write(self.addr_abs, result);
+        self.set_zn(result);
         0
     }
-    pub fn dex() -> u8 {
+    pub fn dex(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.x = self.x.wrapping_sub(1);
+        self.set_zn(self.x);
         0
     }
-    pub fn dey() -> u8 {
+    pub fn dey(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.y = self.y.wrapping_sub(1);
+        self.set_zn(self.y);
         0
     }
 
-    pub fn eor() -> u8 {
-        0
+    pub fn eor(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.acc ^= self.fetch(bus);
+        self.set_zn(self.acc);
+        1
     }
 
-    pub fn inc() -> u8 {
+    pub fn inc(&mut self, bus: &mut dyn Bus) -> u8 {
+        let result = self.fetch(bus).wrapping_add(1);
+        bus.write(self.addr_abs, result);
+        self.set_zn(result);
         0
     }
-    pub fn inx() -> u8 {
+    pub fn inx(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.x = self.x.wrapping_add(1);
+        self.set_zn(self.x);
         0
     }
-    pub fn iny() -> u8 {
+    pub fn iny(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.y = self.y.wrapping_add(1);
+        self.set_zn(self.y);
         0
     }
 
-    pub fn jmp() -> u8 {
+    pub fn jmp(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.pc = self.addr_abs;
         0
     }
 
-    pub fn jsr() -> u8 {
+    pub fn jsr(&mut self, bus: &mut dyn Bus) -> u8 {
+        // pushes the address of the last byte of the JSR instruction
+        self.push_u16(bus, self.pc.wrapping_sub(1));
+        self.pc = self.addr_abs;
         0
     }
 
-    pub fn lda() -> u8 {
-        0
+    pub fn lda(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.acc = self.fetch(bus);
+        self.set_zn(self.acc);
+        1
     }
-    pub fn ldx() -> u8 {
-        0
+    pub fn ldx(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.x = self.fetch(bus);
+        self.set_zn(self.x);
+        1
     }
-    pub fn ldy() -> u8 {
-        0
+    pub fn ldy(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.y = self.fetch(bus);
+        self.set_zn(self.y);
+        1
     }
 
-    pub fn lsr() -> u8 {
+    pub fn lsr(&mut self, bus: &mut dyn Bus) -> u8 {
+        let data = self.fetch(bus);
+        self.set_flag(FlagsOLC6502::CARRY, data & 0x01 != 0);
+        let result = data >> 1;
+        self.set_zn(result);
+        self.write_back(bus, result);
         0
     }
 
-    pub fn nop() -> u8 {
+    pub fn nop(&mut self, _bus: &mut dyn Bus) -> u8 {
         0
     }
 
-    pub fn ora() -> u8 {
-        0
+    pub fn ora(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.acc |= self.fetch(bus);
+        self.set_zn(self.acc);
+        1
     }
 
-    pub fn pha() -> u8 {
+    pub fn pha(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.push(bus, self.acc);
         0
     }
-    pub fn php() -> u8 {
+    pub fn php(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.push(
+            bus,
+            self.status | FlagsOLC6502::BREAK | FlagsOLC6502::UNUSED,
+        );
         0
     }
 
-    pub fn pla() -> u8 {
+    pub fn pla(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.acc = self.pop(bus);
+        self.set_zn(self.acc);
         0
     }
-    pub fn plp() -> u8 {
+    pub fn plp(&mut self, bus: &mut dyn Bus) -> u8 {
+        // B and U only exist on the stack copy of the status register
+        self.status = (self.pop(bus) & !FlagsOLC6502::BREAK) | FlagsOLC6502::UNUSED;
         0
     }
 
-    pub fn rol() -> u8 {
+    pub fn rol(&mut self, bus: &mut dyn Bus) -> u8 {
+        let data = self.fetch(bus);
+        let result = (data << 1) | self.get_flag(FlagsOLC6502::CARRY);
+        self.set_flag(FlagsOLC6502::CARRY, data & 0x80 != 0);
+        self.set_zn(result);
+        self.write_back(bus, result);
         0
     }
-    pub fn ror() -> u8 {
+    pub fn ror(&mut self, bus: &mut dyn Bus) -> u8 {
+        let data = self.fetch(bus);
+        let result = (data >> 1) | (self.get_flag(FlagsOLC6502::CARRY) << 7);
+        self.set_flag(FlagsOLC6502::CARRY, data & 0x01 != 0);
+        self.set_zn(result);
+        self.write_back(bus, result);
         0
     }
 
-    pub fn rti() -> u8 {
+    pub fn rti(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.status = (self.pop(bus) & !FlagsOLC6502::BREAK) | FlagsOLC6502::UNUSED;
+        self.pc = self.pop_u16(bus);
         0
     }
-    pub fn rts() -> u8 {
+    pub fn rts(&mut self, bus: &mut dyn Bus) -> u8 {
+        self.pc = self.pop_u16(bus).wrapping_add(1);
         0
     }
 
-    pub fn sbc() -> u8 {
-        0
+    pub fn sbc(&mut self, bus: &mut dyn Bus) -> u8 {
+        // A - M - (1 - C) == A + !M + C
+        let data = self.fetch(bus);
+        self.add_with_carry(!data);
+        1
     }
 
-    pub fn sec() -> u8 {
+    pub fn sec(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.set_flag(FlagsOLC6502::CARRY, true);
         0
     }
-    pub fn sed() -> u8 {
+    pub fn sed(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.set_flag(FlagsOLC6502::DECIMAL_MODE, true);
         0
     }
-    pub fn sei() -> u8 {
+    pub fn sei(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.set_flag(FlagsOLC6502::DISABLE_INTERRUPTS, true);
         0
     }
 
-    pub fn sta() -> u8 {
+    pub fn sta(&mut self, bus: &mut dyn Bus) -> u8 {
+        bus.write(self.addr_abs, self.acc);
         0
     }
-    pub fn stx() -> u8 {
+    pub fn stx(&mut self, bus: &mut dyn Bus) -> u8 {
+        bus.write(self.addr_abs, self.x);
         0
     }
-    pub fn sty() -> u8 {
+    pub fn sty(&mut self, bus: &mut dyn Bus) -> u8 {
+        bus.write(self.addr_abs, self.y);
         0
     }
 
-    pub fn tax() -> u8 {
+    pub fn tax(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.x = self.acc;
+        self.set_zn(self.x);
         0
     }
-    pub fn tay() -> u8 {
+    pub fn tay(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.y = self.acc;
+        self.set_zn(self.y);
         0
     }
-    pub fn tsx() -> u8 {
+    pub fn tsx(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.x = self.stkp;
+        self.set_zn(self.x);
         0
     }
-    pub fn txa() -> u8 {
+    pub fn txa(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.acc = self.x;
+        self.set_zn(self.acc);
         0
     }
-    pub fn txs() -> u8 {
+    pub fn txs(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.stkp = self.x;
         0
     }
-    pub fn tya() -> u8 {
+    pub fn tya(&mut self, _bus: &mut dyn Bus) -> u8 {
+        self.acc = self.y;
+        self.set_zn(self.acc);
         0
     }
 
-    pub fn xxx() -> u8 {
+    /// unofficial opcodes. behaves like a NOP
+    pub fn xxx(&mut self, _bus: &mut dyn Bus) -> u8 {
         0
     }
 }
+
+const fn i(
+    name: &'static str,
+    operation: fn(&mut OLC6502, &mut dyn Bus) -> u8,
+    addrmode: AddrMode,
+    cycles: u8,
+) -> Instruction {
+    Instruction {
+        name,
+        operation,
+        addrmode,
+        cycles,
+    }
+}
+
+type O = OLC6502;
+use AddrMode::*;
+
+#[rustfmt::skip]
+static LOOKUP: [Instruction; 256] = [
+    i("BRK", O::brk, IMM, 7), i("ORA", O::ora, IZX, 6), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 8), i("???", O::xxx, IMP, 3), i("ORA", O::ora, ZP0, 3), i("ASL", O::asl, ZP0, 5), i("???", O::xxx, IMP, 5), i("PHP", O::php, IMP, 3), i("ORA", O::ora, IMM, 2), i("ASL", O::asl, IMP, 2), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 4), i("ORA", O::ora, ABS, 4), i("ASL", O::asl, ABS, 6), i("???", O::xxx, IMP, 6),
+    i("BPL", O::bpl, REL, 2), i("ORA", O::ora, IZY, 5), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 8), i("???", O::xxx, IMP, 4), i("ORA", O::ora, ZPX, 4), i("ASL", O::asl, ZPX, 6), i("???", O::xxx, IMP, 6), i("CLC", O::clc, IMP, 2), i("ORA", O::ora, ABY, 4), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 7), i("???", O::xxx, IMP, 4), i("ORA", O::ora, ABX, 4), i("ASL", O::asl, ABX, 7), i("???", O::xxx, IMP, 7),
+    i("JSR", O::jsr, ABS, 6), i("AND", O::and, IZX, 6), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 8), i("BIT", O::bit, ZP0, 3), i("AND", O::and, ZP0, 3), i("ROL", O::rol, ZP0, 5), i("???", O::xxx, IMP, 5), i("PLP", O::plp, IMP, 4), i("AND", O::and, IMM, 2), i("ROL", O::rol, IMP, 2), i("???", O::xxx, IMP, 2), i("BIT", O::bit, ABS, 4), i("AND", O::and, ABS, 4), i("ROL", O::rol, ABS, 6), i("???", O::xxx, IMP, 6),
+    i("BMI", O::bmi, REL, 2), i("AND", O::and, IZY, 5), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 8), i("???", O::xxx, IMP, 4), i("AND", O::and, ZPX, 4), i("ROL", O::rol, ZPX, 6), i("???", O::xxx, IMP, 6), i("SEC", O::sec, IMP, 2), i("AND", O::and, ABY, 4), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 7), i("???", O::xxx, IMP, 4), i("AND", O::and, ABX, 4), i("ROL", O::rol, ABX, 7), i("???", O::xxx, IMP, 7),
+    i("RTI", O::rti, IMP, 6), i("EOR", O::eor, IZX, 6), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 8), i("???", O::xxx, IMP, 3), i("EOR", O::eor, ZP0, 3), i("LSR", O::lsr, ZP0, 5), i("???", O::xxx, IMP, 5), i("PHA", O::pha, IMP, 3), i("EOR", O::eor, IMM, 2), i("LSR", O::lsr, IMP, 2), i("???", O::xxx, IMP, 2), i("JMP", O::jmp, ABS, 3), i("EOR", O::eor, ABS, 4), i("LSR", O::lsr, ABS, 6), i("???", O::xxx, IMP, 6),
+    i("BVC", O::bvc, REL, 2), i("EOR", O::eor, IZY, 5), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 8), i("???", O::xxx, IMP, 4), i("EOR", O::eor, ZPX, 4), i("LSR", O::lsr, ZPX, 6), i("???", O::xxx, IMP, 6), i("CLI", O::cli, IMP, 2), i("EOR", O::eor, ABY, 4), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 7), i("???", O::xxx, IMP, 4), i("EOR", O::eor, ABX, 4), i("LSR", O::lsr, ABX, 7), i("???", O::xxx, IMP, 7),
+    i("RTS", O::rts, IMP, 6), i("ADC", O::adc, IZX, 6), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 8), i("???", O::xxx, IMP, 3), i("ADC", O::adc, ZP0, 3), i("ROR", O::ror, ZP0, 5), i("???", O::xxx, IMP, 5), i("PLA", O::pla, IMP, 4), i("ADC", O::adc, IMM, 2), i("ROR", O::ror, IMP, 2), i("???", O::xxx, IMP, 2), i("JMP", O::jmp, IND, 5), i("ADC", O::adc, ABS, 4), i("ROR", O::ror, ABS, 6), i("???", O::xxx, IMP, 6),
+    i("BVS", O::bvs, REL, 2), i("ADC", O::adc, IZY, 5), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 8), i("???", O::xxx, IMP, 4), i("ADC", O::adc, ZPX, 4), i("ROR", O::ror, ZPX, 6), i("???", O::xxx, IMP, 6), i("SEI", O::sei, IMP, 2), i("ADC", O::adc, ABY, 4), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 7), i("???", O::xxx, IMP, 4), i("ADC", O::adc, ABX, 4), i("ROR", O::ror, ABX, 7), i("???", O::xxx, IMP, 7),
+    i("???", O::xxx, IMP, 2), i("STA", O::sta, IZX, 6), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 6), i("STY", O::sty, ZP0, 3), i("STA", O::sta, ZP0, 3), i("STX", O::stx, ZP0, 3), i("???", O::xxx, IMP, 3), i("DEY", O::dey, IMP, 2), i("???", O::xxx, IMP, 2), i("TXA", O::txa, IMP, 2), i("???", O::xxx, IMP, 2), i("STY", O::sty, ABS, 4), i("STA", O::sta, ABS, 4), i("STX", O::stx, ABS, 4), i("???", O::xxx, IMP, 4),
+    i("BCC", O::bcc, REL, 2), i("STA", O::sta, IZY, 6), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 6), i("STY", O::sty, ZPX, 4), i("STA", O::sta, ZPX, 4), i("STX", O::stx, ZPY, 4), i("???", O::xxx, IMP, 4), i("TYA", O::tya, IMP, 2), i("STA", O::sta, ABY, 5), i("TXS", O::txs, IMP, 2), i("???", O::xxx, IMP, 5), i("???", O::xxx, IMP, 5), i("STA", O::sta, ABX, 5), i("???", O::xxx, IMP, 5), i("???", O::xxx, IMP, 5),
+    i("LDY", O::ldy, IMM, 2), i("LDA", O::lda, IZX, 6), i("LDX", O::ldx, IMM, 2), i("???", O::xxx, IMP, 6), i("LDY", O::ldy, ZP0, 3), i("LDA", O::lda, ZP0, 3), i("LDX", O::ldx, ZP0, 3), i("???", O::xxx, IMP, 3), i("TAY", O::tay, IMP, 2), i("LDA", O::lda, IMM, 2), i("TAX", O::tax, IMP, 2), i("???", O::xxx, IMP, 2), i("LDY", O::ldy, ABS, 4), i("LDA", O::lda, ABS, 4), i("LDX", O::ldx, ABS, 4), i("???", O::xxx, IMP, 4),
+    i("BCS", O::bcs, REL, 2), i("LDA", O::lda, IZY, 5), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 5), i("LDY", O::ldy, ZPX, 4), i("LDA", O::lda, ZPX, 4), i("LDX", O::ldx, ZPY, 4), i("???", O::xxx, IMP, 4), i("CLV", O::clv, IMP, 2), i("LDA", O::lda, ABY, 4), i("TSX", O::tsx, IMP, 2), i("???", O::xxx, IMP, 4), i("LDY", O::ldy, ABX, 4), i("LDA", O::lda, ABX, 4), i("LDX", O::ldx, ABY, 4), i("???", O::xxx, IMP, 4),
+    i("CPY", O::cpy, IMM, 2), i("CMP", O::cmp, IZX, 6), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 8), i("CPY", O::cpy, ZP0, 3), i("CMP", O::cmp, ZP0, 3), i("DEC", O::dec, ZP0, 5), i("???", O::xxx, IMP, 5), i("INY", O::iny, IMP, 2), i("CMP", O::cmp, IMM, 2), i("DEX", O::dex, IMP, 2), i("???", O::xxx, IMP, 2), i("CPY", O::cpy, ABS, 4), i("CMP", O::cmp, ABS, 4), i("DEC", O::dec, ABS, 6), i("???", O::xxx, IMP, 6),
+    i("BNE", O::bne, REL, 2), i("CMP", O::cmp, IZY, 5), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 8), i("???", O::xxx, IMP, 4), i("CMP", O::cmp, ZPX, 4), i("DEC", O::dec, ZPX, 6), i("???", O::xxx, IMP, 6), i("CLD", O::cld, IMP, 2), i("CMP", O::cmp, ABY, 4), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 7), i("???", O::xxx, IMP, 4), i("CMP", O::cmp, ABX, 4), i("DEC", O::dec, ABX, 7), i("???", O::xxx, IMP, 7),
+    i("CPX", O::cpx, IMM, 2), i("SBC", O::sbc, IZX, 6), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 8), i("CPX", O::cpx, ZP0, 3), i("SBC", O::sbc, ZP0, 3), i("INC", O::inc, ZP0, 5), i("???", O::xxx, IMP, 5), i("INX", O::inx, IMP, 2), i("SBC", O::sbc, IMM, 2), i("NOP", O::nop, IMP, 2), i("???", O::xxx, IMP, 2), i("CPX", O::cpx, ABS, 4), i("SBC", O::sbc, ABS, 4), i("INC", O::inc, ABS, 6), i("???", O::xxx, IMP, 6),
+    i("BEQ", O::beq, REL, 2), i("SBC", O::sbc, IZY, 5), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 8), i("???", O::xxx, IMP, 4), i("SBC", O::sbc, ZPX, 4), i("INC", O::inc, ZPX, 6), i("???", O::xxx, IMP, 6), i("SED", O::sed, IMP, 2), i("SBC", O::sbc, ABY, 4), i("???", O::xxx, IMP, 2), i("???", O::xxx, IMP, 7), i("???", O::xxx, IMP, 4), i("SBC", O::sbc, ABX, 4), i("INC", O::inc, ABX, 7), i("???", O::xxx, IMP, 7),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Mem64K;
+    use std::collections::HashSet;
+
+    /// loads `program` at $8000, points the reset vector at it and resets the CPU
+    fn setup(program: &[u8]) -> (OLC6502, Mem64K) {
+        let mut ram = Mem64K::default();
+        for (offset, byte) in program.iter().enumerate() {
+            ram.write(0x8000 + offset as u16, *byte);
+        }
+        boot(ram)
+    }
+
+    /// like `setup`, with the program written in assembly. Code starts at $8000
+    fn setup_asm(source: &str) -> (OLC6502, Mem64K) {
+        let program = crate::assemble(&format!(".org $8000\n{}", source)).unwrap();
+        boot(program.to_mem())
+    }
+
+    fn boot(mut ram: Mem64K) -> (OLC6502, Mem64K) {
+        ram.write(OLC6502::RESET_VECTOR, 0x00);
+        ram.write(OLC6502::RESET_VECTOR + 1, 0x80);
+
+        let mut cpu = OLC6502::default();
+        cpu.reset(&mut ram);
+        run_instruction(&mut cpu, &mut ram);
+
+        (cpu, ram)
+    }
+
+    /// clocks the CPU until the current instruction completes. returns the cycles taken
+    fn run_instruction(cpu: &mut OLC6502, bus: &mut dyn Bus) -> usize {
+        let mut cycles = 0;
+        loop {
+            cpu.clock(bus);
+            cycles += 1;
+            if cpu.complete() {
+                return cycles;
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_has_all_official_opcodes() {
+        assert_eq!(
+            LOOKUP.iter().filter(|instr| instr.name != "???").count(),
+            151
+        );
+    }
+
+    #[test]
+    fn test_adc_sbc_flags() {
+        #[rustfmt::skip]
+        let (mut cpu, mut ram) = setup(&[
+            0xA9, 0x50, // LDA #$50
+            0x69, 0x50, // ADC #$50
+            0x38,       // SEC
+            0xE9, 0xF0, // SBC #$F0
+            0xA9, 0xFF, // LDA #$FF
+            0x69, 0x01, // ADC #$01
+        ]);
+
+        run_instruction(&mut cpu, &mut ram);
+        run_instruction(&mut cpu, &mut ram);
+        assert_eq!(cpu.acc, 0xA0);
+        assert_eq!(cpu.get_flag(FlagsOLC6502::OVERFLOW), 1);
+        assert_eq!(cpu.get_flag(FlagsOLC6502::NEGATIVE), 1);
+        assert_eq!(cpu.get_flag(FlagsOLC6502::CARRY), 0);
+
+        run_instruction(&mut cpu, &mut ram);
+        run_instruction(&mut cpu, &mut ram);
+        assert_eq!(cpu.acc, 0xB0);
+        assert_eq!(cpu.get_flag(FlagsOLC6502::CARRY), 0); // borrowed
+        assert_eq!(cpu.get_flag(FlagsOLC6502::OVERFLOW), 0);
+
+        run_instruction(&mut cpu, &mut ram);
+        run_instruction(&mut cpu, &mut ram);
+        assert_eq!(cpu.acc, 0x00);
+        assert_eq!(cpu.get_flag(FlagsOLC6502::ZERO), 1);
+        assert_eq!(cpu.get_flag(FlagsOLC6502::CARRY), 1);
+    }
+
+    #[test]
+    fn test_page_crossing_penalty() {
+        #[rustfmt::skip]
+        let (mut cpu, mut ram) = setup(&[
+            0xA2, 0x01,       // LDX #$01
+            0xBD, 0x00, 0x12, // LDA $1200,X
+            0xBD, 0xFF, 0x12, // LDA $12FF,X
+            0x9D, 0xFF, 0x12, // STA $12FF,X
+            0xD0, 0x00,       // BNE +0 (not taken, Z set by LDA)
+        ]);
+
+        assert_eq!(run_instruction(&mut cpu, &mut ram), 2);
+        assert_eq!(run_instruction(&mut cpu, &mut ram), 4);
+        assert_eq!(run_instruction(&mut cpu, &mut ram), 5);
+        assert_eq!(run_instruction(&mut cpu, &mut ram), 5);
+        assert_eq!(run_instruction(&mut cpu, &mut ram), 2);
+    }
+
+    #[test]
+    fn test_branch_cycles() {
+        let (mut cpu, mut ram) = setup_asm(
+            "
+                    clc
+                    bcc first
+                    .org $8013
+            first:  bcc second
+                    .org $8094
+            second: bcc third       ; into the next page
+                    .org $8106
+            third:
+            ",
+        );
+
+        assert_eq!(run_instruction(&mut cpu, &mut ram), 2);
+        assert_eq!(run_instruction(&mut cpu, &mut ram), 3);
+        assert_eq!(cpu.pc, 0x8013);
+        assert_eq!(run_instruction(&mut cpu, &mut ram), 3);
+        assert_eq!(cpu.pc, 0x8094);
+        assert_eq!(run_instruction(&mut cpu, &mut ram), 4);
+        assert_eq!(cpu.pc, 0x8106);
+    }
+
+    #[test]
+    fn test_jsr_rts_and_stack() {
+        let (mut cpu, mut ram) = setup_asm(
+            "
+                    jsr sub
+                    ldx #$42
+            sub:    lda #$07
+                    pha
+                    pla
+                    rts
+            ",
+        );
+
+        assert_eq!(run_instruction(&mut cpu, &mut ram), 6);
+        assert_eq!(cpu.pc, 0x8005);
+        assert_eq!(cpu.stkp, 0xFB);
+        assert_eq!(ram.read(0x01FD), 0x80);
+        assert_eq!(ram.read(0x01FC), 0x02);
+
+        run_instruction(&mut cpu, &mut ram);
+        run_instruction(&mut cpu, &mut ram);
+        run_instruction(&mut cpu, &mut ram);
+        assert_eq!(cpu.acc, 0x07);
+        assert_eq!(run_instruction(&mut cpu, &mut ram), 6);
+        assert_eq!(cpu.pc, 0x8003);
+        assert_eq!(cpu.stkp, 0xFD);
+        run_instruction(&mut cpu, &mut ram);
+        assert_eq!(cpu.x, 0x42);
+    }
+
+    #[test]
+    fn test_jmp_indirect_page_boundary_bug() {
+        let (mut cpu, mut ram) = setup(&[0x6C, 0xFF, 0x10]); // JMP ($10FF)
+        ram.write(0x10FF, 0x34);
+        ram.write(0x1000, 0x12);
+        ram.write(0x1100, 0x56);
+
+        run_instruction(&mut cpu, &mut ram);
+        assert_eq!(cpu.pc, 0x1234);
+    }
+
+    #[test]
+    fn test_interrupts() {
+        #[rustfmt::skip]
+        let (mut cpu, mut ram) = setup(&[
+            0x58,       // CLI
+            0xEA,       // NOP
+            0x00, 0x00, // BRK
+        ]);
+        ram.write(OLC6502::IRQ_VECTOR, 0x00);
+        ram.write(OLC6502::IRQ_VECTOR + 1, 0x90);
+        ram.write(OLC6502::NMI_VECTOR, 0x00);
+        ram.write(OLC6502::NMI_VECTOR + 1, 0xA0);
+        ram.write(0x9000, 0x40); // RTI
+        ram.write(0xA000, 0x40); // RTI
+
+        // masked
+        cpu.irq(&mut ram);
+        assert!(cpu.complete());
+        run_instruction(&mut cpu, &mut ram);
+
+        cpu.irq(&mut ram);
+        assert_eq!(run_instruction(&mut cpu, &mut ram), 7);
+        assert_eq!(cpu.pc, 0x9000);
+        assert_eq!(cpu.get_flag(FlagsOLC6502::DISABLE_INTERRUPTS), 1);
+        assert_eq!(ram.read(0x01FB) & FlagsOLC6502::BREAK, 0);
+
+        run_instruction(&mut cpu, &mut ram);
+        assert_eq!(cpu.pc, 0x8001);
+        assert_eq!(cpu.get_flag(FlagsOLC6502::DISABLE_INTERRUPTS), 0);
+
+        cpu.set_flag(FlagsOLC6502::DISABLE_INTERRUPTS, true);
+        cpu.nmi(&mut ram);
+        run_instruction(&mut cpu, &mut ram);
+        assert_eq!(cpu.pc, 0xA000);
+        run_instruction(&mut cpu, &mut ram);
+        assert_eq!(cpu.pc, 0x8001);
+
+        run_instruction(&mut cpu, &mut ram);
+        assert_eq!(run_instruction(&mut cpu, &mut ram), 7);
+        assert_eq!(cpu.pc, 0x9000);
+        assert_ne!(ram.read(0x01FB) & FlagsOLC6502::BREAK, 0);
+        run_instruction(&mut cpu, &mut ram);
+        assert_eq!(cpu.pc, 0x8004);
+    }
+
+    /// records every access on top of flat RAM
+    #[derive(Default)]
+    struct LoggingBus {
+        ram: Mem64K,
+        log: Vec<(char, u16, u8)>,
+        ticks: usize,
+    }
+
+    impl Bus for LoggingBus {
+        fn read(&mut self, addr: u16) -> u8 {
+            let data = self.ram.read(addr);
+            self.log.push(('r', addr, data));
+            data
+        }
+
+        fn write(&mut self, addr: u16, data: u8) {
+            self.log.push(('w', addr, data));
+            self.ram.write(addr, data);
+        }
+
+        fn tick(&mut self) {
+            self.ticks += 1;
+        }
+    }
+
+    #[test]
+    fn test_bus_accesses() {
+        let mut bus = LoggingBus::default();
+        #[rustfmt::skip]
+        let program = [
+            0xA9, 0x42,       // LDA #$42
+            0x8D, 0x00, 0x02, // STA $0200
+        ];
+        for (offset, byte) in program.iter().enumerate() {
+            bus.ram.write(0x8000 + offset as u16, *byte);
+        }
+        bus.ram.write(OLC6502::RESET_VECTOR + 1, 0x80);
+
+        let mut cpu = OLC6502::default();
+        cpu.reset(&mut bus);
+        run_instruction(&mut cpu, &mut bus);
+        bus.log.clear();
+        bus.ticks = 0;
+
+        run_instruction(&mut cpu, &mut bus);
+        run_instruction(&mut cpu, &mut bus);
+        assert_eq!(
+            bus.log,
+            vec![
+                ('r', 0x8000, 0xA9),
+                ('r', 0x8001, 0x42),
+                ('r', 0x8002, 0x8D),
+                ('r', 0x8003, 0x00),
+                ('r', 0x8004, 0x02),
+                ('w', 0x0200, 0x42),
+            ]
+        );
+        assert_eq!(bus.ticks, 6);
+    }
+
+    /// Cycles for every official opcode by row (high nibble) and column, written
+    /// out from the datasheet rather than taken from `LOOKUP`. `+` marks a cycle
+    /// more when an indexed operand crosses a page, `b` a branch, which takes
+    /// one more when taken in the same page
+    #[rustfmt::skip]
+    const CYCLES: [[&str; 16]; 16] = [
+        ["7", "6", ".", ".", ".", "3", "5", ".", "3", "2", "2", ".", ".", "4", "6", "."],
+        ["2b", "5+", ".", ".", ".", "4", "6", ".", "2", "4+", ".", ".", ".", "4+", "7", "."],
+        ["6", "6", ".", ".", "3", "3", "5", ".", "4", "2", "2", ".", "4", "4", "6", "."],
+        ["2b", "5+", ".", ".", ".", "4", "6", ".", "2", "4+", ".", ".", ".", "4+", "7", "."],
+        ["6", "6", ".", ".", ".", "3", "5", ".", "3", "2", "2", ".", "3", "4", "6", "."],
+        ["2b", "5+", ".", ".", ".", "4", "6", ".", "2", "4+", ".", ".", ".", "4+", "7", "."],
+        ["6", "6", ".", ".", ".", "3", "5", ".", "4", "2", "2", ".", "5", "4", "6", "."],
+        ["2b", "5+", ".", ".", ".", "4", "6", ".", "2", "4+", ".", ".", ".", "4+", "7", "."],
+        [".", "6", ".", ".", "3", "3", "3", ".", "2", ".", "2", ".", "4", "4", "4", "."],
+        ["2b", "6", ".", ".", "4", "4", "4", ".", "2", "5", "2", ".", ".", "5", ".", "."],
+        ["2", "6", "2", ".", "3", "3", "3", ".", "2", "2", "2", ".", "4", "4", "4", "."],
+        ["2b", "5+", ".", ".", "4", "4", "4", ".", "2", "4+", "2", ".", "4+", "4+", "4+", "."],
+        ["2", "6", ".", ".", "3", "3", "5", ".", "2", "2", "2", ".", "4", "4", "6", "."],
+        ["2b", "5+", ".", ".", ".", "4", "6", ".", "2", "4+", ".", ".", ".", "4+", "7", "."],
+        ["2", "6", ".", ".", "3", "3", "5", ".", "2", "2", "2", ".", "4", "4", "6", "."],
+        ["2b", "5+", ".", ".", ".", "4", "6", ".", "2", "4+", ".", ".", ".", "4+", "7", "."],
+    ];
+
+    /// (opcode, cycles) of every official opcode in `CYCLES`
+    fn reference_cycles() -> Vec<(u8, &'static str)> {
+        (0..=255u8)
+            .map(|opcode| (opcode, CYCLES[opcode as usize >> 4][opcode as usize & 0x0F]))
+            .filter(|(_, cycles)| *cycles != ".")
+            .collect()
+    }
+
+    #[test]
+    fn test_official_opcode_cycles() {
+        let reference = reference_cycles();
+        let official: Vec<u8> = (0..=255)
+            .filter(|opcode| OLC6502::lookup(*opcode).is_official())
+            .collect();
+        assert_eq!(
+            official,
+            reference
+                .iter()
+                .map(|(opcode, _)| *opcode)
+                .collect::<Vec<_>>()
+        );
+
+        for (opcode, cycles) in reference {
+            let base: usize = cycles.trim_end_matches(['+', 'b']).parse().unwrap();
+            // X and Y of $FF push every indexed operand into the next page, and
+            // every branch is taken under one of the two statuses
+            for index in [0x00, 0xFF] {
+                for status in [0x00, 0xFF] {
+                    // operand $10, $1210 or a branch to $8012. ($10) points at $1210
+                    let (mut cpu, mut ram) = setup(&[opcode, 0x10, 0x12]);
+                    ram.write(0x0010, 0x10);
+                    ram.write(0x0011, 0x12);
+                    (cpu.x, cpu.y, cpu.status) = (index, index, status);
+
+                    let taken = run_instruction(&mut cpu, &mut ram);
+                    let expected = match cycles.chars().last() {
+                        Some('+') if index == 0xFF => base + 1,
+                        Some('b') if cpu.pc == 0x8012 => base + 1,
+                        _ => base,
+                    };
+                    assert_eq!(
+                        taken, expected,
+                        "{} (${:02X}) with X and Y ${:02X}, P ${:02X}",
+                        LOOKUP[opcode as usize].name, opcode, index, status
+                    );
+                }
+            }
+        }
+    }
+
+    /// A self-checking program. Each check stores its number in $02 and jumps to
+    /// `fail` when a result is wrong, so what's left in $02 names the check
+    #[derive(Default)]
+    struct Checks {
+        source: String,
+        names: Vec<String>,
+        labels: usize,
+    }
+
+    impl Checks {
+        fn asm(&mut self, lines: &str) {
+            self.source.push_str(lines);
+            self.source.push('\n');
+        }
+
+        /// starts the check `name` with C and V clear
+        fn check(&mut self, name: &str) {
+            self.names.push(name.to_string());
+            self.asm(&format!("lda #{}\nsta $02\nclc\nclv", self.names.len()));
+        }
+
+        /// fails the check unless Z is set, e.g. by a compare
+        fn expect_equal(&mut self) {
+            self.labels += 1;
+            self.asm(&format!("beq ok{0}\njmp fail\nok{0}:", self.labels));
+        }
+
+        fn expect_a(&mut self, value: u8) {
+            self.asm(&format!("cmp #${:02X}", value));
+            self.expect_equal();
+        }
+
+        /// pulls the status pushed by a PHP and compares its N, V, Z and C with `flags`
+        fn expect_flags(&mut self, flags: u8) {
+            self.asm(&format!("pla\nand #$C3\ncmp #${:02X}", flags));
+            self.expect_equal();
+        }
+    }
+
+    /// every official instruction in each of its addressing modes, checked
+    fn functional_program() -> Checks {
+        let mut checks = Checks::default();
+        // X = 4 and Y = 8 throughout, so each operand below reaches $C3 in
+        // either $20 or $0320. ($10) is $0320 and ($12) is $0318
+        let restore = "lda #$C3\nsta $20\nsta $0320";
+        checks.asm("ldx #4\nldy #8");
+        checks.asm("lda #$20\nsta $10\nlda #$18\nsta $12\nlda #$03\nsta $11\nsta $13");
+        checks.asm(restore);
+
+        const MODES: [&str; 8] = [
+            "#$C3", "$20", "$1C,x", "$0320", "$031C,x", "$0318,y", "($0C,x)", "($12),y",
+        ];
+        // $5A against $C3: (mnemonic, carry in, A after, N V Z C after)
+        let alu = [
+            ("ora", false, 0xDB, 0x80),
+            ("and", false, 0x42, 0x00),
+            ("eor", false, 0x99, 0x80),
+            ("adc", false, 0x1D, 0x01),
+            ("sbc", true, 0x97, 0xC0),
+            ("cmp", false, 0x5A, 0x80),
+            ("lda", false, 0xC3, 0x80),
+        ];
+        for (mnemonic, carry, result, flags) in alu {
+            for mode in MODES {
+                checks.check(&format!("{} {}", mnemonic, mode));
+                if carry {
+                    checks.asm("sec");
+                }
+                checks.asm(&format!("lda #$5A\n{} {}\nphp", mnemonic, mode));
+                checks.expect_a(result);
+                checks.expect_flags(flags);
+            }
+        }
+        for mode in &MODES[1..] {
+            checks.check(&format!("sta {}", mode));
+            checks.asm(&format!(
+                "lda #0\nsta $20\nsta $0320\nlda #$C3\nsta {}",
+                mode
+            ));
+            checks.asm("lda $20\nora $0320");
+            checks.expect_a(0xC3);
+            checks.asm(restore);
+        }
+
+        for (register, index, modes) in [
+            ("x", "ldx #4", ["#$C3", "$20", "$18,y", "$0320", "$0318,y"]),
+            ("y", "ldy #8", ["#$C3", "$20", "$1C,x", "$0320", "$031C,x"]),
+        ] {
+            for mode in modes {
+                checks.check(&format!("ld{} {}", register, mode));
+                checks.asm(&format!("ld{0} {1}\nphp\ncp{0} #$C3", register, mode));
+                checks.expect_equal();
+                checks.expect_flags(0x80);
+                checks.asm(index);
+            }
+            for mode in [
+                "$20",
+                if register == "x" { "$18,y" } else { "$1C,x" },
+                "$0320",
+            ] {
+                checks.check(&format!("st{} {}", register, mode));
+                checks.asm("lda #0\nsta $20\nsta $0320");
+                checks.asm(&format!(
+                    "ld{0} #$C3\nst{0} {1}\n{2}",
+                    register, mode, index
+                ));
+                checks.asm("lda $20\nora $0320");
+                checks.expect_a(0xC3);
+                checks.asm(restore);
+            }
+            // X = 4 and Y = 8 are both below $C3
+            for mode in ["#$C3", "$20", "$0320"] {
+                checks.check(&format!("cp{} {}", register, mode));
+                checks.asm(&format!("cp{} {}\nphp", register, mode));
+                checks.expect_flags(0x00);
+            }
+        }
+        checks.check("cpx cpy equal");
+        checks.asm("cpx #4\nphp");
+        checks.expect_flags(0x03);
+        checks.asm("cpy #8\nphp");
+        checks.expect_flags(0x03);
+
+        for mode in ["$20", "$0320"] {
+            checks.check(&format!("bit {}", mode));
+            checks.asm(&format!("lda #$5A\nbit {}\nphp", mode));
+            checks.expect_flags(0xC0);
+        }
+
+        // $C3: (mnemonic, carry in, result, N V Z C after)
+        let read_modify_write = [
+            ("asl", false, 0x86, 0x81),
+            ("lsr", false, 0x61, 0x01),
+            ("rol", true, 0x87, 0x81),
+            ("ror", true, 0xE1, 0x81),
+            ("inc", false, 0xC4, 0x80),
+            ("dec", false, 0xC2, 0x80),
+        ];
+        for (mnemonic, carry, result, flags) in read_modify_write {
+            let modes = [
+                ("a", ""),
+                ("$20", "$20"),
+                ("$1C,x", "$20"),
+                ("$0320", "$0320"),
+                ("$031C,x", "$0320"),
+            ];
+            // there's no INC A or DEC A
+            let skip = usize::from(matches!(mnemonic, "inc" | "dec"));
+            for (mode, target) in &modes[skip..] {
+                checks.check(&format!("{} {}", mnemonic, mode));
+                if carry {
+                    checks.asm("sec");
+                }
+                match *target {
+                    "" => checks.asm(&format!("lda #$C3\n{} a\nphp", mnemonic)),
+                    _ => checks.asm(&format!("{} {}\nphp\nlda {}", mnemonic, mode, target)),
+                }
+                checks.expect_a(result);
+                checks.expect_flags(flags);
+                checks.asm(restore);
+            }
+        }
+
+        checks.check("tax tay");
+        checks.asm("lda #$C3\ntax\ntay\ncpx #$C3");
+        checks.expect_equal();
+        checks.asm("cpy #$C3");
+        checks.expect_equal();
+        checks.check("txa tya");
+        checks.asm("ldx #$11\ntxa");
+        checks.expect_a(0x11);
+        checks.asm("ldy #$22\ntya");
+        checks.expect_a(0x22);
+        checks.check("txs tsx");
+        checks.asm("ldx #$80\ntxs\nldx #0\ntsx\ncpx #$80");
+        checks.expect_equal();
+        checks.asm("ldx #$FD\ntxs");
+        checks.check("inx dex");
+        checks.asm("ldx #$FF\ninx\nphp");
+        checks.expect_flags(0x02);
+        checks.asm("dex\ncpx #$FF");
+        checks.expect_equal();
+        checks.check("dey iny");
+        checks.asm("ldy #0\ndey\nphp");
+        checks.expect_flags(0x80);
+        checks.asm("iny\niny\ncpy #1");
+        checks.expect_equal();
+        checks.asm("ldx #4\nldy #8");
+
+        checks.check("sec clc");
+        checks.asm("lda #1\nsec\nphp");
+        checks.expect_flags(0x01);
+        checks.asm("lda #1\nsec\nclc\nphp");
+        checks.expect_flags(0x00);
+        checks.check("clv");
+        checks.asm("lda #$40\nadc #$40\nclv\nphp");
+        checks.expect_flags(0x80);
+        checks.check("sei cli");
+        checks.asm("sei\nphp\npla\nand #$04");
+        checks.expect_a(0x04);
+        checks.asm("cli\nphp\npla\nand #$04");
+        checks.expect_a(0x00);
+        // the 2A03 adds in binary even with D set
+        checks.check("sed cld");
+        checks.asm("sed\nphp\npla\nand #$08");
+        checks.expect_a(0x08);
+        checks.asm("sed\nclc\nlda #$09\nadc #$01\ncld");
+        checks.expect_a(0x0A);
+        checks.check("pha pla");
+        checks.asm("lda #$C3\npha\nlda #0\npla\nphp");
+        checks.expect_a(0xC3);
+        checks.expect_flags(0x80);
+        // PHP pushes B and the unused bit set
+        checks.check("plp php");
+        checks.asm("lda #$C3\npha\nplp\nphp\npla");
+        checks.expect_a(0xF3);
+        checks.check("nop");
+        checks.asm("nop");
+
+        checks.check("jmp");
+        checks.asm(
+            "jmp jmp_absolute\njmp fail\njmp_absolute:\njmp (jmp_vector)\njmp fail\njmp_indirect:",
+        );
+        checks.check("jsr rts");
+        checks.asm("lda #0\njsr subroutine");
+        checks.expect_a(0x42);
+        // the handler counts in $03 and keeps the pushed status in $04
+        checks.check("brk rti");
+        checks.asm("lda #0\nsta $03\nbrk #$FF\nlda $03");
+        checks.expect_a(1);
+        checks.asm("lda $04\nand #$10");
+        checks.expect_a(0x10);
+
+        // every branch taken and not taken, the ones not taken would go to branch_bad
+        checks.check("branches");
+        checks.asm(
+            "
+                    lda #0
+                    bne branch_bad
+                    bmi branch_bad
+                    beq branch_zero
+            branch_bad:
+                    jmp fail
+            branch_zero:
+                    bpl branch_positive
+                    jmp fail
+            branch_positive:
+                    sec
+                    bcc branch_bad
+                    bcs branch_carry
+                    jmp fail
+            branch_carry:
+                    clc
+                    bcs branch_bad
+                    bcc branch_no_carry
+                    jmp fail
+            branch_no_carry:
+                    lda #$40
+                    adc #$40
+                    bvc branch_bad
+                    bpl branch_bad
+                    beq branch_bad
+                    bvs branch_overflow
+                    jmp fail
+            branch_overflow:
+                    bmi branch_negative
+                    jmp fail
+            branch_negative:
+                    clv
+                    bvs branch_bad
+                    bvc branch_no_overflow
+                    jmp fail
+            branch_no_overflow:
+                    bne branch_done
+                    jmp fail
+            branch_done:
+            ",
+        );
+
+        checks.asm(
+            "
+                    lda #0
+                    sta $02
+            done:   jmp done
+            fail:   jmp fail
+            subroutine:
+                    lda #$42
+                    rts
+            interrupt:
+                    inc $03
+                    pla
+                    pha
+                    sta $04
+                    rti
+            jmp_vector:
+                    .word jmp_indirect
+                    .org $FFFE
+                    .word interrupt
+            ",
+        );
+        checks
+    }
+
+    #[test]
+    fn test_functional_program() {
+        let checks = functional_program();
+        let program = crate::assemble(&format!(".org $8000\n{}", checks.source)).unwrap();
+        let done = program.label("done").unwrap();
+        let fail = program.label("fail").unwrap();
+        let (mut cpu, mut ram) = boot(program.to_mem());
+
+        let mut executed = HashSet::new();
+        for _ in 0..10_000 {
+            if cpu.pc == done || cpu.pc == fail {
+                break;
+            }
+            executed.insert(ram.read(cpu.pc));
+            run_instruction(&mut cpu, &mut ram);
+        }
+
+        let check = ram.read(0x0002) as usize;
+        assert_eq!(cpu.pc, done, "{} failed", checks.names[check.max(1) - 1]);
+        let official: HashSet<u8> = reference_cycles()
+            .iter()
+            .map(|(opcode, _)| *opcode)
+            .collect();
+        let mut missing: Vec<_> = official.difference(&executed).collect();
+        missing.sort();
+        assert!(missing.is_empty(), "never ran {:02X?}", missing);
+    }
+
+    /// Runs the official opcode section of nestest in automation mode (PC = $C000)
+    /// and compares the registers and cycle count against nestest.log before every instruction.
+    #[test]
+    #[ignore = "requires assets/nes/nestest.nes and assets/nes/nestest.log"]
+    fn test_nestest() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/nes");
+        let rom = std::fs::read(format!("{}/nestest.nes", dir)).expect("nestest.nes");
+        let log = std::fs::read_to_string(format!("{}/nestest.log", dir)).expect("nestest.log");
+
+        // NROM-128: the 16K PRG bank is mirrored at $8000 and $C000
+        let mut ram = Mem64K::default();
+        for (offset, byte) in rom[16..16 + 0x4000].iter().enumerate() {
+            ram.write(0x8000 + offset as u16, *byte);
+            ram.write(0xC000 + offset as u16, *byte);
+        }
+
+        let mut cpu = OLC6502::default();
+        cpu.reset(&mut ram);
+        let mut cycles = run_instruction(&mut cpu, &mut ram);
+        cpu.pc = 0xC000;
+
+        let reg = |line: &str, name: &str| {
+            let start = line.find(name).expect(name) + name.len();
+            u8::from_str_radix(&line[start..start + 2], 16).expect(name)
+        };
+
+        // unofficial opcodes are marked with a `*` in front of the mnemonic
+        for line in log.lines().take_while(|line| &line[15..16] != "*") {
+            let expected = (
+                u16::from_str_radix(&line[0..4], 16).expect("PC"),
+                reg(line, "A:"),
+                reg(line, "X:"),
+                reg(line, "Y:"),
+                reg(line, "P:"),
+                reg(line, "SP:"),
+                line[line.find("CYC:").expect("CYC") + 4..]
+                    .trim()
+                    .parse::<usize>()
+                    .expect("CYC"),
+            );
+            let actual = (cpu.pc, cpu.acc, cpu.x, cpu.y, cpu.status, cpu.stkp, cycles);
+            assert_eq!(actual, expected, "{}", line);
+
+            cycles += run_instruction(&mut cpu, &mut ram);
+        }
+
+        // error code of the official opcode tests
+        assert_eq!(ram.read(0x0002), 0x00);
+    }
+}
@@ -0,0 +1,411 @@
+use crate::{trace_line, Bus, Bus16, Registers};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Which accesses trigger a watchpoint
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Watch {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+impl Watch {
+    fn matches(&self, access: Access) -> bool {
+        matches!(
+            (self, access),
+            (Watch::ReadWrite, _) | (Watch::Read, Access::Read) | (Watch::Write, Access::Write)
+        )
+    }
+}
+
+/// Why the debugger handed control back
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StopReason {
+    /// the requested instruction(s) completed
+    Step,
+    /// PC reached a breakpoint, the instruction there has not run yet
+    Breakpoint(u16),
+    /// the last instruction touched a watched address
+    Watchpoint { addr: u16, access: Access, data: u8 },
+    /// `run` executed its maximum number of instructions
+    InstructionLimit,
+}
+
+/// Drives a [`Bus16`] one instruction at a time.
+///
+/// Breakpoints are checked before an instruction executes, watchpoints
+/// after the instruction that triggered them has completed.
+#[derive(Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    watchpoints: BTreeMap<u16, Watch>,
+    trace: Option<Vec<String>>,
+}
+
+/// Forwards to the real bus and records the first watched access
+struct WatchBus<'a> {
+    bus: &'a mut dyn Bus,
+    watchpoints: &'a BTreeMap<u16, Watch>,
+    hit: &'a mut Option<StopReason>,
+}
+
+impl WatchBus<'_> {
+    fn check(&mut self, addr: u16, access: Access, data: u8) {
+        if self.hit.is_none()
+            && self
+                .watchpoints
+                .get(&addr)
+                .is_some_and(|w| w.matches(access))
+        {
+            *self.hit = Some(StopReason::Watchpoint { addr, access, data });
+        }
+    }
+}
+
+impl Bus for WatchBus<'_> {
+    fn read(&mut self, addr: u16) -> u8 {
+        let data = self.bus.read(addr);
+        self.check(addr, Access::Read, data);
+        data
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        self.bus.write(addr, data);
+        self.check(addr, Access::Write, data);
+    }
+
+    fn peek(&mut self, addr: u16) -> u8 {
+        self.bus.peek(addr)
+    }
+
+    fn tick(&mut self) {
+        self.bus.tick();
+    }
+}
+
+impl Debugger {
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn add_watchpoint(&mut self, addr: u16, watch: Watch) {
+        self.watchpoints.insert(addr, watch);
+    }
+
+    pub fn remove_watchpoint(&mut self, addr: u16) -> bool {
+        self.watchpoints.remove(&addr).is_some()
+    }
+
+    pub fn watchpoints(&self) -> impl Iterator<Item = (u16, Watch)> + '_ {
+        self.watchpoints.iter().map(|(addr, watch)| (*addr, *watch))
+    }
+
+    /// Starts or stops recording a nestest.log style line before each instruction
+    pub fn set_trace(&mut self, enabled: bool) {
+        self.trace = match enabled {
+            true => Some(self.trace.take().unwrap_or_default()),
+            false => None,
+        };
+    }
+
+    /// Returns the trace recorded so far and starts a new one
+    pub fn take_trace(&mut self) -> Vec<String> {
+        self.trace.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    pub fn registers(&self, nes: &Bus16) -> Registers {
+        nes.cpu.registers()
+    }
+
+    pub fn flag(&self, nes: &Bus16, flag: u8) -> bool {
+        nes.cpu.get_flag(flag) != 0
+    }
+
+    fn clock(&self, nes: &mut Bus16, hit: &mut Option<StopReason>) {
+        nes.clock_with(&mut |cpu, bus| {
+            let mut bus = WatchBus {
+                bus,
+                watchpoints: &self.watchpoints,
+                hit,
+            };
+            cpu.clock(&mut bus);
+        });
+    }
+
+    /// Executes exactly one instruction, along with any interrupt or DMA it triggers
+    pub fn step(&mut self, nes: &mut Bus16) -> StopReason {
+        let mut hit = None;
+
+        // finish whatever is in flight (reset, interrupt entry, DMA) so PC is at an instruction
+        while !nes.cpu.complete() || nes.dma_stalled() {
+            self.clock(nes, &mut hit);
+        }
+
+        if let Some(trace) = &mut self.trace {
+            let registers = nes.cpu.registers();
+            let ppu = (nes.ppu.scanline(), nes.ppu.cycle());
+            let cycles = nes.cpu.total_cycles();
+            trace.push(trace_line(nes, registers, ppu, cycles));
+        }
+
+        loop {
+            self.clock(nes, &mut hit);
+            if nes.cpu.complete() && !nes.dma_stalled() {
+                break;
+            }
+        }
+
+        hit.unwrap_or(StopReason::Step)
+    }
+
+    /// Like `step`, but runs a JSR's subroutine to completion
+    pub fn step_over(&mut self, nes: &mut Bus16) -> StopReason {
+        const JSR: u8 = 0x20;
+
+        let start = nes.cpu.registers();
+        if nes.peek(start.pc) != JSR {
+            return self.step(nes);
+        }
+
+        let return_addr = start.pc.wrapping_add(3);
+        let mut reason = self.step(nes);
+        loop {
+            if reason != StopReason::Step {
+                return reason;
+            }
+            let registers = nes.cpu.registers();
+            if registers.pc == return_addr && registers.sp == start.sp {
+                return StopReason::Step;
+            }
+            if self.breakpoints.contains(&registers.pc) {
+                return StopReason::Breakpoint(registers.pc);
+            }
+            reason = self.step(nes);
+        }
+    }
+
+    /// Runs until a breakpoint or watchpoint is hit, or `max_instructions` have executed.
+    /// A breakpoint at the current PC does not stop the first instruction, so
+    /// calling `run` again after a breakpoint continues past it
+    pub fn run(&mut self, nes: &mut Bus16, max_instructions: usize) -> StopReason {
+        for _ in 0..max_instructions {
+            let reason = self.step(nes);
+            if reason != StopReason::Step {
+                return reason;
+            }
+            let pc = nes.cpu.registers().pc;
+            if self.breakpoints.contains(&pc) {
+                return StopReason::Breakpoint(pc);
+            }
+        }
+        StopReason::InstructionLimit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::tests::rom;
+    use crate::{Cartridge, FlagsOLC6502};
+
+    /// an NROM cartridge with `program` at $8000
+    fn nes(program: &[u8]) -> Bus16 {
+        let mut bytes = rom(0, 1, 1, 0);
+        bytes[16..16 + program.len()].copy_from_slice(program);
+        bytes[16 + 0x3FFC..16 + 0x3FFE].copy_from_slice(&[0x00, 0x80]);
+
+        let mut nes = Bus16::default();
+        nes.insert_cartridge(Cartridge::from_bytes(&bytes).unwrap());
+        nes.reset();
+        nes
+    }
+
+    #[rustfmt::skip]
+    const PROGRAM: [u8; 14] = [
+        0xA2, 0x05,       // $8000 LDX #$05
+        0x20, 0x0B, 0x80, // $8002 JSR $800B
+        0x8E, 0x00, 0x02, // $8005 STX $0200
+        0x4C, 0x08, 0x80, // $8008 JMP $8008
+        0xCA,             // $800B DEX
+        0x60,             // $800C RTS
+        0x00,
+    ];
+
+    #[test]
+    fn test_step_and_inspect() {
+        let mut nes = nes(&PROGRAM);
+        let mut debugger = Debugger::default();
+
+        assert_eq!(debugger.step(&mut nes), StopReason::Step);
+        let registers = debugger.registers(&nes);
+        assert_eq!((registers.pc, registers.x), (0x8002, 0x05));
+        assert!(!debugger.flag(&nes, FlagsOLC6502::ZERO));
+
+        debugger.step(&mut nes);
+        assert_eq!(debugger.registers(&nes).pc, 0x800B);
+        assert_eq!(debugger.registers(&nes).sp, 0xFB);
+        debugger.step(&mut nes);
+        debugger.step(&mut nes);
+        assert_eq!(debugger.registers(&nes).pc, 0x8005);
+        assert_eq!(debugger.registers(&nes).x, 0x04);
+    }
+
+    #[test]
+    fn test_step_over() {
+        let mut nes = nes(&PROGRAM);
+        let mut debugger = Debugger::default();
+
+        debugger.step(&mut nes);
+        assert_eq!(debugger.step_over(&mut nes), StopReason::Step);
+        assert_eq!(debugger.registers(&nes).pc, 0x8005);
+        assert_eq!(debugger.registers(&nes).x, 0x04);
+
+        // breakpoints inside the subroutine still stop it
+        let mut nes = self::nes(&PROGRAM);
+        debugger.add_breakpoint(0x800C);
+        debugger.step(&mut nes);
+        assert_eq!(debugger.step_over(&mut nes), StopReason::Breakpoint(0x800C));
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut nes = nes(&PROGRAM);
+        let mut debugger = Debugger::default();
+        debugger.add_breakpoint(0x8008);
+
+        assert_eq!(debugger.run(&mut nes, 100), StopReason::Breakpoint(0x8008));
+        assert_eq!(nes.read(0x0200), 0x04);
+        // JMP $8008 loops back onto the breakpoint
+        assert_eq!(debugger.run(&mut nes, 100), StopReason::Breakpoint(0x8008));
+
+        assert!(debugger.remove_breakpoint(0x8008));
+        assert_eq!(debugger.run(&mut nes, 100), StopReason::InstructionLimit);
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let mut nes = nes(&PROGRAM);
+        let mut debugger = Debugger::default();
+        debugger.add_watchpoint(0x0200, Watch::Write);
+        // the return address JSR pushes
+        debugger.add_watchpoint(0x01FD, Watch::Read);
+
+        assert_eq!(
+            debugger.run(&mut nes, 100),
+            StopReason::Watchpoint {
+                addr: 0x01FD,
+                access: Access::Read,
+                data: 0x80,
+            }
+        );
+        assert_eq!(debugger.registers(&nes).pc, 0x8005);
+        assert_eq!(
+            debugger.run(&mut nes, 100),
+            StopReason::Watchpoint {
+                addr: 0x0200,
+                access: Access::Write,
+                data: 0x04,
+            }
+        );
+        assert_eq!(debugger.registers(&nes).pc, 0x8008);
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut nes = nes(&PROGRAM);
+        let mut debugger = Debugger::default();
+        debugger.set_trace(true);
+        debugger.run(&mut nes, 2);
+
+        assert_eq!(
+            debugger.take_trace(),
+            vec![
+                "8000  A2 05     LDX #$05                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7",
+                "8002  20 0B 80  JSR $800B                       A:00 X:05 Y:00 P:24 SP:FD PPU:  0, 27 CYC:9",
+            ]
+        );
+        assert!(debugger.take_trace().is_empty());
+    }
+
+    /// Diffs the trace of an assembled loop against lines worked out by hand,
+    /// across the PPU wrapping onto the next scanline
+    #[test]
+    fn test_trace_log() {
+        let program = crate::assemble(
+            "
+                    .org $8000
+                    ldx #$1E
+                    stx $10
+            loop:   dex
+                    bne loop
+                    stx $10
+            done:   jmp done
+            ",
+        )
+        .unwrap();
+        let mut nes = nes(&program.segments()[0].bytes);
+        let mut debugger = Debugger::default();
+        debugger.set_trace(true);
+        debugger.run(&mut nes, 63);
+
+        // 2 lines before the loop, and then DEX and BNE 30 times
+        let trace = debugger.take_trace();
+        assert_eq!(trace.len(), 63);
+        let expected = [
+            (0, "8000  A2 1E     LDX #$1E                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7"),
+            (1, "8002  86 10     STX $10 = 00                    A:00 X:1E Y:00 P:24 SP:FD PPU:  0, 27 CYC:9"),
+            (2, "8004  CA        DEX                             A:00 X:1E Y:00 P:24 SP:FD PPU:  0, 36 CYC:12"),
+            (3, "8005  D0 FD     BNE $8004                       A:00 X:1D Y:00 P:24 SP:FD PPU:  0, 42 CYC:14"),
+            (42, "8004  CA        DEX                             A:00 X:0A Y:00 P:24 SP:FD PPU:  0,336 CYC:112"),
+            (43, "8005  D0 FD     BNE $8004                       A:00 X:09 Y:00 P:24 SP:FD PPU:  1,  1 CYC:114"),
+            (60, "8004  CA        DEX                             A:00 X:01 Y:00 P:24 SP:FD PPU:  1,130 CYC:157"),
+            (61, "8005  D0 FD     BNE $8004                       A:00 X:00 Y:00 P:26 SP:FD PPU:  1,136 CYC:159"),
+            (62, "8007  86 10     STX $10 = 1E                    A:00 X:00 Y:00 P:26 SP:FD PPU:  1,142 CYC:161"),
+        ];
+        for (number, line) in expected {
+            assert_eq!(trace[number], line, "line {}", number + 1);
+        }
+    }
+
+    /// Diffs a run of nestest.nes, started at $C000 in automation mode,
+    /// against the reference log until the first unofficial opcode
+    #[test]
+    #[ignore = "requires assets/nes/nestest.nes and assets/nes/nestest.log"]
+    fn test_nestest_log() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../assets/nes");
+        let log = std::fs::read_to_string(format!("{}/nestest.log", dir)).unwrap();
+
+        let mut nes = Bus16::default();
+        nes.insert_cartridge(Cartridge::load(format!("{}/nestest.nes", dir)).unwrap());
+        nes.reset();
+        let registers = nes.cpu.registers();
+        nes.cpu.set_registers(Registers {
+            pc: 0xC000,
+            ..registers
+        });
+
+        let mut debugger = Debugger::default();
+        debugger.set_trace(true);
+        for (number, expected) in log.lines().enumerate() {
+            if expected.as_bytes()[15] == b'*' {
+                break;
+            }
+            debugger.step(&mut nes);
+            assert_eq!(debugger.take_trace()[0], expected, "line {}", number + 1);
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use crate::{AddrMode, Bus, Registers, OLC6502};
+use std::fmt;
+
+/// A single decoded instruction
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Disassembly {
+    pub addr: u16,
+    pub bytes: Vec<u8>,
+    pub mnemonic: &'static str,
+    /// the operand in the addressing mode's assembler syntax, e.g. `($20),Y`
+    pub operand: String,
+}
+
+impl Disassembly {
+    /// address of the instruction that follows this one in memory
+    pub fn next_addr(&self) -> u16 {
+        self.addr.wrapping_add(self.bytes.len() as u16)
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = format!("{} {}", self.mnemonic, self.operand);
+        write!(
+            f,
+            "{:04X}  {:<8}  {}",
+            self.addr,
+            hex_bytes(&self.bytes),
+            text.trim_end()
+        )
+    }
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// shifts and rotates in implied mode operate on the accumulator
+fn is_accumulator(mnemonic: &str, mode: AddrMode) -> bool {
+    mode == AddrMode::IMP && matches!(mnemonic, "ASL" | "LSR" | "ROL" | "ROR")
+}
+
+fn branch_target(addr: u16, offset: u8) -> u16 {
+    addr.wrapping_add(2).wrapping_add(offset as i8 as u16)
+}
+
+/// reads a pointer from the zero page, wrapping within it
+fn peek_zp_u16(bus: &mut dyn Bus, addr: u8) -> u16 {
+    u16::from_le_bytes([bus.peek(addr as u16), bus.peek(addr.wrapping_add(1) as u16)])
+}
+
+/// Decodes the instruction at `addr`. Memory is read with [`Bus::peek`]
+pub fn disassemble(bus: &mut dyn Bus, addr: u16) -> Disassembly {
+    let opcode = bus.peek(addr);
+    let instruction = OLC6502::lookup(opcode);
+    let mode = instruction.addrmode();
+
+    let bytes: Vec<u8> = (0..=mode.operand_bytes())
+        .map(|offset| bus.peek(addr.wrapping_add(offset)))
+        .collect();
+    let byte = bytes.get(1).copied().unwrap_or(0);
+    let word = u16::from_le_bytes([byte, bytes.get(2).copied().unwrap_or(0)]);
+
+    let operand = match mode {
+        AddrMode::IMP if is_accumulator(instruction.name(), mode) => "A".to_string(),
+        AddrMode::IMP => String::new(),
+        AddrMode::IMM => format!("#${:02X}", byte),
+        AddrMode::ZP0 => format!("${:02X}", byte),
+        AddrMode::ZPX => format!("${:02X},X", byte),
+        AddrMode::ZPY => format!("${:02X},Y", byte),
+        AddrMode::REL => format!("${:04X}", branch_target(addr, byte)),
+        AddrMode::ABS => format!("${:04X}", word),
+        AddrMode::ABX => format!("${:04X},X", word),
+        AddrMode::ABY => format!("${:04X},Y", word),
+        AddrMode::IND => format!("(${:04X})", word),
+        AddrMode::IZX => format!("(${:02X},X)", byte),
+        AddrMode::IZY => format!("(${:02X}),Y", byte),
+    };
+
+    Disassembly {
+        addr,
+        bytes,
+        mnemonic: instruction.name(),
+        operand,
+    }
+}
+
+/// Decodes `count` consecutive instructions starting at `addr`
+pub fn disassemble_range(bus: &mut dyn Bus, addr: u16, count: usize) -> Vec<Disassembly> {
+    let mut addr = addr;
+    (0..count)
+        .map(|_| {
+            let disassembly = disassemble(bus, addr);
+            addr = disassembly.next_addr();
+            disassembly
+        })
+        .collect()
+}
+
+/// The operand as nestest.log prints it: with the effective address and
+/// the value found there before the instruction runs
+fn annotated_operand(
+    bus: &mut dyn Bus,
+    registers: &Registers,
+    disassembly: &Disassembly,
+) -> String {
+    let instruction = OLC6502::lookup(disassembly.bytes[0]);
+    let operand = &disassembly.operand;
+    let byte = disassembly.bytes.get(1).copied().unwrap_or(0);
+    let word = u16::from_le_bytes([byte, disassembly.bytes.get(2).copied().unwrap_or(0)]);
+
+    match instruction.addrmode() {
+        AddrMode::IMP | AddrMode::IMM | AddrMode::REL => operand.clone(),
+        AddrMode::ZP0 => format!("{} = {:02X}", operand, bus.peek(byte as u16)),
+        AddrMode::ZPX | AddrMode::ZPY => {
+            let index = match instruction.addrmode() {
+                AddrMode::ZPX => registers.x,
+                _ => registers.y,
+            };
+            let addr = byte.wrapping_add(index);
+            format!("{} @ {:02X} = {:02X}", operand, addr, bus.peek(addr as u16))
+        }
+        AddrMode::ABS => match instruction.name() {
+            "JMP" | "JSR" => operand.clone(),
+            _ => format!("{} = {:02X}", operand, bus.peek(word)),
+        },
+        AddrMode::ABX | AddrMode::ABY => {
+            let index = match instruction.addrmode() {
+                AddrMode::ABX => registers.x,
+                _ => registers.y,
+            };
+            let addr = word.wrapping_add(index as u16);
+            format!("{} @ {:04X} = {:02X}", operand, addr, bus.peek(addr))
+        }
+        AddrMode::IND => {
+            // same page wrapping bug as the CPU
+            let hi_addr = (word & 0xFF00) | (word.wrapping_add(1) & 0x00FF);
+            let target = u16::from_le_bytes([bus.peek(word), bus.peek(hi_addr)]);
+            format!("{} = {:04X}", operand, target)
+        }
+        AddrMode::IZX => {
+            let ptr = byte.wrapping_add(registers.x);
+            let addr = peek_zp_u16(bus, ptr);
+            format!(
+                "{} @ {:02X} = {:04X} = {:02X}",
+                operand,
+                ptr,
+                addr,
+                bus.peek(addr)
+            )
+        }
+        AddrMode::IZY => {
+            let base = peek_zp_u16(bus, byte);
+            let addr = base.wrapping_add(registers.y as u16);
+            format!(
+                "{} = {:04X} @ {:04X} = {:02X}",
+                operand,
+                base,
+                addr,
+                bus.peek(addr)
+            )
+        }
+    }
+}
+
+/// Formats the state before executing the instruction at PC the way nestest.log does,
+/// so runs can be diffed against it (or each other) line by line.
+///
+/// `ppu` is the (scanline, dot) position, with the pre-render line numbered 261
+pub fn trace_line(bus: &mut dyn Bus, registers: Registers, ppu: (i16, u16), cycles: u64) -> String {
+    let disassembly = disassemble(bus, registers.pc);
+    let official = OLC6502::lookup(disassembly.bytes[0]).is_official();
+    let text = format!(
+        "{} {}",
+        disassembly.mnemonic,
+        annotated_operand(bus, &registers, &disassembly)
+    );
+    let scanline = match ppu.0 {
+        -1 => 261,
+        scanline => scanline,
+    };
+
+    format!(
+        "{:04X}  {:<8} {}{:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+        registers.pc,
+        hex_bytes(&disassembly.bytes),
+        match official {
+            true => ' ',
+            false => '*',
+        },
+        text.trim_end(),
+        registers.a,
+        registers.x,
+        registers.y,
+        registers.status,
+        registers.sp,
+        scanline,
+        ppu.1,
+        cycles
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Mem64K;
+
+    fn load(program: &[u8], at: u16) -> Mem64K {
+        let mut ram = Mem64K::default();
+        for (offset, byte) in program.iter().enumerate() {
+            ram.write(at + offset as u16, *byte);
+        }
+        ram
+    }
+
+    #[test]
+    fn test_addressing_mode_syntax() {
+        #[rustfmt::skip]
+        let mut ram = load(&[
+            0xEA,             // NOP
+            0x0A,             // ASL A
+            0xA9, 0x10,       // LDA #$10
+            0xA5, 0x20,       // LDA $20
+            0xB5, 0x20,       // LDA $20,X
+            0xB6, 0x20,       // LDX $20,Y
+            0xD0, 0xFC,       // BNE $8008
+            0xAD, 0x34, 0x12, // LDA $1234
+            0xBD, 0x34, 0x12, // LDA $1234,X
+            0xB9, 0x34, 0x12, // LDA $1234,Y
+            0x6C, 0x34, 0x12, // JMP ($1234)
+            0xA1, 0x20,       // LDA ($20,X)
+            0xB1, 0x20,       // LDA ($20),Y
+            0x02,             // unofficial
+        ], 0x8000);
+
+        let lines: Vec<String> = disassemble_range(&mut ram, 0x8000, 14)
+            .iter()
+            .map(|disassembly| disassembly.to_string())
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "8000  EA        NOP",
+                "8001  0A        ASL A",
+                "8002  A9 10     LDA #$10",
+                "8004  A5 20     LDA $20",
+                "8006  B5 20     LDA $20,X",
+                "8008  B6 20     LDX $20,Y",
+                "800A  D0 FC     BNE $8008",
+                "800C  AD 34 12  LDA $1234",
+                "800F  BD 34 12  LDA $1234,X",
+                "8012  B9 34 12  LDA $1234,Y",
+                "8015  6C 34 12  JMP ($1234)",
+                "8018  A1 20     LDA ($20,X)",
+                "801A  B1 20     LDA ($20),Y",
+                "801C  02        ???",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_nestest_trace_format() {
+        let mut ram = load(&[0x4C, 0xF5, 0xC5], 0xC000);
+        let registers = Registers {
+            pc: 0xC000,
+            sp: 0xFD,
+            status: 0x24,
+            ..Registers::default()
+        };
+        assert_eq!(
+            trace_line(&mut ram, registers, (0, 21), 7),
+            "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7"
+        );
+    }
+
+    #[test]
+    fn test_trace_annotations() {
+        #[rustfmt::skip]
+        let mut ram = load(&[
+            0x86, 0x00,       // STX $00
+            0xB5, 0xFF,       // LDA $FF,X
+            0xBD, 0xFF, 0x02, // LDA $02FF,X
+            0x6C, 0xFF, 0x02, // JMP ($02FF)
+            0xA1, 0x80,       // LDA ($80,X)
+            0xB1, 0x89,       // LDA ($89),Y
+        ], 0xC000);
+        ram.write(0x0000, 0x5A);
+        ram.write(0x0001, 0x11);
+        ram.write(0x0081, 0x00);
+        ram.write(0x0082, 0x02);
+        ram.write(0x0089, 0x00);
+        ram.write(0x008A, 0x03);
+        ram.write(0x0200, 0xDB);
+        ram.write(0x02FF, 0x7E);
+        ram.write(0x0300, 0x89);
+        ram.write(0x0302, 0x42);
+
+        let registers = Registers {
+            x: 0x01,
+            y: 0x02,
+            ..Registers::default()
+        };
+        let annotations: Vec<String> = disassemble_range(&mut ram, 0xC000, 6)
+            .iter()
+            .map(|disassembly| annotated_operand(&mut ram, &registers, disassembly))
+            .collect();
+        assert_eq!(
+            annotations,
+            vec![
+                "$00 = 5A",
+                "$FF,X @ 00 = 5A",
+                "$02FF,X @ 0300 = 89",
+                "($02FF) = DB7E",
+                "($80,X) @ 81 = 0200 = DB",
+                "($89),Y = 0300 @ 0302 = 42",
+            ]
+        );
+    }
+}
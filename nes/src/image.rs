@@ -0,0 +1,64 @@
+use png::{BitDepth, ColorType, Encoder};
+use std::io::{self, Write};
+
+/// Writes packed 8 bit RGB as a binary PPM (P6)
+pub fn write_ppm(
+    writer: &mut impl Write,
+    width: usize,
+    height: usize,
+    rgb: &[u8],
+) -> io::Result<()> {
+    assert_eq!(rgb.len(), width * height * 3, "expected packed RGB");
+    write!(writer, "P6\n{} {}\n255\n", width, height)?;
+    writer.write_all(rgb)
+}
+
+/// Writes packed 8 bit RGB as a PNG
+pub fn write_png(
+    writer: &mut impl Write,
+    width: usize,
+    height: usize,
+    rgb: &[u8],
+) -> io::Result<()> {
+    assert_eq!(rgb.len(), width * height * 3, "expected packed RGB");
+
+    let mut encoder = Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(ColorType::Rgb);
+    encoder.set_depth(BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgb)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ppm() {
+        let mut bytes = Vec::new();
+        write_ppm(&mut bytes, 2, 1, &[255, 0, 0, 0, 0, 255]).unwrap();
+        assert_eq!(bytes, b"P6\n2 1\n255\n\xFF\x00\x00\x00\x00\xFF");
+    }
+
+    #[test]
+    fn test_png() {
+        let (width, height) = (300, 240);
+        let rgb: Vec<u8> = (0..width * height * 3).map(|i| i as u8).collect();
+        let mut bytes = Vec::new();
+        write_png(&mut bytes, width, height, &rgb).unwrap();
+
+        let mut reader = png::Decoder::new(bytes.as_slice()).read_info().unwrap();
+        let mut decoded = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut decoded).unwrap();
+        assert_eq!((info.width, info.height), (300, 240));
+        assert_eq!(
+            (info.color_type, info.bit_depth),
+            (ColorType::Rgb, BitDepth::Eight)
+        );
+        assert_eq!(&decoded[..info.buffer_size()], rgb.as_slice());
+    }
+}
@@ -1,9 +1,34 @@
 #![allow(dead_code, unused_variables)]
 
+mod apu;
+mod asm;
 mod bus;
+mod cartridge;
+mod controller;
 mod cpu;
+mod debugger;
+mod disasm;
+mod image;
+pub mod mapper;
 mod mem;
+mod movie;
+mod ppu;
+mod ring_buffer;
+mod savestate;
+mod wav;
 
+pub use apu::*;
+pub use asm::*;
 pub use bus::*;
+pub use cartridge::*;
+pub use controller::*;
 pub use cpu::*;
+pub use debugger::*;
+pub use disasm::*;
+pub use image::*;
 pub use mem::*;
+pub use movie::*;
+pub use ppu::*;
+pub use ring_buffer::*;
+pub use savestate::*;
+pub use wav::*;
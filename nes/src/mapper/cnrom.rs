@@ -0,0 +1,56 @@
+use super::{prg_ram, CpuMapped, Mapper, CHR_BANK_8K};
+use crate::{SaveState, SaveStateError, StateReader, StateWriter};
+
+/// Mapper 3. Fixed PRG like NROM with a switchable 8K CHR bank
+pub struct CnRom {
+    chr_banks: usize,
+    chr_bank: usize,
+}
+
+impl CnRom {
+    pub fn new(chr_banks: usize) -> Self {
+        Self {
+            chr_banks,
+            chr_bank: 0,
+        }
+    }
+}
+
+impl SaveState for CnRom {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.u32(self.chr_bank as u32);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.chr_bank = state.u32()? as usize;
+        Ok(())
+    }
+}
+
+impl Mapper for CnRom {
+    fn cpu_map_read(&self, addr: u16) -> CpuMapped {
+        match addr {
+            // the cartridge wraps offsets, which also mirrors a single 16K bank
+            0x8000..=0xFFFF => CpuMapped::PrgRom((addr & 0x7FFF) as usize),
+            _ => prg_ram(addr),
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, data: u8) -> CpuMapped {
+        match addr {
+            0x8000..=0xFFFF => {
+                self.chr_bank = data as usize % self.chr_banks.max(1);
+                CpuMapped::None
+            }
+            _ => prg_ram(addr),
+        }
+    }
+
+    fn ppu_map(&self, addr: u16) -> usize {
+        self.chr_bank * CHR_BANK_8K + (addr & 0x1FFF) as usize
+    }
+
+    fn reset(&mut self) {
+        self.chr_bank = 0;
+    }
+}
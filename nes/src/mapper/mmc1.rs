@@ -0,0 +1,193 @@
+use super::{prg_ram, CpuMapped, Mapper, CHR_BANK_4K, PRG_BANK_16K};
+use crate::{Mirroring, SaveState, SaveStateError, StateReader, StateWriter};
+
+/// Mapper 1. Registers are loaded one bit at a time through a 5 bit shift register
+pub struct Mmc1 {
+    prg_banks: usize,
+
+    shift: u8,
+    shift_count: u8,
+
+    control: u8,
+    chr_bank0: u8,
+    chr_bank1: u8,
+    prg_bank: u8,
+}
+
+impl Mmc1 {
+    pub fn new(prg_banks: usize) -> Self {
+        Self {
+            prg_banks,
+            shift: 0,
+            shift_count: 0,
+            // power up in PRG mode 3, last bank fixed at $C000
+            control: 0x0C,
+            chr_bank0: 0,
+            chr_bank1: 0,
+            prg_bank: 0,
+        }
+    }
+
+    fn load_register(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x8000..=0x9FFF => self.control = value,
+            0xA000..=0xBFFF => self.chr_bank0 = value,
+            0xC000..=0xDFFF => self.chr_bank1 = value,
+            _ => self.prg_bank = value & 0x0F,
+        }
+    }
+}
+
+impl SaveState for Mmc1 {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.u8(self.shift);
+        state.u8(self.shift_count);
+        state.u8(self.control);
+        state.u8(self.chr_bank0);
+        state.u8(self.chr_bank1);
+        state.u8(self.prg_bank);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.shift = state.u8()?;
+        self.shift_count = state.u8()?;
+        self.control = state.u8()?;
+        self.chr_bank0 = state.u8()?;
+        self.chr_bank1 = state.u8()?;
+        self.prg_bank = state.u8()?;
+        if self.shift_count >= 5 {
+            return Err(SaveStateError::InvalidData("MMC1 shift register"));
+        }
+        Ok(())
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn cpu_map_read(&self, addr: u16) -> CpuMapped {
+        if addr < 0x8000 {
+            return prg_ram(addr);
+        }
+
+        let bank = self.prg_bank as usize;
+        let last = self.prg_banks.max(1) - 1;
+        let offset = (addr & 0x3FFF) as usize;
+
+        match ((self.control >> 2) & 0x03, addr) {
+            // 32K mode ignores the low bit of the bank number
+            (0 | 1, _) => CpuMapped::PrgRom((bank & !1) * PRG_BANK_16K + (addr & 0x7FFF) as usize),
+            // first bank fixed at $8000
+            (2, 0x8000..=0xBFFF) => CpuMapped::PrgRom(offset),
+            (2, _) => CpuMapped::PrgRom(bank * PRG_BANK_16K + offset),
+            // last bank fixed at $C000
+            (_, 0x8000..=0xBFFF) => CpuMapped::PrgRom(bank * PRG_BANK_16K + offset),
+            (_, _) => CpuMapped::PrgRom(last * PRG_BANK_16K + offset),
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, data: u8) -> CpuMapped {
+        if addr < 0x8000 {
+            return prg_ram(addr);
+        }
+
+        if data & 0x80 != 0 {
+            self.shift = 0;
+            self.shift_count = 0;
+            self.control |= 0x0C;
+            return CpuMapped::None;
+        }
+
+        self.shift |= (data & 0x01) << self.shift_count;
+        self.shift_count += 1;
+        if self.shift_count == 5 {
+            self.load_register(addr, self.shift);
+            self.shift = 0;
+            self.shift_count = 0;
+        }
+
+        CpuMapped::None
+    }
+
+    fn ppu_map(&self, addr: u16) -> usize {
+        let offset = (addr & 0x0FFF) as usize;
+        match (self.control & 0x10 != 0, addr) {
+            // 8K mode ignores the low bit of the bank number
+            (false, _) => (self.chr_bank0 & !1) as usize * CHR_BANK_4K + (addr & 0x1FFF) as usize,
+            (true, 0x0000..=0x0FFF) => self.chr_bank0 as usize * CHR_BANK_4K + offset,
+            (true, _) => self.chr_bank1 as usize * CHR_BANK_4K + offset,
+        }
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(match self.control & 0x03 {
+            0 => Mirroring::SingleScreenLow,
+            1 => Mirroring::SingleScreenHigh,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        })
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(self.prg_banks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cartridge::tests::rom;
+    use crate::{Cartridge, Mirroring};
+
+    /// loads a 5 bit register the way games do, LSB first
+    fn load(cart: &mut Cartridge, addr: u16, value: u8) {
+        for bit in 0..5 {
+            cart.cpu_write(addr, (value >> bit) & 0x01);
+        }
+    }
+
+    #[test]
+    fn test_prg_banking() {
+        let mut cart = Cartridge::from_bytes(&rom(1, 8, 2, 0)).unwrap();
+        assert_eq!(cart.cpu_read(0x8000), Some(0));
+        assert_eq!(cart.cpu_read(0xC000), Some(7));
+
+        load(&mut cart, 0xE000, 3);
+        assert_eq!(cart.cpu_read(0x8000), Some(3));
+        assert_eq!(cart.cpu_read(0xC000), Some(7));
+
+        // fix the first bank at $8000
+        load(&mut cart, 0x8000, 0b01000);
+        assert_eq!(cart.cpu_read(0x8000), Some(0));
+        assert_eq!(cart.cpu_read(0xC000), Some(3));
+
+        // 32K
+        load(&mut cart, 0x8000, 0b00000);
+        assert_eq!(cart.cpu_read(0x8000), Some(2));
+        assert_eq!(cart.cpu_read(0xC000), Some(3));
+    }
+
+    #[test]
+    fn test_shift_register_reset() {
+        let mut cart = Cartridge::from_bytes(&rom(1, 8, 2, 0)).unwrap();
+        cart.cpu_write(0xE000, 1);
+        cart.cpu_write(0xE000, 1);
+        cart.cpu_write(0xE000, 0x80);
+        load(&mut cart, 0xE000, 2);
+        assert_eq!(cart.cpu_read(0x8000), Some(2));
+    }
+
+    #[test]
+    fn test_chr_banking_and_mirroring() {
+        let mut cart = Cartridge::from_bytes(&rom(1, 2, 2, 0)).unwrap();
+        load(&mut cart, 0x8000, 0b11110);
+        assert_eq!(cart.mirroring(), Mirroring::Vertical);
+
+        // 4K banks: the second half of CHR bank 0 and the first half of CHR bank 1
+        load(&mut cart, 0xA000, 1);
+        load(&mut cart, 0xC000, 2);
+        assert_eq!(cart.ppu_read(0x0000), 0x80);
+        assert_eq!(cart.ppu_read(0x1000), 0x81);
+
+        load(&mut cart, 0x8000, 0b00001);
+        assert_eq!(cart.mirroring(), Mirroring::SingleScreenHigh);
+        assert_eq!(cart.ppu_read(0x1000), 0x80);
+    }
+}
@@ -0,0 +1,216 @@
+use super::{prg_ram, CpuMapped, Mapper, CHR_BANK_1K, PRG_BANK_8K};
+use crate::{Mirroring, SaveState, SaveStateError, StateReader, StateWriter};
+
+/// Mapper 4. 8K PRG and 1K/2K CHR banks plus a scanline counter that can raise IRQs
+pub struct Mmc3 {
+    prg_banks: usize,
+
+    bank_select: u8,
+    registers: [u8; 8],
+    mirroring: Mirroring,
+
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+}
+
+impl Mmc3 {
+    pub fn new(prg_banks: usize) -> Self {
+        Self {
+            prg_banks,
+            bank_select: 0,
+            registers: [0; 8],
+            mirroring: Mirroring::Vertical,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+        }
+    }
+
+    /// the 8K PRG bank mapped into one of the four $2000 byte windows at $8000-$FFFF
+    fn prg_bank(&self, window: usize) -> usize {
+        let second_last = (self.prg_banks * 2).max(2) - 2;
+        let swapped = self.bank_select & 0x40 != 0;
+        match (window, swapped) {
+            (0, false) | (2, true) => self.registers[6] as usize,
+            (0, true) | (2, false) => second_last,
+            (1, _) => self.registers[7] as usize,
+            _ => second_last + 1,
+        }
+    }
+
+    /// the 1K CHR bank mapped into one of the eight 1K windows of the pattern tables
+    fn chr_bank(&self, window: usize) -> usize {
+        // CHR A12 inversion swaps the 2K and 1K halves
+        let window = match self.bank_select & 0x80 != 0 {
+            true => window ^ 4,
+            false => window,
+        };
+        match window {
+            0 | 1 => (self.registers[0] & !1) as usize + window,
+            2 | 3 => (self.registers[1] & !1) as usize + window - 2,
+            _ => self.registers[window - 2] as usize,
+        }
+    }
+}
+
+impl SaveState for Mmc3 {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.u8(self.bank_select);
+        state.bytes(&self.registers);
+        self.mirroring.save_state(state);
+        state.u8(self.irq_latch);
+        state.u8(self.irq_counter);
+        state.bool(self.irq_reload);
+        state.bool(self.irq_enabled);
+        state.bool(self.irq_pending);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.bank_select = state.u8()?;
+        state.bytes(&mut self.registers)?;
+        self.mirroring.load_state(state)?;
+        self.irq_latch = state.u8()?;
+        self.irq_counter = state.u8()?;
+        self.irq_reload = state.bool()?;
+        self.irq_enabled = state.bool()?;
+        self.irq_pending = state.bool()?;
+        Ok(())
+    }
+}
+
+impl Mapper for Mmc3 {
+    fn cpu_map_read(&self, addr: u16) -> CpuMapped {
+        match addr {
+            0x8000..=0xFFFF => {
+                let window = (addr as usize - 0x8000) / PRG_BANK_8K;
+                CpuMapped::PrgRom(self.prg_bank(window) * PRG_BANK_8K + (addr & 0x1FFF) as usize)
+            }
+            _ => prg_ram(addr),
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, data: u8) -> CpuMapped {
+        let even = addr & 0x01 == 0;
+        match (addr, even) {
+            (0x8000..=0x9FFF, true) => self.bank_select = data,
+            (0x8000..=0x9FFF, false) => self.registers[(self.bank_select & 0x07) as usize] = data,
+            (0xA000..=0xBFFF, true) => {
+                self.mirroring = match data & 0x01 {
+                    0 => Mirroring::Vertical,
+                    _ => Mirroring::Horizontal,
+                }
+            }
+            // PRG RAM protect. not emulated
+            (0xA000..=0xBFFF, false) => {}
+            (0xC000..=0xDFFF, true) => self.irq_latch = data,
+            (0xC000..=0xDFFF, false) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            (0xE000..=0xFFFF, true) => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            }
+            (0xE000..=0xFFFF, false) => self.irq_enabled = true,
+            _ => return prg_ram(addr),
+        }
+        CpuMapped::None
+    }
+
+    fn ppu_map(&self, addr: u16) -> usize {
+        let addr = (addr & 0x1FFF) as usize;
+        self.chr_bank(addr / CHR_BANK_1K) * CHR_BANK_1K + addr % CHR_BANK_1K
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        Some(self.mirroring)
+    }
+
+    fn irq_pending(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn irq_clear(&mut self) {
+        self.irq_pending = false;
+    }
+
+    fn scanline(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new(self.prg_banks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cartridge::tests::rom;
+    use crate::{Cartridge, Mirroring};
+
+    #[test]
+    fn test_prg_banking() {
+        // 16K test banks are two 8K MMC3 banks each
+        let mut cart = Cartridge::from_bytes(&rom(4, 4, 2, 0)).unwrap();
+        cart.cpu_write(0x8000, 6);
+        cart.cpu_write(0x8001, 2);
+        cart.cpu_write(0x8000, 7);
+        cart.cpu_write(0x8001, 5);
+        assert_eq!(cart.cpu_read(0x8000), Some(1));
+        assert_eq!(cart.cpu_read(0xA000), Some(2));
+        assert_eq!(cart.cpu_read(0xC000), Some(3));
+        assert_eq!(cart.cpu_read(0xE000), Some(3));
+
+        cart.cpu_write(0x8000, 0x40);
+        assert_eq!(cart.cpu_read(0x8000), Some(3));
+        assert_eq!(cart.cpu_read(0xC000), Some(1));
+    }
+
+    #[test]
+    fn test_chr_banking() {
+        let mut cart = Cartridge::from_bytes(&rom(4, 2, 2, 0)).unwrap();
+        // R0 = 2K at $0000 from 1K bank 8, the start of the second 8K test bank
+        cart.cpu_write(0x8000, 0);
+        cart.cpu_write(0x8001, 9);
+        assert_eq!(cart.ppu_read(0x0000), 0x81);
+        assert_eq!(cart.ppu_read(0x1000), 0x80);
+
+        cart.cpu_write(0x8000, 0x80);
+        assert_eq!(cart.ppu_read(0x0000), 0x80);
+        assert_eq!(cart.ppu_read(0x1000), 0x81);
+    }
+
+    #[test]
+    fn test_mirroring_and_irq() {
+        let mut cart = Cartridge::from_bytes(&rom(4, 2, 2, 0)).unwrap();
+        cart.cpu_write(0xA000, 1);
+        assert_eq!(cart.mirroring(), Mirroring::Horizontal);
+
+        cart.cpu_write(0xC000, 2);
+        cart.cpu_write(0xC001, 0);
+        cart.cpu_write(0xE001, 0);
+
+        cart.scanline();
+        cart.scanline();
+        assert!(!cart.irq_pending());
+        cart.scanline();
+        assert!(cart.irq_pending());
+
+        cart.cpu_write(0xE000, 0);
+        assert!(!cart.irq_pending());
+    }
+}
@@ -0,0 +1,122 @@
+mod cnrom;
+mod mmc1;
+mod mmc3;
+mod nrom;
+mod uxrom;
+
+pub use cnrom::*;
+pub use mmc1::*;
+pub use mmc3::*;
+pub use nrom::*;
+pub use uxrom::*;
+
+use crate::{CartridgeError, Header, Mirroring, SaveState};
+
+pub(crate) const PRG_BANK_16K: usize = 16 * 1024;
+pub(crate) const PRG_BANK_8K: usize = 8 * 1024;
+pub(crate) const CHR_BANK_8K: usize = 8 * 1024;
+pub(crate) const CHR_BANK_4K: usize = 4 * 1024;
+pub(crate) const CHR_BANK_1K: usize = 1024;
+
+/// Where a CPU access to the cartridge ends up
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CpuMapped {
+    PrgRom(usize),
+    PrgRam(usize),
+    /// nothing responds, or the mapper consumed a register write
+    None,
+}
+
+/// Bank switching hardware on the cartridge.
+///
+/// Mappers only translate addresses. The cartridge owns the ROM and RAM and
+/// wraps the returned offsets to the actual memory sizes.
+///
+/// Save states only need the bank and IRQ registers, the bank counts come from the header.
+pub trait Mapper: SaveState {
+    fn cpu_map_read(&self, addr: u16) -> CpuMapped;
+    fn cpu_map_write(&mut self, addr: u16, data: u8) -> CpuMapped;
+
+    /// offset into CHR ROM/RAM for a pattern table address ($0000-$1FFF)
+    fn ppu_map(&self, addr: u16) -> usize;
+
+    /// overrides the mirroring in the header for mappers that control it
+    fn mirroring(&self) -> Option<Mirroring> {
+        None
+    }
+
+    fn irq_pending(&self) -> bool {
+        false
+    }
+
+    fn irq_clear(&mut self) {}
+
+    fn scanline(&mut self) {}
+
+    fn reset(&mut self) {}
+}
+
+pub fn new(header: &Header) -> Result<Box<dyn Mapper>, CartridgeError> {
+    let prg_banks = header.prg_rom_size / PRG_BANK_16K;
+    let chr_banks = header.chr_rom_size / CHR_BANK_8K;
+
+    let mapper: Box<dyn Mapper> = match header.mapper {
+        0 => Box::new(Nrom::new(prg_banks)),
+        1 => Box::new(Mmc1::new(prg_banks)),
+        2 => Box::new(UxRom::new(prg_banks)),
+        3 => Box::new(CnRom::new(chr_banks)),
+        4 => Box::new(Mmc3::new(prg_banks)),
+        mapper => return Err(CartridgeError::UnsupportedMapper(mapper)),
+    };
+
+    Ok(mapper)
+}
+
+/// PRG RAM at $6000-$7FFF, common to every mapper here
+pub(crate) fn prg_ram(addr: u16) -> CpuMapped {
+    match addr {
+        0x6000..=0x7FFF => CpuMapped::PrgRam((addr & 0x1FFF) as usize),
+        _ => CpuMapped::None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cartridge::tests::rom;
+    use crate::Cartridge;
+
+    #[test]
+    fn test_nrom() {
+        let mut cart = Cartridge::from_bytes(&rom(0, 1, 1, 0)).unwrap();
+        assert_eq!(cart.cpu_read(0x8000), Some(0));
+        assert_eq!(cart.cpu_read(0xC000), Some(0));
+        assert_eq!(cart.cpu_read(0x5000), None);
+
+        let mut cart = Cartridge::from_bytes(&rom(0, 2, 1, 0)).unwrap();
+        assert_eq!(cart.cpu_read(0xBFFF), Some(0));
+        assert_eq!(cart.cpu_read(0xC000), Some(1));
+    }
+
+    #[test]
+    fn test_uxrom() {
+        let mut cart = Cartridge::from_bytes(&rom(2, 8, 0, 0)).unwrap();
+        assert_eq!(cart.cpu_read(0x8000), Some(0));
+        assert_eq!(cart.cpu_read(0xC000), Some(7));
+
+        cart.cpu_write(0x8000, 5);
+        assert_eq!(cart.cpu_read(0x8000), Some(5));
+        assert_eq!(cart.cpu_read(0xBFFF), Some(5));
+        assert_eq!(cart.cpu_read(0xFFFF), Some(7));
+    }
+
+    #[test]
+    fn test_cnrom() {
+        let mut cart = Cartridge::from_bytes(&rom(3, 2, 4, 0)).unwrap();
+        assert_eq!(cart.ppu_read(0x0000), 0x80);
+
+        cart.cpu_write(0xFFFF, 2);
+        assert_eq!(cart.ppu_read(0x0000), 0x82);
+        assert_eq!(cart.ppu_read(0x1FFF), 0x82);
+        assert_eq!(cart.cpu_read(0xC000), Some(1));
+    }
+}
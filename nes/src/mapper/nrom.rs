@@ -0,0 +1,43 @@
+use super::{prg_ram, CpuMapped, Mapper};
+use crate::{SaveState, SaveStateError, StateReader, StateWriter};
+
+/// Mapper 0. 16K or 32K of PRG ROM, 8K of CHR, no bank switching
+pub struct Nrom {
+    prg_banks: usize,
+}
+
+impl Nrom {
+    pub fn new(prg_banks: usize) -> Self {
+        Self { prg_banks }
+    }
+}
+
+/// no registers
+impl SaveState for Nrom {
+    fn save_state(&self, _state: &mut StateWriter) {}
+
+    fn load_state(&mut self, _state: &mut StateReader) -> Result<(), SaveStateError> {
+        Ok(())
+    }
+}
+
+impl Mapper for Nrom {
+    fn cpu_map_read(&self, addr: u16) -> CpuMapped {
+        match addr {
+            // a single 16K bank is mirrored into $C000-$FFFF
+            0x8000..=0xFFFF => match self.prg_banks {
+                1 => CpuMapped::PrgRom((addr & 0x3FFF) as usize),
+                _ => CpuMapped::PrgRom((addr & 0x7FFF) as usize),
+            },
+            _ => prg_ram(addr),
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, data: u8) -> CpuMapped {
+        prg_ram(addr)
+    }
+
+    fn ppu_map(&self, addr: u16) -> usize {
+        addr as usize
+    }
+}
@@ -0,0 +1,59 @@
+use super::{prg_ram, CpuMapped, Mapper, PRG_BANK_16K};
+use crate::{SaveState, SaveStateError, StateReader, StateWriter};
+
+/// Mapper 2. Switchable 16K PRG bank at $8000, last bank fixed at $C000, CHR RAM
+pub struct UxRom {
+    prg_banks: usize,
+    prg_bank: usize,
+}
+
+impl UxRom {
+    pub fn new(prg_banks: usize) -> Self {
+        Self {
+            prg_banks,
+            prg_bank: 0,
+        }
+    }
+}
+
+impl SaveState for UxRom {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.u32(self.prg_bank as u32);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        self.prg_bank = state.u32()? as usize;
+        Ok(())
+    }
+}
+
+impl Mapper for UxRom {
+    fn cpu_map_read(&self, addr: u16) -> CpuMapped {
+        let offset = (addr & 0x3FFF) as usize;
+        match addr {
+            0x8000..=0xBFFF => CpuMapped::PrgRom(self.prg_bank * PRG_BANK_16K + offset),
+            0xC000..=0xFFFF => {
+                CpuMapped::PrgRom((self.prg_banks.max(1) - 1) * PRG_BANK_16K + offset)
+            }
+            _ => prg_ram(addr),
+        }
+    }
+
+    fn cpu_map_write(&mut self, addr: u16, data: u8) -> CpuMapped {
+        match addr {
+            0x8000..=0xFFFF => {
+                self.prg_bank = data as usize;
+                CpuMapped::None
+            }
+            _ => prg_ram(addr),
+        }
+    }
+
+    fn ppu_map(&self, addr: u16) -> usize {
+        addr as usize
+    }
+
+    fn reset(&mut self) {
+        self.prg_bank = 0;
+    }
+}
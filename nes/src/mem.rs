@@ -1,9 +1,11 @@
+use crate::{Bus, SaveState, SaveStateError, StateReader, StateWriter};
+
 pub struct Mem64K {
-    mem: [u8; Self::MEM_SIZE as usize],
+    mem: [u8; Self::MEM_SIZE],
 }
 
 impl Mem64K {
-    const MEM_SIZE: u16 = u16::MAX;
+    const MEM_SIZE: usize = 1 << 16;
 
     pub fn write(&mut self, addr: u16, data: u8) {
         self.mem[addr as usize] = data;
@@ -17,7 +19,27 @@ impl Mem64K {
 impl Default for Mem64K {
     fn default() -> Self {
         Self {
-            mem: [0; Self::MEM_SIZE as usize],
+            mem: [0; Self::MEM_SIZE],
         }
     }
 }
+
+impl Bus for Mem64K {
+    fn read(&mut self, addr: u16) -> u8 {
+        Mem64K::read(self, addr)
+    }
+
+    fn write(&mut self, addr: u16, data: u8) {
+        Mem64K::write(self, addr, data);
+    }
+}
+
+impl SaveState for Mem64K {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.bytes(&self.mem);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        state.bytes(&mut self.mem)
+    }
+}
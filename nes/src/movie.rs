@@ -0,0 +1,174 @@
+use crate::Bus16;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+/// Button letters from bit 7 down to bit 0, see [`crate::Button`]
+const BUTTONS: &[u8; 8] = b"RLDUTSBA";
+
+#[derive(Debug)]
+pub enum MovieError {
+    Io(io::Error),
+    /// 1 based line number of a line that isn't one or two button fields
+    InvalidLine(usize),
+}
+
+impl From<io::Error> for MovieError {
+    fn from(e: io::Error) -> Self {
+        MovieError::Io(e)
+    }
+}
+
+/// Controller input for both ports, one entry per frame.
+///
+/// The text format has a line per frame with a field per controller, each
+/// listing the buttons `RLDUTSBA` (Right, Left, Down, Up, sTart, Select, B, A)
+/// with `.` for a released button:
+///
+/// ```text
+/// # frame 0: nothing pressed, frame 1: Start, frame 2: Right + A on pad 1, B on pad 2
+/// ........ ........
+/// ....T... ........
+/// R......A ......B.
+/// ```
+///
+/// The second field may be left out. Blank lines and lines starting with `#` are skipped.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Movie {
+    frames: Vec<[u8; 2]>,
+}
+
+impl Movie {
+    pub fn load(path: impl AsRef<Path>) -> Result<Movie, MovieError> {
+        Movie::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    pub fn parse(text: &str) -> Result<Movie, MovieError> {
+        let mut frames = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || MovieError::InvalidLine(number + 1);
+            let mut input = [0; 2];
+            let mut fields = line.split_whitespace();
+            for buttons in input.iter_mut() {
+                if let Some(field) = fields.next() {
+                    *buttons = parse_buttons(field).ok_or_else(invalid)?;
+                }
+            }
+            if fields.next().is_some() {
+                return Err(invalid());
+            }
+            frames.push(input);
+        }
+        Ok(Movie { frames })
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn push(&mut self, input: [u8; 2]) {
+        self.frames.push(input);
+    }
+
+    /// The buttons held on each port during `frame`. Nothing is pressed past the end
+    pub fn input(&self, frame: usize) -> [u8; 2] {
+        self.frames.get(frame).copied().unwrap_or_default()
+    }
+
+    /// Presses the buttons for `frame` and runs it
+    pub fn play_frame(&self, nes: &mut Bus16, frame: usize) {
+        for (controller, buttons) in nes.controllers.iter_mut().zip(self.input(frame)) {
+            controller.set_buttons(buttons);
+        }
+        nes.run_frame();
+    }
+}
+
+fn parse_buttons(field: &str) -> Option<u8> {
+    if field.len() != BUTTONS.len() {
+        return None;
+    }
+    field
+        .bytes()
+        .zip(BUTTONS)
+        .try_fold(0, |buttons, (c, button)| match c {
+            b'.' => Some(buttons << 1),
+            _ if c == *button => Some((buttons << 1) | 1),
+            _ => None,
+        })
+}
+
+fn format_buttons(buttons: u8) -> String {
+    BUTTONS
+        .iter()
+        .enumerate()
+        .map(|(i, button)| match buttons & (0x80 >> i) {
+            0 => '.',
+            _ => *button as char,
+        })
+        .collect()
+}
+
+impl fmt::Display for Movie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for [pad1, pad2] in &self.frames {
+            writeln!(f, "{} {}", format_buttons(*pad1), format_buttons(*pad2))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Button;
+
+    #[test]
+    fn test_parse() {
+        let movie = Movie::parse(
+            "# a comment\n\
+             ........ ........\n\
+             \n\
+             ....T...\n\
+             R......A ......B.\n",
+        )
+        .unwrap();
+        assert_eq!(movie.len(), 3);
+        assert_eq!(movie.input(0), [0, 0]);
+        assert_eq!(movie.input(1), [Button::START, 0]);
+        assert_eq!(movie.input(2), [Button::RIGHT | Button::A, Button::B]);
+        assert_eq!(movie.input(3), [0, 0]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut movie = Movie::default();
+        movie.push([Button::UP | Button::SELECT, Button::LEFT]);
+        movie.push([0xFF, Button::DOWN]);
+        assert_eq!(movie.to_string(), "...U.S.. .L......\nRLDUTSBA ..D.....\n");
+        assert_eq!(Movie::parse(&movie.to_string()).unwrap(), movie);
+    }
+
+    #[test]
+    fn test_invalid_lines() {
+        for text in ["RLDUTSB", "A....... ........", "........ ........ ........"] {
+            assert!(matches!(
+                Movie::parse(&format!("........\n{}", text)),
+                Err(MovieError::InvalidLine(2))
+            ));
+        }
+    }
+}
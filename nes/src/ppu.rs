@@ -0,0 +1,965 @@
+use crate::{Cartridge, Mirroring, SaveState, SaveStateError, StateReader, StateWriter};
+
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+
+pub struct PpuCtrl;
+
+impl PpuCtrl {
+    pub const NAMETABLE_X: u8 = 1 << 0;
+    pub const NAMETABLE_Y: u8 = 1 << 1;
+    pub const INCREMENT_32: u8 = 1 << 2;
+    pub const SPRITE_TABLE: u8 = 1 << 3;
+    pub const BACKGROUND_TABLE: u8 = 1 << 4;
+    pub const SPRITE_SIZE_16: u8 = 1 << 5;
+    pub const ENABLE_NMI: u8 = 1 << 7;
+}
+
+pub struct PpuMask;
+
+impl PpuMask {
+    pub const GRAYSCALE: u8 = 1 << 0;
+    pub const SHOW_BACKGROUND_LEFT: u8 = 1 << 1;
+    pub const SHOW_SPRITES_LEFT: u8 = 1 << 2;
+    pub const SHOW_BACKGROUND: u8 = 1 << 3;
+    pub const SHOW_SPRITES: u8 = 1 << 4;
+}
+
+pub struct PpuStatus;
+
+impl PpuStatus {
+    pub const SPRITE_OVERFLOW: u8 = 1 << 5;
+    pub const SPRITE_ZERO_HIT: u8 = 1 << 6;
+    pub const VBLANK: u8 = 1 << 7;
+}
+
+/// A sprite as laid out in OAM
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Sprite {
+    y: u8,
+    tile: u8,
+    attr: u8,
+    x: u8,
+}
+
+/// The 2C02 picture processing unit.
+///
+/// Owns nametable VRAM, palette RAM and OAM. The pattern tables live on the
+/// cartridge, which is passed in by whoever owns both.
+pub struct Ppu2C02 {
+    vram: [u8; 4 * 1024],
+    palette: [u8; 32],
+    oam: [u8; 256],
+
+    ctrl: u8,
+    mask: u8,
+    status: u8,
+    oam_addr: u8,
+
+    // "loopy" scroll registers: current and temporary VRAM address, fine x scroll and the write toggle
+    v: u16,
+    t: u16,
+    fine_x: u8,
+    write_latch: bool,
+    data_buffer: u8,
+
+    scanline: i16,
+    cycle: u16,
+    odd_frame: bool,
+    pub frame_complete: bool,
+    nmi: bool,
+
+    bg_next_tile_id: u8,
+    bg_next_tile_attrib: u8,
+    bg_next_tile_lsb: u8,
+    bg_next_tile_msb: u8,
+    bg_shifter_pattern_lo: u16,
+    bg_shifter_pattern_hi: u16,
+    bg_shifter_attrib_lo: u16,
+    bg_shifter_attrib_hi: u16,
+
+    sprite_scanline: [Sprite; 8],
+    sprite_count: usize,
+    sprite_pattern_lo: [u8; 8],
+    sprite_pattern_hi: [u8; 8],
+    sprite_zero_on_line: bool,
+
+    frame: Vec<u8>,
+}
+
+impl Default for Ppu2C02 {
+    fn default() -> Self {
+        Self {
+            vram: [0; 4 * 1024],
+            palette: [0; 32],
+            oam: [0; 256],
+            ctrl: 0,
+            mask: 0,
+            status: 0,
+            oam_addr: 0,
+            v: 0,
+            t: 0,
+            fine_x: 0,
+            write_latch: false,
+            data_buffer: 0,
+            scanline: 0,
+            cycle: 0,
+            odd_frame: false,
+            frame_complete: false,
+            nmi: false,
+            bg_next_tile_id: 0,
+            bg_next_tile_attrib: 0,
+            bg_next_tile_lsb: 0,
+            bg_next_tile_msb: 0,
+            bg_shifter_pattern_lo: 0,
+            bg_shifter_pattern_hi: 0,
+            bg_shifter_attrib_lo: 0,
+            bg_shifter_attrib_hi: 0,
+            sprite_scanline: [Sprite::default(); 8],
+            sprite_count: 0,
+            sprite_pattern_lo: [0; 8],
+            sprite_pattern_hi: [0; 8],
+            sprite_zero_on_line: false,
+            frame: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * 3],
+        }
+    }
+}
+
+impl SaveState for Ppu2C02 {
+    fn save_state(&self, state: &mut StateWriter) {
+        state.bytes(&self.vram);
+        state.bytes(&self.palette);
+        state.bytes(&self.oam);
+
+        state.u8(self.ctrl);
+        state.u8(self.mask);
+        state.u8(self.status);
+        state.u8(self.oam_addr);
+
+        state.u16(self.v);
+        state.u16(self.t);
+        state.u8(self.fine_x);
+        state.bool(self.write_latch);
+        state.u8(self.data_buffer);
+
+        state.i16(self.scanline);
+        state.u16(self.cycle);
+        state.bool(self.odd_frame);
+        state.bool(self.frame_complete);
+        state.bool(self.nmi);
+
+        state.u8(self.bg_next_tile_id);
+        state.u8(self.bg_next_tile_attrib);
+        state.u8(self.bg_next_tile_lsb);
+        state.u8(self.bg_next_tile_msb);
+        state.u16(self.bg_shifter_pattern_lo);
+        state.u16(self.bg_shifter_pattern_hi);
+        state.u16(self.bg_shifter_attrib_lo);
+        state.u16(self.bg_shifter_attrib_hi);
+
+        for sprite in &self.sprite_scanline {
+            state.bytes(&[sprite.y, sprite.tile, sprite.attr, sprite.x]);
+        }
+        state.u8(self.sprite_count as u8);
+        state.bytes(&self.sprite_pattern_lo);
+        state.bytes(&self.sprite_pattern_hi);
+        state.bool(self.sprite_zero_on_line);
+
+        // a state saved mid frame has to resume with the top of the picture already drawn
+        state.bytes(&self.frame);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError> {
+        state.bytes(&mut self.vram)?;
+        state.bytes(&mut self.palette)?;
+        state.bytes(&mut self.oam)?;
+
+        self.ctrl = state.u8()?;
+        self.mask = state.u8()?;
+        self.status = state.u8()?;
+        self.oam_addr = state.u8()?;
+
+        self.v = state.u16()?;
+        self.t = state.u16()?;
+        self.fine_x = state.u8()?;
+        self.write_latch = state.bool()?;
+        self.data_buffer = state.u8()?;
+
+        self.scanline = state.i16()?;
+        self.cycle = state.u16()?;
+        if !(-1..=260).contains(&self.scanline) || self.cycle > 340 {
+            return Err(SaveStateError::InvalidData("PPU position"));
+        }
+        self.odd_frame = state.bool()?;
+        self.frame_complete = state.bool()?;
+        self.nmi = state.bool()?;
+
+        self.bg_next_tile_id = state.u8()?;
+        self.bg_next_tile_attrib = state.u8()?;
+        self.bg_next_tile_lsb = state.u8()?;
+        self.bg_next_tile_msb = state.u8()?;
+        self.bg_shifter_pattern_lo = state.u16()?;
+        self.bg_shifter_pattern_hi = state.u16()?;
+        self.bg_shifter_attrib_lo = state.u16()?;
+        self.bg_shifter_attrib_hi = state.u16()?;
+
+        for sprite in &mut self.sprite_scanline {
+            let mut bytes = [0; 4];
+            state.bytes(&mut bytes)?;
+            let [y, tile, attr, x] = bytes;
+            *sprite = Sprite { y, tile, attr, x };
+        }
+        self.sprite_count = state.u8()? as usize;
+        if self.sprite_count > self.sprite_scanline.len() {
+            return Err(SaveStateError::InvalidData("sprite count"));
+        }
+        state.bytes(&mut self.sprite_pattern_lo)?;
+        state.bytes(&mut self.sprite_pattern_hi)?;
+        self.sprite_zero_on_line = state.bool()?;
+
+        state.bytes(&mut self.frame)?;
+        Ok(())
+    }
+}
+
+/// CPU facing registers at $2000-$2007
+impl Ppu2C02 {
+    pub fn cpu_read(&mut self, cart: &mut Option<Cartridge>, addr: u16) -> u8 {
+        match addr & 0x0007 {
+            0x0002 => {
+                // the low bits are whatever was last on the PPU data bus
+                let data = (self.status & 0xE0) | (self.data_buffer & 0x1F);
+                self.status &= !PpuStatus::VBLANK;
+                self.write_latch = false;
+                data
+            }
+            0x0004 => self.oam[self.oam_addr as usize],
+            0x0007 => {
+                // reads are delayed by one through a buffer, except for palette RAM
+                let mut data = self.data_buffer;
+                self.data_buffer = self.read(cart, self.v);
+                if self.v & 0x3FFF >= 0x3F00 {
+                    data = self.data_buffer;
+                    // the buffer gets the nametable byte "underneath" the palette
+                    self.data_buffer = self.read(cart, self.v & 0x2FFF);
+                }
+                self.increment_v();
+                data
+            }
+            // write only registers
+            _ => 0,
+        }
+    }
+
+    pub fn cpu_write(&mut self, cart: &mut Option<Cartridge>, addr: u16, data: u8) {
+        match addr & 0x0007 {
+            0x0000 => {
+                let was_enabled = self.ctrl & PpuCtrl::ENABLE_NMI != 0;
+                self.ctrl = data;
+                self.t = (self.t & 0xF3FF) | ((data as u16 & 0x03) << 10);
+                // enabling NMI during vblank fires one straight away
+                if !was_enabled
+                    && data & PpuCtrl::ENABLE_NMI != 0
+                    && self.status & PpuStatus::VBLANK != 0
+                {
+                    self.nmi = true;
+                }
+            }
+            0x0001 => self.mask = data,
+            0x0003 => self.oam_addr = data,
+            0x0004 => self.write_oam(data),
+            0x0005 => {
+                match self.write_latch {
+                    false => {
+                        self.fine_x = data & 0x07;
+                        self.t = (self.t & !0x001F) | (data as u16 >> 3);
+                    }
+                    true => {
+                        self.t = (self.t & !0x73E0)
+                            | ((data as u16 & 0x07) << 12)
+                            | ((data as u16 >> 3) << 5);
+                    }
+                }
+                self.write_latch = !self.write_latch;
+            }
+            0x0006 => {
+                match self.write_latch {
+                    false => self.t = (self.t & 0x00FF) | ((data as u16 & 0x3F) << 8),
+                    true => {
+                        self.t = (self.t & 0xFF00) | data as u16;
+                        self.v = self.t;
+                    }
+                }
+                self.write_latch = !self.write_latch;
+            }
+            0x0007 => {
+                self.write(cart, self.v, data);
+                self.increment_v();
+            }
+            // PPUSTATUS is read only
+            _ => {}
+        }
+    }
+
+    /// Writes through OAMADDR. Used by $2004 and OAM DMA
+    pub fn write_oam(&mut self, data: u8) {
+        self.oam[self.oam_addr as usize] = data;
+        self.oam_addr = self.oam_addr.wrapping_add(1);
+    }
+
+    pub fn oam(&self) -> &[u8; 256] {
+        &self.oam
+    }
+
+    fn increment_v(&mut self) {
+        let step = match self.ctrl & PpuCtrl::INCREMENT_32 {
+            0 => 1,
+            _ => 32,
+        };
+        self.v = self.v.wrapping_add(step) & 0x7FFF;
+    }
+
+    /// true once per vblank when NMIs are enabled. clears the request
+    pub fn take_nmi(&mut self) -> bool {
+        std::mem::take(&mut self.nmi)
+    }
+
+    pub fn scanline(&self) -> i16 {
+        self.scanline
+    }
+
+    pub fn cycle(&self) -> u16 {
+        self.cycle
+    }
+}
+
+/// PPU address space
+impl Ppu2C02 {
+    pub fn read(&mut self, cart: &mut Option<Cartridge>, addr: u16) -> u8 {
+        let addr = addr & 0x3FFF;
+        match addr {
+            0x0000..=0x1FFF => match cart {
+                Some(cart) => cart.ppu_read(addr),
+                None => 0,
+            },
+            0x2000..=0x3EFF => self.vram[vram_offset(cart, addr)],
+            _ => self.palette[palette_offset(addr)],
+        }
+    }
+
+    pub fn write(&mut self, cart: &mut Option<Cartridge>, addr: u16, data: u8) {
+        let addr = addr & 0x3FFF;
+        match addr {
+            0x0000..=0x1FFF => {
+                if let Some(cart) = cart {
+                    cart.ppu_write(addr, data);
+                }
+            }
+            0x2000..=0x3EFF => self.vram[vram_offset(cart, addr)] = data,
+            _ => self.palette[palette_offset(addr)] = data & 0x3F,
+        }
+    }
+}
+
+fn vram_offset(cart: &Option<Cartridge>, addr: u16) -> usize {
+    let mirroring = match cart {
+        Some(cart) => cart.mirroring(),
+        None => Mirroring::Horizontal,
+    };
+    mirroring.vram_offset(addr)
+}
+
+/// $3F10/$3F14/$3F18/$3F1C mirror the background entries below them
+fn palette_offset(addr: u16) -> usize {
+    match addr as usize & 0x1F {
+        offset @ (0x10 | 0x14 | 0x18 | 0x1C) => offset - 0x10,
+        offset => offset,
+    }
+}
+
+/// Rendering
+impl Ppu2C02 {
+    fn rendering_enabled(&self) -> bool {
+        self.mask & (PpuMask::SHOW_BACKGROUND | PpuMask::SHOW_SPRITES) != 0
+    }
+
+    /// Advances by one dot. 341 dots make a scanline, 262 scanlines a frame
+    pub fn clock(&mut self, cart: &mut Option<Cartridge>) {
+        if (-1..240).contains(&self.scanline) {
+            self.render_scanline_dot(cart);
+        }
+
+        if self.scanline == 241 && self.cycle == 1 {
+            self.status |= PpuStatus::VBLANK;
+            if self.ctrl & PpuCtrl::ENABLE_NMI != 0 {
+                self.nmi = true;
+            }
+        }
+
+        if (0..240).contains(&self.scanline) && (1..=256).contains(&self.cycle) {
+            self.draw_pixel(cart);
+        }
+
+        self.cycle += 1;
+        if self.cycle > 340 {
+            self.cycle = 0;
+            self.scanline += 1;
+            if self.scanline > 260 {
+                self.scanline = -1;
+                self.odd_frame = !self.odd_frame;
+                self.frame_complete = true;
+            }
+        }
+    }
+
+    fn render_scanline_dot(&mut self, cart: &mut Option<Cartridge>) {
+        // the idle dot of the first visible scanline is skipped on odd frames
+        if self.scanline == 0 && self.cycle == 0 && self.odd_frame && self.rendering_enabled() {
+            self.cycle = 1;
+        }
+
+        if self.scanline == -1 && self.cycle == 1 {
+            self.status &=
+                !(PpuStatus::VBLANK | PpuStatus::SPRITE_ZERO_HIT | PpuStatus::SPRITE_OVERFLOW);
+            self.sprite_count = 0;
+        }
+
+        if (2..258).contains(&self.cycle) || (321..338).contains(&self.cycle) {
+            self.update_shifters();
+
+            match (self.cycle - 1) % 8 {
+                0 => {
+                    self.load_background_shifters();
+                    self.bg_next_tile_id = self.read(cart, 0x2000 | (self.v & 0x0FFF));
+                }
+                2 => {
+                    let coarse_x = self.v & 0x001F;
+                    let coarse_y = (self.v >> 5) & 0x001F;
+                    let addr =
+                        0x23C0 | (self.v & 0x0C00) | ((coarse_y >> 2) << 3) | (coarse_x >> 2);
+                    let mut attrib = self.read(cart, addr);
+                    if coarse_y & 0x02 != 0 {
+                        attrib >>= 4;
+                    }
+                    if coarse_x & 0x02 != 0 {
+                        attrib >>= 2;
+                    }
+                    self.bg_next_tile_attrib = attrib & 0x03;
+                }
+                4 => self.bg_next_tile_lsb = self.read(cart, self.background_pattern_addr()),
+                6 => self.bg_next_tile_msb = self.read(cart, self.background_pattern_addr() + 8),
+                7 => self.increment_scroll_x(),
+                _ => {}
+            }
+        }
+
+        if self.cycle == 256 {
+            self.increment_scroll_y();
+        }
+
+        if self.cycle == 257 {
+            self.load_background_shifters();
+            self.transfer_address_x();
+            if self.scanline >= 0 {
+                self.evaluate_sprites();
+            }
+        }
+
+        // the two unused nametable fetches at the end of the line
+        if self.cycle == 338 || self.cycle == 340 {
+            self.bg_next_tile_id = self.read(cart, 0x2000 | (self.v & 0x0FFF));
+        }
+
+        if self.scanline == -1 && (280..305).contains(&self.cycle) {
+            self.transfer_address_y();
+        }
+
+        if self.cycle == 340 {
+            self.fetch_sprite_patterns(cart);
+        }
+
+        // approximates the MMC3 watching A12 rise during the sprite fetches
+        if self.cycle == 260 && self.rendering_enabled() {
+            if let Some(cart) = cart {
+                cart.scanline();
+            }
+        }
+    }
+
+    fn background_pattern_addr(&self) -> u16 {
+        let table = match self.ctrl & PpuCtrl::BACKGROUND_TABLE {
+            0 => 0x0000,
+            _ => 0x1000,
+        };
+        let fine_y = (self.v >> 12) & 0x07;
+        table + ((self.bg_next_tile_id as u16) << 4) + fine_y
+    }
+
+    fn increment_scroll_x(&mut self) {
+        if !self.rendering_enabled() {
+            return;
+        }
+        if self.v & 0x001F == 31 {
+            // wrap into the horizontally adjacent nametable
+            self.v &= !0x001F;
+            self.v ^= 0x0400;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    fn increment_scroll_y(&mut self) {
+        if !self.rendering_enabled() {
+            return;
+        }
+        if self.v & 0x7000 != 0x7000 {
+            self.v += 0x1000;
+            return;
+        }
+
+        self.v &= !0x7000;
+        let mut coarse_y = (self.v & 0x03E0) >> 5;
+        match coarse_y {
+            // rows 30 and 31 hold attributes, wrap into the vertically adjacent nametable
+            29 => {
+                coarse_y = 0;
+                self.v ^= 0x0800;
+            }
+            31 => coarse_y = 0,
+            _ => coarse_y += 1,
+        }
+        self.v = (self.v & !0x03E0) | (coarse_y << 5);
+    }
+
+    fn transfer_address_x(&mut self) {
+        if self.rendering_enabled() {
+            self.v = (self.v & !0x041F) | (self.t & 0x041F);
+        }
+    }
+
+    fn transfer_address_y(&mut self) {
+        if self.rendering_enabled() {
+            self.v = (self.v & !0x7BE0) | (self.t & 0x7BE0);
+        }
+    }
+
+    fn load_background_shifters(&mut self) {
+        self.bg_shifter_pattern_lo =
+            (self.bg_shifter_pattern_lo & 0xFF00) | self.bg_next_tile_lsb as u16;
+        self.bg_shifter_pattern_hi =
+            (self.bg_shifter_pattern_hi & 0xFF00) | self.bg_next_tile_msb as u16;
+
+        // the palette is the same for all 8 pixels of a tile
+        let spread = |bit: u8| match bit {
+            0 => 0x00,
+            _ => 0xFF,
+        };
+        self.bg_shifter_attrib_lo =
+            (self.bg_shifter_attrib_lo & 0xFF00) | spread(self.bg_next_tile_attrib & 0x01);
+        self.bg_shifter_attrib_hi =
+            (self.bg_shifter_attrib_hi & 0xFF00) | spread(self.bg_next_tile_attrib & 0x02);
+    }
+
+    fn update_shifters(&mut self) {
+        if self.mask & PpuMask::SHOW_BACKGROUND != 0 {
+            self.bg_shifter_pattern_lo <<= 1;
+            self.bg_shifter_pattern_hi <<= 1;
+            self.bg_shifter_attrib_lo <<= 1;
+            self.bg_shifter_attrib_hi <<= 1;
+        }
+    }
+
+    fn sprite_height(&self) -> i16 {
+        match self.ctrl & PpuCtrl::SPRITE_SIZE_16 {
+            0 => 8,
+            _ => 16,
+        }
+    }
+
+    /// Finds the first 8 sprites on the next scanline, flagging any overflow
+    fn evaluate_sprites(&mut self) {
+        self.sprite_count = 0;
+        self.sprite_zero_on_line = false;
+        if !self.rendering_enabled() {
+            return;
+        }
+
+        let height = self.sprite_height();
+        for (index, entry) in self.oam.chunks_exact(4).enumerate() {
+            let row = self.scanline - entry[0] as i16;
+            if !(0..height).contains(&row) {
+                continue;
+            }
+
+            if self.sprite_count == 8 {
+                self.status |= PpuStatus::SPRITE_OVERFLOW;
+                break;
+            }
+            if index == 0 {
+                self.sprite_zero_on_line = true;
+            }
+            self.sprite_scanline[self.sprite_count] = Sprite {
+                y: entry[0],
+                tile: entry[1],
+                attr: entry[2],
+                x: entry[3],
+            };
+            self.sprite_count += 1;
+        }
+    }
+
+    fn fetch_sprite_patterns(&mut self, cart: &mut Option<Cartridge>) {
+        let height = self.sprite_height();
+
+        for i in 0..self.sprite_count {
+            let sprite = self.sprite_scanline[i];
+            let mut row = self.scanline - sprite.y as i16;
+            if sprite.attr & 0x80 != 0 {
+                row = height - 1 - row;
+            }
+
+            let addr = match height {
+                8 => {
+                    let table = match self.ctrl & PpuCtrl::SPRITE_TABLE {
+                        0 => 0x0000,
+                        _ => 0x1000,
+                    };
+                    table | ((sprite.tile as u16) << 4) | row as u16
+                }
+                // 8x16 sprites pick their table with bit 0 of the tile number
+                _ => {
+                    let table = (sprite.tile as u16 & 0x01) << 12;
+                    let tile = (sprite.tile & 0xFE) as u16 + (row / 8) as u16;
+                    table | (tile << 4) | (row % 8) as u16
+                }
+            };
+
+            let mut lo = self.read(cart, addr);
+            let mut hi = self.read(cart, addr + 8);
+            if sprite.attr & 0x40 != 0 {
+                lo = lo.reverse_bits();
+                hi = hi.reverse_bits();
+            }
+            self.sprite_pattern_lo[i] = lo;
+            self.sprite_pattern_hi[i] = hi;
+        }
+    }
+
+    fn draw_pixel(&mut self, cart: &mut Option<Cartridge>) {
+        let x = self.cycle as usize - 1;
+        let y = self.scanline as usize;
+
+        let (mut bg_pixel, mut bg_palette) = (0u8, 0u8);
+        if self.mask & PpuMask::SHOW_BACKGROUND != 0
+            && (x >= 8 || self.mask & PpuMask::SHOW_BACKGROUND_LEFT != 0)
+        {
+            let mux = 0x8000 >> self.fine_x;
+            let bit = |shifter: u16| (shifter & mux != 0) as u8;
+            bg_pixel = (bit(self.bg_shifter_pattern_hi) << 1) | bit(self.bg_shifter_pattern_lo);
+            bg_palette = (bit(self.bg_shifter_attrib_hi) << 1) | bit(self.bg_shifter_attrib_lo);
+        }
+
+        let (mut fg_pixel, mut fg_palette, mut fg_priority, mut sprite_zero) =
+            (0u8, 0u8, false, false);
+        if self.mask & PpuMask::SHOW_SPRITES != 0
+            && (x >= 8 || self.mask & PpuMask::SHOW_SPRITES_LEFT != 0)
+        {
+            for i in 0..self.sprite_count {
+                let sprite = self.sprite_scanline[i];
+                let column = x as i16 - sprite.x as i16;
+                if !(0..8).contains(&column) {
+                    continue;
+                }
+
+                let shift = 7 - column;
+                let pixel = (((self.sprite_pattern_hi[i] >> shift) & 0x01) << 1)
+                    | ((self.sprite_pattern_lo[i] >> shift) & 0x01);
+                if pixel != 0 {
+                    fg_pixel = pixel;
+                    fg_palette = (sprite.attr & 0x03) + 4;
+                    fg_priority = sprite.attr & 0x20 == 0;
+                    sprite_zero = i == 0 && self.sprite_zero_on_line;
+                    break;
+                }
+            }
+        }
+
+        if sprite_zero && bg_pixel != 0 && fg_pixel != 0 && x != 255 {
+            self.status |= PpuStatus::SPRITE_ZERO_HIT;
+        }
+
+        let (pixel, palette) = match (bg_pixel, fg_pixel) {
+            (0, 0) => (0, 0),
+            (0, _) => (fg_pixel, fg_palette),
+            (_, 0) => (bg_pixel, bg_palette),
+            _ if fg_priority => (fg_pixel, fg_palette),
+            _ => (bg_pixel, bg_palette),
+        };
+
+        let mut color = self.read(cart, 0x3F00 + ((palette as u16) << 2) + pixel as u16) & 0x3F;
+        if self.mask & PpuMask::GRAYSCALE != 0 {
+            color &= 0x30;
+        }
+
+        let (r, g, b) = PALETTE[color as usize];
+        let offset = (y * SCREEN_WIDTH + x) * 3;
+        self.frame[offset..offset + 3].copy_from_slice(&[r, g, b]);
+    }
+
+    /// the last rendered frame as packed RGB, 256x240
+    pub fn frame(&self) -> &[u8] {
+        &self.frame
+    }
+
+    /// FNV-1a hash of the frame buffer. Stable across runs and platforms,
+    /// so frames of test ROMs can be checked against known values
+    pub fn frame_hash(&self) -> u64 {
+        self.frame.iter().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+}
+
+/// The 2C02's 64 colors as RGB
+#[rustfmt::skip]
+pub const PALETTE: [(u8, u8, u8); 64] = [
+    (84, 84, 84), (0, 30, 116), (8, 16, 144), (48, 0, 136), (68, 0, 100), (92, 0, 48), (84, 4, 0), (60, 24, 0),
+    (32, 42, 0), (8, 58, 0), (0, 64, 0), (0, 60, 0), (0, 50, 60), (0, 0, 0), (0, 0, 0), (0, 0, 0),
+    (152, 150, 152), (8, 76, 196), (48, 50, 236), (92, 30, 228), (136, 20, 176), (160, 20, 100), (152, 34, 32), (120, 60, 0),
+    (84, 90, 0), (40, 114, 0), (8, 124, 0), (0, 118, 40), (0, 102, 120), (0, 0, 0), (0, 0, 0), (0, 0, 0),
+    (236, 238, 236), (76, 154, 236), (120, 124, 236), (176, 98, 236), (228, 84, 236), (236, 88, 180), (236, 106, 100), (212, 136, 32),
+    (160, 170, 0), (116, 196, 0), (76, 208, 32), (56, 204, 108), (56, 180, 204), (60, 60, 60), (0, 0, 0), (0, 0, 0),
+    (236, 238, 236), (168, 204, 236), (188, 188, 236), (212, 178, 236), (236, 174, 236), (236, 174, 212), (236, 180, 176), (228, 196, 144),
+    (204, 210, 120), (180, 222, 120), (168, 226, 144), (152, 226, 180), (160, 214, 228), (160, 162, 160), (0, 0, 0), (0, 0, 0),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::tests::rom;
+
+    /// a horizontally mirrored NROM cartridge with CHR RAM
+    fn chr_ram_cart() -> Option<Cartridge> {
+        Some(Cartridge::from_bytes(&rom(0, 1, 0, 0)).unwrap())
+    }
+
+    fn set_addr(ppu: &mut Ppu2C02, cart: &mut Option<Cartridge>, addr: u16) {
+        ppu.cpu_write(cart, 0x2006, (addr >> 8) as u8);
+        ppu.cpu_write(cart, 0x2006, addr as u8);
+    }
+
+    fn run_frame(ppu: &mut Ppu2C02, cart: &mut Option<Cartridge>) {
+        ppu.frame_complete = false;
+        while !ppu.frame_complete {
+            ppu.clock(cart);
+        }
+    }
+
+    fn pixel(ppu: &Ppu2C02, x: usize, y: usize) -> (u8, u8, u8) {
+        let offset = (y * SCREEN_WIDTH + x) * 3;
+        (
+            ppu.frame[offset],
+            ppu.frame[offset + 1],
+            ppu.frame[offset + 2],
+        )
+    }
+
+    /// tile 1 is solid color 1, tile 2 solid color 3
+    fn setup_tiles(ppu: &mut Ppu2C02, cart: &mut Option<Cartridge>) {
+        set_addr(ppu, cart, 0x0010);
+        for byte in [0xFF; 8].iter().chain([0x00; 8].iter()) {
+            ppu.cpu_write(cart, 0x2007, *byte);
+        }
+        for _ in 0..16 {
+            ppu.cpu_write(cart, 0x2007, 0xFF);
+        }
+
+        set_addr(ppu, cart, 0x3F00);
+        for color in [0x0F, 0x16, 0x27, 0x18, 0x0F, 0x01, 0x02, 0x03] {
+            ppu.cpu_write(cart, 0x2007, color);
+        }
+        set_addr(ppu, cart, 0x3F11);
+        // $3F14 mirrors $3F04
+        for color in [0x2A, 0x2B, 0x2C, 0x0F, 0x05, 0x06, 0x07] {
+            ppu.cpu_write(cart, 0x2007, color);
+        }
+    }
+
+    #[test]
+    fn test_data_port() {
+        let mut ppu = Ppu2C02::default();
+        let mut cart = chr_ram_cart();
+
+        set_addr(&mut ppu, &mut cart, 0x2000);
+        ppu.cpu_write(&mut cart, 0x2007, 0x11);
+        ppu.cpu_write(&mut cart, 0x2007, 0x22);
+
+        // horizontal mirroring
+        set_addr(&mut ppu, &mut cart, 0x2400);
+        assert_eq!(ppu.cpu_read(&mut cart, 0x2007), 0x00); // stale buffer
+        assert_eq!(ppu.cpu_read(&mut cart, 0x2007), 0x11);
+        assert_eq!(ppu.cpu_read(&mut cart, 0x2007), 0x22);
+
+        ppu.cpu_write(&mut cart, 0x2000, PpuCtrl::INCREMENT_32);
+        set_addr(&mut ppu, &mut cart, 0x2000);
+        ppu.cpu_write(&mut cart, 0x2007, 0x33);
+        ppu.cpu_write(&mut cart, 0x2007, 0x44);
+        assert_eq!(ppu.vram[0x20], 0x44);
+
+        // palette reads aren't buffered
+        set_addr(&mut ppu, &mut cart, 0x3F00);
+        ppu.cpu_write(&mut cart, 0x2007, 0x2C);
+        set_addr(&mut ppu, &mut cart, 0x3F10);
+        assert_eq!(ppu.cpu_read(&mut cart, 0x2007), 0x2C);
+    }
+
+    #[test]
+    fn test_scroll_registers() {
+        let mut ppu = Ppu2C02::default();
+        let mut cart = None;
+
+        ppu.cpu_write(&mut cart, 0x2000, PpuCtrl::NAMETABLE_X);
+        ppu.cpu_write(&mut cart, 0x2005, 0x7D);
+        ppu.cpu_write(&mut cart, 0x2005, 0x5E);
+        assert_eq!(ppu.fine_x, 0x05);
+        // yyy NN YYYYY XXXXX
+        assert_eq!(ppu.t >> 12, 0b110);
+        assert_eq!((ppu.t >> 10) & 0x03, 0b01);
+        assert_eq!((ppu.t >> 5) & 0x1F, 0b01011);
+        assert_eq!(ppu.t & 0x1F, 0b01111);
+
+        // $2002 resets the write toggle
+        ppu.cpu_write(&mut cart, 0x2006, 0x3D);
+        ppu.cpu_read(&mut cart, 0x2002);
+        ppu.cpu_write(&mut cart, 0x2006, 0x21);
+        ppu.cpu_write(&mut cart, 0x2006, 0x08);
+        assert_eq!(ppu.v, 0x2108);
+    }
+
+    #[test]
+    fn test_vblank_and_nmi() {
+        let mut ppu = Ppu2C02::default();
+        let mut cart = None;
+        ppu.cpu_write(&mut cart, 0x2000, PpuCtrl::ENABLE_NMI);
+
+        while !(ppu.scanline == 241 && ppu.cycle == 2) {
+            ppu.clock(&mut cart);
+            assert!(!ppu.nmi || ppu.scanline == 241);
+        }
+        assert!(ppu.take_nmi());
+        assert!(!ppu.take_nmi());
+
+        assert_ne!(ppu.cpu_read(&mut cart, 0x2002) & PpuStatus::VBLANK, 0);
+        assert_eq!(ppu.cpu_read(&mut cart, 0x2002) & PpuStatus::VBLANK, 0);
+
+        // re-enabling NMI during vblank triggers it again
+        ppu.status |= PpuStatus::VBLANK;
+        ppu.cpu_write(&mut cart, 0x2000, 0);
+        ppu.cpu_write(&mut cart, 0x2000, PpuCtrl::ENABLE_NMI);
+        assert!(ppu.take_nmi());
+
+        // 262 scanlines of 341 dots
+        let mut dots = 0;
+        run_frame(&mut ppu, &mut cart);
+        ppu.frame_complete = false;
+        while !ppu.frame_complete {
+            ppu.clock(&mut cart);
+            dots += 1;
+        }
+        assert_eq!(dots, 262 * 341);
+    }
+
+    #[test]
+    fn test_background_rendering() {
+        let mut ppu = Ppu2C02::default();
+        let mut cart = chr_ram_cart();
+        setup_tiles(&mut ppu, &mut cart);
+
+        // tile 1 in the top left corner, tile 2 in the next 16x16 attribute area with palette 1
+        set_addr(&mut ppu, &mut cart, 0x2000);
+        for tile in [0x01, 0x00, 0x02] {
+            ppu.cpu_write(&mut cart, 0x2007, tile);
+        }
+        set_addr(&mut ppu, &mut cart, 0x23C0);
+        ppu.cpu_write(&mut cart, 0x2007, 0b01_00);
+
+        set_addr(&mut ppu, &mut cart, 0x0000);
+        ppu.cpu_write(
+            &mut cart,
+            0x2001,
+            PpuMask::SHOW_BACKGROUND | PpuMask::SHOW_BACKGROUND_LEFT,
+        );
+
+        run_frame(&mut ppu, &mut cart);
+        run_frame(&mut ppu, &mut cart);
+
+        assert_eq!(pixel(&ppu, 0, 0), PALETTE[0x16]);
+        assert_eq!(pixel(&ppu, 7, 7), PALETTE[0x16]);
+        assert_eq!(pixel(&ppu, 8, 0), PALETTE[0x0F]);
+        assert_eq!(pixel(&ppu, 16, 0), PALETTE[0x03]);
+        assert_eq!(pixel(&ppu, 0, 8), PALETTE[0x0F]);
+
+        // scrolled by 4 pixels
+        let hash = ppu.frame_hash();
+        ppu.cpu_write(&mut cart, 0x2005, 4);
+        ppu.cpu_write(&mut cart, 0x2005, 0);
+        run_frame(&mut ppu, &mut cart);
+        assert_eq!(pixel(&ppu, 3, 0), PALETTE[0x16]);
+        assert_eq!(pixel(&ppu, 4, 0), PALETTE[0x0F]);
+        assert_eq!(pixel(&ppu, 12, 0), PALETTE[0x03]);
+        assert_ne!(ppu.frame_hash(), hash);
+    }
+
+    #[test]
+    fn test_sprites() {
+        let mut ppu = Ppu2C02::default();
+        let mut cart = chr_ram_cart();
+        setup_tiles(&mut ppu, &mut cart);
+
+        // background tile 1 at (2, 2)
+        set_addr(&mut ppu, &mut cart, 0x2000 + 2 * 32 + 2);
+        ppu.cpu_write(&mut cart, 0x2007, 0x01);
+
+        // sprite 0 overlapping it, 9 more on a later line
+        ppu.cpu_write(&mut cart, 0x2003, 0);
+        for byte in [15, 2, 0x00, 20] {
+            ppu.cpu_write(&mut cart, 0x2004, byte);
+        }
+        for i in 0..9 {
+            for byte in [100, 2, 0x01, i * 10] {
+                ppu.cpu_write(&mut cart, 0x2004, byte);
+            }
+        }
+
+        set_addr(&mut ppu, &mut cart, 0x0000);
+        ppu.cpu_write(
+            &mut cart,
+            0x2001,
+            PpuMask::SHOW_BACKGROUND | PpuMask::SHOW_SPRITES,
+        );
+        run_frame(&mut ppu, &mut cart);
+        run_frame(&mut ppu, &mut cart);
+
+        // sprites are drawn one line below their OAM y
+        assert_eq!(pixel(&ppu, 20, 16), PALETTE[0x2C]);
+        assert_eq!(pixel(&ppu, 20, 15), PALETTE[0x0F]);
+        // sprite palette 1
+        assert_eq!(pixel(&ppu, 10, 101), PALETTE[0x07]);
+
+        while ppu.scanline != 241 {
+            ppu.clock(&mut cart);
+        }
+        let status = ppu.cpu_read(&mut cart, 0x2002);
+        assert_ne!(status & PpuStatus::SPRITE_ZERO_HIT, 0);
+        assert_ne!(status & PpuStatus::SPRITE_OVERFLOW, 0);
+
+        // flags are cleared on the pre-render line
+        while ppu.scanline != -1 || ppu.cycle != 2 {
+            ppu.clock(&mut cart);
+        }
+        assert_eq!(
+            ppu.status & (PpuStatus::SPRITE_ZERO_HIT | PpuStatus::SPRITE_OVERFLOW),
+            0
+        );
+    }
+}
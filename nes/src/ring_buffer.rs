@@ -0,0 +1,88 @@
+/// Fixed capacity FIFO. When full, pushing drops the oldest element so a slow
+/// consumer loses old audio rather than stalling emulation
+pub struct RingBuffer<T> {
+    buffer: Vec<Option<T>>,
+    head: usize,
+    len: usize,
+}
+
+impl<T> RingBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "RingBuffer needs a non zero capacity");
+        Self {
+            buffer: (0..capacity).map(|_| None).collect(),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, value: T) {
+        let tail = (self.head + self.len) % self.capacity();
+        self.buffer[tail] = Some(value);
+        if self.len == self.capacity() {
+            self.head = (self.head + 1) % self.capacity();
+        } else {
+            self.len += 1;
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let value = self.buffer[self.head].take();
+        self.head = (self.head + 1) % self.capacity();
+        self.len -= 1;
+        value
+    }
+
+    pub fn clear(&mut self) {
+        while self.pop().is_some() {}
+    }
+
+    /// Empties the buffer, oldest first
+    pub fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(|| self.pop())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fifo() {
+        let mut ring = RingBuffer::new(3);
+        assert!(ring.is_empty());
+        ring.push(1);
+        ring.push(2);
+        assert_eq!(ring.pop(), Some(1));
+        ring.push(3);
+        ring.push(4);
+        assert_eq!(ring.len(), 3);
+        assert_eq!(ring.drain().collect::<Vec<_>>(), vec![2, 3, 4]);
+        assert_eq!(ring.pop(), None);
+    }
+
+    #[test]
+    fn test_overwrites_oldest() {
+        let mut ring = RingBuffer::new(2);
+        for i in 0..5 {
+            ring.push(i);
+        }
+        assert_eq!(ring.len(), 2);
+        assert_eq!(ring.drain().collect::<Vec<_>>(), vec![3, 4]);
+    }
+}
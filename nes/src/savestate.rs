@@ -0,0 +1,465 @@
+use crate::Bus16;
+use std::collections::VecDeque;
+
+/// Bumped whenever the layout of any component's state changes
+pub const SAVE_STATE_VERSION: u16 = 2;
+const MAGIC: &[u8; 4] = b"NESS";
+
+#[derive(Debug)]
+pub enum SaveStateError {
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    /// the state ended before every component was restored
+    Truncated,
+    /// the state was saved with a different ROM inserted
+    CartridgeMismatch,
+    /// a value that no emulator state can have, e.g. a scanline of 1000
+    InvalidData(&'static str),
+}
+
+/// Emulator components that can be snapshotted.
+///
+/// Only emulated state is saved, host configuration such as the audio
+/// sample rate is left alone when a state is loaded.
+pub trait SaveState {
+    fn save_state(&self, state: &mut StateWriter);
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), SaveStateError>;
+}
+
+/// Appends little endian values to a byte buffer
+#[derive(Default)]
+pub struct StateWriter {
+    bytes: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn i16(&mut self, value: i16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    /// a buffer whose size both sides already know, e.g. a fixed size array
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+}
+
+/// Reads back what a [`StateWriter`] wrote, in the same order
+pub struct StateReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], SaveStateError> {
+        let mut array = [0; N];
+        self.bytes(&mut array)?;
+        Ok(array)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, SaveStateError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, SaveStateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SaveStateError::InvalidData("bool")),
+        }
+    }
+
+    pub fn u16(&mut self) -> Result<u16, SaveStateError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    pub fn i16(&mut self) -> Result<i16, SaveStateError> {
+        Ok(i16::from_le_bytes(self.take()?))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, SaveStateError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, SaveStateError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    pub fn f32(&mut self) -> Result<f32, SaveStateError> {
+        Ok(f32::from_le_bytes(self.take()?))
+    }
+
+    /// fills `buffer` completely
+    pub fn bytes(&mut self, buffer: &mut [u8]) -> Result<(), SaveStateError> {
+        if self.bytes.len() < buffer.len() {
+            return Err(SaveStateError::Truncated);
+        }
+        let (head, tail) = self.bytes.split_at(buffer.len());
+        buffer.copy_from_slice(head);
+        self.bytes = tail;
+        Ok(())
+    }
+}
+
+impl Bus16 {
+    /// Serializes the whole machine: CPU, RAM, PPU, APU and cartridge RAM and mapper registers
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = StateWriter::default();
+        state.bytes(MAGIC);
+        state.u16(SAVE_STATE_VERSION);
+        SaveState::save_state(self, &mut state);
+        state.into_bytes()
+    }
+
+    /// Restores a state from [`Bus16::save_state`]. The same ROM has to be inserted.
+    ///
+    /// On error the machine is left in an unspecified state and should be reset.
+    pub fn load_state(&mut self, bytes: &[u8]) -> Result<(), SaveStateError> {
+        let mut state = StateReader::new(bytes);
+        if &state.take::<4>()? != MAGIC {
+            return Err(SaveStateError::InvalidMagic);
+        }
+        match state.u16()? {
+            SAVE_STATE_VERSION => {}
+            version => return Err(SaveStateError::UnsupportedVersion(version)),
+        }
+        SaveState::load_state(self, &mut state)?;
+        match state.is_empty() {
+            true => Ok(()),
+            false => Err(SaveStateError::InvalidData("trailing bytes")),
+        }
+    }
+}
+
+/// Keeps a snapshot every `interval` frames so emulation can be stepped backward.
+///
+/// Call [`Rewind::record`] after every frame; old snapshots are dropped once
+/// `capacity` is reached.
+pub struct Rewind {
+    interval: u64,
+    capacity: usize,
+    /// frames recorded since the rewind buffer was created
+    frame: u64,
+    snapshots: VecDeque<(u64, Vec<u8>)>,
+}
+
+impl Rewind {
+    pub fn new(interval: u64, capacity: usize) -> Self {
+        assert!(interval > 0, "Rewind needs a non zero interval");
+        Self {
+            interval,
+            capacity,
+            frame: 0,
+            snapshots: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn record(&mut self, nes: &Bus16) {
+        self.frame += 1;
+        if !self.frame.is_multiple_of(self.interval) || self.capacity == 0 {
+            return;
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back((self.frame, nes.save_state()));
+    }
+
+    /// Restores the newest snapshot older than the current frame.
+    /// Returns false, leaving `nes` untouched, when there is none
+    pub fn step_back(&mut self, nes: &mut Bus16) -> Result<bool, SaveStateError> {
+        let Some(index) = self
+            .snapshots
+            .iter()
+            .rposition(|(frame, _)| *frame < self.frame)
+        else {
+            return Ok(false);
+        };
+
+        // anything newer belongs to the future being abandoned
+        self.snapshots.truncate(index + 1);
+        let (frame, state) = &self.snapshots[index];
+        nes.load_state(state)?;
+        self.frame = *frame;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::tests::rom;
+    use crate::{Cartridge, Movie};
+    use std::ops::Range;
+
+    /// UxROM with CHR RAM. The NMI handler rewrites a CHR row, the backdrop color,
+    /// the PRG bank and the pulse period every frame, so every part of the state changes.
+    /// It also reads controller 1 and draws the buttons into the top row of tile 0
+    fn nes() -> Bus16 {
+        #[rustfmt::skip]
+        let reset = [
+            0xA9, 0x80,       // LDA #$80
+            0x8D, 0x00, 0x20, // STA $2000
+            0xA9, 0x0A,       // LDA #$0A
+            0x8D, 0x01, 0x20, // STA $2001
+            0xA9, 0x01,       // LDA #$01
+            0x8D, 0x15, 0x40, // STA $4015
+            0xA9, 0xBF,       // LDA #$BF
+            0x8D, 0x00, 0x40, // STA $4000
+            0xE6, 0x10,       // INC $10
+            0x4C, 0x14, 0xC0, // JMP $C014
+        ];
+        #[rustfmt::skip]
+        let nmi = [
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA5, 0x11,       // LDA $11
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA5, 0x10,       // LDA $10
+            0x8D, 0x07, 0x20, // STA $2007
+            0xA9, 0x3F,       // LDA #$3F
+            0x8D, 0x06, 0x20, // STA $2006
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x06, 0x20, // STA $2006
+            0xAD, 0x00, 0x80, // LDA $8000
+            0x05, 0x11,       // ORA $11
+            0x8D, 0x07, 0x20, // STA $2007
+            0xA5, 0x11,       // LDA $11
+            0x29, 0x01,       // AND #$01
+            0x8D, 0x00, 0x80, // STA $8000
+            0xA5, 0x10,       // LDA $10
+            0x8D, 0x02, 0x40, // STA $4002
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x03, 0x40, // STA $4003
+            0x8D, 0x06, 0x20, // STA $2006
+            0x8D, 0x06, 0x20, // STA $2006
+            0x8D, 0x05, 0x20, // STA $2005
+            0x8D, 0x05, 0x20, // STA $2005
+            0xE6, 0x11,       // INC $11
+            0xA9, 0x01,       // LDA #$01
+            0x8D, 0x16, 0x40, // STA $4016
+            0xA9, 0x00,       // LDA #$00
+            0x8D, 0x16, 0x40, // STA $4016
+            0xA2, 0x08,       // LDX #$08
+            0xAD, 0x16, 0x40, // LDA $4016
+            0x4A,             // LSR A
+            0x26, 0x12,       // ROL $12
+            0xCA,             // DEX
+            0xD0, 0xF7,       // BNE $C076
+            0xA5, 0x12,       // LDA $12
+            0x8D, 0x07, 0x20, // STA $2007
+            0x40,             // RTI
+        ];
+
+        // the second bank is fixed at $C000
+        let mut bytes = rom(2, 2, 0, 0);
+        let bank = 16 + 0x4000;
+        bytes[bank..bank + reset.len()].copy_from_slice(&reset);
+        bytes[bank + 0x20..bank + 0x20 + nmi.len()].copy_from_slice(&nmi);
+        bytes[bank + 0x3FFA..bank + 0x4000].copy_from_slice(&[0x20, 0xC0, 0x00, 0xC0, 0x00, 0xC0]);
+
+        let mut nes = Bus16::default();
+        nes.insert_cartridge(Cartridge::from_bytes(&bytes).unwrap());
+        nes.reset();
+        nes
+    }
+
+    /// a different button combination every frame
+    fn movie() -> Movie {
+        let mut movie = Movie::default();
+        for frame in 0..60 {
+            movie.push([(frame * 37) as u8, 0]);
+        }
+        movie
+    }
+
+    /// plays `frames` of `movie`, returning the frame hash and audio of each frame
+    fn run(nes: &mut Bus16, movie: &Movie, frames: Range<usize>) -> Vec<(u64, Vec<f32>)> {
+        frames
+            .map(|frame| {
+                movie.play_frame(nes, frame);
+                (nes.ppu.frame_hash(), nes.apu.samples.drain().collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_deterministic_replay() {
+        let movie = movie();
+        let mut nes = nes();
+        run(&mut nes, &movie, 0..30);
+        let state = nes.save_state();
+
+        let expected = run(&mut nes, &movie, 30..60);
+        assert!(expected.iter().any(|(hash, _)| *hash != expected[0].0));
+        assert!(expected.iter().all(|(_, samples)| !samples.is_empty()));
+
+        nes.load_state(&state).unwrap();
+        assert!(nes.save_state() == state);
+        assert!(run(&mut nes, &movie, 30..60) == expected);
+
+        // a freshly powered on machine with the same cartridge follows the same timeline
+        let mut other = self::nes();
+        other.load_state(&state).unwrap();
+        assert!(run(&mut other, &movie, 30..60) == expected);
+
+        // and the input really is part of it
+        nes.load_state(&state).unwrap();
+        assert!(run(&mut nes, &Movie::default(), 30..60) != expected);
+    }
+
+    #[test]
+    fn test_rewind() {
+        let mut nes = nes();
+        let mut rewind = Rewind::new(5, 3);
+        let mut states = Vec::new();
+        for _ in 0..20 {
+            nes.run_frame();
+            rewind.record(&nes);
+            states.push(nes.save_state());
+        }
+        // frames 10, 15 and 20, the one at 5 fell out
+        assert_eq!(rewind.len(), 3);
+
+        assert!(rewind.step_back(&mut nes).unwrap());
+        assert_eq!(rewind.frame(), 15);
+        assert!(nes.save_state() == states[14]);
+        assert!(rewind.step_back(&mut nes).unwrap());
+        assert_eq!(rewind.frame(), 10);
+        assert!(nes.save_state() == states[9]);
+        assert!(!rewind.step_back(&mut nes).unwrap());
+        assert!(nes.save_state() == states[9]);
+
+        // playing forward again retraces the original frames
+        for _ in 0..5 {
+            nes.run_frame();
+            rewind.record(&nes);
+        }
+        assert_eq!(rewind.frame(), 15);
+        assert!(nes.save_state() == states[14]);
+        assert_eq!(rewind.len(), 2);
+    }
+
+    #[test]
+    fn test_rejects_invalid_states() {
+        let mut nes = nes();
+        nes.run_frame();
+        let state = nes.save_state();
+
+        let mut bad_magic = state.clone();
+        bad_magic[0] = b'X';
+        assert!(matches!(
+            nes.load_state(&bad_magic),
+            Err(SaveStateError::InvalidMagic)
+        ));
+
+        let mut bad_version = state.clone();
+        bad_version[4] = 0xFF;
+        assert!(matches!(
+            nes.load_state(&bad_version),
+            Err(SaveStateError::UnsupportedVersion(0x00FF))
+        ));
+
+        assert!(matches!(
+            nes.load_state(&state[..state.len() - 1]),
+            Err(SaveStateError::Truncated)
+        ));
+
+        let mut trailing = state.clone();
+        trailing.push(0);
+        assert!(matches!(
+            nes.load_state(&trailing),
+            Err(SaveStateError::InvalidData(_))
+        ));
+
+        let mut other = Bus16::default();
+        other.insert_cartridge(Cartridge::from_bytes(&rom(2, 2, 0, 0)).unwrap());
+        assert!(matches!(
+            other.load_state(&state),
+            Err(SaveStateError::CartridgeMismatch)
+        ));
+        assert!(matches!(
+            Bus16::default().load_state(&state),
+            Err(SaveStateError::CartridgeMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_writer_reader() {
+        let mut writer = StateWriter::default();
+        writer.u8(0x12);
+        writer.bool(true);
+        writer.u16(0x3456);
+        writer.i16(-1);
+        writer.u32(0x789A_BCDE);
+        writer.u64(u64::MAX);
+        writer.f32(0.5);
+        writer.bytes(&[1, 2, 3]);
+        let bytes = writer.into_bytes();
+
+        let mut reader = StateReader::new(&bytes);
+        assert_eq!(reader.u8().unwrap(), 0x12);
+        assert!(reader.bool().unwrap());
+        assert_eq!(reader.u16().unwrap(), 0x3456);
+        assert_eq!(reader.i16().unwrap(), -1);
+        assert_eq!(reader.u32().unwrap(), 0x789A_BCDE);
+        assert_eq!(reader.u64().unwrap(), u64::MAX);
+        assert_eq!(reader.f32().unwrap(), 0.5);
+        let mut buffer = [0; 3];
+        reader.bytes(&mut buffer).unwrap();
+        assert_eq!(buffer, [1, 2, 3]);
+        assert!(reader.is_empty());
+        assert!(matches!(reader.u8(), Err(SaveStateError::Truncated)));
+
+        assert!(matches!(
+            StateReader::new(&[2]).bool(),
+            Err(SaveStateError::InvalidData(_))
+        ));
+    }
+}
@@ -0,0 +1,79 @@
+use std::io::{self, Read, Write};
+
+/// Writes mono 16 bit PCM. Samples are clamped to -1.0..=1.0
+pub fn write_wav(writer: &mut impl Write, sample_rate: u32, samples: &[f32]) -> io::Result<()> {
+    let data_size = samples.len() as u32 * 2;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&1u16.to_le_bytes())?; // mono
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * 2).to_le_bytes())?; // byte rate
+    writer.write_all(&2u16.to_le_bytes())?; // block align
+    writer.write_all(&16u16.to_le_bytes())?; // bits per sample
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    for sample in samples {
+        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Reads a file written by [`write_wav`]. Returns the sample rate and the samples
+pub fn read_wav(reader: &mut impl Read) -> io::Result<(u32, Vec<i16>)> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    if bytes.len() < 44 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(invalid("not a WAV file"));
+    }
+
+    let u16_at = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+    let u32_at = |offset: usize| {
+        u32::from_le_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ])
+    };
+    if u16_at(20) != 1 || u16_at(22) != 1 || u16_at(34) != 16 {
+        return Err(invalid("only mono 16 bit PCM is supported"));
+    }
+
+    let sample_rate = u32_at(24);
+    let data_size = (u32_at(40) as usize).min(bytes.len() - 44);
+    let samples = bytes[44..44 + data_size]
+        .chunks_exact(2)
+        .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+        .collect();
+
+    Ok((sample_rate, samples))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut bytes = Vec::new();
+        write_wav(&mut bytes, 44100, &[0.0, 0.5, -1.0, 2.0]).unwrap();
+        assert_eq!(bytes.len(), 44 + 8);
+
+        let (sample_rate, samples) = read_wav(&mut bytes.as_slice()).unwrap();
+        assert_eq!(sample_rate, 44100);
+        assert_eq!(samples, vec![0, 16383, -32767, 32767]);
+
+        assert!(read_wav(&mut &b"RIFF"[..]).is_err());
+    }
+}